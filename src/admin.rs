@@ -0,0 +1,155 @@
+//! Mountable admin scope for introspecting and tuning a [RequestHook] at runtime.
+use std::time::Duration;
+
+use actix_web::guard::Guard;
+use actix_web::{web, HttpResponse, Scope};
+use serde::{Deserialize, Serialize};
+
+use crate::builder::BuildError;
+use crate::settings::HookSettings;
+use crate::stats::HookStats;
+use crate::RequestHook;
+
+/// Configuration of a hook, from `GET {path}/config`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HookConfig {
+    pub exclude: Vec<String>,
+    pub exclude_regex: Vec<String>,
+    pub observers: Vec<String>,
+    pub settings: SettingsState,
+}
+
+/// Runtime settings, from `GET {path}/settings`. Fields of `PUT {path}/settings` are optional, missing fields
+/// keep their value. A sample rate outside of `0.0..=1.0` is answered with `400 Bad Request`, changing nothing.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SettingsState {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capture_body: Option<bool>,
+}
+
+/// Live stats, from `GET {path}/stats?window=60` (seconds, 60 by default).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatsState {
+    pub window_secs: u64,
+    pub requests: u64,
+    pub requests_per_second: f64,
+    pub error_rate: f64,
+    pub average_latency_us: u64,
+    pub in_flight: usize,
+    #[serde(default)]
+    pub orphaned: usize,
+    pub apdex: Option<f64>,
+    #[serde(default)]
+    pub unique_clients: u64,
+}
+
+#[derive(Deserialize)]
+struct StatsQuery {
+    window: Option<u64>,
+}
+
+struct AdminState {
+    exclude: Vec<String>,
+    exclude_regex: Vec<String>,
+    observers: Vec<String>,
+    stats: HookStats,
+    settings: HookSettings,
+}
+
+impl AdminState {
+    fn settings(&self) -> SettingsState {
+        SettingsState {
+            sample_rate: Some(self.settings.sample_rate()),
+            capture_body: Some(self.settings.capture_body()),
+        }
+    }
+}
+
+/// Scope at `path` exposing JSON endpoints of `hook`, only for requests passing `guard`, e.g. a header with a
+/// secret token. Other requests fall through to the app, typically a 404.
+///
+/// * `GET {path}/config` - excluded paths, observer names and settings, see [HookConfig].
+/// * `GET {path}/observers` - names of registered observers.
+/// * `GET {path}/stats` - live stats snapshot, see [StatsState].
+/// * `GET {path}/settings` and `PUT {path}/settings` - sample rate and body capture, see [SettingsState].
+///
+/// Stats and settings are shared with the hook, so build the scope before the hook is moved into `wrap`:
+/// ```
+/// use actix_web::{guard, App};
+/// use actix_request_hook::{admin, RequestHook};
+///
+/// let request_hook = RequestHook::new().exclude("/health");
+/// App::new()
+///     .service(admin::scope("/__hook", &request_hook, guard::Header("x-admin-token", "secret")))
+///     .wrap(request_hook);
+/// ```
+pub fn scope<G: 'static + Guard>(path: &str, hook: &RequestHook, guard: G) -> Scope {
+    let state = web::Data::new(AdminState {
+        exclude: hook.excluded(),
+        exclude_regex: hook.excluded_patterns().to_vec(),
+        observers: hook.observer_names(),
+        stats: hook.stats(),
+        settings: hook.settings(),
+    });
+    web::scope(path)
+        .guard(guard)
+        .app_data(state)
+        .route("/config", web::get().to(config))
+        .route("/observers", web::get().to(observers))
+        .route("/stats", web::get().to(stats))
+        .route("/settings", web::get().to(settings))
+        .route("/settings", web::put().to(update_settings))
+}
+
+async fn config(state: web::Data<AdminState>) -> HttpResponse {
+    HttpResponse::Ok().json(HookConfig {
+        exclude: state.exclude.clone(),
+        exclude_regex: state.exclude_regex.clone(),
+        observers: state.observers.clone(),
+        settings: state.settings(),
+    })
+}
+
+async fn observers(state: web::Data<AdminState>) -> HttpResponse {
+    HttpResponse::Ok().json(&state.observers)
+}
+
+async fn stats(state: web::Data<AdminState>, query: web::Query<StatsQuery>) -> HttpResponse {
+    let snapshot = state
+        .stats
+        .snapshot(Duration::from_secs(query.window.unwrap_or(60)));
+    HttpResponse::Ok().json(StatsState {
+        window_secs: snapshot.window.as_secs(),
+        requests: snapshot.requests,
+        requests_per_second: snapshot.requests_per_second,
+        error_rate: snapshot.error_rate,
+        average_latency_us: snapshot.average_latency.as_micros() as u64,
+        in_flight: snapshot.in_flight,
+        orphaned: snapshot.orphaned,
+        apdex: snapshot.apdex,
+        unique_clients: snapshot.unique_clients,
+    })
+}
+
+async fn settings(state: web::Data<AdminState>) -> HttpResponse {
+    HttpResponse::Ok().json(state.settings())
+}
+
+async fn update_settings(
+    state: web::Data<AdminState>,
+    update: web::Json<SettingsState>,
+) -> HttpResponse {
+    if let Some(sample_rate) = update.sample_rate {
+        if !(0.0..=1.0).contains(&sample_rate) {
+            return HttpResponse::BadRequest()
+                .body(BuildError::InvalidSampleRate(sample_rate).to_string());
+        }
+        state.settings.set_sample_rate(sample_rate);
+    }
+    if let Some(capture_body) = update.capture_body {
+        state.settings.set_capture_body(capture_body);
+    }
+    HttpResponse::Ok().json(state.settings())
+}
@@ -0,0 +1,248 @@
+//! [LatencyAnomalyDetector] flags routes whose latency deviates from their recent baseline.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use actix_web::dev::ServiceRequest;
+use uuid::Uuid;
+
+use crate::observer::{Observer, RequestCancelledData, RequestEndData, RequestStartData};
+
+type AnomalyFn = Box<dyn Fn(&LatencyAnomaly) + Send + Sync>;
+
+/// Passed to [LatencyAnomalyDetector::on_latency_anomaly] when a route becomes anomalous.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LatencyAnomaly {
+    pub method: String,
+    /// Matched route pattern, or normalized path if no route matched.
+    pub route: String,
+    /// Request which made the route anomalous.
+    pub request_id: Uuid,
+    /// Recent average latency of the route.
+    pub latency: Duration,
+    /// Baseline average latency of the route.
+    pub baseline: Duration,
+    /// Baseline standard deviation of the route.
+    pub deviation: Duration,
+    /// Number of baseline standard deviations recent latency is above the baseline.
+    pub z_score: f64,
+}
+
+/// Exponentially weighted latency statistics of a route, in microseconds.
+#[derive(Clone, Debug, Default)]
+struct RouteState {
+    samples: u64,
+    recent: f64,
+    mean: f64,
+    variance: f64,
+    anomalous: bool,
+}
+
+/// Keeps an exponentially weighted baseline (mean and variance) of latency per method and route, and calls back
+/// when recent latency of a route rises significantly above it, e.g. after a slow deploy.
+///
+/// Recent latency is a fast moving average over the last few requests, so short spikes weigh less than lasting
+/// slowdowns. A route is anomalous when recent latency is at least `z_threshold` baseline standard deviations, and at
+/// least `min_deviation`, above the baseline. The callback is called once when a route becomes anomalous, and again
+/// only after it recovered. Baseline keeps adapting, so a lasting shift becomes the new normal.
+///
+/// Detector is an [Observer] and a cloneable handle, register the same detector with hooks of all workers.
+///
+/// ```
+/// use std::rc::Rc;
+/// use std::time::Duration;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::anomaly::LatencyAnomalyDetector;
+///
+/// let detector = LatencyAnomalyDetector::new()
+///     .z_threshold(4.0)
+///     .min_deviation(Duration::from_millis(50))
+///     .on_latency_anomaly(|anomaly| {
+///         eprintln!("{} {} slowed down to {:?}, usually {:?}", anomaly.method, anomaly.route, anomaly.latency, anomaly.baseline)
+///     });
+/// let request_hook = RequestHook::new().register(Rc::new(detector));
+/// ```
+#[derive(Clone)]
+pub struct LatencyAnomalyDetector {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    baseline_alpha: f64,
+    recent_alpha: f64,
+    z_threshold: f64,
+    min_deviation: Duration,
+    min_samples: u64,
+    max_routes: usize,
+    on_latency_anomaly: Option<AnomalyFn>,
+    routes: Mutex<HashMap<(String, String), RouteState>>,
+    pending: Mutex<HashMap<Uuid, String>>,
+}
+
+impl Default for LatencyAnomalyDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatencyAnomalyDetector {
+    /// Creates a detector with a baseline of roughly last 100 requests, recent latency of roughly last 5 requests,
+    /// a z-score threshold of 3, minimum deviation of 10ms and 50 samples of warm up per route.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                baseline_alpha: 0.01,
+                recent_alpha: 0.2,
+                z_threshold: 3.0,
+                min_deviation: Duration::from_millis(10),
+                min_samples: 50,
+                max_routes: 500,
+                on_latency_anomaly: None,
+                routes: Mutex::new(HashMap::new()),
+                pending: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Smoothing factor of the baseline, between 0 and 1. Smaller factors remember longer, `0.01` by default.
+    pub fn baseline_alpha(mut self, alpha: f64) -> Self {
+        self.inner_mut().baseline_alpha = alpha;
+        self
+    }
+
+    /// Smoothing factor of recent latency, between 0 and 1. `1.0` compares every request alone, `0.2` by default.
+    pub fn recent_alpha(mut self, alpha: f64) -> Self {
+        self.inner_mut().recent_alpha = alpha;
+        self
+    }
+
+    /// Number of baseline standard deviations recent latency must be above the baseline.
+    pub fn z_threshold(mut self, z_threshold: f64) -> Self {
+        self.inner_mut().z_threshold = z_threshold;
+        self
+    }
+
+    /// Minimum absolute deviation from the baseline, which keeps jitter of very stable routes from alerting.
+    pub fn min_deviation(mut self, min_deviation: Duration) -> Self {
+        self.inner_mut().min_deviation = min_deviation;
+        self
+    }
+
+    /// Number of requests of a route before it can become anomalous.
+    pub fn min_samples(mut self, min_samples: u64) -> Self {
+        self.inner_mut().min_samples = min_samples;
+        self
+    }
+
+    /// Maximum number of distinct method and route pairs tracked, 500 by default. Further routes are ignored.
+    pub fn max_routes(mut self, max_routes: usize) -> Self {
+        self.inner_mut().max_routes = max_routes;
+        self
+    }
+
+    /// Called when a route becomes anomalous.
+    pub fn on_latency_anomaly<F: 'static + Fn(&LatencyAnomaly) + Send + Sync>(
+        mut self,
+        callback: F,
+    ) -> Self {
+        self.inner_mut().on_latency_anomaly = Some(Box::new(callback));
+        self
+    }
+
+    /// Method and route pairs which are currently anomalous, sorted by route and method.
+    pub fn anomalous(&self) -> Vec<(String, String)> {
+        let routes = self.inner.routes.lock().unwrap();
+        let mut anomalous: Vec<_> = routes
+            .iter()
+            .filter(|(_, state)| state.anomalous)
+            .map(|(key, _)| key.clone())
+            .collect();
+        anomalous.sort_by(|a, b| (&a.1, &a.0).cmp(&(&b.1, &b.0)));
+        anomalous
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Arc::get_mut(&mut self.inner).unwrap()
+    }
+
+    pub(crate) fn observe(&self, key: (String, String), request_id: Uuid, elapsed: Duration) {
+        let inner = &self.inner;
+        let anomaly = {
+            let mut routes = inner.routes.lock().unwrap();
+            if routes.len() >= inner.max_routes && !routes.contains_key(&key) {
+                return;
+            }
+            let state = routes.entry(key.clone()).or_default();
+            let latency = elapsed.as_micros() as f64;
+            if state.samples == 0 {
+                state.recent = latency;
+                state.mean = latency;
+            }
+            state.samples += 1;
+            state.recent += inner.recent_alpha * (latency - state.recent);
+
+            let deviation = state.variance.sqrt();
+            let excess = state.recent - state.mean;
+            let z_score = if deviation > 0.0 {
+                excess / deviation
+            } else if excess > 0.0 {
+                f64::INFINITY
+            } else {
+                0.0
+            };
+            let anomalous = state.samples > inner.min_samples
+                && z_score >= inner.z_threshold
+                && excess >= inner.min_deviation.as_micros() as f64;
+            let anomaly = (anomalous && !state.anomalous).then(|| LatencyAnomaly {
+                method: key.0,
+                route: key.1,
+                request_id,
+                latency: Duration::from_micros(state.recent as u64),
+                baseline: Duration::from_micros(state.mean as u64),
+                deviation: Duration::from_micros(deviation as u64),
+                z_score,
+            });
+            state.anomalous = anomalous;
+
+            let difference = latency - state.mean;
+            state.mean += inner.baseline_alpha * difference;
+            state.variance = (1.0 - inner.baseline_alpha)
+                * (state.variance + inner.baseline_alpha * difference * difference);
+            anomaly
+        };
+        if let (Some(anomaly), Some(callback)) = (anomaly, &inner.on_latency_anomaly) {
+            callback(&anomaly);
+        }
+    }
+}
+
+impl Observer for LatencyAnomalyDetector {
+    fn on_request_started(&self, data: RequestStartData) {
+        let route = crate::normalize::route(data.req);
+        self.inner
+            .pending
+            .lock()
+            .unwrap()
+            .insert(data.request_id, route);
+    }
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        let route = match self.inner.pending.lock().unwrap().remove(&data.request_id) {
+            Some(route) => route,
+            None => return,
+        };
+        self.observe(
+            (data.method.to_string(), route),
+            data.request_id,
+            data.elapsed,
+        );
+    }
+
+    fn on_request_cancelled(&self, data: RequestCancelledData) {
+        self.inner.pending.lock().unwrap().remove(&data.request_id);
+    }
+
+    fn capture_body(&self, _req: &ServiceRequest) -> bool {
+        false
+    }
+}
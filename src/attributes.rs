@@ -0,0 +1,277 @@
+//! [HookAttributes] and [HookResponseAttributes], context added to end events of a request by middleware or
+//! handlers.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::iter::FromIterator;
+
+use actix_web::{HttpMessage, HttpResponse};
+
+/// Key/value pairs of business context of a request, e.g. an order id or a job type, which the hook merges into the
+/// `attributes` field of its end data, so access logs carry it.
+///
+/// Middleware wrapped outside of the hook, or the handler, add attributes to the extensions of the request with
+/// [HookAttributes::set], which merges them with attributes added before. Of duplicate keys the last one wins.
+/// Attributes are read once the handler returned a response, the end data of requests without a response, e.g. ones
+/// failing in middleware, has none.
+///
+/// ```
+/// use actix_request_hook::attributes::HookAttributes;
+/// use actix_web::{web, HttpRequest};
+///
+/// async fn checkout(req: HttpRequest, order: web::Path<u64>) -> String {
+///     HookAttributes::set(&req, "order_id", order.to_string());
+///     HookAttributes::set(&req, "job_type", "checkout");
+///     "ok".to_string()
+/// }
+/// ```
+///
+/// With `serde` feature enabled, it is (de)serialized as a map.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct HookAttributes(BTreeMap<String, String>);
+
+impl HookAttributes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `key` with `value` to attributes of `req`, e.g. an `HttpRequest` or `ServiceRequest`, replacing a
+    /// previous value of `key`.
+    pub fn set<R, K, V>(req: &R, key: K, value: V)
+    where
+        R: HttpMessage,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let mut extensions = req.extensions_mut();
+        match extensions.get_mut::<HookAttributes>() {
+            Some(attributes) => {
+                attributes.insert(key, value);
+            }
+            None => {
+                let mut attributes = HookAttributes::new();
+                attributes.insert(key, value);
+                extensions.insert(attributes);
+            }
+        }
+    }
+
+    /// Adds `key` with `value`, returning the previous value of `key`.
+    pub fn insert<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) -> Option<String> {
+        self.0.insert(key.into(), value.into())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// Entries ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for HookAttributes {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self(
+            iter.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
+}
+
+/// Displays as `key=value` pairs ordered by key, separated by spaces, e.g. `job_type=checkout order_id=42`.
+impl fmt::Display for HookAttributes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (key, value)) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Value of [HookResponseAttributes], e.g. a count or whether a cache was hit.
+///
+/// With `serde` feature enabled, it is (de)serialized as a plain JSON value.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+pub enum AttributeValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+impl From<bool> for AttributeValue {
+    fn from(value: bool) -> Self {
+        AttributeValue::Bool(value)
+    }
+}
+
+impl From<i64> for AttributeValue {
+    fn from(value: i64) -> Self {
+        AttributeValue::Int(value)
+    }
+}
+
+impl From<i32> for AttributeValue {
+    fn from(value: i32) -> Self {
+        AttributeValue::Int(value.into())
+    }
+}
+
+impl From<u32> for AttributeValue {
+    fn from(value: u32) -> Self {
+        AttributeValue::Int(value.into())
+    }
+}
+
+impl From<usize> for AttributeValue {
+    fn from(value: usize) -> Self {
+        AttributeValue::Int(value as i64)
+    }
+}
+
+impl From<f64> for AttributeValue {
+    fn from(value: f64) -> Self {
+        AttributeValue::Float(value)
+    }
+}
+
+impl From<String> for AttributeValue {
+    fn from(value: String) -> Self {
+        AttributeValue::String(value)
+    }
+}
+
+impl From<&str> for AttributeValue {
+    fn from(value: &str) -> Self {
+        AttributeValue::String(value.to_string())
+    }
+}
+
+impl fmt::Display for AttributeValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttributeValue::Bool(value) => write!(f, "{}", value),
+            AttributeValue::Int(value) => write!(f, "{}", value),
+            AttributeValue::Float(value) => write!(f, "{}", value),
+            AttributeValue::String(value) => f.write_str(value),
+        }
+    }
+}
+
+/// Typed values the handler derived while answering a request, e.g. the number of database queries it ran or cache
+/// hits, which the hook copies into the `response_attributes` field of its end data, so events carry application level
+/// metrics next to latency and status.
+///
+/// Handlers attach them to the extensions of their response, [HookResponseAttributes::set] merges them with
+/// attributes attached before. Of duplicate keys the last one wins.
+///
+/// ```
+/// use actix_request_hook::attributes::HookResponseAttributes;
+/// use actix_web::HttpResponse;
+///
+/// async fn users() -> HttpResponse {
+///     let mut res = HttpResponse::Ok().body("[]");
+///     HookResponseAttributes::set(&mut res, "db_queries", 3);
+///     HookResponseAttributes::set(&mut res, "cache_hit", false);
+///     res
+/// }
+/// ```
+///
+/// With `serde` feature enabled, it is (de)serialized as a map.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct HookResponseAttributes(BTreeMap<String, AttributeValue>);
+
+impl HookResponseAttributes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `key` with `value` to attributes of `res`, replacing a previous value of `key`.
+    pub fn set<B, K, V>(res: &mut HttpResponse<B>, key: K, value: V)
+    where
+        K: Into<String>,
+        V: Into<AttributeValue>,
+    {
+        let mut extensions = res.extensions_mut();
+        match extensions.get_mut::<HookResponseAttributes>() {
+            Some(attributes) => {
+                attributes.insert(key, value);
+            }
+            None => {
+                let mut attributes = HookResponseAttributes::new();
+                attributes.insert(key, value);
+                extensions.insert(attributes);
+            }
+        }
+    }
+
+    /// Adds `key` with `value`, returning the previous value of `key`.
+    pub fn insert<K: Into<String>, V: Into<AttributeValue>>(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Option<AttributeValue> {
+        self.0.insert(key.into(), value.into())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&AttributeValue> {
+        self.0.get(key)
+    }
+
+    /// Entries ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &AttributeValue)> {
+        self.0.iter().map(|(key, value)| (key.as_str(), value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<K: Into<String>, V: Into<AttributeValue>> FromIterator<(K, V)> for HookResponseAttributes {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self(
+            iter.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
+}
+
+/// Displays as `key=value` pairs ordered by key, separated by spaces, e.g. `cache_hit=false db_queries=3`.
+impl fmt::Display for HookResponseAttributes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (key, value)) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
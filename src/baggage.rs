@@ -0,0 +1,153 @@
+//! [Baggage] of W3C `baggage` headers, business context such as experiment ids or customer tier set at the edge.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::{ready, Ready};
+use std::sync::Arc;
+
+use actix_web::dev::Payload;
+use actix_web::http::header::HeaderMap;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest};
+
+use crate::util::percent_decode;
+
+/// Name of the W3C baggage header.
+pub const BAGGAGE: &str = "baggage";
+
+/// Most entries parsed from a request, further ones are dropped.
+const MAX_ENTRIES: usize = 64;
+
+/// Most bytes of `baggage` headers parsed from a request, longer headers are ignored.
+const MAX_LEN: usize = 8192;
+
+/// Key/value pairs of W3C `baggage` headers of a request, e.g. `experiment=b7,tier=gold`, with values percent
+/// decoded and properties dropped. Events of requests carrying baggage have it in their `baggage` field, so its
+/// context flows into request logs.
+///
+/// Displays as a `baggage` header value, and is an extractor of the baggage a handler should pass on to services it
+/// calls, as allowed by [RequestHook::propagate_baggage](crate::RequestHook::propagate_baggage). Without it,
+/// handlers extract empty baggage.
+///
+/// ```
+/// use actix_request_hook::baggage::Baggage;
+///
+/// let baggage = Baggage::parse("tier=gold;ttl=60, experiment = b7,note=a%20b");
+/// assert_eq!(baggage.get("tier"), Some("gold"));
+/// assert_eq!(baggage.get("note"), Some("a b"));
+/// assert_eq!(baggage.to_string(), "experiment=b7,note=a%20b,tier=gold");
+/// ```
+///
+/// With `serde` feature enabled, it is (de)serialized as a map.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Baggage(Arc<BTreeMap<String, String>>);
+
+impl Baggage {
+    /// Parses a `baggage` header value, skipping malformed entries. Of duplicate keys the last one wins.
+    pub fn parse(header: &str) -> Self {
+        let mut entries = BTreeMap::new();
+        for member in header.split(',').take(MAX_ENTRIES) {
+            let pair = member.split(';').next().unwrap_or_default();
+            let Some((key, value)) = pair.split_once('=') else {
+                continue;
+            };
+            let key = key.trim();
+            if key.is_empty() || !key.bytes().all(is_token) {
+                continue;
+            }
+            entries.insert(key.to_string(), percent_decode(value.trim()));
+        }
+        Self(Arc::new(entries))
+    }
+
+    /// Baggage of all `baggage` headers of a request, unless it has none or they are too long.
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let mut values = headers
+            .get_all(BAGGAGE)
+            .filter_map(|value| value.to_str().ok());
+        let first = values.next()?;
+        let mut header = first.to_string();
+        for value in values {
+            header.push(',');
+            header.push_str(value);
+        }
+        if header.len() > MAX_LEN {
+            return None;
+        }
+        let baggage = Self::parse(&header);
+        (!baggage.is_empty()).then_some(baggage)
+    }
+
+    /// Entries of keys in `allowlist` only.
+    pub(crate) fn allowed(&self, allowlist: &[String]) -> Self {
+        let entries = self
+            .0
+            .iter()
+            .filter(|(key, _)| allowlist.contains(key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        Self(Arc::new(entries))
+    }
+
+    /// Decoded value of `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// Entries ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Display for Baggage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (key, value)) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}=", key)?;
+            for byte in value.bytes() {
+                if is_baggage_octet(byte) && byte != b'%' {
+                    write!(f, "{}", byte as char)?;
+                } else {
+                    write!(f, "%{:02X}", byte)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromRequest for Baggage {
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Ok(req
+            .extensions()
+            .get::<Baggage>()
+            .cloned()
+            .unwrap_or_default()))
+    }
+}
+
+/// Characters of a token, RFC 7230.
+fn is_token(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte)
+}
+
+/// Characters of a value which aren't percent encoded.
+fn is_baggage_octet(byte: u8) -> bool {
+    matches!(byte, 0x21 | 0x23..=0x2B | 0x2D..=0x3A | 0x3C..=0x5B | 0x5D..=0x7E)
+}
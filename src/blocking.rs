@@ -0,0 +1,319 @@
+//! [BlockingObserver]s run on a dedicated thread, off the event loop of the worker.
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::future::ready;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+use actix_web::dev::ServiceRequest;
+use futures_util::future::LocalBoxFuture;
+
+use crate::event::{RequestEvent, RequestStartEvent};
+use crate::observer::{
+    DeadlineExceededData, Observer, RequestCancelledData, RequestEndData, RequestStartData,
+    UpgradeData,
+};
+
+/// Observer doing blocking work, e.g. writing to a file or a database with a synchronous client, which would stall
+/// every request of the worker if it ran on its event loop. Receives owned data, as it runs on another thread.
+///
+/// Register with [RequestHook::register_blocking](crate::RequestHook::register_blocking):
+/// ```
+/// use std::fs::File;
+/// use std::io::Write;
+/// use std::sync::Mutex;
+/// use actix_request_hook::blocking::BlockingObserver;
+/// use actix_request_hook::event::RequestStartEvent;
+/// use actix_request_hook::observer::RequestEndData;
+/// use actix_request_hook::RequestHook;
+///
+/// struct FileLogger(Mutex<File>);
+///
+/// impl BlockingObserver for FileLogger {
+///     fn on_request_started(&self, _event: RequestStartEvent) {}
+///
+///     fn on_request_ended(&self, data: RequestEndData) {
+///         let mut file = self.0.lock().unwrap();
+///         writeln!(file, "{} {} {}", data.method, data.uri, data.status).ok();
+///     }
+/// }
+///
+/// # fn setup() -> std::io::Result<()> {
+/// let logger = FileLogger(Mutex::new(File::create("requests.log")?));
+/// let request_hook = RequestHook::new().register_blocking(logger);
+/// # Ok(())
+/// # }
+/// ```
+pub trait BlockingObserver: Send + Sync {
+    /// Fired on the observer's thread after the request started.
+    fn on_request_started(&self, event: RequestStartEvent);
+
+    /// Fired on the observer's thread after the request ended.
+    fn on_request_ended(&self, data: RequestEndData);
+
+    /// Same as [Observer::on_request_cancelled], fired on the observer's thread.
+    fn on_request_cancelled(&self, _data: RequestCancelledData) {}
+
+    /// Same as [Observer::on_deadline_exceeded], fired on the observer's thread.
+    fn on_deadline_exceeded(&self, _data: DeadlineExceededData) {}
+
+    /// Same as [Observer::on_upgrade], fired on the observer's thread.
+    fn on_upgrade(&self, data: UpgradeData) {
+        self.on_request_ended(data.handshake)
+    }
+
+    /// Fired on the observer's thread once the hook stopped and every pending event was handled, e.g. to flush
+    /// a file.
+    fn on_shutdown(&self) {}
+
+    /// Same as [Observer::capture_body], called on the worker's thread.
+    fn capture_body(&self, _req: &ServiceRequest) -> bool {
+        true
+    }
+
+    /// Same as [Observer::name].
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// Calls the callback of `observer` for `event`.
+pub(crate) fn dispatch<O: BlockingObserver + ?Sized>(observer: &O, event: RequestEvent) {
+    match event {
+        RequestEvent::Started(event) => observer.on_request_started(event),
+        RequestEvent::Ended(data) => observer.on_request_ended(data),
+        RequestEvent::Cancelled(data) => observer.on_request_cancelled(data),
+        RequestEvent::DeadlineExceeded(data) => observer.on_deadline_exceeded(data),
+        RequestEvent::Upgrade(data) => observer.on_upgrade(data),
+    }
+}
+
+/// What [Blocking] does with an event when its queue is full, because the observer can't keep up, e.g. while its
+/// sink is down.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    /// Waits for the observer to make room. No event is lost, but the worker stalls along with the observer.
+    Block,
+    /// Drops the oldest queued event to make room, keeping the most recent events.
+    DropOldest,
+    /// Drops the new event, keeping the queue as it is.
+    DropNewest,
+}
+
+/// [Observer] passing events of a [BlockingObserver] to its own thread, where they are handled one at a time in
+/// the order they happened. The thread starts with the first event, and stops once the hook is dropped and
+/// pending events are handled. On [Observer::on_shutdown], the worker waits for the thread to handle pending
+/// events. If the observer panics, its thread stops and further events are dropped.
+///
+/// Up to 10000 events are queued by default, further events are dropped and counted in [Blocking::dropped].
+///
+/// ```
+/// use std::rc::Rc;
+/// use actix_request_hook::blocking::{Blocking, BlockingObserver, Overflow};
+/// use actix_request_hook::event::RequestStartEvent;
+/// use actix_request_hook::observer::RequestEndData;
+/// use actix_request_hook::RequestHook;
+///
+/// struct AuditLog;
+///
+/// impl BlockingObserver for AuditLog {
+///     fn on_request_started(&self, _event: RequestStartEvent) {}
+///
+///     fn on_request_ended(&self, _data: RequestEndData) {
+///         // synchronous insert into an audit table
+///     }
+/// }
+///
+/// // every request must be audited, even if it slows down requests while the database is slow
+/// let audit = Blocking::new(AuditLog).capacity(1000).overflow(Overflow::Block);
+/// let request_hook = RequestHook::new().register(Rc::new(audit));
+/// ```
+pub struct Blocking<O> {
+    observer: Arc<O>,
+    queue: Arc<Queue>,
+    thread: RefCell<Option<JoinHandle<()>>>,
+    started: Cell<bool>,
+}
+
+struct Queue {
+    events: Mutex<VecDeque<RequestEvent>>,
+    capacity: usize,
+    overflow: Overflow,
+    closed: AtomicBool,
+    dropped: AtomicU64,
+    /// Signals queued events and closing to the observer's thread.
+    queued: Condvar,
+    /// Signals room in the queue, or closing, to a worker blocked by [Overflow::Block].
+    room: Condvar,
+}
+
+impl Queue {
+    fn push(&self, event: RequestEvent) {
+        let mut events = self.events.lock().unwrap();
+        if self.closed.load(Ordering::Relaxed) {
+            return;
+        }
+        if events.len() >= self.capacity {
+            match self.overflow {
+                Overflow::Block => {
+                    events = self
+                        .room
+                        .wait_while(events, |events| {
+                            events.len() >= self.capacity && !self.closed.load(Ordering::Relaxed)
+                        })
+                        .unwrap();
+                    // the observer's thread stopped, nothing takes the event anymore
+                    if self.closed.load(Ordering::Relaxed) {
+                        return;
+                    }
+                }
+                Overflow::DropOldest => {
+                    events.pop_front();
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                Overflow::DropNewest => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+            }
+        }
+        events.push_back(event);
+        self.queued.notify_one();
+    }
+
+    /// Next event, or none once closed and drained.
+    fn pop(&self) -> Option<RequestEvent> {
+        let events = self.events.lock().unwrap();
+        let mut events = self
+            .queued
+            .wait_while(events, |events| {
+                events.is_empty() && !self.closed.load(Ordering::Relaxed)
+            })
+            .unwrap();
+        let event = events.pop_front();
+        self.room.notify_one();
+        event
+    }
+
+    /// Stops the observer's thread once pending events are handled, and releases workers waiting for room.
+    fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+        // lock so waiters either see the flag before waiting, or are woken up
+        let _events = self.events.lock().unwrap();
+        self.queued.notify_one();
+        self.room.notify_all();
+    }
+}
+
+/// Closes the queue once the observer's thread exits, also when the observer panicked.
+struct CloseOnExit(Arc<Queue>);
+
+impl Drop for CloseOnExit {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+impl<O: 'static + BlockingObserver> Blocking<O> {
+    pub fn new(observer: O) -> Self {
+        Self {
+            observer: Arc::new(observer),
+            queue: Arc::new(Queue {
+                events: Mutex::new(VecDeque::new()),
+                capacity: 10_000,
+                overflow: Overflow::DropNewest,
+                closed: AtomicBool::new(false),
+                dropped: AtomicU64::new(0),
+                queued: Condvar::new(),
+                room: Condvar::new(),
+            }),
+            thread: RefCell::new(None),
+            started: Cell::new(false),
+        }
+    }
+
+    /// Maximum number of events waiting for the observer.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.queue_mut().capacity = capacity.max(1);
+        self
+    }
+
+    /// What to do with events once the queue is full, [Overflow::DropNewest] by default.
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.queue_mut().overflow = overflow;
+        self
+    }
+
+    /// Number of events dropped so far because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.queue.dropped.load(Ordering::Relaxed)
+    }
+
+    fn queue_mut(&mut self) -> &mut Queue {
+        Arc::get_mut(&mut self.queue).unwrap()
+    }
+
+    fn send(&self, event: RequestEvent) {
+        if !self.started.replace(true) {
+            let observer = self.observer.clone();
+            let queue = self.queue.clone();
+            let thread = std::thread::Builder::new()
+                .name("request-hook-blocking".to_string())
+                .spawn(move || {
+                    let queue = CloseOnExit(queue);
+                    while let Some(event) = queue.0.pop() {
+                        dispatch(&*observer, event);
+                    }
+                    observer.on_shutdown();
+                })
+                .expect("failed to spawn blocking observer thread");
+            *self.thread.borrow_mut() = Some(thread);
+        }
+        self.queue.push(event);
+    }
+}
+
+impl<O> Drop for Blocking<O> {
+    fn drop(&mut self) {
+        self.queue.close();
+    }
+}
+
+impl<O: 'static + BlockingObserver> Observer for Blocking<O> {
+    fn on_request_started(&self, data: RequestStartData) {
+        self.send(RequestEvent::Started(RequestStartEvent::from(&data)));
+    }
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        self.send(RequestEvent::Ended(data));
+    }
+
+    fn on_request_cancelled(&self, data: RequestCancelledData) {
+        self.send(RequestEvent::Cancelled(data));
+    }
+
+    fn on_deadline_exceeded(&self, data: DeadlineExceededData) {
+        self.send(RequestEvent::DeadlineExceeded(data));
+    }
+
+    fn on_upgrade(&self, data: UpgradeData) {
+        self.send(RequestEvent::Upgrade(data));
+    }
+
+    fn capture_body(&self, req: &ServiceRequest) -> bool {
+        self.observer.capture_body(req)
+    }
+
+    fn name(&self) -> &str {
+        self.observer.name()
+    }
+
+    fn on_shutdown(&self) -> LocalBoxFuture<'static, ()> {
+        self.queue.close();
+        if let Some(thread) = self.thread.borrow_mut().take() {
+            thread.join().ok();
+        }
+        Box::pin(ready(()))
+    }
+}
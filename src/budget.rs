@@ -0,0 +1,224 @@
+//! Service level [Objective]s and their remaining error budget, tracked by [HookStats](crate::stats::HookStats).
+use std::sync::Mutex;
+use std::time::Duration;
+
+use actix_web::http::StatusCode;
+
+use crate::util::path_matches;
+
+/// Windows of [ErrorBudget]s: 1 hour, 6 hours, 24 hours and 30 days.
+pub const ERROR_BUDGET_WINDOWS: [Duration; 4] = [
+    Duration::from_secs(3600),
+    Duration::from_secs(6 * 3600),
+    Duration::from_secs(24 * 3600),
+    Duration::from_secs(30 * 24 * 3600),
+];
+
+/// Which requests are good for an [Objective].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Indicator {
+    /// Requests ending within the threshold are good.
+    Latency(Duration),
+    /// Requests without a 5xx status are good.
+    Availability,
+}
+
+/// Service level objective: share of requests matching a path glob which should be good, e.g. 99.9%.
+///
+/// Paths are matched with `/` separated globs, where `*` matches one segment and `**` any number of segments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Objective {
+    pub name: String,
+    pub path: String,
+    pub indicator: Indicator,
+    /// Share of good requests, e.g. `0.999`.
+    pub target: f64,
+}
+
+impl Objective {
+    /// `target` share of requests matching `path` should end within `threshold`.
+    pub fn latency<P: Into<String>>(path: P, threshold: Duration, target: f64) -> Self {
+        let path = path.into();
+        Self {
+            name: path.clone(),
+            path,
+            indicator: Indicator::Latency(threshold),
+            target,
+        }
+    }
+
+    /// `target` share of requests matching `path` should end without a server error.
+    pub fn availability<P: Into<String>>(path: P, target: f64) -> Self {
+        let path = path.into();
+        Self {
+            name: path.clone(),
+            path,
+            indicator: Indicator::Availability,
+            target,
+        }
+    }
+
+    /// Names the objective in alerts and queries, path glob by default.
+    pub fn named<N: Into<String>>(mut self, name: N) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub(crate) fn is_good(&self, status: StatusCode, elapsed: Duration) -> bool {
+        match self.indicator {
+            Indicator::Latency(threshold) => elapsed <= threshold,
+            Indicator::Availability => !status.is_server_error(),
+        }
+    }
+}
+
+/// Error budget of an objective over one of [ERROR_BUDGET_WINDOWS], from
+/// [HookStats::error_budgets](crate::stats::HookStats::error_budgets).
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ErrorBudget {
+    pub objective: String,
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "window_secs", with = "crate::util::serde_secs")
+    )]
+    pub window: Duration,
+    /// Requests of the objective within the window.
+    pub requests: u64,
+    /// Requests which weren't good.
+    pub bad: u64,
+    /// Share of the budget left, between 1 when no request was bad and 0 when exactly `1 - target` of requests
+    /// were bad, negative once the budget is overspent.
+    pub remaining: f64,
+}
+
+impl ErrorBudget {
+    /// Whether the budget is spent, e.g. to hold off a deploy.
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining <= 0.0
+    }
+}
+
+/// Requests of an objective within one minute or hour.
+#[derive(Clone, Copy, Debug, Default)]
+struct Bucket {
+    period: u64,
+    total: u64,
+    bad: u64,
+}
+
+/// Counts of an objective per minute over a day, and per hour over 30 days.
+#[derive(Debug)]
+struct BudgetState {
+    objective: Objective,
+    minutes: Vec<Bucket>,
+    hours: Vec<Bucket>,
+}
+
+const MINUTES: u64 = 24 * 60;
+const HOURS: u64 = 30 * 24;
+
+impl BudgetState {
+    fn record(&mut self, minute: u64, bad: bool) {
+        record(&mut self.minutes, minute, bad);
+        record(&mut self.hours, minute / 60, bad);
+    }
+
+    fn budget(&self, minute: u64, window: Duration) -> ErrorBudget {
+        let minutes = (window.as_secs() / 60).max(1);
+        let (total, bad) = if minutes <= MINUTES {
+            count(&self.minutes, minute, minutes)
+        } else {
+            count(&self.hours, minute / 60, (minutes / 60).min(HOURS))
+        };
+        let allowed = total as f64 * (1.0 - self.objective.target);
+        let remaining = if bad == 0 {
+            1.0
+        } else if allowed <= 0.0 {
+            0.0
+        } else {
+            1.0 - bad as f64 / allowed
+        };
+        ErrorBudget {
+            objective: self.objective.name.clone(),
+            window,
+            requests: total,
+            bad,
+            remaining,
+        }
+    }
+}
+
+fn record(buckets: &mut [Bucket], period: u64, bad: bool) {
+    let bucket = &mut buckets[(period % buckets.len() as u64) as usize];
+    if bucket.period != period || bucket.total == 0 {
+        *bucket = Bucket {
+            period,
+            ..Bucket::default()
+        };
+    }
+    bucket.total += 1;
+    bucket.bad += bad as u64;
+}
+
+/// Total and bad requests of the last `periods`, including the current one.
+fn count(buckets: &[Bucket], now: u64, periods: u64) -> (u64, u64) {
+    buckets
+        .iter()
+        .filter(|bucket| bucket.total > 0 && now - bucket.period < periods)
+        .fold((0, 0), |(total, bad), bucket| {
+            (total + bucket.total, bad + bucket.bad)
+        })
+}
+
+/// Budget counts of every objective.
+#[derive(Debug, Default)]
+pub(crate) struct ErrorBudgets {
+    objectives: Mutex<Vec<BudgetState>>,
+}
+
+impl ErrorBudgets {
+    pub(crate) fn add(&mut self, objective: Objective) {
+        self.objectives.get_mut().unwrap().push(BudgetState {
+            objective,
+            minutes: vec![Bucket::default(); MINUTES as usize],
+            hours: vec![Bucket::default(); HOURS as usize],
+        });
+    }
+
+    /// Counts a request of `route`, or path, towards every objective it matches.
+    pub(crate) fn record(&self, minute: u64, route: &str, status: StatusCode, elapsed: Duration) {
+        let mut objectives = self.objectives.lock().unwrap();
+        for state in objectives.iter_mut() {
+            if path_matches(&state.objective.path, route) {
+                let bad = !state.objective.is_good(status, elapsed);
+                state.record(minute, bad);
+            }
+        }
+    }
+
+    pub(crate) fn budgets(&self, minute: u64) -> Vec<ErrorBudget> {
+        let objectives = self.objectives.lock().unwrap();
+        objectives
+            .iter()
+            .flat_map(|state| {
+                ERROR_BUDGET_WINDOWS
+                    .iter()
+                    .map(move |window| state.budget(minute, *window))
+            })
+            .collect()
+    }
+
+    pub(crate) fn budget(
+        &self,
+        minute: u64,
+        objective: &str,
+        window: Duration,
+    ) -> Option<ErrorBudget> {
+        let objectives = self.objectives.lock().unwrap();
+        let state = objectives
+            .iter()
+            .find(|state| state.objective.name == objective)?;
+        Some(state.budget(minute, window))
+    }
+}
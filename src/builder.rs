@@ -0,0 +1,593 @@
+//! [RequestHookBuilder], validating configuration of a [RequestHook] up front.
+use std::fmt;
+use std::net::IpAddr;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::dev::ServiceRequest;
+use actix_web::http::header::HeaderName;
+use actix_web::Error;
+use regex::{Regex, RegexSet};
+
+use crate::blocking::BlockingObserver;
+use crate::cache::{CacheRule, CacheSignals};
+use crate::classify::ErrorClassifier;
+use crate::client::{Cidr, GeoResolver};
+use crate::clock::Clock;
+use crate::collector::Collector;
+use crate::consumer::ConsumerResolver;
+use crate::normalize::PathNormalizer;
+use crate::observer::{Observer, OrphanedRequestData, SlowRequestData};
+use crate::render::BodyEncoding;
+use crate::retry::RetryDetector;
+use crate::settings::{HookSettings, UpstreamSampling};
+use crate::shared::SharedRequestHook;
+use crate::stats::HookStats;
+use crate::{RequestHook, MIN_VERBOSE_TOKEN_LEN};
+
+/// Part of the configuration applied to the hook of every worker.
+type Step = Arc<dyn Fn(RequestHook) -> RequestHook + Send + Sync>;
+
+/// Error returned by [RequestHookBuilder::build] for invalid configuration.
+#[derive(Debug)]
+pub enum BuildError {
+    /// Pattern passed to [RequestHookBuilder::exclude_regex] isn't a valid regex.
+    InvalidRegex {
+        pattern: String,
+        error: regex::Error,
+    },
+    /// Sample rate isn't within `0.0..=1.0`.
+    InvalidSampleRate(f64),
+    /// Body size limit of zero, disable body capture with [HookSettings::set_capture_body] instead.
+    InvalidMaxBodySize(usize),
+    /// Name passed to [RequestHookBuilder::deadline_header], [RequestHookBuilder::force_sample_header],
+    /// [RequestHookBuilder::verbose_header] or [RequestHookBuilder::fingerprint] isn't a valid header name.
+    InvalidHeaderName(String),
+    /// Network passed to [RequestHookBuilder::include_ips] or [RequestHookBuilder::exclude_ips] isn't a valid
+    /// [Cidr].
+    InvalidCidr(String),
+    /// Token passed to [RequestHookBuilder::verbose_header] is shorter than [MIN_VERBOSE_TOKEN_LEN] bytes, holds
+    /// its length.
+    ShortVerboseToken(usize),
+    /// Interval passed to [RequestHookBuilder::tick_interval] is zero.
+    InvalidTickInterval,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::InvalidRegex { pattern, error } => {
+                write!(f, "invalid exclude regex `{}`: {}", pattern, error)
+            }
+            BuildError::InvalidSampleRate(rate) => {
+                write!(f, "sample rate {} is not between 0 and 1", rate)
+            }
+            BuildError::InvalidMaxBodySize(size) => {
+                write!(f, "max body size {} must be greater than 0", size)
+            }
+            BuildError::InvalidHeaderName(name) => write!(f, "invalid header name `{}`", name),
+            BuildError::InvalidCidr(cidr) => write!(f, "invalid CIDR `{}`", cidr),
+            BuildError::ShortVerboseToken(len) => write!(
+                f,
+                "verbose header token of {} bytes is shorter than {}",
+                len, MIN_VERBOSE_TOKEN_LEN
+            ),
+            BuildError::InvalidTickInterval => write!(f, "tick interval must be greater than 0"),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BuildError::InvalidRegex { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Builder of hooks which reports invalid configuration from [RequestHookBuilder::build], instead of panicking like
+/// the builder methods of [RequestHook] do, e.g. for patterns and rates read from a config file.
+///
+/// The configuration is `Send`, so [RequestHookBuilder::build] returns a [SharedRequestHook], which can be moved
+/// into the app factory of `HttpServer::new` and builds the hook of every worker from it. Observers registered with
+/// [RequestHookBuilder::register] are shared by all workers, observers bound to a worker's thread are registered
+/// with [RequestHookBuilder::configure].
+///
+/// ```
+/// use std::sync::Arc;
+/// use actix_request_hook::observer::{Observer, RequestEndData, RequestStartData};
+/// use actix_request_hook::RequestHook;
+///
+/// struct RequestLogger;
+///
+/// impl Observer for RequestLogger {
+///     fn on_request_started(&self, _data: RequestStartData) {}
+///
+///     fn on_request_ended(&self, data: RequestEndData) {
+///         println!("ended {}", data.uri)
+///     }
+/// }
+///
+/// # fn setup() -> Result<(), actix_request_hook::builder::BuildError> {
+/// let request_hook = RequestHook::builder()
+///     .exclude_regex("^/\\d+$")
+///     .sample_rate(0.25)
+///     .max_body_size(64 * 1024)
+///     .register(Arc::new(RequestLogger))
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct RequestHookBuilder {
+    steps: Vec<Step>,
+    shared: SharedRequestHook,
+    exclude_regex: Vec<String>,
+    deadline_header: Option<String>,
+    force_sample_header: Option<(String, String)>,
+    verbose_header: Option<(String, String)>,
+    fingerprint: Option<Vec<String>>,
+    include_ips: Vec<String>,
+    exclude_ips: Vec<String>,
+    max_body_size: Option<usize>,
+    sample_rate: Option<f64>,
+    tick_interval: Option<Duration>,
+}
+
+impl Default for RequestHookBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestHookBuilder {
+    pub fn new() -> Self {
+        Self {
+            steps: Vec::new(),
+            shared: SharedRequestHook::new(),
+            exclude_regex: Vec::new(),
+            deadline_header: None,
+            force_sample_header: None,
+            verbose_header: None,
+            fingerprint: None,
+            include_ips: Vec::new(),
+            exclude_ips: Vec::new(),
+            max_body_size: None,
+            sample_rate: None,
+            tick_interval: None,
+        }
+    }
+
+    /// Same as [RequestHook::exclude].
+    pub fn exclude<T: Into<String>>(self, path: T) -> Self {
+        let path = path.into();
+        self.map(move |hook| hook.exclude(path.clone()))
+    }
+
+    /// Same as [RequestHook::exclude_all].
+    pub fn exclude_all<I: IntoIterator<Item = T>, T: Into<String>>(self, paths: I) -> Self {
+        let paths: Vec<String> = paths.into_iter().map(Into::into).collect();
+        self.map(move |hook| hook.exclude_all(paths.clone()))
+    }
+
+    /// Same as [RequestHook::exclude_regex], validated on build.
+    pub fn exclude_regex<T: Into<String>>(mut self, path: T) -> Self {
+        self.exclude_regex.push(path.into());
+        self
+    }
+
+    /// Same as [RequestHook::exclusion_cache_capacity].
+    pub fn exclusion_cache_capacity(self, capacity: usize) -> Self {
+        self.map(move |hook| hook.exclusion_cache_capacity(capacity))
+    }
+
+    /// Same as [SharedRequestHook::register], an observer shared by all workers.
+    pub fn register<T: 'static + Observer + Send + Sync>(mut self, observer: Arc<T>) -> Self {
+        self.shared = self.shared.register(observer);
+        self
+    }
+
+    /// Same as [SharedRequestHook::register_all].
+    pub fn register_all<I: IntoIterator<Item = Arc<dyn Observer + Send + Sync>>>(
+        mut self,
+        observers: I,
+    ) -> Self {
+        self.shared = self.shared.register_all(observers);
+        self
+    }
+
+    /// Registers a [BlockingObserver] notified of events of all workers on a thread of its own, through a
+    /// [Collector].
+    pub fn register_blocking<T: 'static + BlockingObserver>(self, observer: T) -> Self {
+        let collector = Collector::new().register(observer);
+        self.map(move |hook| hook.register(Rc::new(collector.observer())))
+    }
+
+    /// Sets up the hook of every worker with `configure`, after the configuration preceding it, e.g. to register
+    /// observers bound to a worker's thread.
+    pub fn configure<F: 'static + Fn(RequestHook) -> RequestHook + Send + Sync>(
+        self,
+        configure: F,
+    ) -> Self {
+        self.map(configure)
+    }
+
+    /// Same as [RequestHook::on_slow_request].
+    pub fn on_slow_request<F: 'static + Fn(&SlowRequestData) + Send + Sync>(
+        self,
+        threshold: Duration,
+        callback: F,
+    ) -> Self {
+        let callback = Arc::new(callback);
+        self.map(move |hook| {
+            let callback = callback.clone();
+            hook.on_slow_request(threshold, move |data| callback(data))
+        })
+    }
+
+    /// Same as [RequestHook::on_orphaned_request].
+    pub fn on_orphaned_request<F: 'static + Fn(&OrphanedRequestData) + Send + Sync>(
+        self,
+        max_age: Duration,
+        callback: F,
+    ) -> Self {
+        let callback = Arc::new(callback);
+        self.map(move |hook| {
+            let callback = callback.clone();
+            hook.on_orphaned_request(max_age, move |data| callback(data))
+        })
+    }
+
+    /// Same as [RequestHook::soft_timeout].
+    pub fn soft_timeout(self, timeout: Duration) -> Self {
+        self.map(move |hook| hook.soft_timeout(timeout))
+    }
+
+    /// Same as [RequestHook::deadline_header], validated on build.
+    pub fn deadline_header<T: Into<String>>(mut self, name: T) -> Self {
+        self.deadline_header = Some(name.into());
+        self
+    }
+
+    /// Same as [RequestHook::include_ips], validated on build.
+    pub fn include_ips<I: IntoIterator<Item = T>, T: Into<String>>(mut self, cidrs: I) -> Self {
+        self.include_ips.extend(cidrs.into_iter().map(Into::into));
+        self
+    }
+
+    /// Same as [RequestHook::exclude_ips], validated on build.
+    pub fn exclude_ips<I: IntoIterator<Item = T>, T: Into<String>>(mut self, cidrs: I) -> Self {
+        self.exclude_ips.extend(cidrs.into_iter().map(Into::into));
+        self
+    }
+
+    /// Same as [RequestHook::trust_forwarded_headers].
+    pub fn trust_forwarded_headers(self) -> Self {
+        self.map(RequestHook::trust_forwarded_headers)
+    }
+
+    /// Same as [RequestHook::geo_resolver], one resolver shared by all workers.
+    pub fn geo_resolver<R: 'static + GeoResolver + Send + Sync>(self, resolver: R) -> Self {
+        let resolver = Arc::new(resolver);
+        self.map(move |hook| {
+            let resolver = resolver.clone();
+            hook.geo_resolver(move |ip: IpAddr| resolver.resolve(ip))
+        })
+    }
+
+    /// Same as [RequestHook::consumer_resolver], one resolver shared by all workers.
+    pub fn consumer_resolver<R: 'static + ConsumerResolver + Send + Sync>(
+        self,
+        resolver: R,
+    ) -> Self {
+        let resolver = Arc::new(resolver);
+        self.map(move |hook| {
+            let resolver = resolver.clone();
+            hook.consumer_resolver(move |req: &ServiceRequest| resolver.resolve(req))
+        })
+    }
+
+    /// Same as [RequestHook::quota_ledger].
+    #[cfg(feature = "quota")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "quota")))]
+    pub fn quota_ledger(self, ledger: crate::quota::QuotaLedger) -> Self {
+        self.map(move |hook| hook.quota_ledger(ledger.clone()))
+    }
+
+    /// Same as [RequestHook::fingerprint], validated on build.
+    pub fn fingerprint<I: IntoIterator<Item = T>, T: Into<String>>(mut self, headers: I) -> Self {
+        self.fingerprint = Some(headers.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Same as [RequestHook::force_sample_header], validated on build.
+    pub fn force_sample_header<T: Into<String>, S: Into<String>>(
+        mut self,
+        name: T,
+        secret: S,
+    ) -> Self {
+        self.force_sample_header = Some((name.into(), secret.into()));
+        self
+    }
+
+    /// Same as [RequestHook::verbose_header], validated on build.
+    pub fn verbose_header<T: Into<String>, S: Into<String>>(mut self, name: T, token: S) -> Self {
+        self.verbose_header = Some((name.into(), token.into()));
+        self
+    }
+
+    /// Same as [RequestHook::propagate_baggage].
+    pub fn propagate_baggage<I: IntoIterator<Item = T>, T: Into<String>>(
+        self,
+        allowlist: I,
+    ) -> Self {
+        let allowlist: Vec<String> = allowlist.into_iter().map(Into::into).collect();
+        self.map(move |hook| hook.propagate_baggage(allowlist.clone()))
+    }
+
+    /// Same as [RequestHook::upstream_sampling].
+    pub fn upstream_sampling(self, upstream_sampling: UpstreamSampling) -> Self {
+        self.map(move |hook| hook.upstream_sampling(upstream_sampling))
+    }
+
+    /// Same as [RequestHook::error_classifier], one classifier shared by all workers.
+    pub fn error_classifier<C: 'static + ErrorClassifier + Send + Sync>(
+        self,
+        classifier: C,
+    ) -> Self {
+        let classifier = Arc::new(classifier);
+        self.map(move |hook| {
+            let classifier = classifier.clone();
+            hook.error_classifier(move |error: &Error| classifier.classify(error))
+        })
+    }
+
+    /// Same as [RequestHook::cache_rule], one rule shared by all workers.
+    pub fn cache_rule<R: 'static + CacheRule + Send + Sync>(self, rule: R) -> Self {
+        let rule = Arc::new(rule);
+        self.map(move |hook| {
+            let rule = rule.clone();
+            hook.cache_rule(move |signals: &CacheSignals| rule.hit(signals))
+        })
+    }
+
+    /// Same as [RequestHook::detect_retries].
+    pub fn detect_retries(self, detector: RetryDetector) -> Self {
+        self.map(move |hook| hook.detect_retries(detector.clone()))
+    }
+
+    /// Same as [RequestHook::openapi].
+    #[cfg(feature = "openapi")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "openapi")))]
+    pub fn openapi(self, api: crate::openapi::OpenApi) -> Self {
+        self.map(move |hook| hook.openapi(api.clone()))
+    }
+
+    /// Same as [RequestHook::render_bodies].
+    pub fn render_bodies(self, encoding: BodyEncoding) -> Self {
+        self.map(move |hook| hook.render_bodies(encoding))
+    }
+
+    /// Same as [RequestHook::deprecate_route].
+    pub fn deprecate_route<T: Into<String>>(self, route: T) -> Self {
+        let route = route.into();
+        self.map(move |hook| hook.deprecate_route(route.clone()))
+    }
+
+    /// Same as [RequestHook::instance_id].
+    pub fn instance_id<T: Into<String>>(self, id: T) -> Self {
+        let id = id.into();
+        self.map(move |hook| hook.instance_id(id.clone()))
+    }
+
+    /// Same as [RequestHook::instance_id_from_env].
+    pub fn instance_id_from_env(self) -> Self {
+        self.map(RequestHook::instance_id_from_env)
+    }
+
+    /// Same as [RequestHook::tick_interval], which must be greater than 0. Shared observers are ticked once for all
+    /// workers, see [SharedRequestHook::tick_interval].
+    pub fn tick_interval(mut self, interval: Duration) -> Self {
+        self.tick_interval = Some(interval);
+        self
+    }
+
+    /// Same as [RequestHook::tee_body].
+    pub fn tee_body(self) -> Self {
+        self.map(RequestHook::tee_body)
+    }
+
+    /// Same as [RequestHook::max_body_size], which must be greater than 0.
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = Some(max_body_size);
+        self
+    }
+
+    /// Same as [RequestHook::clock], one clock shared by all workers.
+    pub fn clock<C: 'static + Clock>(self, clock: C) -> Self {
+        let clock: Arc<dyn Clock> = Arc::new(clock);
+        self.map(move |mut hook| {
+            Rc::get_mut(&mut hook.0).unwrap().clock = clock.clone();
+            hook
+        })
+    }
+
+    /// Same as [RequestHook::normalize_paths].
+    pub fn normalize_paths(self, normalizer: PathNormalizer) -> Self {
+        self.map(move |hook| hook.normalize_paths(normalizer.clone()))
+    }
+
+    /// Same as [SharedRequestHook::with_stats].
+    pub fn with_stats(mut self, stats: HookStats) -> Self {
+        self.shared = self.shared.with_stats(stats);
+        self
+    }
+
+    /// Same as [SharedRequestHook::with_settings].
+    pub fn with_settings(mut self, settings: HookSettings) -> Self {
+        self.shared = self.shared.with_settings(settings);
+        self
+    }
+
+    /// Same as [RequestHook::decompress_body].
+    pub fn decompress_body(self) -> Self {
+        self.map(RequestHook::decompress_body)
+    }
+
+    /// Same as [RequestHook::track_upgrades].
+    pub fn track_upgrades(self) -> Self {
+        self.map(RequestHook::track_upgrades)
+    }
+
+    /// Same as [RequestHook::defer_end].
+    pub fn defer_end<T: Into<String>>(self, content_type: T) -> Self {
+        let content_type = content_type.into();
+        self.map(move |hook| hook.defer_end(content_type.clone()))
+    }
+
+    /// Same as [RequestHook::enabled].
+    pub fn enabled(self, enabled: Arc<AtomicBool>) -> Self {
+        self.map(move |hook| hook.enabled(enabled.clone()))
+    }
+
+    /// Same as [RequestHook::app_data], cloned into the hook of every worker.
+    pub fn app_data<T: 'static + Clone + Send + Sync>(self, data: T) -> Self {
+        self.map(move |hook| hook.app_data(data.clone()))
+    }
+
+    /// Same as [RequestHook::expose_stats].
+    pub fn expose_stats(self) -> Self {
+        self.map(RequestHook::expose_stats)
+    }
+
+    /// Initial sample rate of the hooks' [HookSettings], which must be within `0.0..=1.0`.
+    pub fn sample_rate(mut self, sample_rate: f64) -> Self {
+        self.sample_rate = Some(sample_rate);
+        self
+    }
+
+    /// Same as [SharedRequestHook::event_sender].
+    #[cfg(feature = "events")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "events")))]
+    pub fn event_sender(
+        mut self,
+        sender: tokio::sync::broadcast::Sender<crate::event::RequestEvent>,
+    ) -> Self {
+        self.shared = self.shared.event_sender(sender);
+        self
+    }
+
+    /// Validates the configuration, and builds the [SharedRequestHook] building the hook of every worker.
+    pub fn build(self) -> Result<SharedRequestHook, BuildError> {
+        if let Some((pattern, error)) = self
+            .exclude_regex
+            .iter()
+            .find_map(|pattern| Regex::new(pattern).err().map(|error| (pattern, error)))
+        {
+            return Err(BuildError::InvalidRegex {
+                pattern: pattern.clone(),
+                error,
+            });
+        }
+        let deadline_header = self
+            .deadline_header
+            .map(|name| {
+                HeaderName::from_str(&name).map_err(|_| BuildError::InvalidHeaderName(name))
+            })
+            .transpose()?;
+        let force_sample_header = self
+            .force_sample_header
+            .map(|(name, secret)| {
+                HeaderName::from_str(&name)
+                    .map(|name| (name, secret))
+                    .map_err(|_| BuildError::InvalidHeaderName(name))
+            })
+            .transpose()?;
+        let verbose_header = match self.verbose_header {
+            Some((name, token)) => {
+                let name =
+                    HeaderName::from_str(&name).map_err(|_| BuildError::InvalidHeaderName(name))?;
+                if token.len() < MIN_VERBOSE_TOKEN_LEN {
+                    return Err(BuildError::ShortVerboseToken(token.len()));
+                }
+                Some((name, token))
+            }
+            None => None,
+        };
+        let fingerprint = self
+            .fingerprint
+            .map(|headers| {
+                headers
+                    .into_iter()
+                    .map(|name| {
+                        HeaderName::from_str(&name).map_err(|_| BuildError::InvalidHeaderName(name))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+        let parse_cidrs = |cidrs: Vec<String>| {
+            cidrs
+                .into_iter()
+                .map(|cidr| Cidr::from_str(&cidr).map_err(|_| BuildError::InvalidCidr(cidr)))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let include_ips = parse_cidrs(self.include_ips)?;
+        let exclude_ips = parse_cidrs(self.exclude_ips)?;
+        if self.max_body_size == Some(0) {
+            return Err(BuildError::InvalidMaxBodySize(0));
+        }
+        let mut shared = self.shared;
+        if let Some(sample_rate) = self.sample_rate {
+            if !(0.0..=1.0).contains(&sample_rate) {
+                return Err(BuildError::InvalidSampleRate(sample_rate));
+            }
+            shared.settings().set_sample_rate(sample_rate);
+        }
+        let mut steps = self.steps;
+        if let Some(interval) = self.tick_interval {
+            if interval.is_zero() {
+                return Err(BuildError::InvalidTickInterval);
+            }
+            shared = shared.tick_interval(interval);
+            steps.push(Arc::new(move |hook| hook.tick_interval(interval)));
+        }
+        let exclude_regex = self.exclude_regex;
+        let max_body_size = self.max_body_size;
+        steps.push(Arc::new(move |mut hook| {
+            let inner = Rc::get_mut(&mut hook.0).unwrap();
+            if !exclude_regex.is_empty() {
+                let mut patterns = inner.exclude_regex.patterns().to_vec();
+                patterns.extend(exclude_regex.iter().cloned());
+                // every pattern is valid on its own, so the set is too
+                inner.exclude_regex = RegexSet::new(&patterns).unwrap();
+            }
+            if let Some(name) = &deadline_header {
+                inner.deadline_header = Some(name.clone());
+            }
+            if let Some(header) = &force_sample_header {
+                inner.force_sample_header = Some(header.clone());
+            }
+            if let Some(header) = &verbose_header {
+                inner.verbose_header = Some(header.clone());
+            }
+            if let Some(headers) = &fingerprint {
+                inner.fingerprint = Some(headers.clone());
+            }
+            inner.ip_lists.include.extend(include_ips.iter().cloned());
+            inner.ip_lists.exclude.extend(exclude_ips.iter().cloned());
+            if let Some(max_body_size) = max_body_size {
+                inner.max_body_size = max_body_size;
+            }
+            hook
+        }));
+        Ok(shared.configure(move |hook| steps.iter().fold(hook, |hook, step| step(hook))))
+    }
+
+    fn map<F: 'static + Fn(RequestHook) -> RequestHook + Send + Sync>(mut self, f: F) -> Self {
+        self.steps.push(Arc::new(f));
+        self
+    }
+}
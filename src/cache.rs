@@ -0,0 +1,115 @@
+//! [CacheSignals] of responses, and the [CacheRule] telling cache hits from misses.
+use std::sync::Arc;
+
+use actix_web::http::header::{HeaderMap, AGE, CACHE_CONTROL, ETAG};
+
+/// Name of the header caches and CDNs report hits and misses with, e.g. `HIT from varnish`.
+pub const X_CACHE: &str = "x-cache";
+
+/// Cache related headers of a response, in the `cache` field of end data of responses with any of them, so hit
+/// ratios can be reported per route.
+///
+/// # Properties
+///
+/// * `cache_control` - `Cache-Control` header, e.g. `public, max-age=60`.
+/// * `etag` - `ETag` header.
+/// * `age` - seconds of the `Age` header, how long a cache held the response.
+/// * `x_cache` - `X-Cache` header, e.g. `HIT` or `MISS`.
+/// * `hit` - whether the response was a cache hit, if the [CacheRule] could tell.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CacheSignals {
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub cache_control: Option<Arc<str>>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub etag: Option<Arc<str>>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub age: Option<u64>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub x_cache: Option<Arc<str>>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub hit: Option<bool>,
+}
+
+impl CacheSignals {
+    /// Signals of response `headers`, unless it has none of them.
+    pub(crate) fn from_headers(headers: &HeaderMap, rule: Option<&dyn CacheRule>) -> Option<Self> {
+        let text = |name| -> Option<Arc<str>> { Some(headers.get(name)?.to_str().ok()?.into()) };
+        let mut signals = Self {
+            cache_control: text(CACHE_CONTROL),
+            etag: text(ETAG),
+            age: headers
+                .get(AGE)
+                .and_then(|age| age.to_str().ok()?.trim().parse().ok()),
+            x_cache: headers
+                .get(X_CACHE)
+                .and_then(|value| Some(value.to_str().ok()?.into())),
+            hit: None,
+        };
+        if signals == Self::default() {
+            return None;
+        }
+        signals.hit = match rule {
+            Some(rule) => rule.hit(&signals),
+            None => signals.default_hit(),
+        };
+        Some(signals)
+    }
+
+    /// Rule used unless [RequestHook::cache_rule](crate::RequestHook::cache_rule) sets another: a hit if `X-Cache`
+    /// starts with `HIT`, a miss if it starts with `MISS`, ignoring case. Without `X-Cache`, a positive `Age` is a
+    /// hit, as a cache held the response.
+    pub fn default_hit(&self) -> Option<bool> {
+        if let Some(x_cache) = &self.x_cache {
+            let x_cache = x_cache.trim().to_ascii_uppercase();
+            if x_cache.starts_with("HIT") {
+                return Some(true);
+            }
+            if x_cache.starts_with("MISS") {
+                return Some(false);
+            }
+        }
+        self.age.filter(|age| *age > 0).map(|_| true)
+    }
+}
+
+/// Tells whether a response was a cache hit from its [CacheSignals], for caches reporting hits their own way.
+/// Register one with [RequestHook::cache_rule](crate::RequestHook::cache_rule).
+///
+/// Implemented for closures:
+///
+/// ```
+/// use actix_request_hook::cache::CacheSignals;
+/// use actix_request_hook::RequestHook;
+///
+/// // e.g. a CDN answering `X-Cache: TCP_HIT` or `TCP_MISS`
+/// let request_hook = RequestHook::new().cache_rule(|signals: &CacheSignals| {
+///     let x_cache = signals.x_cache.as_deref()?;
+///     Some(x_cache.contains("HIT"))
+/// });
+/// ```
+pub trait CacheRule {
+    /// Whether the response was a cache hit, or none if it can't tell.
+    fn hit(&self, signals: &CacheSignals) -> Option<bool>;
+}
+
+impl<F: Fn(&CacheSignals) -> Option<bool>> CacheRule for F {
+    fn hit(&self, signals: &CacheSignals) -> Option<bool> {
+        self(signals)
+    }
+}
@@ -0,0 +1,225 @@
+//! Circuit breaker signals per route, tracked by [HookStats](crate::stats::HookStats).
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use actix_web::http::StatusCode;
+
+pub(crate) type StateChangeFn = Box<dyn Fn(&CircuitStateChange) + Send + Sync>;
+
+/// When a route's circuit opens, and for how long, see [HookStats::circuit_breaker](crate::stats::HookStats::circuit_breaker).
+#[derive(Clone, Debug, PartialEq)]
+pub struct CircuitBreakerConfig {
+    /// Server errors in a row which open the circuit.
+    pub consecutive_failures: u32,
+    /// Share of server errors within `window` which opens the circuit, between 0 and 1.
+    pub error_rate: f64,
+    /// Minimum number of requests within `window` before `error_rate` is considered.
+    pub min_requests: u64,
+    /// Window of `error_rate`, counts restart when it passes.
+    pub window: Duration,
+    /// How long the circuit stays open before the next request probes the route.
+    pub open_for: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    /// Opens after 5 server errors in a row, or at least half of at least 20 requests within 10 seconds failing,
+    /// for 30 seconds.
+    fn default() -> Self {
+        Self {
+            consecutive_failures: 5,
+            error_rate: 0.5,
+            min_requests: 20,
+            window: Duration::from_secs(10),
+            open_for: Duration::from_secs(30),
+        }
+    }
+}
+
+/// State of a route's circuit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Route is healthy.
+    Closed,
+    /// Route is failing persistently, shed its load.
+    Open,
+    /// Open long enough, the next request probes whether the route recovered.
+    HalfOpen,
+}
+
+/// Passed to [HookStats::on_circuit_state_change](crate::stats::HookStats::on_circuit_state_change).
+#[derive(Clone, Debug, PartialEq)]
+pub struct CircuitStateChange {
+    pub method: String,
+    /// Matched route pattern, or normalized path if no route matched.
+    pub route: String,
+    pub from: CircuitState,
+    pub to: CircuitState,
+    /// Server errors in a row when the state changed.
+    pub consecutive_failures: u32,
+    /// Share of server errors within the current window when the state changed.
+    pub error_rate: f64,
+}
+
+#[derive(Debug)]
+struct Circuit {
+    open: bool,
+    opened_at: Instant,
+    consecutive_failures: u32,
+    window_start: Instant,
+    requests: u64,
+    errors: u64,
+}
+
+impl Circuit {
+    fn state(&self, config: &CircuitBreakerConfig, now: Instant) -> CircuitState {
+        if !self.open {
+            CircuitState::Closed
+        } else if now.duration_since(self.opened_at) >= config.open_for {
+            CircuitState::HalfOpen
+        } else {
+            CircuitState::Open
+        }
+    }
+
+    fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.requests as f64
+        }
+    }
+}
+
+/// Circuits of routes, bounded by `max_routes`.
+pub(crate) struct Circuits {
+    pub(crate) config: CircuitBreakerConfig,
+    pub(crate) on_state_change: Option<StateChangeFn>,
+    circuits: Mutex<HashMap<(String, String), Circuit>>,
+}
+
+impl std::fmt::Debug for Circuits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Circuits")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl Circuits {
+    pub(crate) fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            on_state_change: None,
+            circuits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn state(&self, method: &str, route: &str, now: Instant) -> CircuitState {
+        let key = (method.to_string(), route.to_string());
+        let circuits = self.circuits.lock().unwrap();
+        circuits.get(&key).map_or(CircuitState::Closed, |circuit| {
+            circuit.state(&self.config, now)
+        })
+    }
+
+    /// Method and route pairs with a circuit which isn't closed, and their state.
+    pub(crate) fn not_closed(&self, now: Instant) -> Vec<(String, String, CircuitState)> {
+        let circuits = self.circuits.lock().unwrap();
+        let mut not_closed: Vec<_> = circuits
+            .iter()
+            .filter(|(_, circuit)| circuit.open)
+            .map(|((method, route), circuit)| {
+                (
+                    method.clone(),
+                    route.clone(),
+                    circuit.state(&self.config, now),
+                )
+            })
+            .collect();
+        not_closed.sort_by(|a, b| (&a.1, &a.0).cmp(&(&b.1, &b.0)));
+        not_closed
+    }
+
+    pub(crate) fn record(
+        &self,
+        method: &str,
+        route: &str,
+        status: StatusCode,
+        max_routes: usize,
+        now: Instant,
+    ) {
+        let config = &self.config;
+        let failed = status.is_server_error();
+        let change = {
+            let mut circuits = self.circuits.lock().unwrap();
+            let key = (method.to_string(), route.to_string());
+            if circuits.len() >= max_routes && !circuits.contains_key(&key) {
+                return;
+            }
+            let circuit = circuits.entry(key).or_insert_with(|| Circuit {
+                open: false,
+                opened_at: now,
+                consecutive_failures: 0,
+                window_start: now,
+                requests: 0,
+                errors: 0,
+            });
+            if now.duration_since(circuit.window_start) >= config.window {
+                circuit.window_start = now;
+                circuit.requests = 0;
+                circuit.errors = 0;
+            }
+            circuit.requests += 1;
+            circuit.errors += failed as u64;
+            circuit.consecutive_failures = if failed {
+                circuit.consecutive_failures + 1
+            } else {
+                0
+            };
+
+            let from = circuit.state(config, now);
+            let to = match from {
+                CircuitState::Closed => {
+                    let tripped = circuit.consecutive_failures >= config.consecutive_failures
+                        || (circuit.requests >= config.min_requests
+                            && circuit.error_rate() >= config.error_rate);
+                    if tripped {
+                        CircuitState::Open
+                    } else {
+                        CircuitState::Closed
+                    }
+                }
+                // a request ending while open started before the circuit opened
+                CircuitState::Open => CircuitState::Open,
+                CircuitState::HalfOpen if failed => CircuitState::Open,
+                CircuitState::HalfOpen => CircuitState::Closed,
+            };
+            match to {
+                CircuitState::Open if from != CircuitState::Open => {
+                    circuit.open = true;
+                    circuit.opened_at = now;
+                }
+                CircuitState::Closed if from != CircuitState::Closed => {
+                    circuit.open = false;
+                    circuit.window_start = now;
+                    circuit.requests = 0;
+                    circuit.errors = 0;
+                }
+                _ => {}
+            }
+            // half open to open again is a change too, the route failed its probe
+            (from != to).then(|| CircuitStateChange {
+                method: method.to_string(),
+                route: route.to_string(),
+                from,
+                to,
+                consecutive_failures: circuit.consecutive_failures,
+                error_rate: circuit.error_rate(),
+            })
+        };
+        if let (Some(change), Some(callback)) = (change, &self.on_state_change) {
+            callback(&change);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! [ErrorClassifier], categorizing errors of failed requests for observers.
+use std::sync::Arc;
+
+use actix_web::Error;
+
+/// Categorizes the error a request failed with, e.g. `db_timeout` or `validation`, so observers receive a meaningful
+/// [RequestEndData::error_category](crate::RequestEndData::error_category) instead of just a status. Register one
+/// with [RequestHook::error_classifier](crate::RequestHook::error_classifier). It sees errors returned by handlers
+/// and middleware, including those turned into responses, and panics of handlers as an internal server error.
+///
+/// Implemented for closures returning an optional category:
+///
+/// ```
+/// use actix_web::error::{JsonPayloadError, QueryPayloadError};
+/// use actix_request_hook::RequestHook;
+///
+/// let request_hook = RequestHook::new().error_classifier(|err: &actix_web::Error| {
+///     if err.as_error::<JsonPayloadError>().is_some() || err.as_error::<QueryPayloadError>().is_some() {
+///         Some("validation")
+///     } else {
+///         None
+///     }
+/// });
+/// ```
+pub trait ErrorClassifier {
+    /// Category of `error`, or none to leave it uncategorized.
+    fn classify(&self, error: &Error) -> Option<Arc<str>>;
+}
+
+impl<F, T> ErrorClassifier for F
+where
+    F: Fn(&Error) -> Option<T>,
+    T: Into<Arc<str>>,
+{
+    fn classify(&self, error: &Error) -> Option<Arc<str>> {
+        self(error).map(Into::into)
+    }
+}
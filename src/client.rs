@@ -0,0 +1,258 @@
+//! Client IP of requests: [Cidr] networks observed or excluded, and [ClientGeo] enrichment by a [GeoResolver].
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use actix_web::dev::ServiceRequest;
+
+/// Network of IP addresses in CIDR notation, e.g. `10.0.0.0/8` or `2001:db8::/32`. A plain address is a network
+/// of its own, e.g. `192.0.2.1` is `192.0.2.1/32`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+/// Error of parsing a [Cidr], holds the invalid input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidCidr(pub String);
+
+impl fmt::Display for InvalidCidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid CIDR `{}`", self.0)
+    }
+}
+
+impl std::error::Error for InvalidCidr {}
+
+impl FromStr for Cidr {
+    type Err = InvalidCidr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidCidr(s.to_string());
+        let (addr, prefix) = match s.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s.trim(), None),
+        };
+        let addr = IpAddr::from_str(addr).map_err(|_| invalid())?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse()
+                .ok()
+                .filter(|prefix| *prefix <= max)
+                .ok_or_else(invalid)?,
+            None => max,
+        };
+        Ok(Self { addr, prefix })
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+impl Cidr {
+    /// Whether `ip` is within the network. IPv4 addresses mapped to IPv6, `::ffff:a.b.c.d`, are matched as IPv4.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            ip => ip,
+        };
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => prefix_eq(
+                u32::from(net) as u128,
+                u32::from(ip) as u128,
+                32,
+                self.prefix,
+            ),
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_eq(u128::from(net), u128::from(ip), 128, self.prefix)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Whether the first `prefix` of `bits` bits of `a` and `b` are equal.
+fn prefix_eq(a: u128, b: u128, bits: u8, prefix: u8) -> bool {
+    let shift = bits - prefix;
+    shift >= 128 || a >> shift == b >> shift
+}
+
+/// Networks whose requests are observed or excluded, see [RequestHook::include_ips](crate::RequestHook::include_ips)
+/// and [RequestHook::exclude_ips](crate::RequestHook::exclude_ips).
+#[derive(Clone, Debug, Default)]
+pub(crate) struct IpLists {
+    pub(crate) include: Vec<Cidr>,
+    pub(crate) exclude: Vec<Cidr>,
+}
+
+impl IpLists {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Whether requests of `ip` are excluded from observation. Requests of an unknown IP are only excluded if some
+    /// networks are included.
+    pub(crate) fn excludes(&self, ip: Option<IpAddr>) -> bool {
+        match ip {
+            Some(ip) => {
+                self.exclude.iter().any(|cidr| cidr.contains(ip))
+                    || (!self.include.is_empty()
+                        && !self.include.iter().any(|cidr| cidr.contains(ip)))
+            }
+            None => !self.include.is_empty(),
+        }
+    }
+}
+
+/// IP address of the client of `req`, from `Forwarded` or `X-Forwarded-For` headers if `trust_forwarded`, else of
+/// the peer.
+pub(crate) fn client_ip(req: &ServiceRequest, trust_forwarded: bool) -> Option<IpAddr> {
+    if !trust_forwarded {
+        return req.peer_addr().map(|addr| addr.ip());
+    }
+    let info = req.connection_info();
+    let addr = info.realip_remote_addr()?;
+    // `192.0.2.1`, `192.0.2.1:80`, `[2001:db8::1]:80` or `[2001:db8::1]`
+    IpAddr::from_str(addr)
+        .or_else(|_| SocketAddr::from_str(addr).map(|addr| addr.ip()))
+        .or_else(|_| IpAddr::from_str(addr.trim_start_matches('[').trim_end_matches(']')))
+        .ok()
+}
+
+/// Client IP of a request with its country and autonomous system, resolved by a [GeoResolver], in the `geo` field
+/// of events, e.g. for security focused logging.
+///
+/// # Properties
+///
+/// * `ip` - IP address of the client.
+/// * `country` - ISO 3166-1 alpha-2 code of the country, e.g. `DE`.
+/// * `asn` - number of the autonomous system, e.g. `15169`.
+/// * `as_org` - organization of the autonomous system, e.g. `GOOGLE`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClientGeo {
+    pub ip: IpAddr,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub country: Option<Arc<str>>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub asn: Option<u32>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub as_org: Option<Arc<str>>,
+}
+
+impl ClientGeo {
+    /// Client `ip` without country or autonomous system, for resolvers to fill in.
+    pub fn new(ip: IpAddr) -> Self {
+        Self {
+            ip,
+            country: None,
+            asn: None,
+            as_org: None,
+        }
+    }
+}
+
+/// Resolves client IPs to [ClientGeo], register one with
+/// [RequestHook::geo_resolver](crate::RequestHook::geo_resolver). With `geoip` feature enabled, [MaxMindResolver]
+/// looks them up in MaxMind databases. Implemented for closures:
+///
+/// ```
+/// use actix_request_hook::client::ClientGeo;
+/// use actix_request_hook::RequestHook;
+///
+/// let request_hook = RequestHook::new().geo_resolver(|ip| {
+///     let mut geo = ClientGeo::new(ip);
+///     geo.country = ip.is_loopback().then(|| "ZZ".into());
+///     Some(geo)
+/// });
+/// ```
+pub trait GeoResolver {
+    /// Country and autonomous system of `ip`, or none to leave events without `geo`.
+    fn resolve(&self, ip: IpAddr) -> Option<ClientGeo>;
+}
+
+impl<F: Fn(IpAddr) -> Option<ClientGeo>> GeoResolver for F {
+    fn resolve(&self, ip: IpAddr) -> Option<ClientGeo> {
+        self(ip)
+    }
+}
+
+/// [GeoResolver] looking up countries and autonomous systems in MaxMind databases the application opened, e.g.
+/// GeoLite2 Country and ASN. Addresses not found in a database are left without its fields.
+///
+/// ```no_run
+/// use actix_request_hook::client::MaxMindResolver;
+/// use actix_request_hook::RequestHook;
+/// use maxminddb::Reader;
+///
+/// let resolver = MaxMindResolver::new()
+///     .country(Reader::open_readfile("GeoLite2-Country.mmdb").unwrap())
+///     .asn(Reader::open_readfile("GeoLite2-ASN.mmdb").unwrap());
+/// // in the app factory of every worker
+/// let request_hook = RequestHook::new().geo_resolver(resolver.clone());
+/// ```
+#[cfg(feature = "geoip")]
+#[cfg_attr(docsrs, doc(cfg(feature = "geoip")))]
+#[derive(Clone, Default)]
+pub struct MaxMindResolver {
+    country: Option<Arc<maxminddb::Reader<Vec<u8>>>>,
+    asn: Option<Arc<maxminddb::Reader<Vec<u8>>>>,
+}
+
+#[cfg(feature = "geoip")]
+impl MaxMindResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Looks up countries in `reader` of a GeoIP2 or GeoLite2 Country or City database.
+    pub fn country(mut self, reader: maxminddb::Reader<Vec<u8>>) -> Self {
+        self.country = Some(Arc::new(reader));
+        self
+    }
+
+    /// Looks up autonomous systems in `reader` of a GeoLite2 ASN database.
+    pub fn asn(mut self, reader: maxminddb::Reader<Vec<u8>>) -> Self {
+        self.asn = Some(Arc::new(reader));
+        self
+    }
+}
+
+#[cfg(feature = "geoip")]
+impl GeoResolver for MaxMindResolver {
+    fn resolve(&self, ip: IpAddr) -> Option<ClientGeo> {
+        use maxminddb::geoip2;
+
+        let mut geo = ClientGeo::new(ip);
+        if let Some(reader) = &self.country {
+            if let Ok(country) = reader.lookup::<geoip2::Country>(ip) {
+                geo.country = country
+                    .country
+                    .and_then(|country| country.iso_code)
+                    .map(Into::into);
+            }
+        }
+        if let Some(reader) = &self.asn {
+            if let Ok(asn) = reader.lookup::<geoip2::Asn>(ip) {
+                geo.asn = asn.autonomous_system_number;
+                geo.as_org = asn.autonomous_system_organization.map(Into::into);
+            }
+        }
+        Some(geo)
+    }
+}
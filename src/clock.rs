@@ -0,0 +1,114 @@
+//! [Clock] sources for elapsed times of requests and windows of [HookStats](crate::stats::HookStats).
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+/// Source of the current instant, see [RequestHook::clock](crate::RequestHook::clock) and
+/// [HookStats::clock](crate::stats::HookStats::clock).
+pub trait Clock: std::fmt::Debug + Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// Reads the monotonic system clock on every call, the default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MonotonicClock;
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Clock advanced by a background thread every `resolution`, so reading it is a single atomic load instead of a
+/// system call, e.g. for services with extreme throughput where elapsed times within a millisecond are enough.
+/// The thread stops when the last clone of the clock is dropped.
+///
+/// ```
+/// use std::time::Duration;
+/// use actix_request_hook::clock::CoarseClock;
+/// use actix_request_hook::RequestHook;
+///
+/// let request_hook = RequestHook::new().clock(CoarseClock::new(Duration::from_millis(1)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct CoarseClock {
+    inner: Arc<CoarseInner>,
+}
+
+#[derive(Debug)]
+struct CoarseInner {
+    started: Instant,
+    /// Nanoseconds since `started` at the last update.
+    elapsed: AtomicU64,
+}
+
+impl CoarseClock {
+    pub fn new(resolution: Duration) -> Self {
+        let inner = Arc::new(CoarseInner {
+            started: Instant::now(),
+            elapsed: AtomicU64::new(0),
+        });
+        let weak: Weak<CoarseInner> = Arc::downgrade(&inner);
+        std::thread::Builder::new()
+            .name("request-hook-clock".to_string())
+            .spawn(move || {
+                while let Some(inner) = weak.upgrade() {
+                    let elapsed = inner.started.elapsed().as_nanos() as u64;
+                    inner.elapsed.store(elapsed, Ordering::Relaxed);
+                    drop(inner);
+                    std::thread::sleep(resolution);
+                }
+            })
+            .expect("failed to spawn clock thread");
+        Self { inner }
+    }
+}
+
+impl Clock for CoarseClock {
+    fn now(&self) -> Instant {
+        self.inner.started + Duration::from_nanos(self.inner.elapsed.load(Ordering::Relaxed))
+    }
+}
+
+/// Clock which only moves when advanced, for deterministic tests of elapsed times and windows. Clones share the
+/// same time.
+///
+/// ```
+/// use std::time::Duration;
+/// use actix_request_hook::clock::{Clock, MockClock};
+///
+/// let clock = MockClock::new();
+/// let start = clock.now();
+/// clock.advance(Duration::from_millis(250));
+/// assert_eq!(clock.now() - start, Duration::from_millis(250));
+/// ```
+#[derive(Clone, Debug)]
+pub struct MockClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockClock {
+    /// Clock standing at the current instant.
+    pub fn new() -> Self {
+        Self {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
@@ -0,0 +1,205 @@
+//! [Collector] gathers events of all workers on a single thread, for process wide aggregation.
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::JoinHandle;
+
+use actix_web::dev::ServiceRequest;
+
+use crate::blocking::{dispatch, BlockingObserver};
+use crate::event::{RequestEvent, RequestStartEvent};
+use crate::observer::{
+    DeadlineExceededData, Observer, RequestCancelledData, RequestEndData, RequestStartData,
+    UpgradeData,
+};
+
+/// Forwards owned events of every worker to a single thread, which passes them to its [BlockingObserver]s in the
+/// order they arrived. Since actix runs a hook per worker, this gives observers like metrics or batching sinks
+/// a view of all traffic of the process, without locking on the request path.
+///
+/// The collector is a cloneable handle, create it once in `main` and register its
+/// [observer](Collector::observer) with the hook of every worker. Its thread starts with the first event. Once
+/// the last handle and hook are dropped, it handles pending events, calls [BlockingObserver::on_shutdown] of its
+/// observers and stops. Up to 10000 events are queued by default, further events are dropped and counted in
+/// [Collector::dropped].
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use actix_web::{web, App, HttpResponse, HttpServer};
+/// use actix_request_hook::blocking::BlockingObserver;
+/// use actix_request_hook::collector::Collector;
+/// use actix_request_hook::event::RequestStartEvent;
+/// use actix_request_hook::observer::RequestEndData;
+/// use actix_request_hook::RequestHook;
+///
+/// #[derive(Default)]
+/// struct Requests(AtomicU64);
+///
+/// impl BlockingObserver for Requests {
+///     fn on_request_started(&self, _event: RequestStartEvent) {}
+///
+///     fn on_request_ended(&self, _data: RequestEndData) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// #[actix_web::main]
+/// async fn main() -> std::io::Result<()> {
+///     let collector = Collector::new().register(Requests::default());
+///     HttpServer::new(move || {
+///         App::new()
+///             .wrap(RequestHook::new().register(Rc::new(collector.observer())))
+///             .route("/", web::get().to(HttpResponse::Ok))
+///     })
+///     .bind("127.0.0.1:8080")?
+///     .run()
+///     .await
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Collector {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    observers: Arc<Vec<Box<dyn BlockingObserver>>>,
+    capacity: usize,
+    sender: OnceLock<SyncSender<RequestEvent>>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+    dropped: AtomicU64,
+}
+
+impl Default for Collector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Collector {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                observers: Arc::new(Vec::new()),
+                capacity: 10_000,
+                sender: OnceLock::new(),
+                thread: Mutex::new(None),
+                dropped: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Registers an observer notified of events of all workers, on the collector's thread. Panics if called after
+    /// the handle was cloned or an [observer](Collector::observer) was created.
+    pub fn register<T: 'static + BlockingObserver>(mut self, observer: T) -> Self {
+        Arc::get_mut(&mut self.inner_mut().observers)
+            .unwrap()
+            .push(Box::new(observer));
+        self
+    }
+
+    /// Maximum number of events waiting for the collector's thread, 10000 by default. Same as
+    /// [Collector::register], it must be set before the handle is shared.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.inner_mut().capacity = capacity.max(1);
+        self
+    }
+
+    /// [Observer] forwarding events of a worker's hook to the collector.
+    pub fn observer(&self) -> CollectorObserver {
+        CollectorObserver {
+            inner: self.inner.clone(),
+        }
+    }
+
+    /// Number of events dropped so far because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.inner.dropped.load(Ordering::Relaxed)
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Arc::get_mut(&mut self.inner).unwrap()
+    }
+}
+
+impl Inner {
+    fn send(&self, event: RequestEvent) {
+        let sender = self.sender.get_or_init(|| {
+            let (sender, receiver) = sync_channel(self.capacity);
+            let observers = self.observers.clone();
+            let thread = std::thread::Builder::new()
+                .name("request-hook-collector".to_string())
+                .spawn(move || collect(&observers, receiver))
+                .expect("failed to spawn collector thread");
+            *self.thread.lock().unwrap() = Some(thread);
+            sender
+        });
+        if let Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) = sender.try_send(event) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Passes every event to all observers until every sender is dropped.
+fn collect(observers: &[Box<dyn BlockingObserver>], receiver: Receiver<RequestEvent>) {
+    while let Ok(event) = receiver.recv() {
+        if let Some((last, others)) = observers.split_last() {
+            for observer in others {
+                dispatch(&**observer, event.clone());
+            }
+            dispatch(&**last, event);
+        }
+    }
+    for observer in observers {
+        observer.on_shutdown();
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        // stops the thread once pending events are handled
+        self.sender.take();
+        if let Some(thread) = self.thread.get_mut().unwrap().take() {
+            thread.join().ok();
+        }
+    }
+}
+
+/// [Observer] of a worker's hook, forwarding owned events to a [Collector].
+pub struct CollectorObserver {
+    inner: Arc<Inner>,
+}
+
+impl Observer for CollectorObserver {
+    fn on_request_started(&self, data: RequestStartData) {
+        self.inner
+            .send(RequestEvent::Started(RequestStartEvent::from(&data)));
+    }
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        self.inner.send(RequestEvent::Ended(data));
+    }
+
+    fn on_request_cancelled(&self, data: RequestCancelledData) {
+        self.inner.send(RequestEvent::Cancelled(data));
+    }
+
+    fn on_deadline_exceeded(&self, data: DeadlineExceededData) {
+        self.inner.send(RequestEvent::DeadlineExceeded(data));
+    }
+
+    fn on_upgrade(&self, data: UpgradeData) {
+        self.inner.send(RequestEvent::Upgrade(data));
+    }
+
+    fn capture_body(&self, req: &ServiceRequest) -> bool {
+        self.inner
+            .observers
+            .iter()
+            .any(|observer| observer.capture_body(req))
+    }
+
+    fn name(&self) -> &str {
+        "Collector"
+    }
+}
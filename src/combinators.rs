@@ -0,0 +1,312 @@
+//! [ObserverExt] combinators, composing observers from existing ones without a new struct for every variation.
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::time::Duration;
+
+use actix_web::dev::ServiceRequest;
+use actix_web::HttpResponse;
+use futures_util::future::{join, LocalBoxFuture};
+use futures_util::FutureExt;
+use uuid::Uuid;
+
+use crate::observer::{
+    DeadlineExceededData, Observer, RequestCancelledData, RequestEndData, RequestStartData,
+    UpgradeData,
+};
+
+/// Combinators of every [Observer].
+///
+/// ```
+/// use std::rc::Rc;
+/// use actix_request_hook::combinators::ObserverExt;
+/// use actix_request_hook::observer::{Observer, RequestEndData, RequestStartData};
+/// use actix_request_hook::RequestHook;
+///
+/// struct Printer(&'static str);
+///
+/// impl Observer for Printer {
+///     fn on_request_started(&self, _data: RequestStartData) {}
+///
+///     fn on_request_ended(&self, data: RequestEndData) {
+///         println!("{}: {}", self.0, data);
+///     }
+/// }
+///
+/// let sink = Printer("api")
+///     .filtered(|req| req.path().starts_with("/api"))
+///     .mapped(|mut data| {
+///         // drop query strings, they may hold tokens
+///         data.uri = data.uri.split('?').next().unwrap_or_default().into();
+///         data
+///     })
+///     .chained(Printer("audit"));
+/// let request_hook = RequestHook::new().register(Rc::new(sink));
+/// ```
+pub trait ObserverExt: Observer + Sized {
+    /// Observes only requests for which `predicate` returns true. The decision is made when a request starts, and
+    /// applies to its end, cancellation and deadline, as well as to body capture and interception.
+    fn filtered<F: Fn(&ServiceRequest) -> bool>(self, predicate: F) -> Filtered<Self, F> {
+        Filtered {
+            observer: self,
+            predicate,
+            observed: RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// Passes end data through `f` before the observer receives it, e.g. to scrub the uri or the captured
+    /// response.
+    fn mapped<F: Fn(RequestEndData) -> RequestEndData>(self, f: F) -> Mapped<Self, F> {
+        Mapped { observer: self, f }
+    }
+
+    /// Notifies this observer and then `other` of every event. Bodies and responses are captured if either asks
+    /// for them, and the first interceptor returning a response wins.
+    fn chained<O: Observer>(self, other: O) -> Chained<Self, O> {
+        let name = format!("{} > {}", self.name(), other.name());
+        Chained {
+            first: self,
+            second: other,
+            name,
+        }
+    }
+}
+
+impl<T: Observer> ObserverExt for T {}
+
+/// Observer of requests matching a predicate, see [ObserverExt::filtered].
+pub struct Filtered<O, F> {
+    observer: O,
+    predicate: F,
+    /// Requests which started and matched, until they end.
+    observed: RefCell<HashSet<Uuid>>,
+}
+
+impl<O: Observer, F: Fn(&ServiceRequest) -> bool> Observer for Filtered<O, F> {
+    fn on_request_started(&self, data: RequestStartData) {
+        if (self.predicate)(data.req) {
+            self.observed.borrow_mut().insert(data.request_id);
+            self.observer.on_request_started(data);
+        }
+    }
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        if self.observed.borrow_mut().remove(&data.request_id) {
+            self.observer.on_request_ended(data);
+        }
+    }
+
+    fn on_request_cancelled(&self, data: RequestCancelledData) {
+        if self.observed.borrow_mut().remove(&data.request_id) {
+            self.observer.on_request_cancelled(data);
+        }
+    }
+
+    fn on_deadline_exceeded(&self, data: DeadlineExceededData) {
+        if self.observed.borrow().contains(&data.request_id) {
+            self.observer.on_deadline_exceeded(data);
+        }
+    }
+
+    fn on_upgrade(&self, data: UpgradeData) {
+        if self
+            .observed
+            .borrow_mut()
+            .remove(&data.handshake.request_id)
+        {
+            self.observer.on_upgrade(data);
+        }
+    }
+
+    fn capture_response(&self, req: &ServiceRequest) -> bool {
+        (self.predicate)(req) && self.observer.capture_response(req)
+    }
+
+    fn capture_body(&self, req: &ServiceRequest) -> bool {
+        (self.predicate)(req) && self.observer.capture_body(req)
+    }
+
+    fn force_sample(&self, req: &ServiceRequest) -> bool {
+        (self.predicate)(req) && self.observer.force_sample(req)
+    }
+
+    fn intercept(&self, req: &ServiceRequest) -> Option<HttpResponse> {
+        if (self.predicate)(req) {
+            self.observer.intercept(req)
+        } else {
+            None
+        }
+    }
+
+    fn intercept_body(&self, req: &ServiceRequest, body: &[u8]) -> Option<HttpResponse> {
+        if (self.predicate)(req) {
+            self.observer.intercept_body(req, body)
+        } else {
+            None
+        }
+    }
+
+    fn on_tick(&self, elapsed: Duration) {
+        self.observer.on_tick(elapsed)
+    }
+
+    fn on_shutdown(&self) -> LocalBoxFuture<'static, ()> {
+        self.observer.on_shutdown()
+    }
+
+    fn name(&self) -> &str {
+        self.observer.name()
+    }
+}
+
+/// Observer receiving transformed end data, see [ObserverExt::mapped].
+pub struct Mapped<O, F> {
+    observer: O,
+    f: F,
+}
+
+impl<O: Observer, F: Fn(RequestEndData) -> RequestEndData> Observer for Mapped<O, F> {
+    fn on_request_started(&self, data: RequestStartData) {
+        self.observer.on_request_started(data)
+    }
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        self.observer.on_request_ended((self.f)(data))
+    }
+
+    fn on_request_cancelled(&self, data: RequestCancelledData) {
+        self.observer.on_request_cancelled(data)
+    }
+
+    fn on_deadline_exceeded(&self, data: DeadlineExceededData) {
+        self.observer.on_deadline_exceeded(data)
+    }
+
+    fn on_upgrade(&self, mut data: UpgradeData) {
+        data.handshake = (self.f)(data.handshake);
+        self.observer.on_upgrade(data)
+    }
+
+    fn capture_response(&self, req: &ServiceRequest) -> bool {
+        self.observer.capture_response(req)
+    }
+
+    fn capture_body(&self, req: &ServiceRequest) -> bool {
+        self.observer.capture_body(req)
+    }
+
+    fn force_sample(&self, req: &ServiceRequest) -> bool {
+        self.observer.force_sample(req)
+    }
+
+    fn intercept(&self, req: &ServiceRequest) -> Option<HttpResponse> {
+        self.observer.intercept(req)
+    }
+
+    fn intercept_body(&self, req: &ServiceRequest, body: &[u8]) -> Option<HttpResponse> {
+        self.observer.intercept_body(req, body)
+    }
+
+    fn on_tick(&self, elapsed: Duration) {
+        self.observer.on_tick(elapsed)
+    }
+
+    fn on_shutdown(&self) -> LocalBoxFuture<'static, ()> {
+        self.observer.on_shutdown()
+    }
+
+    fn name(&self) -> &str {
+        self.observer.name()
+    }
+}
+
+/// Two observers notified in order, see [ObserverExt::chained].
+pub struct Chained<A, B> {
+    first: A,
+    second: B,
+    name: String,
+}
+
+impl<A: Observer, B: Observer> Observer for Chained<A, B> {
+    fn on_request_started(&self, data: RequestStartData) {
+        self.first.on_request_started(RequestStartData {
+            req: data.req,
+            request_id: data.request_id,
+            uri: data.uri.clone(),
+            method: data.method.clone(),
+            body: data.body.clone(),
+            origin: data.origin.clone(),
+            body_sizes: data.body_sizes,
+            body_truncated: data.body_truncated,
+            grpc: data.grpc.clone(),
+            sampled: data.sampled,
+            force_sampled: data.force_sampled,
+            baggage: data.baggage.clone(),
+            retry_of: data.retry_of,
+            fingerprint: data.fingerprint,
+            geo: data.geo.clone(),
+            security_flags: data.security_flags,
+            rendered_body: data.rendered_body.clone(),
+            operation_id: data.operation_id.clone(),
+            deprecated: data.deprecated,
+            consumer: data.consumer.clone(),
+        });
+        self.second.on_request_started(data);
+    }
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        self.first.on_request_ended(data.clone());
+        self.second.on_request_ended(data);
+    }
+
+    fn on_request_cancelled(&self, data: RequestCancelledData) {
+        self.first.on_request_cancelled(data.clone());
+        self.second.on_request_cancelled(data);
+    }
+
+    fn on_deadline_exceeded(&self, data: DeadlineExceededData) {
+        self.first.on_deadline_exceeded(data.clone());
+        self.second.on_deadline_exceeded(data);
+    }
+
+    fn on_upgrade(&self, data: UpgradeData) {
+        self.first.on_upgrade(data.clone());
+        self.second.on_upgrade(data);
+    }
+
+    fn capture_response(&self, req: &ServiceRequest) -> bool {
+        self.first.capture_response(req) || self.second.capture_response(req)
+    }
+
+    fn capture_body(&self, req: &ServiceRequest) -> bool {
+        self.first.capture_body(req) || self.second.capture_body(req)
+    }
+
+    fn force_sample(&self, req: &ServiceRequest) -> bool {
+        self.first.force_sample(req) || self.second.force_sample(req)
+    }
+
+    fn intercept(&self, req: &ServiceRequest) -> Option<HttpResponse> {
+        self.first
+            .intercept(req)
+            .or_else(|| self.second.intercept(req))
+    }
+
+    fn intercept_body(&self, req: &ServiceRequest, body: &[u8]) -> Option<HttpResponse> {
+        self.first
+            .intercept_body(req, body)
+            .or_else(|| self.second.intercept_body(req, body))
+    }
+
+    fn on_tick(&self, elapsed: Duration) {
+        self.first.on_tick(elapsed);
+        self.second.on_tick(elapsed);
+    }
+
+    fn on_shutdown(&self) -> LocalBoxFuture<'static, ()> {
+        Box::pin(join(self.first.on_shutdown(), self.second.on_shutdown()).map(|_| ()))
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
@@ -0,0 +1,99 @@
+//! [ConsumerResolver], identifying the API consumer of requests, e.g. by API key.
+use std::str::FromStr;
+use std::sync::Arc;
+
+use actix_web::dev::ServiceRequest;
+use actix_web::http::header::HeaderName;
+
+use crate::util::percent_decode;
+
+/// Consumer under which requests are aggregated once an aggregate tracks its maximum number of consumers, e.g.
+/// [MetricsCollector::max_consumers](crate::metrics::MetricsCollector::max_consumers).
+pub const OTHER_CONSUMER: &str = "<other>";
+
+/// Identifies the consumer of a request, e.g. a customer by its API key or a tenant, so events carry it in their
+/// `consumer` field and observers such as [MetricsCollector](crate::metrics::MetricsCollector) aggregate per
+/// consumer, the basis of API analytics and quota reporting. Register one with
+/// [RequestHook::consumer_resolver](crate::RequestHook::consumer_resolver).
+///
+/// [HeaderConsumer] and [QueryConsumer] read it from a header or a query parameter as it is sent. Resolve secrets
+/// such as API keys to the name of their owner with a closure instead, so events don't spread them:
+///
+/// ```
+/// use actix_web::dev::ServiceRequest;
+/// use actix_request_hook::RequestHook;
+///
+/// fn owner_of(api_key: &str) -> Option<String> {
+///     (api_key == "k-123").then(|| "acme".to_string())
+/// }
+///
+/// let request_hook = RequestHook::new().consumer_resolver(|req: &ServiceRequest| {
+///     owner_of(req.headers().get("X-Api-Key")?.to_str().ok()?)
+/// });
+/// ```
+pub trait ConsumerResolver {
+    /// Consumer of `req`, or none to leave it anonymous.
+    fn resolve(&self, req: &ServiceRequest) -> Option<Arc<str>>;
+}
+
+impl<F, T> ConsumerResolver for F
+where
+    F: Fn(&ServiceRequest) -> Option<T>,
+    T: Into<Arc<str>>,
+{
+    fn resolve(&self, req: &ServiceRequest) -> Option<Arc<str>> {
+        self(req).map(Into::into)
+    }
+}
+
+/// [ConsumerResolver] reading the consumer from a request header, e.g. `X-Consumer-Id` set by an API gateway.
+/// Requests without the header, or with an empty or non visible ASCII value, are anonymous.
+///
+/// ```
+/// use actix_request_hook::consumer::HeaderConsumer;
+/// use actix_request_hook::RequestHook;
+///
+/// let request_hook = RequestHook::new().consumer_resolver(HeaderConsumer::new("X-Consumer-Id"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct HeaderConsumer(HeaderName);
+
+impl HeaderConsumer {
+    /// # Panics
+    ///
+    /// Panics if `name` isn't a valid header name.
+    pub fn new(name: &str) -> Self {
+        Self(HeaderName::from_str(name).unwrap())
+    }
+}
+
+impl ConsumerResolver for HeaderConsumer {
+    fn resolve(&self, req: &ServiceRequest) -> Option<Arc<str>> {
+        let value = req.headers().get(&self.0)?.to_str().ok()?.trim();
+        (!value.is_empty()).then(|| value.into())
+    }
+}
+
+/// [ConsumerResolver] reading the consumer from a query parameter, e.g. `client_id` of `/users?client_id=acme`,
+/// percent decoded. Of repeated parameters the first one wins, requests without it or with an empty value are
+/// anonymous.
+#[derive(Clone, Debug)]
+pub struct QueryConsumer(String);
+
+impl QueryConsumer {
+    pub fn new<T: Into<String>>(name: T) -> Self {
+        Self(name.into())
+    }
+}
+
+impl ConsumerResolver for QueryConsumer {
+    fn resolve(&self, req: &ServiceRequest) -> Option<Arc<str>> {
+        req.query_string()
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == self.0 || (key.contains('%') && percent_decode(key) == self.0))
+            .map(|(_, value)| percent_decode(&value.replace('+', " ")))
+            .filter(|value| !value.is_empty())
+            .map(Into::into)
+    }
+}
@@ -0,0 +1,119 @@
+//! [CurrentRequest], the request a handler runs for, for code deep down the call stack.
+use std::cell::RefCell;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+
+use actix_web::http::header::HeaderValue;
+use futures_util::future::poll_fn;
+use futures_util::pin_mut;
+use uuid::Uuid;
+
+thread_local! {
+    static CURRENT: RefCell<Option<CurrentRequest>> = const { RefCell::new(None) };
+}
+
+/// Id, route and start of the request whose handler is running, set by the hook while it polls the handler, so
+/// nested application code and logs can reference the request without passing its id through every function.
+/// Outside of handlers of observed requests there is none.
+///
+/// ```
+/// use actix_request_hook::current::CurrentRequest;
+///
+/// fn audit(action: &str) {
+///     match CurrentRequest::get() {
+///         Some(current) => println!("[{}] {} on {}", current.request_id(), action, current.route()),
+///         None => println!("{}", action),
+///     }
+/// }
+/// ```
+///
+/// Work a handler spawns runs outside of it, carry the request along with [CurrentRequest::scope]:
+///
+/// ```
+/// use actix_request_hook::current::CurrentRequest;
+///
+/// async fn index() -> &'static str {
+///     if let Some(current) = CurrentRequest::get() {
+///         actix_web::rt::spawn(current.scope(async {
+///             // CurrentRequest::get() is still the request here
+///         }));
+///     }
+///     "ok"
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct CurrentRequest {
+    request_id: Uuid,
+    route: Arc<str>,
+    start: Instant,
+    pub(crate) sampled: bool,
+    pub(crate) traceparent: Option<HeaderValue>,
+}
+
+impl CurrentRequest {
+    pub(crate) fn new(
+        request_id: Uuid,
+        route: Arc<str>,
+        start: Instant,
+        sampled: bool,
+        traceparent: Option<HeaderValue>,
+    ) -> Self {
+        Self {
+            request_id,
+            route,
+            start,
+            sampled,
+            traceparent,
+        }
+    }
+
+    /// Request whose handler is running on this thread, if any.
+    pub fn get() -> Option<CurrentRequest> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    /// Same as [CurrentRequest::get], without cloning the request.
+    pub fn with<R, F: FnOnce(Option<&CurrentRequest>) -> R>(f: F) -> R {
+        CURRENT.with(|current| f(current.borrow().as_ref()))
+    }
+
+    /// Id of the request, same as in events of observers.
+    pub fn request_id(&self) -> Uuid {
+        self.request_id
+    }
+
+    /// Matched route pattern of the request, else its path, normalized if
+    /// [RequestHook::normalize_paths](crate::RequestHook::normalize_paths) is on.
+    pub fn route(&self) -> &str {
+        &self.route
+    }
+
+    /// Instant the hook started observing the request.
+    pub fn start(&self) -> Instant {
+        self.start
+    }
+
+    /// Runs `future` with this request as the current one, e.g. work spawned by a handler.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        pin_mut!(future);
+        poll_fn(|cx| self.enter(|| future.as_mut().poll(cx))).await
+    }
+
+    /// Calls `f` with this request as the current one.
+    pub(crate) fn enter<R, F: FnOnce() -> R>(&self, f: F) -> R {
+        let previous = CURRENT.with(|current| current.replace(Some(self.clone())));
+        // restores the previous request even if `f` panics
+        let _restore = Restore(previous);
+        f()
+    }
+}
+
+struct Restore(Option<CurrentRequest>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
@@ -0,0 +1,113 @@
+//! [`EventEncoder`] serializes [RequestEndData] for binary transports.
+//!
+//! Encoders are enabled with `msgpack`, `cbor` and `protobuf` features. MessagePack and CBOR are self describing,
+//! events are encoded as maps with the same field names as JSON, see [RequestEndData]. Protobuf events follow
+//! the schema in [crate::proto].
+use std::fmt;
+
+use crate::observer::RequestEndData;
+
+/// Serializes events into bytes for a sink. Custom formats implement it too:
+///
+/// ```
+/// use actix_request_hook::encoding::{EncodeError, EventEncoder};
+/// use actix_request_hook::observer::RequestEndData;
+///
+/// struct LineEncoder;
+///
+/// impl EventEncoder for LineEncoder {
+///     fn content_type(&self) -> &'static str {
+///         "text/plain"
+///     }
+///
+///     fn encode(&self, data: &RequestEndData) -> Result<Vec<u8>, EncodeError> {
+///         Ok(format!("{} {} {}", data.method, data.uri, data.status.as_u16()).into_bytes())
+///     }
+/// }
+/// ```
+pub trait EventEncoder {
+    /// MIME type of encoded events, e.g. for a `Content-Type` header or message property.
+    fn content_type(&self) -> &'static str;
+
+    /// Encodes a request end event.
+    fn encode(&self, data: &RequestEndData) -> Result<Vec<u8>, EncodeError>;
+}
+
+/// Error returned by [EventEncoder::encode].
+#[derive(Debug)]
+pub enum EncodeError {
+    #[cfg(feature = "msgpack")]
+    MessagePack(rmp_serde::encode::Error),
+    #[cfg(feature = "cbor")]
+    Cbor(ciborium::ser::Error<std::io::Error>),
+    /// Failure of a custom [EventEncoder].
+    Custom(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "msgpack")]
+            EncodeError::MessagePack(err) => write!(f, "MessagePack encoding failed: {}", err),
+            #[cfg(feature = "cbor")]
+            EncodeError::Cbor(err) => write!(f, "CBOR encoding failed: {}", err),
+            EncodeError::Custom(err) => write!(f, "encoding failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+/// Encodes events as MessagePack maps.
+#[cfg(feature = "msgpack")]
+#[cfg_attr(docsrs, doc(cfg(feature = "msgpack")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MessagePackEncoder;
+
+#[cfg(feature = "msgpack")]
+impl EventEncoder for MessagePackEncoder {
+    fn content_type(&self) -> &'static str {
+        "application/msgpack"
+    }
+
+    fn encode(&self, data: &RequestEndData) -> Result<Vec<u8>, EncodeError> {
+        rmp_serde::to_vec_named(data).map_err(EncodeError::MessagePack)
+    }
+}
+
+/// Encodes events as CBOR maps.
+#[cfg(feature = "cbor")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cbor")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CborEncoder;
+
+#[cfg(feature = "cbor")]
+impl EventEncoder for CborEncoder {
+    fn content_type(&self) -> &'static str {
+        "application/cbor"
+    }
+
+    fn encode(&self, data: &RequestEndData) -> Result<Vec<u8>, EncodeError> {
+        let mut encoded = Vec::new();
+        ciborium::ser::into_writer(data, &mut encoded).map_err(EncodeError::Cbor)?;
+        Ok(encoded)
+    }
+}
+
+/// Encodes events as protobuf [RequestEvent](crate::proto::RequestEvent) envelopes.
+#[cfg(feature = "protobuf")]
+#[cfg_attr(docsrs, doc(cfg(feature = "protobuf")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProtobufEncoder;
+
+#[cfg(feature = "protobuf")]
+impl EventEncoder for ProtobufEncoder {
+    fn content_type(&self) -> &'static str {
+        "application/x-protobuf"
+    }
+
+    fn encode(&self, data: &RequestEndData) -> Result<Vec<u8>, EncodeError> {
+        let event = crate::proto::RequestEvent::from(crate::proto::RequestEnded::from(data));
+        Ok(prost::Message::encode_to_vec(&event))
+    }
+}
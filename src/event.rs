@@ -0,0 +1,306 @@
+//! Owned [RequestEvent]s, for consumers outside of the [Observer](crate::observer::Observer) callbacks.
+use std::fmt;
+#[cfg(feature = "events")]
+use std::pin::Pin;
+use std::sync::Arc;
+#[cfg(feature = "events")]
+use std::task::{Context, Poll};
+
+use actix_web::web::Bytes;
+#[cfg(feature = "events")]
+use futures_util::stream::{self, BoxStream};
+#[cfg(feature = "events")]
+use futures_util::Stream;
+#[cfg(feature = "events")]
+use tokio::sync::broadcast::error::RecvError;
+#[cfg(feature = "events")]
+use tokio::sync::broadcast::Receiver;
+use uuid::Uuid;
+
+use crate::baggage::Baggage;
+use crate::client::ClientGeo;
+use crate::fingerprint::Fingerprint;
+use crate::observer::{
+    BodySizes, DeadlineExceededData, GrpcCall, Origin, RequestCancelledData, RequestEndData,
+    RequestStartData, UpgradeData,
+};
+use crate::render::RenderedBody;
+use crate::security::SecurityFlags;
+use crate::util::BodyLen;
+
+/// Owned copy of [RequestStartData], without the borrowed request. Displays as `GET /users [id]`.
+///
+/// With `serde` feature enabled, `body` is skipped.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RequestStartEvent {
+    pub request_id: Uuid,
+    pub uri: Arc<str>,
+    pub method: Arc<str>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub body: Bytes,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub origin: Origin,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub body_sizes: Option<BodySizes>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub grpc: Option<GrpcCall>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "crate::observer::is_false")
+    )]
+    pub force_sampled: bool,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub baggage: Option<Baggage>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub retry_of: Option<Uuid>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub fingerprint: Option<Fingerprint>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub geo: Option<ClientGeo>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "SecurityFlags::is_empty")
+    )]
+    pub security_flags: SecurityFlags,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub rendered_body: Option<RenderedBody>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub operation_id: Option<Arc<str>>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "crate::observer::is_false")
+    )]
+    pub deprecated: bool,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub consumer: Option<Arc<str>>,
+}
+
+impl From<&RequestStartData<'_>> for RequestStartEvent {
+    fn from(data: &RequestStartData<'_>) -> Self {
+        Self {
+            request_id: data.request_id,
+            uri: data.uri.clone(),
+            method: data.method.clone(),
+            body: data.body.clone().freeze(),
+            origin: data.origin.clone(),
+            body_sizes: data.body_sizes,
+            grpc: data.grpc.clone(),
+            force_sampled: data.force_sampled,
+            baggage: data.baggage.clone(),
+            retry_of: data.retry_of,
+            fingerprint: data.fingerprint,
+            geo: data.geo.clone(),
+            security_flags: data.security_flags,
+            rendered_body: data.rendered_body.clone(),
+            operation_id: data.operation_id.clone(),
+            deprecated: data.deprecated,
+            consumer: data.consumer.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for RequestStartEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestStartEvent")
+            .field("request_id", &self.request_id)
+            .field("uri", &self.uri)
+            .field("method", &self.method)
+            .field("body", &BodyLen(self.body.len()))
+            .field("origin", &self.origin)
+            .field("body_sizes", &self.body_sizes)
+            .field("grpc", &self.grpc)
+            .field("force_sampled", &self.force_sampled)
+            .field("baggage", &self.baggage)
+            .field("retry_of", &self.retry_of)
+            .field("fingerprint", &self.fingerprint)
+            .field("geo", &self.geo)
+            .field("security_flags", &self.security_flags)
+            .field("rendered_body", &self.rendered_body)
+            .field("operation_id", &self.operation_id)
+            .field("deprecated", &self.deprecated)
+            .field("consumer", &self.consumer)
+            .finish()
+    }
+}
+
+impl fmt::Display for RequestStartEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} [{}]", self.method, self.uri, self.request_id)
+    }
+}
+
+/// Request start, end, cancellation or exceeded deadline, as a single type for event pipelines, e.g. as published
+/// to `RequestHook::event_receiver` with `events` feature. Converts from the data of each event with [From], and
+/// displays as the event it holds.
+///
+/// [RequestEvent::kind] is a stable name of the variant. With `serde` feature enabled, events are (de)serialized
+/// with it in a `type` field, next to the fields of the data, e.g. `{"type":"cancelled","request_id":...}`.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+pub enum RequestEvent {
+    Started(RequestStartEvent),
+    Ended(RequestEndData),
+    Cancelled(RequestCancelledData),
+    DeadlineExceeded(DeadlineExceededData),
+    Upgrade(UpgradeData),
+}
+
+impl RequestEvent {
+    /// Name of the variant, one of `started`, `ended`, `cancelled`, `deadline_exceeded` and `upgrade`, which won't
+    /// change between versions.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RequestEvent::Started(_) => "started",
+            RequestEvent::Ended(_) => "ended",
+            RequestEvent::Cancelled(_) => "cancelled",
+            RequestEvent::DeadlineExceeded(_) => "deadline_exceeded",
+            RequestEvent::Upgrade(_) => "upgrade",
+        }
+    }
+
+    /// Id of the request the event belongs to.
+    pub fn request_id(&self) -> Uuid {
+        match self {
+            RequestEvent::Started(event) => event.request_id,
+            RequestEvent::Ended(data) => data.request_id,
+            RequestEvent::Cancelled(data) => data.request_id,
+            RequestEvent::DeadlineExceeded(data) => data.request_id,
+            RequestEvent::Upgrade(data) => data.handshake.request_id,
+        }
+    }
+}
+
+impl From<RequestStartEvent> for RequestEvent {
+    fn from(event: RequestStartEvent) -> Self {
+        RequestEvent::Started(event)
+    }
+}
+
+impl From<&RequestStartData<'_>> for RequestEvent {
+    fn from(data: &RequestStartData<'_>) -> Self {
+        RequestEvent::Started(data.into())
+    }
+}
+
+impl From<RequestEndData> for RequestEvent {
+    fn from(data: RequestEndData) -> Self {
+        RequestEvent::Ended(data)
+    }
+}
+
+impl From<RequestCancelledData> for RequestEvent {
+    fn from(data: RequestCancelledData) -> Self {
+        RequestEvent::Cancelled(data)
+    }
+}
+
+impl From<DeadlineExceededData> for RequestEvent {
+    fn from(data: DeadlineExceededData) -> Self {
+        RequestEvent::DeadlineExceeded(data)
+    }
+}
+
+impl From<UpgradeData> for RequestEvent {
+    fn from(data: UpgradeData) -> Self {
+        RequestEvent::Upgrade(data)
+    }
+}
+
+impl fmt::Display for RequestEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestEvent::Started(event) => fmt::Display::fmt(event, f),
+            RequestEvent::Ended(data) => fmt::Display::fmt(data, f),
+            RequestEvent::Cancelled(data) => fmt::Display::fmt(data, f),
+            RequestEvent::DeadlineExceeded(data) => fmt::Display::fmt(data, f),
+            RequestEvent::Upgrade(data) => fmt::Display::fmt(data, f),
+        }
+    }
+}
+
+/// [Stream] of [RequestEvent]s, from [RequestHook::event_stream](crate::RequestHook::event_stream).
+///
+/// Events missed by a consumer that fell behind the channel capacity are skipped. The stream ends when
+/// every hook publishing to the channel is dropped.
+///
+/// ```no_run
+/// use actix_request_hook::event::RequestEvent;
+/// use actix_request_hook::RequestHook;
+/// use futures_util::StreamExt;
+///
+/// let request_hook = RequestHook::new();
+/// let server_errors = request_hook.event_stream().filter_map(|event| async move {
+///     match event {
+///         RequestEvent::Ended(ended) if ended.status.is_server_error() => Some(ended),
+///         _ => None,
+///     }
+/// });
+/// actix_web::rt::spawn(server_errors.for_each(|ended| async move {
+///     println!("{} {} failed", ended.method, ended.uri);
+/// }));
+/// ```
+#[cfg(feature = "events")]
+#[cfg_attr(docsrs, doc(cfg(feature = "events")))]
+pub struct EventStream {
+    inner: BoxStream<'static, RequestEvent>,
+}
+
+#[cfg(feature = "events")]
+impl EventStream {
+    /// Streams events received by `receiver`.
+    pub fn new(receiver: Receiver<RequestEvent>) -> Self {
+        let inner = stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, receiver)),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        });
+        Self {
+            inner: Box::pin(inner),
+        }
+    }
+}
+
+#[cfg(feature = "events")]
+impl Stream for EventStream {
+    type Item = RequestEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
@@ -0,0 +1,226 @@
+//! Extractors of what the hook measured so far, for handlers.
+use std::future::{ready, Ready};
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use actix_web::dev::Payload;
+use actix_web::error::ErrorInternalServerError;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest};
+use uuid::Uuid;
+
+use crate::clock::Clock;
+use crate::current::CurrentRequest;
+use crate::util::parse_traceparent;
+
+/// Name of the request id header of [Correlation].
+const REQUEST_ID: &str = "x-request-id";
+
+/// Name of the W3C trace context header.
+pub(crate) const TRACEPARENT: &str = "traceparent";
+
+/// Start of a request as measured by the hook, in extensions of every request which isn't excluded.
+#[derive(Clone, Debug)]
+pub struct RequestStart {
+    pub(crate) instant: Instant,
+    pub(crate) clock: Arc<dyn Clock>,
+    pub(crate) request_id: Uuid,
+    pub(crate) sampled: bool,
+}
+
+impl RequestStart {
+    /// Instant the hook started observing the request, from the hook's [Clock].
+    pub fn instant(&self) -> Instant {
+        self.instant
+    }
+
+    /// Id of the request, same as in events of observers.
+    pub fn request_id(&self) -> Uuid {
+        self.request_id
+    }
+
+    /// Whether the request is sampled, for observers.
+    pub fn sampled(&self) -> bool {
+        self.sampled
+    }
+
+    /// Time since the start of the request, measured the same way as [RequestEndData::elapsed](
+    /// crate::observer::RequestEndData::elapsed).
+    pub fn elapsed(&self) -> Duration {
+        self.clock.now().saturating_duration_since(self.instant)
+    }
+}
+
+/// Extractor of time elapsed since the hook started observing the request, e.g. for an `X-Runtime` header
+/// consistent with elapsed times observers receive. Fails with `500 Internal Server Error` on requests the hook
+/// doesn't observe, use `Option<ElapsedSoFar>` on excluded paths.
+///
+/// ```
+/// use actix_web::HttpResponse;
+/// use actix_request_hook::extract::ElapsedSoFar;
+///
+/// async fn index(elapsed: ElapsedSoFar) -> HttpResponse {
+///     // ... handle the request
+///     HttpResponse::Ok()
+///         .insert_header(("X-Runtime", elapsed.as_secs_f64().to_string()))
+///         .finish()
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ElapsedSoFar(pub Duration);
+
+impl Deref for ElapsedSoFar {
+    type Target = Duration;
+
+    fn deref(&self) -> &Duration {
+        &self.0
+    }
+}
+
+impl FromRequest for ElapsedSoFar {
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<RequestStart>()
+                .map(|start| ElapsedSoFar(start.elapsed()))
+                .ok_or_else(|| ErrorInternalServerError("request isn't observed by RequestHook")),
+        )
+    }
+}
+
+/// Extractor of headers correlating calls a handler makes to other services with the request: `x-request-id` with
+/// the id of the request, and a W3C `traceparent` continuing the trace of the caller, or a trace of the request id
+/// without one. The parent span id is derived from the request id, the same way as span ids the `honeycomb` observer
+/// reports. Add them to requests of any HTTP client, e.g. with [CorrelateRequest] of `awc` with `awc` feature enabled.
+/// Fails with `500 Internal Server Error` on requests the hook doesn't observe, use `Option<Correlation>` on excluded
+/// paths.
+///
+/// Code without access to the request takes the correlation of [CurrentRequest] with [Correlation::current].
+///
+/// ```
+/// use actix_web::http::header::HeaderMap;
+/// use actix_request_hook::extract::Correlation;
+///
+/// async fn index(correlation: Correlation) -> String {
+///     let mut headers = HeaderMap::new();
+///     for (name, value) in correlation.headers() {
+///         headers.insert(name, value);
+///     }
+///     // ... call another service with the headers
+///     correlation.request_id.to_string()
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Correlation {
+    pub request_id: Uuid,
+    pub traceparent: String,
+}
+
+impl Correlation {
+    /// Correlation of the request whose handler is running on this thread, if any, see [CurrentRequest].
+    pub fn current() -> Option<Correlation> {
+        CurrentRequest::with(|current| {
+            current.map(|current| {
+                Correlation::new(
+                    current.request_id(),
+                    current.traceparent.as_ref(),
+                    current.sampled,
+                )
+            })
+        })
+    }
+
+    /// Correlation of `req`, from the [RequestStart] in its extensions, if the hook observes it.
+    pub fn of(req: &HttpRequest) -> Option<Correlation> {
+        let extensions = req.extensions();
+        let start = extensions.get::<RequestStart>()?;
+        Some(Correlation::new(
+            start.request_id,
+            req.headers().get(TRACEPARENT),
+            start.sampled,
+        ))
+    }
+
+    fn new(request_id: Uuid, traceparent: Option<&HeaderValue>, sampled: bool) -> Self {
+        let simple = request_id.to_simple().to_string();
+        let (trace_id, sampled) = traceparent
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_traceparent)
+            .map(|(trace_id, _, sampled)| (trace_id, sampled))
+            .unwrap_or_else(|| (simple.clone(), sampled));
+        Correlation {
+            request_id,
+            traceparent: format!("00-{}-{}-{:02x}", trace_id, &simple[..16], sampled as u8),
+        }
+    }
+
+    /// `x-request-id` and `traceparent` headers.
+    pub fn headers(&self) -> [(HeaderName, HeaderValue); 2] {
+        [
+            (
+                HeaderName::from_static(REQUEST_ID),
+                HeaderValue::from_str(&self.request_id.to_string()).unwrap(),
+            ),
+            (
+                HeaderName::from_static(TRACEPARENT),
+                HeaderValue::from_str(&self.traceparent).unwrap(),
+            ),
+        ]
+    }
+}
+
+impl FromRequest for Correlation {
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(
+            Correlation::of(req)
+                .ok_or_else(|| ErrorInternalServerError("request isn't observed by RequestHook")),
+        )
+    }
+}
+
+/// Adds [Correlation] headers to requests of an `awc` client, replacing headers of the same name.
+///
+/// ```
+/// use actix_request_hook::extract::CorrelateRequest;
+///
+/// async fn index() -> String {
+///     let client = awc::Client::default();
+///     // ... send with the ids of the request being handled
+///     let request = client.get("http://users.internal/users/7").correlate();
+///     format!("{:?}", request.headers().get("x-request-id"))
+/// }
+/// ```
+#[cfg(feature = "awc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "awc")))]
+pub trait CorrelateRequest {
+    /// Adds headers of the request whose handler is running on this thread, see [Correlation::current]. Leaves the
+    /// request as is outside of handlers of observed requests.
+    fn correlate(self) -> Self;
+
+    /// Adds headers of `correlation`, e.g. [Correlation::of] a request.
+    fn correlate_with(self, correlation: &Correlation) -> Self;
+}
+
+#[cfg(feature = "awc")]
+impl CorrelateRequest for awc::ClientRequest {
+    fn correlate(self) -> Self {
+        match Correlation::current() {
+            Some(correlation) => self.correlate_with(&correlation),
+            None => self,
+        }
+    }
+
+    fn correlate_with(mut self, correlation: &Correlation) -> Self {
+        for header in correlation.headers() {
+            self = self.insert_header(header);
+        }
+        self
+    }
+}
@@ -0,0 +1,93 @@
+//! [Fingerprint] of requests, telling replays and scripted requests apart from distinct ones.
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+use actix_web::dev::ServiceRequest;
+use actix_web::http::header::HeaderName;
+
+use crate::normalize;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Stable hash of a request's method, route, selected headers and body, equal for requests repeating them, e.g.
+/// a replayed webhook or a script hammering an endpoint. Enable it with
+/// [RequestHook::fingerprint](crate::RequestHook::fingerprint), events then have it in their `fingerprint` field.
+///
+/// Its route is the matched route pattern or normalized path, see [normalize::route], and its body the body as
+/// captured for observers at the start of the request, so it is left out with
+/// [RequestHook::tee_body](crate::RequestHook::tee_body), when bodies aren't captured or longer than the body size
+/// limit. The hash is FNV-1a, stable across processes and versions, not a cryptographic one.
+///
+/// Displays as 16 hex digits, and is (de)serialized as such with `serde` feature enabled.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Fingerprint(u64);
+
+impl Fingerprint {
+    /// Fingerprint of `req` with values of `headers` and `body`.
+    pub(crate) fn of(req: &ServiceRequest, headers: &[HeaderName], body: &[u8]) -> Self {
+        let mut hasher = Fnv(FNV_OFFSET);
+        hasher.write(req.method().as_str().as_bytes());
+        hasher.write(normalize::route(req).as_bytes());
+        for name in headers {
+            for value in req.headers().get_all(name) {
+                hasher.write(name.as_str().as_bytes());
+                hasher.write(value.as_bytes());
+            }
+        }
+        hasher.write(body);
+        Self(hasher.0)
+    }
+
+    pub fn value(&self) -> u64 {
+        self.0
+    }
+}
+
+/// FNV-1a hasher, each written part prefixed by its length so parts can't run into each other.
+struct Fnv(u64);
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in (bytes.len() as u64).to_le_bytes().iter().chain(bytes) {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+impl From<u64> for Fingerprint {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u64::from_str_radix(s, 16).map(Self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Fingerprint {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Fingerprint {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        hex.parse().map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,75 @@
+//! [HyperLogLog] sketches, estimating numbers of distinct values in constant memory.
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Bits of a hash selecting its register.
+const PRECISION: u32 = 10;
+
+const REGISTERS: usize = 1 << PRECISION;
+
+/// Estimates the number of distinct hashes inserted, within about 3% (standard error of `1.04 / sqrt(1024)`), in
+/// 1 KiB of registers allocated on first insert.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// Hash of `value` to insert, stable within a process.
+    pub(crate) fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub(crate) fn insert(&mut self, hash: u64) {
+        if self.registers.is_empty() {
+            self.registers = vec![0; REGISTERS];
+        }
+        let index = (hash >> (64 - PRECISION)) as usize;
+        // bit below the remaining ones bounds the rank when they are all zero
+        let rank = ((hash << PRECISION) | (1 << (PRECISION - 1))).leading_zeros() as u8 + 1;
+        let register = &mut self.registers[index];
+        *register = (*register).max(rank);
+    }
+
+    /// Adds hashes inserted into `other`.
+    pub(crate) fn merge(&mut self, other: &HyperLogLog) {
+        if other.registers.is_empty() {
+            return;
+        }
+        if self.registers.is_empty() {
+            self.registers = other.registers.clone();
+            return;
+        }
+        for (register, other) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(*other);
+        }
+    }
+
+    /// Estimated number of distinct hashes inserted, by linear counting while registers are still empty, which is
+    /// more accurate for few hashes.
+    pub(crate) fn estimate(&self) -> u64 {
+        if self.registers.is_empty() {
+            return 0;
+        }
+        let m = REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|register| 2f64.powi(-(*register as i32)))
+            .sum();
+        let estimate = alpha * m * m / sum;
+        let zeros = self
+            .registers
+            .iter()
+            .filter(|register| **register == 0)
+            .count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+}
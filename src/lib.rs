@@ -36,30 +36,144 @@
 //!
 //! ```
 //!
-use std::cell::RefCell;
+//! Built-in observers and integrations live behind cargo features, see [observers]. Default features include
+//! only the hook itself.
+#![cfg_attr(docsrs, feature(doc_cfg))]
+use std::cell::{Cell, RefCell};
 use std::collections::HashSet;
-use std::future::{ready, Future, Ready};
+use std::future::{ready, Ready};
+use std::panic::AssertUnwindSafe;
 use std::pin::Pin;
-use std::rc::Rc;
-use std::time::Instant;
+use std::rc::{Rc, Weak};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use actix_web::body::MessageBody;
-use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
-use actix_web::web::{Buf, BytesMut};
+use actix_web::body::{BodySize, BoxBody, EitherBody, MessageBody};
+use actix_web::dev::{Extensions, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::error::ErrorInternalServerError;
+use actix_web::http::header::{
+    HeaderMap, HeaderName, HeaderValue, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE,
+};
+use actix_web::http::StatusCode;
+use actix_web::web::{Buf, Bytes, BytesMut, Data};
 use actix_web::{Error, HttpMessage};
+use futures_util::future::{join_all, select, Either, LocalBoxFuture, MapOk};
 use futures_util::task::{Context, Poll};
-use futures_util::StreamExt;
+use futures_util::{pin_mut, FutureExt, StreamExt, TryFutureExt};
 use regex::RegexSet;
 use uuid::Uuid;
 
-use crate::observer::{Observer, RequestEndData, RequestStartData};
-use crate::util::get_payload;
+use crate::attributes::{HookAttributes, HookResponseAttributes};
+use crate::baggage::Baggage;
+use crate::blocking::{Blocking, BlockingObserver};
+use crate::cache::{CacheRule, CacheSignals};
+use crate::classify::ErrorClassifier;
+use crate::client::{Cidr, GeoResolver, IpLists};
+use crate::clock::{Clock, MonotonicClock};
+use crate::consumer::ConsumerResolver;
+use crate::current::CurrentRequest;
+#[cfg(feature = "events")]
+use crate::event::RequestEvent;
+use crate::fingerprint::Fingerprint;
+use crate::normalize::PathNormalizer;
+use crate::observer::{
+    CapturedResponse, DeadlineExceededData, Observer, Origin, OrphanedRequestData, Outcome,
+    RequestCancelledData, RequestEndData, RequestStartData, SlowRequestData, UpgradeData,
+    SLOW_REQUEST_BODY_PREFIX,
+};
+use crate::render::{BodyEncoding, RenderedBody};
+use crate::retry::RetryDetector;
+use crate::security::SecurityDetectors;
+use crate::settings::{HookSettings, UpstreamSampling};
+use crate::stats::HookStats;
+use crate::util::{
+    body_size, constant_time_eq, decode_body, generated_by_framework, grpc_call, grpc_status,
+    panic_message, payload_error, repack_payload, tee_payload, upgrade_protocol, upstream_sampled,
+    ExclusionCache,
+};
 
+#[cfg(feature = "admin")]
+#[cfg_attr(docsrs, doc(cfg(feature = "admin")))]
+pub mod admin;
+#[cfg(feature = "anomaly")]
+#[cfg_attr(docsrs, doc(cfg(feature = "anomaly")))]
+pub mod anomaly;
+pub mod attributes;
+pub mod baggage;
+pub mod blocking;
+pub mod budget;
+pub mod builder;
+pub mod cache;
+pub mod circuit;
+pub mod classify;
+pub mod client;
+pub mod clock;
+pub mod collector;
+pub mod combinators;
+pub mod consumer;
+pub mod current;
+#[cfg(any(feature = "msgpack", feature = "cbor", feature = "protobuf"))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "msgpack", feature = "cbor", feature = "protobuf")))
+)]
+pub mod encoding;
+pub mod event;
+pub mod extract;
+pub mod fingerprint;
+mod hll;
+#[cfg(feature = "metrics")]
+#[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+pub mod metrics;
+pub mod normalize;
 pub mod observer;
+pub mod observers;
+#[cfg(feature = "openapi")]
+#[cfg_attr(docsrs, doc(cfg(feature = "openapi")))]
+pub mod openapi;
+pub mod prelude;
+#[cfg(feature = "protobuf")]
+#[cfg_attr(docsrs, doc(cfg(feature = "protobuf")))]
+pub mod proto;
+#[cfg(feature = "quota")]
+#[cfg_attr(docsrs, doc(cfg(feature = "quota")))]
+pub mod quota;
+pub mod render;
+pub mod retry;
+#[cfg(feature = "scrub")]
+#[cfg_attr(docsrs, doc(cfg(feature = "scrub")))]
+pub mod scrub;
+pub mod security;
+pub mod settings;
+pub mod shared;
+#[cfg(feature = "slo")]
+#[cfg_attr(docsrs, doc(cfg(feature = "slo")))]
+pub mod slo;
+pub mod stats;
+#[cfg(feature = "test-utils")]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-utils")))]
+pub mod test_utils;
 mod tests;
 mod util;
 
+type SlowRequestFn = Rc<dyn Fn(&SlowRequestData)>;
+type OrphanedRequestFn = Rc<dyn Fn(&OrphanedRequestData)>;
+type MapIntoLeftBody<B> = fn(ServiceResponse<B>) -> ServiceResponse<EitherBody<B>>;
+
+/// Shortest token accepted by [RequestHook::verbose_header], so it can't be guessed.
+pub const MIN_VERBOSE_TOKEN_LEN: usize = 16;
+
 /// Middleware for subscribing to request start and end. Enables access to request data, id, status and request duration.
+///
+/// Clones are cheap and share observers, stats and settings, e.g. to wrap several scopes of an app with one hook.
+/// Configure the hook before cloning it, builder methods panic on a cloned hook. Observers are bound to the thread
+/// of their worker, so with many workers build a hook in the app factory of `HttpServer::new`, or validate the
+/// configuration once at startup with [RequestHook::builder], which builds a
+/// [SharedRequestHook](shared::SharedRequestHook) to move into the app factory instead. Observers registered with it
+/// see traffic of all workers.
+#[derive(Clone)]
 pub struct RequestHook(Rc<Inner>);
 
 impl Default for RequestHook {
@@ -73,10 +187,60 @@ impl RequestHook {
         Self(Rc::new(Inner {
             exclude: HashSet::new(),
             exclude_regex: RegexSet::empty(),
+            exclusion_cache: RefCell::new(ExclusionCache::new(1024)),
             observers: Vec::new(),
+            slow_request: None,
+            orphaned_request: None,
+            watching_orphans: Cell::new(false),
+            normalizer: None,
+            tick_interval: None,
+            ticking: Cell::new(false),
+            shut_down: Cell::new(false),
+            tee_body: false,
+            decompress_body: false,
+            track_upgrades: false,
+            defer_end: Vec::new(),
+            max_body_size: 1024 * 1024,
+            clock: Arc::new(MonotonicClock),
+            soft_timeout: None,
+            deadline_header: None,
+            force_sample_header: None,
+            verbose_header: None,
+            propagate_baggage: None,
+            upstream_sampling: UpstreamSampling::Ignore,
+            error_classifier: None,
+            cache_rule: None,
+            retries: None,
+            fingerprint: None,
+            ip_lists: IpLists::default(),
+            trust_forwarded: false,
+            geo_resolver: None,
+            consumer_resolver: None,
+            #[cfg(feature = "quota")]
+            quota_ledger: None,
+            security: None,
+            #[cfg(feature = "scrub")]
+            scrubber: None,
+            render_bodies: None,
+            #[cfg(feature = "openapi")]
+            openapi: None,
+            deprecated_routes: HashSet::new(),
+            instance_id: None,
+            stats: HookStats::default(),
+            settings: HookSettings::default(),
+            app_data: None,
+            enabled: None,
+            #[cfg(feature = "events")]
+            events: tokio::sync::broadcast::channel(1024).0,
         }))
     }
 
+    /// [RequestHookBuilder](builder::RequestHookBuilder) which reports invalid configuration as an error, instead
+    /// of panicking, and builds a [SharedRequestHook](shared::SharedRequestHook) creating the hook of every worker.
+    pub fn builder() -> builder::RequestHookBuilder {
+        builder::RequestHookBuilder::new()
+    }
+
     /// Ignore and do not log access info for specified path.
     pub fn exclude<T: Into<String>>(mut self, path: T) -> Self {
         Rc::get_mut(&mut self.0)
@@ -86,6 +250,15 @@ impl RequestHook {
         self
     }
 
+    /// Same as [RequestHook::exclude] for every path of `paths`, e.g. read from a config file.
+    pub fn exclude_all<I: IntoIterator<Item = T>, T: Into<String>>(mut self, paths: I) -> Self {
+        Rc::get_mut(&mut self.0)
+            .unwrap()
+            .exclude
+            .extend(paths.into_iter().map(Into::into));
+        self
+    }
+
     /// Ignore and do not log access info for paths that match regex.
     pub fn exclude_regex<T: Into<String>>(mut self, path: T) -> Self {
         let inner = Rc::get_mut(&mut self.0).unwrap();
@@ -96,11 +269,499 @@ impl RequestHook {
         self
     }
 
+    /// Observes requests of clients within `cidrs` only, e.g. `10.0.0.0/8`, unless excluded with
+    /// [RequestHook::exclude_ips]. Requests of clients with an unknown IP aren't observed either.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of `cidrs` isn't a valid [Cidr].
+    pub fn include_ips<I: IntoIterator<Item = T>, T: AsRef<str>>(mut self, cidrs: I) -> Self {
+        let inner = Rc::get_mut(&mut self.0).unwrap();
+        inner.ip_lists.include.extend(
+            cidrs
+                .into_iter()
+                .map(|cidr| Cidr::from_str(cidr.as_ref()).unwrap()),
+        );
+        self
+    }
+
+    /// Ignores requests of clients within `cidrs`, e.g. health checks of a load balancer from `10.0.0.0/8`, like
+    /// excluded paths.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of `cidrs` isn't a valid [Cidr].
+    pub fn exclude_ips<I: IntoIterator<Item = T>, T: AsRef<str>>(mut self, cidrs: I) -> Self {
+        let inner = Rc::get_mut(&mut self.0).unwrap();
+        inner.ip_lists.exclude.extend(
+            cidrs
+                .into_iter()
+                .map(|cidr| Cidr::from_str(cidr.as_ref()).unwrap()),
+        );
+        self
+    }
+
+    /// Reads client IPs of [RequestHook::include_ips], [RequestHook::exclude_ips] and
+    /// [RequestHook::geo_resolver] from `Forwarded` or `X-Forwarded-For` headers instead of the peer address. Only
+    /// for apps behind a proxy setting them, otherwise clients choose their IP.
+    pub fn trust_forwarded_headers(mut self) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().trust_forwarded = true;
+        self
+    }
+
+    /// Resolves client IPs to their country and autonomous system with `resolver`, into the `geo` field of events,
+    /// see [ClientGeo](client::ClientGeo).
+    pub fn geo_resolver<R: 'static + GeoResolver>(mut self, resolver: R) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().geo_resolver = Some(Rc::new(resolver));
+        self
+    }
+
+    /// Identifies the consumer of requests with `resolver`, e.g. by API key, into the `consumer` field of events, see
+    /// [ConsumerResolver](consumer::ConsumerResolver).
+    pub fn consumer_resolver<R: 'static + ConsumerResolver>(mut self, resolver: R) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().consumer_resolver = Some(Rc::new(resolver));
+        self
+    }
+
+    /// Counts requests and bytes of every consumer in `ledger` when requests end, regardless of sampling, so usage
+    /// isn't undercounted at sample rates below 1. Ended windows are flushed on ticks, see
+    /// [QuotaLedger](quota::QuotaLedger) and [RequestHook::tick_interval].
+    #[cfg(feature = "quota")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "quota")))]
+    pub fn quota_ledger(mut self, ledger: quota::QuotaLedger) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().quota_ledger = Some(ledger);
+        self
+    }
+
+    /// Flags suspicious requests, e.g. path traversal or SQL injection attempts, with `detectors`, into the
+    /// `security_flags` field of events, see [SecurityFlags](security::SecurityFlags).
+    pub fn security_detectors(mut self, detectors: SecurityDetectors) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().security = Some(detectors);
+        self
+    }
+
+    /// Redacts fields of captured JSON request and response bodies with `scrubber` before observers see them, e.g.
+    /// passwords and card numbers.
+    #[cfg(feature = "scrub")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "scrub")))]
+    pub fn scrub_bodies(mut self, scrubber: scrub::BodyScrubber) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().scrubber = Some(scrubber);
+        self
+    }
+
+    /// Renders captured request and response bodies as text with `encoding` for observers, e.g. base64 of binary
+    /// bodies, into the `rendered_body` field of events, see [RenderedBody]. Bodies are rendered after scrubbing.
+    pub fn render_bodies(mut self, encoding: BodyEncoding) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().render_bodies = Some(encoding);
+        self
+    }
+
+    /// Resolves the operation of requests in the OpenAPI document `api`, into the `operation_id` field of events, and
+    /// flags requests to undocumented endpoints, lacking required parameters or answered with an undocumented
+    /// status in `api_deviations` of end data, see [ApiDeviation](observer::ApiDeviation).
+    #[cfg(feature = "openapi")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "openapi")))]
+    pub fn openapi(mut self, api: openapi::OpenApi) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().openapi = Some(api);
+        self
+    }
+
+    /// Marks route pattern `route`, e.g. `/v1/users/{id}`, as deprecated. Events of its requests have their
+    /// `deprecated` flag set, and [HookStats::deprecated_usage](stats::HookStats::deprecated_usage) counts them per
+    /// tenant, to tell when no consumer uses the route anymore.
+    pub fn deprecate_route<T: Into<String>>(mut self, route: T) -> Self {
+        Rc::get_mut(&mut self.0)
+            .unwrap()
+            .deprecated_routes
+            .insert(route.into());
+        self
+    }
+
+    /// Number of recently requested paths whose `exclude_regex` decision is cached, so hot endpoints don't
+    /// evaluate every pattern on each request, 1024 by default. Least recently requested paths are evicted first,
+    /// 0 disables caching.
+    pub fn exclusion_cache_capacity(mut self, capacity: usize) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().exclusion_cache =
+            RefCell::new(ExclusionCache::new(capacity));
+        self
+    }
+
     /// Registers an [Observer].
     pub fn register<T: 'static + Observer>(mut self, observer: Rc<T>) -> Self {
         Rc::get_mut(&mut self.0).unwrap().observers.push(observer);
         self
     }
+
+    /// Registers every observer of `observers`, in order, e.g. a list of plugins assembled at startup.
+    pub fn register_all<I: IntoIterator<Item = Rc<dyn Observer>>>(mut self, observers: I) -> Self {
+        Rc::get_mut(&mut self.0)
+            .unwrap()
+            .observers
+            .extend(observers);
+        self
+    }
+
+    /// Registers a [BlockingObserver], which runs on its own thread so that its blocking I/O doesn't stall the
+    /// worker. Every call starts a thread, register blocking observers within the app factory once per worker. Register
+    /// a [Blocking](crate::blocking::Blocking) for a different queue capacity or [Overflow](crate::blocking::Overflow).
+    pub fn register_blocking<T: 'static + BlockingObserver>(self, observer: T) -> Self {
+        self.register(Rc::new(Blocking::new(observer)))
+    }
+
+    /// Calls `callback` with headers, route and body prefix of every request which took longer than `threshold`,
+    /// including requests skipped by sampling of [HookSettings].
+    pub fn on_slow_request<F: 'static + Fn(&SlowRequestData)>(
+        mut self,
+        threshold: Duration,
+        callback: F,
+    ) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().slow_request = Some((threshold, Rc::new(callback)));
+        self
+    }
+
+    /// Calls `callback` once for every request which started but neither ended nor was cancelled within `max_age`,
+    /// e.g. because a middleware leaked its future, which would otherwise silently skew in-flight counts and
+    /// metrics. Requests in flight are registered by id in [HookStats], which counts reported ones still in flight in
+    /// [HookStats::orphaned](stats::HookStats::orphaned). Registry is checked every `max_age` from a task started
+    /// with the hook's middleware, so orphans are reported within twice `max_age`.
+    ///
+    /// Requests whose end is deferred until their body was sent, see [RequestHook::defer_end], stay in flight until
+    /// then, pick a `max_age` above the longest stream.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use actix_request_hook::RequestHook;
+    ///
+    /// let request_hook = RequestHook::new().on_orphaned_request(Duration::from_secs(600), |orphan| {
+    ///     eprintln!("request never ended: {}", orphan);
+    /// });
+    /// ```
+    pub fn on_orphaned_request<F: 'static + Fn(&OrphanedRequestData)>(
+        mut self,
+        max_age: Duration,
+        callback: F,
+    ) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().orphaned_request = Some((max_age, Rc::new(callback)));
+        self
+    }
+
+    /// Lets sampling honor the decision of the caller in the flags of a W3C `traceparent` header or in B3 headers,
+    /// so traces and request logs stay consistent across the call chain, ignored by default. Forced sampling
+    /// applies either way.
+    ///
+    /// ```
+    /// use actix_request_hook::RequestHook;
+    /// use actix_request_hook::settings::UpstreamSampling;
+    ///
+    /// let request_hook = RequestHook::new().upstream_sampling(UpstreamSampling::AlwaysOnError);
+    /// ```
+    pub fn upstream_sampling(mut self, upstream_sampling: UpstreamSampling) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().upstream_sampling = upstream_sampling;
+        self
+    }
+
+    /// Categorizes errors of failed requests with `classifier`, into [RequestEndData::error_category].
+    pub fn error_classifier<C: 'static + ErrorClassifier>(mut self, classifier: C) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().error_classifier = Some(Rc::new(classifier));
+        self
+    }
+
+    /// Tells cache hits from misses with `rule` rather than [CacheSignals::default_hit], into `hit` of
+    /// [RequestEndData::cache].
+    pub fn cache_rule<R: 'static + CacheRule>(mut self, rule: R) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().cache_rule = Some(Rc::new(rule));
+        self
+    }
+
+    /// Detects retried requests with `detector`, setting `retry_of` of their events to the id of the first request
+    /// with the same idempotency key.
+    pub fn detect_retries(mut self, detector: RetryDetector) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().retries = Some(detector);
+        self
+    }
+
+    /// Computes a [Fingerprint] of every request from its method, route, values of `headers` and body, e.g. to spot
+    /// replayed webhooks or scripted abuse. Bodies are captured for it, within settings and the body size limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of `headers` isn't a valid header name.
+    pub fn fingerprint<I: IntoIterator<Item = T>, T: AsRef<str>>(mut self, headers: I) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().fingerprint = Some(
+            headers
+                .into_iter()
+                .map(|name| HeaderName::from_str(name.as_ref()).unwrap())
+                .collect(),
+        );
+        self
+    }
+
+    /// Marks requests running longer than `timeout` with [RequestEndData::deadline_exceeded], and calls
+    /// [Observer::on_deadline_exceeded] as soon as the timeout passes, while the handler is still running. The
+    /// handler is not interrupted.
+    pub fn soft_timeout(mut self, timeout: Duration) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().soft_timeout = Some(timeout);
+        self
+    }
+
+    /// Reads the deadline of a request from header `name`, as a Unix timestamp in milliseconds set by the client
+    /// or an upstream service, and treats it like a [RequestHook::soft_timeout]. With both, the earlier deadline
+    /// applies.
+    pub fn deadline_header(mut self, name: &str) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().deadline_header =
+            Some(HeaderName::from_str(name).unwrap());
+        self
+    }
+
+    /// Samples requests with header `name` set to `secret` regardless of the sample rate, and captures their body
+    /// even if [HookSettings::set_capture_body] disabled it, e.g. for support engineers to observe a single
+    /// reproduction in full with `x-debug-trace: <secret>`. Observers can force sampling too, see
+    /// [Observer::force_sample].
+    pub fn force_sample_header<T: Into<String>>(mut self, name: &str, secret: T) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().force_sample_header =
+            Some((HeaderName::from_str(name).unwrap(), secret.into()));
+        self
+    }
+
+    /// Verbose capture of requests with header `name` set to `token`: they are force sampled like with
+    /// [RequestHook::force_sample_header], and their request body, response headers and response body are captured
+    /// for all observers, regardless of settings and what observers ask for. The header is removed from every
+    /// request before observers and handlers see it, so tokens never end up in logs or reach the app, and
+    /// tokens are compared in constant time.
+    ///
+    /// # Panics
+    ///
+    /// If `name` isn't a valid header name or `token` is shorter than [MIN_VERBOSE_TOKEN_LEN] bytes.
+    pub fn verbose_header<T: Into<String>>(mut self, name: &str, token: T) -> Self {
+        let token = token.into();
+        assert!(
+            token.len() >= MIN_VERBOSE_TOKEN_LEN,
+            "verbose header token must be at least {} bytes",
+            MIN_VERBOSE_TOKEN_LEN
+        );
+        Rc::get_mut(&mut self.0).unwrap().verbose_header =
+            Some((HeaderName::from_str(name).unwrap(), token));
+        self
+    }
+
+    /// Passes entries of W3C `baggage` headers with keys in `allowlist` on to handlers, which extract them as
+    /// [Baggage](baggage::Baggage) and re-emit them with calls to other services, e.g. experiment ids or customer
+    /// tier set at the edge. Events carry all entries either way.
+    pub fn propagate_baggage<I: IntoIterator<Item = T>, T: Into<String>>(
+        mut self,
+        allowlist: I,
+    ) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().propagate_baggage =
+            Some(allowlist.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Identifies this instance of the service in the [Origin] of every event, e.g. by its host or pod name.
+    pub fn instance_id<T: Into<String>>(mut self, id: T) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().instance_id = Some(id.into().into());
+        self
+    }
+
+    /// Same as [RequestHook::instance_id] with the value of environment variable `POD_NAME`, as commonly set from
+    /// the Kubernetes downward API, or `HOSTNAME` otherwise. Leaves the instance id unset without either.
+    pub fn instance_id_from_env(self) -> Self {
+        match std::env::var("POD_NAME").or_else(|_| std::env::var("HOSTNAME")) {
+            Ok(id) => self.instance_id(id),
+            Err(_) => self,
+        }
+    }
+
+    /// Calls [Observer::on_tick] of every observer each `interval`, from a task started with the hook's middleware
+    /// and stopped when the middleware is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn tick_interval(mut self, interval: Duration) -> Self {
+        assert!(!interval.is_zero(), "tick interval must be greater than 0");
+        Rc::get_mut(&mut self.0).unwrap().tick_interval = Some(interval);
+        self
+    }
+
+    /// Copies request body chunks while the handler reads them, instead of reading the whole body before calling
+    /// the handler. Saves the latency and memory of holding the body twice, and keeps backpressure of uploads
+    /// intact. Observers receive the body when the request ends, in [RequestEndData::request_body], instead of
+    /// [RequestStartData::body], which stays empty. Bodies not read by the handler are not captured.
+    pub fn tee_body(mut self) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().tee_body = true;
+        self
+    }
+
+    /// Decodes captured request bodies sent with a `Content-Encoding`, gzip, deflate, brotli or zstd, so observers
+    /// receive readable bodies from compressing clients, with encoded and decoded sizes in
+    /// [RequestStartData::body_sizes], or [RequestEndData::request_body_sizes] with [RequestHook::tee_body]. Actix
+    /// decodes bodies in extractors, after middleware, so the hook otherwise sees them encoded. The handler still
+    /// receives the body as sent. Decoding needs actix-web's `compress-*` features, enabled by default, bodies of
+    /// other encodings are passed as received.
+    pub fn decompress_body(mut self) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().decompress_body = true;
+        self
+    }
+
+    /// Observes upgraded connections, e.g. WebSockets, until they close: [Observer::on_upgrade] fires when the
+    /// connection closes instead of when the handshake is answered, with the time it was open in
+    /// [UpgradeData::duration](observer::UpgradeData::duration). Actix keeps the response body of an accepted
+    /// upgrade for as long as the connection lives, the hook wraps it to notice when it is dropped, which boxes
+    /// the response body of accepted upgrades.
+    pub fn track_upgrades(mut self) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().track_upgrades = true;
+        self
+    }
+
+    /// Defers the end of requests answered with `content_type`, e.g. `text/event-stream`, until the response body
+    /// was sent or the client left, instead of when the handler returned the response, so streams and long polls
+    /// lasting minutes aren't observed as 0ms requests. [RequestEndData::elapsed] then lasts until the body ended,
+    /// and [RequestEndData::headers_elapsed] is the time until the response was ready. Stats count the request as
+    /// in flight until then too. Deferred response bodies are boxed.
+    pub fn defer_end<T: Into<String>>(mut self, content_type: T) -> Self {
+        Rc::get_mut(&mut self.0)
+            .unwrap()
+            .defer_end
+            .push(content_type.into());
+        self
+    }
+
+    /// Most bytes of a request body captured for observers, 1MiB by default. Bodies with a larger
+    /// `Content-Length` aren't buffered at all, and observers receive an empty body. Reading bodies of unknown
+    /// length stops at the limit, observers receive the first `max_body_size` bytes, and the handler still
+    /// receives the whole body. Captured responses are limited too, see [Observer::capture_response].
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().max_body_size = max_body_size;
+        self
+    }
+
+    /// Measures elapsed time of requests with `clock` instead of the monotonic system clock, e.g. a
+    /// [CoarseClock](clock::CoarseClock) under extreme throughput, or a [MockClock](clock::MockClock) in tests.
+    pub fn clock<C: 'static + Clock>(mut self, clock: C) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().clock = Arc::new(clock);
+        self
+    }
+
+    /// Groups requests without a matched route pattern by their path normalized with `normalizer`, e.g.
+    /// `/users/:id`, instead of the raw path, in stats and built-in observers, see [normalize::route].
+    pub fn normalize_paths(mut self, normalizer: PathNormalizer) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().normalizer = Some(normalizer);
+        self
+    }
+
+    /// Counts into `stats` instead of the hook's own [HookStats], e.g. to share counters across workers of an
+    /// `HttpServer`, where each worker builds its own hook.
+    pub fn with_stats(mut self, stats: HookStats) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().stats = stats;
+        self
+    }
+
+    /// Handle to live counters of requests observed by this hook.
+    pub fn stats(&self) -> HookStats {
+        self.0.stats.clone()
+    }
+
+    /// Reads sample rate and body capture from `settings` instead of the hook's own [HookSettings], e.g. to
+    /// switch them for all workers of an `HttpServer` at once.
+    pub fn with_settings(mut self, settings: HookSettings) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().settings = settings;
+        self
+    }
+
+    /// Handle to settings which can be changed while the hook is running.
+    pub fn settings(&self) -> HookSettings {
+        self.0.settings.clone()
+    }
+
+    /// Adds `data` to app data of every request passing the hook, excluded ones too, same as `App::app_data`, e.g.
+    /// `web::Data::new(metrics.clone())` for a status handler.
+    pub fn app_data<T: 'static>(mut self, data: T) -> Self {
+        let inner = Rc::get_mut(&mut self.0).unwrap();
+        let app_data = inner
+            .app_data
+            .get_or_insert_with(|| Rc::new(Extensions::new()));
+        Rc::get_mut(app_data).unwrap().insert(data);
+        self
+    }
+
+    /// Adds the hook's [HookStats] to app data of every request, handlers read them by taking `web::Data<HookStats>`.
+    /// Call it after [RequestHook::with_stats], it exposes the stats the hook has at that point.
+    pub fn expose_stats(self) -> Self {
+        let stats = self.stats();
+        self.app_data(Data::new(stats))
+    }
+
+    /// Observes requests only while `enabled` is true, so observation can be flipped live, e.g. from an admin
+    /// handler, without rebuilding the app. While disabled, requests pass through the hook like excluded ones.
+    /// To decide once at startup, e.g. per environment, wrap the hook in `actix_web::middleware::Condition` instead.
+    pub fn enabled(mut self, enabled: Arc<AtomicBool>) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().enabled = Some(enabled);
+        self
+    }
+
+    /// Excluded paths, sorted.
+    pub fn excluded(&self) -> Vec<String> {
+        let mut excluded: Vec<_> = self.0.exclude.iter().cloned().collect();
+        excluded.sort();
+        excluded
+    }
+
+    /// Regex patterns of excluded paths, in registration order.
+    pub fn excluded_patterns(&self) -> &[String] {
+        self.0.exclude_regex.patterns()
+    }
+
+    /// Names of registered observers, in registration order, see [Observer::name].
+    pub fn observer_names(&self) -> Vec<String> {
+        self.0
+            .observers
+            .iter()
+            .map(|observer| observer.name().to_string())
+            .collect()
+    }
+
+    /// Fires [Observer::on_shutdown] of every observer and waits until they complete, e.g. until batching
+    /// observers sent events they hold, instead of leaving it to the hook being dropped when the worker stops. Call
+    /// it once the hook no longer serves requests, e.g. after `server.await` of an app run on the current thread or
+    /// at the end of a test. Observers of a hook shut down aren't notified again when it's dropped.
+    pub async fn shutdown(&self) {
+        if !self.0.shut_down.replace(true) {
+            join_all(
+                self.0
+                    .observers
+                    .iter()
+                    .map(|observer| observer.on_shutdown()),
+            )
+            .await;
+        }
+    }
+
+    /// Publishes events to `sender` instead of the hook's own channel, e.g. to share one channel across
+    /// workers of an `HttpServer`, where each worker builds its own hook.
+    #[cfg(feature = "events")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "events")))]
+    pub fn event_sender(mut self, sender: tokio::sync::broadcast::Sender<RequestEvent>) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().events = sender;
+        self
+    }
+
+    /// Subscribes to the live stream of [RequestEvent]s, without implementing an [Observer].
+    ///
+    /// Events are only cloned while there is a receiver. A receiver that falls more than 1024 events behind
+    /// (or the capacity of a channel passed to [RequestHook::event_sender]) skips the oldest events,
+    /// see [tokio::sync::broadcast].
+    #[cfg(feature = "events")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "events")))]
+    pub fn event_receiver(&self) -> tokio::sync::broadcast::Receiver<RequestEvent> {
+        self.0.events.subscribe()
+    }
+
+    /// Same as [RequestHook::event_receiver], as a [Stream](futures_util::Stream) for use with stream combinators.
+    #[cfg(feature = "events")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "events")))]
+    pub fn event_stream(&self) -> event::EventStream {
+        event::EventStream::new(self.event_receiver())
+    }
 }
 
 /// Contains configuration for [RequestHook]
@@ -108,115 +769,1065 @@ impl RequestHook {
 /// # Properties
 /// * `exclude` - excluded path is ignored.
 /// * `exclude_regex` - same as `exclude`, just uses regex instead of exact match.
+/// * `exclusion_cache` - `exclude_regex` decisions of recently requested paths.
 /// * `observers` - a list of observers for actix request.
-#[derive(Clone)]
+/// * `slow_request` - threshold and callback of [RequestHook::on_slow_request].
+/// * `orphaned_request` - age and callback of [RequestHook::on_orphaned_request].
+/// * `watching_orphans` - whether a worker started watching for orphaned requests of this hook.
+/// * `normalizer` - normalizes paths of requests without a matched route, see [RequestHook::normalize_paths].
+/// * `tick_interval` - period of [Observer::on_tick], see [RequestHook::tick_interval].
+/// * `ticking` - whether a worker started ticking observers of this hook.
+/// * `shut_down` - whether observers were shut down with [RequestHook::shutdown].
+/// * `tee_body` - whether request bodies are copied while the handler reads them, see [RequestHook::tee_body].
+/// * `decompress_body` - whether captured bodies are decoded, see [RequestHook::decompress_body].
+/// * `track_upgrades` - whether upgraded connections are observed until they close, see
+///   [RequestHook::track_upgrades].
+/// * `defer_end` - content types of responses which end once their body was sent, see [RequestHook::defer_end].
+/// * `max_body_size` - most bytes of a request body captured for observers, see [RequestHook::max_body_size].
+/// * `clock` - source of start and end instants of requests.
+/// * `soft_timeout` - deadline of every request, see [RequestHook::soft_timeout].
+/// * `deadline_header` - header with a deadline of the request, see [RequestHook::deadline_header].
+/// * `force_sample_header` - header and secret forcing sampling of a request, see
+///   [RequestHook::force_sample_header].
+/// * `verbose_header` - header and token turning on verbose capture of a request, see
+///   [RequestHook::verbose_header].
+/// * `propagate_baggage` - keys of baggage passed on to handlers, see [RequestHook::propagate_baggage].
+/// * `upstream_sampling` - how sampling treats the caller's decision, see [RequestHook::upstream_sampling].
+/// * `error_classifier` - categorizes errors of failed requests, see [RequestHook::error_classifier].
+/// * `cache_rule` - tells cache hits from misses, see [RequestHook::cache_rule].
+/// * `retries` - detects retried requests, see [RequestHook::detect_retries].
+/// * `fingerprint` - headers of request fingerprints, see [RequestHook::fingerprint].
+/// * `ip_lists` - networks whose requests are observed or excluded, see [RequestHook::include_ips].
+/// * `trust_forwarded` - whether client IPs are read from proxy headers, see [RequestHook::trust_forwarded_headers].
+/// * `geo_resolver` - resolves client IPs to countries and autonomous systems, see [RequestHook::geo_resolver].
+/// * `consumer_resolver` - identifies consumers of requests, see [RequestHook::consumer_resolver].
+/// * `quota_ledger` - counts usage of consumers, see [RequestHook::quota_ledger].
+/// * `security` - flags suspicious requests, see [RequestHook::security_detectors].
+/// * `scrubber` - redacts fields of captured JSON bodies, see [RequestHook::scrub_bodies].
+/// * `render_bodies` - encoding of captured bodies rendered as text, see [RequestHook::render_bodies].
+/// * `openapi` - document resolving operations of requests, see [RequestHook::openapi].
+/// * `deprecated_routes` - route patterns flagged as deprecated, see [RequestHook::deprecate_route].
+/// * `instance_id` - id of the service instance in [Origin] of events, see [RequestHook::instance_id].
+/// * `stats` - counters of observed requests.
+/// * `settings` - sample rate and body capture, changeable at runtime.
+/// * `events` - sender of events for [RequestHook::event_receiver].
 struct Inner {
     exclude: HashSet<String>,
     exclude_regex: RegexSet,
+    exclusion_cache: RefCell<ExclusionCache>,
     observers: Vec<Rc<dyn Observer>>,
+    slow_request: Option<(Duration, SlowRequestFn)>,
+    orphaned_request: Option<(Duration, OrphanedRequestFn)>,
+    watching_orphans: Cell<bool>,
+    normalizer: Option<PathNormalizer>,
+    tick_interval: Option<Duration>,
+    ticking: Cell<bool>,
+    shut_down: Cell<bool>,
+    tee_body: bool,
+    decompress_body: bool,
+    track_upgrades: bool,
+    defer_end: Vec<String>,
+    max_body_size: usize,
+    clock: Arc<dyn Clock>,
+    soft_timeout: Option<Duration>,
+    deadline_header: Option<HeaderName>,
+    force_sample_header: Option<(HeaderName, String)>,
+    verbose_header: Option<(HeaderName, String)>,
+    propagate_baggage: Option<Vec<String>>,
+    upstream_sampling: UpstreamSampling,
+    error_classifier: Option<Rc<dyn ErrorClassifier>>,
+    cache_rule: Option<Rc<dyn CacheRule>>,
+    retries: Option<RetryDetector>,
+    fingerprint: Option<Vec<HeaderName>>,
+    ip_lists: IpLists,
+    trust_forwarded: bool,
+    geo_resolver: Option<Rc<dyn GeoResolver>>,
+    consumer_resolver: Option<Rc<dyn ConsumerResolver>>,
+    #[cfg(feature = "quota")]
+    quota_ledger: Option<quota::QuotaLedger>,
+    security: Option<SecurityDetectors>,
+    #[cfg(feature = "scrub")]
+    scrubber: Option<scrub::BodyScrubber>,
+    render_bodies: Option<BodyEncoding>,
+    #[cfg(feature = "openapi")]
+    openapi: Option<openapi::OpenApi>,
+    deprecated_routes: HashSet<String>,
+    instance_id: Option<Arc<str>>,
+    stats: HookStats,
+    settings: HookSettings,
+    /// Data added to app data of every request.
+    app_data: Option<Rc<Extensions>>,
+    /// Switch of the whole hook, enabled without one.
+    enabled: Option<Arc<AtomicBool>>,
+    #[cfg(feature = "events")]
+    events: tokio::sync::broadcast::Sender<RequestEvent>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if self.shut_down.get() {
+            return;
+        }
+        for observer in &self.observers {
+            let mut shutdown = observer.on_shutdown();
+            // spawned only if pending, hooks dropped outside of actix runtime can't spawn
+            if (&mut shutdown).now_or_never().is_none() {
+                actix_web::rt::spawn(shutdown);
+            }
+        }
+    }
+}
+
+impl Inner {
+    fn is_excluded_by_regex(&self, path: &str) -> bool {
+        if self.exclude_regex.is_empty() {
+            return false;
+        }
+        let mut cache = self.exclusion_cache.borrow_mut();
+        if cache.capacity() == 0 {
+            return self.exclude_regex.is_match(path);
+        }
+        cache.get_or_insert_with(path, || self.exclude_regex.is_match(path))
+    }
+
+    /// Whether `req` asks for verbose capture, removing its verbose header.
+    fn take_verbose(&self, req: &mut ServiceRequest) -> bool {
+        let Some((name, token)) = &self.verbose_header else {
+            return false;
+        };
+        req.headers_mut()
+            .remove(name)
+            .any(|value| constant_time_eq(value.as_bytes(), token.as_bytes()))
+    }
+
+    /// Whether `req` is sampled regardless of the sample rate, by its header or an observer.
+    fn force_sampled(&self, req: &ServiceRequest) -> bool {
+        let by_header = self
+            .force_sample_header
+            .as_ref()
+            .is_some_and(|(name, secret)| {
+                req.headers()
+                    .get(name)
+                    .is_some_and(|value| constant_time_eq(value.as_bytes(), secret.as_bytes()))
+            });
+        by_header
+            || self
+                .observers
+                .iter()
+                .any(|observer| observer.force_sample(req))
+    }
+
+    /// Time a request has from its start, by the soft timeout and deadline header.
+    fn deadline(&self, req: &ServiceRequest) -> Option<Duration> {
+        let header = self.deadline_header.as_ref().and_then(|name| {
+            let millis = req
+                .headers()
+                .get(name)?
+                .to_str()
+                .ok()?
+                .parse::<u64>()
+                .ok()?;
+            let deadline = UNIX_EPOCH + Duration::from_millis(millis);
+            Some(
+                deadline
+                    .duration_since(SystemTime::now())
+                    .unwrap_or_default(),
+            )
+        });
+        match (header, self.soft_timeout) {
+            (Some(header), Some(timeout)) => Some(header.min(timeout)),
+            (header, timeout) => header.or(timeout),
+        }
+    }
+
+    /// Notifies observers and event subscribers of an ended request.
+    fn ended(&self, sampled: bool, data: RequestEndData) {
+        for observer in self.sampled_observers(sampled) {
+            observer.on_request_ended(data.clone());
+        }
+        #[cfg(feature = "events")]
+        if sampled && self.events.receiver_count() > 0 {
+            let _ = self.events.send(RequestEvent::Ended(data));
+        }
+    }
+
+    /// Captured `body` of a `content_type` with fields redacted, see [RequestHook::scrub_bodies].
+    fn scrubbed(&self, content_type: Option<&HeaderValue>, body: Bytes) -> Bytes {
+        #[cfg(feature = "scrub")]
+        if let Some(scrubber) = &self.scrubber {
+            return scrubber.scrub(content_type, body);
+        }
+        #[cfg(not(feature = "scrub"))]
+        let _ = content_type;
+        body
+    }
+
+    /// Same as [Inner::scrubbed], in place.
+    #[cfg(feature = "scrub")]
+    fn scrub(&self, content_type: Option<&HeaderValue>, body: &mut BytesMut) {
+        if self.scrubber.is_some() {
+            let scrubbed = self.scrubbed(content_type, body.split().freeze());
+            body.extend_from_slice(&scrubbed);
+        }
+    }
+
+    /// Captured `body` rendered as text, unless it's empty, see [RequestHook::render_bodies].
+    fn rendered(&self, body: &[u8]) -> Option<RenderedBody> {
+        let encoding = self.render_bodies?;
+        (!body.is_empty()).then(|| RenderedBody::render(body, encoding))
+    }
+
+    /// Whether the end of a response is deferred until its body was sent, by its content type.
+    fn defers_end(&self, headers: &HeaderMap) -> bool {
+        if self.defer_end.is_empty() {
+            return false;
+        }
+        let content_type = headers
+            .get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .and_then(|content_type| content_type.split(';').next())
+            .map(str::trim)
+            .unwrap_or_default();
+        self.defer_end
+            .iter()
+            .any(|deferred| deferred.eq_ignore_ascii_case(content_type))
+    }
+
+    /// Notifies observers and event subscribers of a request which asked to upgrade its connection.
+    fn upgraded(&self, sampled: bool, data: UpgradeData) {
+        for observer in self.sampled_observers(sampled) {
+            observer.on_upgrade(data.clone());
+        }
+        #[cfg(feature = "events")]
+        if sampled && self.events.receiver_count() > 0 {
+            let _ = self.events.send(RequestEvent::Upgrade(data));
+        }
+    }
+
+    /// Observers of a request, none unless it is sampled.
+    fn sampled_observers(&self, sampled: bool) -> &[Rc<dyn Observer>] {
+        if sampled {
+            &self.observers
+        } else {
+            &[]
+        }
+    }
 }
 
 impl<S: 'static, B> Transform<S, ServiceRequest> for RequestHook
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
-    B: MessageBody,
+    B: 'static + MessageBody,
 {
-    type Response = S::Response;
+    type Response = ServiceResponse<EitherBody<B>>;
     type Error = Error;
     type Transform = RequestHookMiddleware<S>;
     type InitError = ();
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
+        if let Some(interval) = self.0.tick_interval {
+            if !self.0.ticking.replace(true) {
+                actix_web::rt::spawn(tick(Rc::downgrade(&self.0), interval));
+            }
+        }
+        if let Some((max_age, _)) = &self.0.orphaned_request {
+            if !self.0.watching_orphans.replace(true) {
+                self.0.stats.track_orphans();
+                actix_web::rt::spawn(watch_orphans(Rc::downgrade(&self.0), *max_age));
+            }
+        }
         ready(Ok(RequestHookMiddleware {
-            service: Rc::new(RefCell::new(service)),
+            service: Rc::new(service),
             inner: self.0.clone(),
+            // apps are built on their worker's thread
+            origin: Origin::current(self.0.instance_id.clone()),
         }))
     }
 }
 
+/// Ticks observers until the hook and its middleware are dropped.
+async fn tick(inner: Weak<Inner>, period: Duration) {
+    let mut interval =
+        actix_web::rt::time::interval_at(actix_web::rt::time::Instant::now() + period, period);
+    let mut last = Instant::now();
+    loop {
+        interval.tick().await;
+        let inner = match inner.upgrade() {
+            Some(inner) => inner,
+            None => break,
+        };
+        let elapsed = last.elapsed();
+        last = Instant::now();
+        for observer in &inner.observers {
+            observer.on_tick(elapsed);
+        }
+        #[cfg(feature = "quota")]
+        if let Some(ledger) = &inner.quota_ledger {
+            ledger.flush();
+        }
+    }
+}
+
+/// Reports orphaned requests every `max_age` until the hook and its middleware are dropped.
+async fn watch_orphans(inner: Weak<Inner>, max_age: Duration) {
+    let period = max_age.max(Duration::from_millis(1));
+    let mut interval =
+        actix_web::rt::time::interval_at(actix_web::rt::time::Instant::now() + period, period);
+    loop {
+        interval.tick().await;
+        let inner = match inner.upgrade() {
+            Some(inner) => inner,
+            None => break,
+        };
+        if let Some((max_age, callback)) = &inner.orphaned_request {
+            for orphan in inner.stats.take_orphans(*max_age) {
+                callback(&orphan);
+            }
+        }
+    }
+}
+
+/// Resolves after `remaining`, right away if nothing remains.
+async fn deadline_timer(remaining: Duration) {
+    if !remaining.is_zero() {
+        actix_web::rt::time::sleep(remaining).await;
+    }
+}
+
+/// Notifies observers of a started request that it was cancelled, when dropped before it is disarmed at the end of
+/// the request.
+struct CancelGuard {
+    inner: Rc<Inner>,
+    sampled: bool,
+    request_id: Uuid,
+    uri: Arc<str>,
+    method: Arc<str>,
+    origin: Origin,
+    start: Instant,
+    armed: bool,
+}
+
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        let data = RequestCancelledData {
+            request_id: self.request_id,
+            elapsed: self.inner.clock.now().saturating_duration_since(self.start),
+            uri: self.uri.clone(),
+            method: self.method.clone(),
+            origin: self.origin.clone(),
+        };
+        for observer in self.inner.sampled_observers(self.sampled) {
+            observer.on_request_cancelled(data.clone());
+        }
+        #[cfg(feature = "events")]
+        if self.sampled && self.inner.events.receiver_count() > 0 {
+            let _ = self.inner.events.send(RequestEvent::Cancelled(data));
+        }
+    }
+}
+
+/// Response body calling `on_close` once actix drops it, when the body was sent, or the connection closed, e.g. a
+/// client leaving an event stream or an upgraded connection closing.
+struct ObservedBody {
+    body: BoxBody,
+    on_close: Option<Box<dyn FnOnce()>>,
+}
+
+impl ObservedBody {
+    fn new<B: 'static + MessageBody, F: 'static + FnOnce()>(body: B, on_close: F) -> Self {
+        Self {
+            body: BoxBody::new(body),
+            on_close: Some(Box::new(on_close)),
+        }
+    }
+}
+
+impl MessageBody for ObservedBody {
+    type Error = Box<dyn std::error::Error>;
+
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        Pin::new(&mut self.body).poll_next(cx)
+    }
+}
+
+impl Drop for ObservedBody {
+    fn drop(&mut self) {
+        if let Some(on_close) = self.on_close.take() {
+            on_close();
+        }
+    }
+}
+
 pub struct RequestHookMiddleware<S> {
     inner: Rc<Inner>,
-    service: Rc<RefCell<S>>,
+    service: Rc<S>,
+    origin: Origin,
 }
 
 impl<S: 'static, B> Service<ServiceRequest> for RequestHookMiddleware<S>
 where
-    B: MessageBody,
+    B: 'static + MessageBody,
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
 {
-    type Response = ServiceResponse<B>;
+    type Response = ServiceResponse<EitherBody<B>>;
     type Error = Error;
-    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+    type Future = Either<
+        MapOk<S::Future, MapIntoLeftBody<B>>,
+        LocalBoxFuture<'static, Result<Self::Response, Self::Error>>,
+    >;
     fn poll_ready(&self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.service.poll_ready(ctx)
     }
 
     fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        if let Some(app_data) = &self.inner.app_data {
+            req.add_data_container(app_data.clone());
+        }
+        let verbose = self.inner.take_verbose(&mut req);
+        let disabled = self
+            .inner
+            .enabled
+            .as_ref()
+            .is_some_and(|enabled| !enabled.load(Ordering::Relaxed));
+        let excluded = disabled
+            || self.inner.exclude.contains(req.path())
+            || self.inner.is_excluded_by_regex(req.path());
+        let needs_ip = !self.inner.ip_lists.is_empty() || self.inner.geo_resolver.is_some();
+        let client_ip = if excluded || !needs_ip {
+            None
+        } else {
+            client::client_ip(&req, self.inner.trust_forwarded)
+        };
+        if excluded || self.inner.ip_lists.excludes(client_ip) {
+            // excluded requests skip the hook without allocating
+            let map_into_left_body: MapIntoLeftBody<B> = ServiceResponse::map_into_left_body;
+            return Either::Left(self.service.call(req).map_ok(map_into_left_body));
+        }
         let svc = self.service.clone();
 
-        let excluded = self.inner.exclude.contains(req.path())
-            || self.inner.exclude_regex.is_match(req.path());
-        if excluded {
-            return Box::pin(svc.call(req));
+        let start = self.inner.clock.now();
+        let request_id = Uuid::new_v4();
+        let uri: Arc<str> = req.uri().to_string().into();
+        let method: Arc<str> = req.method().as_str().into();
+        let origin = self.origin.clone();
+        let upgrade = upgrade_protocol(req.headers());
+        let grpc = grpc_call(req.path(), req.headers());
+        let baggage = Baggage::from_headers(req.headers());
+        let security_flags = self
+            .inner
+            .security
+            .as_ref()
+            .map(|detectors| detectors.detect(&req))
+            .unwrap_or_default();
+        let geo = client_ip.and_then(|ip| self.inner.geo_resolver.as_ref()?.resolve(ip));
+        let consumer = self
+            .inner
+            .consumer_resolver
+            .as_ref()
+            .and_then(|resolver| resolver.resolve(&req));
+        #[cfg(feature = "openapi")]
+        let operation = self.inner.openapi.as_ref().map(|api| api.operation(&req));
+        #[cfg(feature = "openapi")]
+        let (operation_id, mut api_deviations) = match &operation {
+            Some(Some(operation)) => (
+                operation.operation_id().cloned(),
+                operation.missing_parameters(&req),
+            ),
+            Some(None) => (None, vec![observer::ApiDeviation::UndocumentedEndpoint]),
+            None => (None, Vec::new()),
+        };
+        #[cfg(not(feature = "openapi"))]
+        let (operation_id, api_deviations) = (None, Vec::new());
+        if let (Some(baggage), Some(allowlist)) = (&baggage, &self.inner.propagate_baggage) {
+            req.extensions_mut().insert(baggage.allowed(allowlist));
         }
+        if let Some(normalizer) = &self.inner.normalizer {
+            req.extensions_mut().insert(normalizer.clone());
+        }
+        let deprecated = !self.inner.deprecated_routes.is_empty()
+            && self
+                .inner
+                .deprecated_routes
+                .contains(&normalize::route(&req));
+        let in_flight = self
+            .inner
+            .stats
+            .track(request_id, &req, &uri, &method, deprecated);
+        let retry_of = self
+            .inner
+            .retries
+            .as_ref()
+            .and_then(|retries| retries.retry_of(&req, request_id));
 
-        let observers = self.inner.observers.clone();
-
-        let start = Instant::now();
-        let request_id = Uuid::new_v4();
-        let uri = req.uri().to_string();
-        let method = req.method().to_string();
+        let upstream = match self.inner.upstream_sampling {
+            UpstreamSampling::Ignore => None,
+            UpstreamSampling::Respect | UpstreamSampling::AlwaysOnError => {
+                upstream_sampled(req.headers())
+            }
+        };
+        let rate_sampled = upstream.unwrap_or_else(|| self.inner.settings.is_sampled(request_id));
+        let force_sampled = verbose || self.inner.force_sampled(&req);
+        let sampled = rate_sampled || force_sampled;
+        req.extensions_mut().insert(extract::RequestStart {
+            instant: start,
+            clock: self.inner.clock.clone(),
+            request_id,
+            sampled,
+        });
+        // observed late, if it fails
+        let skipped = !sampled && self.inner.upstream_sampling == UpstreamSampling::AlwaysOnError;
+        let deadline = self.inner.deadline(&req);
+        let current = CurrentRequest::new(
+            request_id,
+            normalize::route(&req).into(),
+            start,
+            sampled,
+            req.headers().get(extract::TRACEPARENT).cloned(),
+        );
+        let inner = self.inner.clone();
+        let observers = inner.sampled_observers(sampled);
+        let slow_request = self
+            .inner
+            .slow_request
+            .clone()
+            .map(|(threshold, callback)| {
+                (
+                    threshold,
+                    callback,
+                    normalize::route(&req),
+                    req.headers().clone(),
+                )
+            });
+        let intercepted = self
+            .inner
+            .observers
+            .iter()
+            .find_map(|observer| observer.intercept(&req));
+        #[cfg(feature = "chaos")]
+        let chaos = req.extensions().get::<observer::ChaosInjection>().cloned();
+        #[cfg(not(feature = "chaos"))]
+        let chaos = None;
+        let wants_body = verbose
+            || slow_request.is_some()
+            || self.inner.fingerprint.is_some()
+            || observers.iter().any(|observer| observer.capture_body(&req));
+        #[cfg(feature = "events")]
+        let wants_body = wants_body || (sampled && inner.events.receiver_count() > 0);
+        let oversized = req
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok()?.parse::<u64>().ok())
+            .is_some_and(|length| length > self.inner.max_body_size as u64);
+        let capture_body = intercepted.is_none()
+            && wants_body
+            && !oversized
+            && (force_sampled || self.inner.settings.capture_body());
 
         let future_response = async move {
-            let mut payload = req.take_payload();
+            let observers = inner.sampled_observers(sampled);
+            #[cfg(feature = "events")]
+            let events = &inner.events;
             let mut body = BytesMut::new();
-            while let Some(chunk) = payload.next().await {
-                body.extend_from_slice(chunk.unwrap().chunk())
+            let mut repacked_payload = None;
+            let mut teed = None;
+            let mut body_sizes = None;
+            let mut body_truncated = false;
+            let mut read_error = None;
+            let content_encoding = if capture_body && inner.decompress_body {
+                req.headers().get(CONTENT_ENCODING).cloned()
+            } else {
+                None
+            };
+            if capture_body && inner.tee_body {
+                let (payload, captured) = tee_payload(req.take_payload(), inner.max_body_size);
+                repacked_payload = Some(payload);
+                teed = Some(captured);
+            } else if capture_body {
+                let mut payload = req.take_payload();
+                let mut complete = true;
+                while let Some(chunk) = payload.next().await {
+                    match chunk {
+                        Ok(chunk) => body.extend_from_slice(chunk.chunk()),
+                        Err(err) => {
+                            read_error = Some(payload_error(err));
+                            break;
+                        }
+                    }
+                    if body.len() > inner.max_body_size {
+                        complete = false;
+                        break;
+                    }
+                }
+                body_truncated = !complete;
+                let read = if complete {
+                    body.clone().freeze()
+                } else {
+                    let read = body.split().freeze();
+                    body.extend_from_slice(&read[..inner.max_body_size]);
+                    read
+                };
+                repacked_payload = Some(repack_payload(read, payload));
+                if inner.decompress_body {
+                    let raw = body.clone().freeze();
+                    if let Some((decoded, sizes)) =
+                        decode_body(raw, content_encoding.as_ref(), inner.max_body_size).await
+                    {
+                        body = decoded;
+                        body_sizes = Some(sizes);
+                    }
+                }
             }
 
-            let handler_body = body.clone();
-            let repacked_payload = get_payload(body.freeze());
-
-            for observer in &observers {
+            let intercepted = match intercepted {
+                None if capture_body && !inner.tee_body && read_error.is_none() => inner
+                    .observers
+                    .iter()
+                    .find_map(|observer| observer.intercept_body(&req, &body)),
+                intercepted => intercepted,
+            };
+            let fingerprint = inner
+                .fingerprint
+                .as_ref()
+                .map(|headers| Fingerprint::of(&req, headers, &body));
+            #[cfg(feature = "quota")]
+            let usage = inner
+                .quota_ledger
+                .as_ref()
+                .and_then(|ledger| ledger.start(consumer.as_ref(), &req, body.len()));
+            #[cfg(feature = "scrub")]
+            inner.scrub(req.headers().get(CONTENT_TYPE), &mut body);
+            #[cfg(feature = "scrub")]
+            let content_type = req.headers().get(CONTENT_TYPE).cloned();
+            let rendered_body = inner.rendered(&body);
+            for observer in observers {
                 observer.on_request_started(RequestStartData {
                     req: &req,
                     request_id,
-                    uri: uri.to_string(),
-                    method: method.to_string(),
-                    body: handler_body.clone(),
+                    uri: uri.clone(),
+                    method: method.clone(),
+                    body: body.clone(),
+                    origin: origin.clone(),
+                    body_sizes,
+                    body_truncated,
+                    grpc: grpc.clone(),
+                    sampled: rate_sampled,
+                    force_sampled,
+                    baggage: baggage.clone(),
+                    retry_of,
+                    fingerprint,
+                    geo: geo.clone(),
+                    security_flags,
+                    rendered_body: rendered_body.clone(),
+                    operation_id: operation_id.clone(),
+                    deprecated,
+                    consumer: consumer.clone(),
                 })
             }
+            #[cfg(feature = "events")]
+            if sampled && events.receiver_count() > 0 {
+                let _ = events.send(RequestEvent::Started(event::RequestStartEvent {
+                    request_id,
+                    uri: uri.clone(),
+                    method: method.clone(),
+                    body: body.clone().freeze(),
+                    origin: origin.clone(),
+                    body_sizes,
+                    grpc: grpc.clone(),
+                    force_sampled,
+                    baggage: baggage.clone(),
+                    retry_of,
+                    fingerprint,
+                    geo: geo.clone(),
+                    security_flags,
+                    rendered_body,
+                    operation_id: operation_id.clone(),
+                    deprecated,
+                    consumer: consumer.clone(),
+                }));
+            }
+            let mut cancel_guard = CancelGuard {
+                inner: inner.clone(),
+                sampled,
+                request_id,
+                uri: uri.clone(),
+                method: method.clone(),
+                origin: origin.clone(),
+                start,
+                armed: true,
+            };
 
-            req.set_payload(repacked_payload);
-            let res: Result<ServiceResponse<B>, Error> = svc.call(req).await;
+            let capture = verbose
+                || observers
+                    .iter()
+                    .any(|observer| observer.capture_response(&req));
 
-            let elapsed = start.elapsed();
+            let mut outcome = Outcome::Completed;
+            let mut deadline_exceeded = false;
+            let (response, status, response_size, captured) = if let Some(intercepted) = intercepted
+            {
+                let status = intercepted.status();
+                let response_size = body_size(intercepted.body().size());
+                (
+                    Ok(req.into_response(intercepted).map_into_right_body()),
+                    status,
+                    response_size,
+                    None,
+                )
+            } else if let Some(err) = read_error {
+                // the body is broken, e.g. the client disconnected, so the handler would fail to read it too
+                let status = err.error_response().status();
+                (Err(err), status, None, None)
+            } else {
+                if let Some(repacked_payload) = repacked_payload {
+                    req.set_payload(repacked_payload);
+                }
+                if let Some(observer::ChaosInjection::Latency { delay }) = &chaos {
+                    actix_web::rt::time::sleep(*delay).await;
+                }
+                // a panicking handler would otherwise unwind through the hook, and the request would never end
+                let called =
+                    std::panic::catch_unwind(AssertUnwindSafe(|| current.enter(|| svc.call(req))));
+                let caught = match called {
+                    Ok(future) => {
+                        let future = AssertUnwindSafe(current.scope(future)).catch_unwind();
+                        match deadline {
+                            Some(deadline) => {
+                                let spent = inner.clock.now().saturating_duration_since(start);
+                                let timer = deadline_timer(deadline.saturating_sub(spent));
+                                pin_mut!(future, timer);
+                                // timer goes first, so a deadline which already passed fires before the handler ends
+                                match select(timer, future.as_mut()).await {
+                                    Either::Left(((), _)) => {
+                                        deadline_exceeded = true;
+                                        let data = DeadlineExceededData {
+                                            request_id,
+                                            deadline,
+                                            uri: uri.clone(),
+                                            method: method.clone(),
+                                        };
+                                        for observer in observers {
+                                            observer.on_deadline_exceeded(data.clone());
+                                        }
+                                        #[cfg(feature = "events")]
+                                        if sampled && events.receiver_count() > 0 {
+                                            let _ =
+                                                events.send(RequestEvent::DeadlineExceeded(data));
+                                        }
+                                        future.await
+                                    }
+                                    Either::Right((res, _)) => res,
+                                }
+                            }
+                            None => future.await,
+                        }
+                    }
+                    Err(panic) => Err(panic),
+                };
+                let res: Result<ServiceResponse<B>, Error> = match caught {
+                    Ok(res) => res,
+                    Err(panic) => {
+                        outcome = Outcome::Panicked(panic_message(&*panic));
+                        Err(ErrorInternalServerError("request handler panicked"))
+                    }
+                };
+
+                match res {
+                    Err(err) => {
+                        let status = err.error_response().status();
+                        (Err(err), status, None, None)
+                    }
+                    // streams, e.g. SSE, and large bodies pass through uncaptured, rather than being held in memory
+                    Ok(service_response)
+                        if capture
+                            && matches!(
+                                service_response.response().body().size(),
+                                BodySize::Sized(size) if size <= inner.max_body_size as u64
+                            ) =>
+                    {
+                        let status = service_response.status();
+                        let (req, res) = service_response.into_parts();
+                        let (res, body) = res.into_parts();
+                        match actix_web::body::to_bytes(body).await {
+                            Ok(body) => {
+                                let response_size = Some(body.len() as u64);
+                                let captured_body =
+                                    inner.scrubbed(res.headers().get(CONTENT_TYPE), body.clone());
+                                let captured = CapturedResponse {
+                                    headers: res.headers().clone(),
+                                    rendered: inner.rendered(&captured_body),
+                                    body: captured_body,
+                                };
+                                let res = res.set_body(EitherBody::right(BoxBody::new(body)));
+
+                                (
+                                    Ok(ServiceResponse::new(req, res)),
+                                    status,
+                                    response_size,
+                                    Some(captured),
+                                )
+                            }
+                            Err(err) => {
+                                let err = ErrorInternalServerError(err.into());
+                                let status = err.error_response().status();
+                                (Err(err), status, None, None)
+                            }
+                        }
+                    }
+                    Ok(service_response) => {
+                        let status = service_response.status();
+                        let response_size = body_size(service_response.response().body().size());
 
-            let (response, status) = match res {
-                Err(err) => {
-                    let status = err.error_response().status();
-                    (Err(err), status)
+                        (
+                            Ok(service_response.map_into_left_body()),
+                            status,
+                            response_size,
+                            None,
+                        )
+                    }
                 }
-                Ok(service_response) => {
-                    let status = service_response.status();
+            };
 
-                    (Ok(service_response), status)
+            let elapsed = inner.clock.now().saturating_duration_since(start);
+            cancel_guard.armed = false;
+            let failed_req = match &response {
+                Ok(res) if skipped && status.is_server_error() => Some(res.request().clone()),
+                _ => None,
+            };
+            let sampled = sampled || failed_req.is_some();
+            if let Some(req) = failed_req {
+                let req = ServiceRequest::from_request(req);
+                for observer in inner.sampled_observers(sampled) {
+                    observer.on_request_started(RequestStartData {
+                        req: &req,
+                        request_id,
+                        uri: uri.clone(),
+                        method: method.clone(),
+                        body: BytesMut::new(),
+                        origin: origin.clone(),
+                        body_sizes: None,
+                        body_truncated: false,
+                        grpc: grpc.clone(),
+                        sampled: false,
+                        force_sampled: false,
+                        baggage: baggage.clone(),
+                        retry_of,
+                        fingerprint,
+                        geo: geo.clone(),
+                        security_flags,
+                        rendered_body: None,
+                        operation_id: operation_id.clone(),
+                        deprecated,
+                        consumer: consumer.clone(),
+                    })
+                }
+                #[cfg(feature = "events")]
+                if events.receiver_count() > 0 {
+                    let _ = events.send(RequestEvent::Started(event::RequestStartEvent {
+                        request_id,
+                        uri: uri.clone(),
+                        method: method.clone(),
+                        body: Bytes::new(),
+                        origin: origin.clone(),
+                        body_sizes: None,
+                        grpc: grpc.clone(),
+                        force_sampled: false,
+                        baggage: baggage.clone(),
+                        retry_of,
+                        fingerprint,
+                        geo: geo.clone(),
+                        security_flags,
+                        rendered_body: None,
+                        operation_id: operation_id.clone(),
+                        deprecated,
+                        consumer: consumer.clone(),
+                    }));
+                }
+            }
+            let deferred = match &response {
+                Ok(res) if upgrade.is_none() => inner.defers_end(res.headers()),
+                _ => false,
+            };
+            let in_flight = if deferred {
+                Some(in_flight)
+            } else {
+                in_flight.end(status, elapsed);
+                None
+            };
+            #[cfg(feature = "quota")]
+            let usage = if deferred {
+                usage
+            } else {
+                if let Some(usage) = usage {
+                    usage.end(response_size);
                 }
+                None
             };
-            for observer in &observers {
-                observer.on_request_ended(RequestEndData {
-                    request_id,
-                    elapsed,
-                    uri: uri.to_string(),
-                    method: method.to_string(),
-                    status,
-                })
+            let grpc = grpc.map(|mut call| {
+                call.status = response
+                    .as_ref()
+                    .ok()
+                    .and_then(|res| grpc_status(res.headers()));
+                call
+            });
+            let error_category = inner.error_classifier.as_ref().and_then(|classifier| {
+                let error = match &response {
+                    Ok(res) => res.response().error()?,
+                    Err(err) => err,
+                };
+                classifier.classify(error)
+            });
+            let attributes = response
+                .as_ref()
+                .ok()
+                .and_then(|res| res.request().extensions().get::<HookAttributes>().cloned());
+            let response_attributes = response.as_ref().ok().and_then(|res| {
+                res.response()
+                    .extensions()
+                    .get::<HookResponseAttributes>()
+                    .cloned()
+            });
+            let cache = response.as_ref().ok().and_then(|res| {
+                CacheSignals::from_headers(res.headers(), inner.cache_rule.as_deref())
+            });
+            let generated_by_framework =
+                matches!(&response, Ok(res) if generated_by_framework(res));
+            #[cfg(feature = "openapi")]
+            if let Some(Some(operation)) = &operation {
+                if !operation.expects(status) {
+                    api_deviations.push(observer::ApiDeviation::UnexpectedStatus {
+                        status: status.as_u16(),
+                    });
+                }
+            }
+            let mut request_body_sizes = None;
+            let request_body = match teed {
+                Some(captured) => {
+                    body = captured.take();
+                    if inner.decompress_body {
+                        let raw = body.clone().freeze();
+                        if let Some((decoded, sizes)) =
+                            decode_body(raw, content_encoding.as_ref(), inner.max_body_size).await
+                        {
+                            body = decoded;
+                            request_body_sizes = Some(sizes);
+                        }
+                    }
+                    #[cfg(feature = "scrub")]
+                    inner.scrub(content_type.as_ref(), &mut body);
+                    Some(body.clone().freeze())
+                }
+                None => None,
+            };
+            let rendered_request_body = request_body.as_ref().and_then(|body| inner.rendered(body));
+            if let Some((threshold, callback, route, headers)) = slow_request {
+                if elapsed > threshold {
+                    callback(&SlowRequestData {
+                        request_id,
+                        elapsed,
+                        uri: uri.clone(),
+                        method: method.clone(),
+                        route,
+                        status,
+                        headers,
+                        body_prefix: Bytes::copy_from_slice(
+                            &body[..body.len().min(SLOW_REQUEST_BODY_PREFIX)],
+                        ),
+                    });
+                }
+            }
+            let end = RequestEndData {
+                request_id,
+                elapsed,
+                uri,
+                method,
+                status,
+                response_size,
+                request_body,
+                response: captured,
+                outcome,
+                deadline_exceeded,
+                origin,
+                request_body_sizes,
+                headers_elapsed: None,
+                grpc,
+                generated_by_framework,
+                baggage,
+                error_category,
+                cache,
+                retry_of,
+                fingerprint,
+                geo,
+                security_flags,
+                rendered_request_body,
+                operation_id,
+                api_deviations,
+                chaos,
+                deprecated,
+                consumer,
+                attributes,
+                response_attributes,
+            };
+            let Some(protocol) = upgrade else {
+                let Some(in_flight) = in_flight else {
+                    inner.ended(sampled, end);
+                    return response;
+                };
+                // the request ends once actix sent or dropped the body
+                let inner = inner.clone();
+                return response.map(|res| {
+                    res.map_body(move |_, body| {
+                        EitherBody::right(BoxBody::new(ObservedBody::new(body, move || {
+                            let mut end = end;
+                            let elapsed = inner.clock.now().saturating_duration_since(start);
+                            end.headers_elapsed = Some(end.elapsed);
+                            end.elapsed = elapsed;
+                            in_flight.end(end.status, elapsed);
+                            #[cfg(feature = "quota")]
+                            if let Some(usage) = usage {
+                                usage.end(end.response_size);
+                            }
+                            inner.ended(sampled, end);
+                        })))
+                    })
+                });
+            };
+            let data = UpgradeData {
+                handshake: end,
+                protocol,
+                duration: None,
+            };
+            match response {
+                Ok(res) if inner.track_upgrades && status == StatusCode::SWITCHING_PROTOCOLS => {
+                    // actix keeps the body while the upgraded connection is open
+                    let opened = inner.clock.now();
+                    let inner = inner.clone();
+                    Ok(res.map_body(move |_, body| {
+                        EitherBody::right(BoxBody::new(ObservedBody::new(body, move || {
+                            let mut data = data;
+                            data.duration =
+                                Some(inner.clock.now().saturating_duration_since(opened));
+                            inner.upgraded(sampled, data);
+                        })))
+                    }))
+                }
+                response => {
+                    inner.upgraded(sampled, data);
+                    response
+                }
             }
-
-            response
         };
 
-        Box::pin(future_response)
+        Either::Right(Box::pin(future_response))
     }
 }
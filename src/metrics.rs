@@ -0,0 +1,770 @@
+//! [MetricsCollector] aggregates request latencies and payload sizes per route into HDR histograms.
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use actix_web::dev::ServiceRequest;
+use actix_web::http::header::CONTENT_LENGTH;
+use hdrhistogram::Histogram;
+use uuid::Uuid;
+
+pub use crate::consumer::OTHER_CONSUMER;
+use crate::fingerprint::Fingerprint;
+use crate::observer::{Observer, RequestCancelledData, RequestEndData, RequestStartData};
+
+/// Route under which requests are aggregated once `max_routes` distinct routes are tracked.
+pub const OTHER_ROUTE: &str = "<other>";
+
+/// Method and route pattern (e.g. `/users/{id}`) identifying an endpoint.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RouteKey {
+    pub method: String,
+    pub route: String,
+}
+
+/// Latency distribution of an endpoint since the last [MetricsCollector::reset].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LatencySummary {
+    pub count: u64,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+/// Distribution of body sizes of an endpoint in bytes, since the last [MetricsCollector::reset].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SizeSummary {
+    pub count: u64,
+    pub p50: u64,
+    pub p95: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+/// Histograms of an endpoint, latencies in microseconds and sizes in bytes.
+struct RouteMetrics {
+    latency: Histogram<u64>,
+    request_size: Histogram<u64>,
+    response_size: Histogram<u64>,
+    apdex: ApdexCounts,
+    cache: CacheCounts,
+    duplicates: u64,
+}
+
+/// Requests of a consumer since the last [MetricsCollector::reset], see
+/// [ConsumerResolver](crate::consumer::ConsumerResolver). Sizes of streamed responses are unknown and not counted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConsumerSummary {
+    pub latency: LatencySummary,
+    pub server_errors: u64,
+    pub request_bytes: u64,
+    pub response_bytes: u64,
+}
+
+/// Latency histogram in microseconds and counters of a consumer.
+struct ConsumerMetrics {
+    latency: Histogram<u64>,
+    server_errors: u64,
+    request_bytes: u64,
+    response_bytes: u64,
+}
+
+/// Requests counted towards an Apdex score.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ApdexCounts {
+    pub satisfied: u64,
+    pub tolerating: u64,
+    pub total: u64,
+}
+
+/// Responses of an endpoint which were cache hits or misses, as told by their
+/// [CacheSignals](crate::cache::CacheSignals). Responses without cache signals aren't counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CacheCounts {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Whole state of a [MetricsCollector], from [MetricsCollector::snapshot]. With `serde` feature enabled, it is
+/// (de)serializable, e.g. to send per worker or per process snapshots to one place and
+/// [merge](MetricsCollector::merge) them into a single view.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MetricsSnapshot {
+    /// Endpoints sorted by method and route.
+    pub routes: Vec<RouteSnapshot>,
+    /// Consumers sorted by name.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub consumers: Vec<ConsumerSnapshot>,
+}
+
+/// Histograms and Apdex counts of an endpoint, latencies in microseconds and sizes in bytes.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RouteSnapshot {
+    pub method: String,
+    pub route: String,
+    pub latency: HistogramSnapshot,
+    pub request_size: HistogramSnapshot,
+    pub response_size: HistogramSnapshot,
+    pub apdex: ApdexCounts,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub cache: CacheCounts,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub duplicates: u64,
+}
+
+/// Latency histogram in microseconds and counters of a consumer.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConsumerSnapshot {
+    pub consumer: String,
+    pub latency: HistogramSnapshot,
+    pub server_errors: u64,
+    pub request_bytes: u64,
+    pub response_bytes: u64,
+}
+
+/// Recorded values of a histogram, as value and count pairs, sorted by value. Values stand for their whole HDR
+/// bucket, so they are as precise as the collector's significant figures.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HistogramSnapshot {
+    pub counts: Vec<(u64, u64)>,
+}
+
+impl HistogramSnapshot {
+    fn of(histogram: &Histogram<u64>) -> Self {
+        Self {
+            counts: histogram
+                .iter_recorded()
+                .map(|value| (value.value_iterated_to(), value.count_at_value()))
+                .collect(),
+        }
+    }
+
+    // auto resizing histograms only fail for values beyond `u64::MAX / 2`
+    fn record_into(&self, histogram: &mut Histogram<u64>) {
+        for (value, count) in &self.counts {
+            let _ = histogram.record_n((*value).min(u64::MAX / 2), *count);
+        }
+    }
+
+    fn merge(&mut self, other: &HistogramSnapshot) {
+        let mut counts: BTreeMap<u64, u64> = self.counts.iter().copied().collect();
+        for (value, count) in &other.counts {
+            *counts.entry(*value).or_default() += count;
+        }
+        self.counts = counts.into_iter().collect();
+    }
+}
+
+impl MetricsSnapshot {
+    /// Adds histograms and counts of `other` to endpoints of this snapshot, e.g. to combine snapshots of workers
+    /// without a collector.
+    pub fn merge(&mut self, other: &MetricsSnapshot) {
+        for route in &other.routes {
+            let existing = self
+                .routes
+                .iter_mut()
+                .find(|existing| existing.method == route.method && existing.route == route.route);
+            match existing {
+                Some(existing) => {
+                    existing.latency.merge(&route.latency);
+                    existing.request_size.merge(&route.request_size);
+                    existing.response_size.merge(&route.response_size);
+                    existing.apdex = existing.apdex.add(route.apdex);
+                    existing.cache = existing.cache.add(route.cache);
+                    existing.duplicates += route.duplicates;
+                }
+                None => self.routes.push(route.clone()),
+            }
+        }
+        self.routes
+            .sort_by(|a, b| (&a.method, &a.route).cmp(&(&b.method, &b.route)));
+        for consumer in &other.consumers {
+            let existing = self
+                .consumers
+                .iter_mut()
+                .find(|existing| existing.consumer == consumer.consumer);
+            match existing {
+                Some(existing) => {
+                    existing.latency.merge(&consumer.latency);
+                    existing.server_errors += consumer.server_errors;
+                    existing.request_bytes += consumer.request_bytes;
+                    existing.response_bytes += consumer.response_bytes;
+                }
+                None => self.consumers.push(consumer.clone()),
+            }
+        }
+        self.consumers.sort_by(|a, b| a.consumer.cmp(&b.consumer));
+    }
+}
+
+impl ApdexCounts {
+    /// Satisfied within `threshold`, tolerating within 4 times `threshold`, otherwise or on a server error
+    /// frustrated.
+    fn record(&mut self, elapsed: Duration, threshold: Duration, server_error: bool) {
+        self.total += 1;
+        if server_error {
+            return;
+        }
+        if elapsed <= threshold {
+            self.satisfied += 1;
+        } else if elapsed <= threshold * 4 {
+            self.tolerating += 1;
+        }
+    }
+
+    fn add(self, other: ApdexCounts) -> ApdexCounts {
+        ApdexCounts {
+            satisfied: self.satisfied + other.satisfied,
+            tolerating: self.tolerating + other.tolerating,
+            total: self.total + other.total,
+        }
+    }
+
+    /// Apdex score between 0 and 1, if any requests were counted.
+    pub fn score(&self) -> Option<f64> {
+        if self.total == 0 {
+            return None;
+        }
+        Some((self.satisfied as f64 + self.tolerating as f64 / 2.0) / self.total as f64)
+    }
+}
+
+impl CacheCounts {
+    fn record(&mut self, hit: Option<bool>) {
+        match hit {
+            Some(true) => self.hits += 1,
+            Some(false) => self.misses += 1,
+            None => {}
+        }
+    }
+
+    fn add(self, other: CacheCounts) -> CacheCounts {
+        CacheCounts {
+            hits: self.hits + other.hits,
+            misses: self.misses + other.misses,
+        }
+    }
+
+    /// Share of hits between 0 and 1, if any hits or misses were counted.
+    pub fn hit_ratio(&self) -> Option<f64> {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            return None;
+        }
+        Some(self.hits as f64 / total as f64)
+    }
+}
+
+/// Records elapsed time of every ended request into an HDR histogram per method and route pattern, and answers
+/// percentile queries, e.g. which endpoint is slow. Request and response body sizes are recorded alongside, e.g.
+/// to spot payload bloat or choose a compression threshold. Sizes of streamed responses are unknown and skipped.
+/// Cache hits and misses are counted per endpoint too, for cache efficiency per route, and with
+/// [MetricsCollector::count_duplicates] requests repeating the [Fingerprint] of a recent one. Requests of
+/// consumers, see [RequestHook::consumer_resolver](crate::RequestHook::consumer_resolver), are aggregated per
+/// consumer too, up to `max_consumers` of them, after which they are aggregated under [OTHER_CONSUMER].
+///
+/// Apdex scores are kept per endpoint and overall, with a threshold `T` of 500ms unless configured otherwise:
+/// requests within `T` are satisfied, within `4T` tolerating, slower requests and server errors frustrated.
+///
+/// Collector is an [Observer] and a cloneable handle at the same time: register it with the hook, and query a
+/// clone from handlers or a reporting task. Register the same collector with hooks of all workers to aggregate
+/// them together. Requests without a matched route are aggregated by path, up to `max_routes` distinct
+/// routes, after which they are aggregated under [OTHER_ROUTE].
+///
+/// ```
+/// use std::rc::Rc;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::metrics::MetricsCollector;
+///
+/// let metrics = MetricsCollector::new();
+/// let request_hook = RequestHook::new().register(Rc::new(metrics.clone()));
+/// // later, e.g. from an admin handler
+/// for (key, summary) in metrics.slowest(5) {
+///     println!("{} {} p99 {:?}", key.method, key.route, summary.p99);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct MetricsCollector {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    significant_figures: u8,
+    max_routes: usize,
+    apdex_threshold: Duration,
+    apdex_route_thresholds: HashMap<String, Duration>,
+    routes: Mutex<HashMap<RouteKey, RouteMetrics>>,
+    /// Up to `duplicates_capacity` recent fingerprints, oldest first.
+    duplicates_capacity: usize,
+    fingerprints: Mutex<(HashSet<Fingerprint>, VecDeque<Fingerprint>)>,
+    max_consumers: usize,
+    consumers: Mutex<HashMap<String, ConsumerMetrics>>,
+    /// Route and request body size of started requests.
+    pending: Mutex<HashMap<Uuid, (String, u64)>>,
+}
+
+impl Default for MetricsCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetricsCollector {
+    /// Creates a collector with 2 significant figures of precision, up to 500 routes and 1000 consumers.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                significant_figures: 2,
+                max_routes: 500,
+                apdex_threshold: Duration::from_millis(500),
+                apdex_route_thresholds: HashMap::new(),
+                routes: Mutex::new(HashMap::new()),
+                duplicates_capacity: 0,
+                fingerprints: Mutex::new((HashSet::new(), VecDeque::new())),
+                max_consumers: 1000,
+                consumers: Mutex::new(HashMap::new()),
+                pending: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Precision of recorded latencies in significant decimal figures, clamped between 1 and 5. Higher precision
+    /// uses more memory per route.
+    pub fn significant_figures(mut self, significant_figures: u8) -> Self {
+        Arc::get_mut(&mut self.inner).unwrap().significant_figures =
+            significant_figures.clamp(1, 5);
+        self
+    }
+
+    /// Maximum number of distinct method and route pairs tracked.
+    pub fn max_routes(mut self, max_routes: usize) -> Self {
+        Arc::get_mut(&mut self.inner).unwrap().max_routes = max_routes;
+        self
+    }
+
+    /// Maximum number of distinct consumers tracked.
+    pub fn max_consumers(mut self, max_consumers: usize) -> Self {
+        Arc::get_mut(&mut self.inner).unwrap().max_consumers = max_consumers;
+        self
+    }
+
+    /// Apdex threshold `T` of endpoints without their own threshold, 500ms by default.
+    pub fn apdex_threshold(mut self, threshold: Duration) -> Self {
+        Arc::get_mut(&mut self.inner).unwrap().apdex_threshold = threshold;
+        self
+    }
+
+    /// Apdex threshold `T` of a route pattern (e.g. `/reports/{id}`), for all its methods.
+    pub fn apdex_route_threshold<R: Into<String>>(mut self, route: R, threshold: Duration) -> Self {
+        Arc::get_mut(&mut self.inner)
+            .unwrap()
+            .apdex_route_thresholds
+            .insert(route.into(), threshold);
+        self
+    }
+
+    /// Counts requests repeating the [Fingerprint] of one of the last `capacity` fingerprinted requests, e.g.
+    /// replayed webhooks, see [RequestHook::fingerprint](crate::RequestHook::fingerprint).
+    pub fn count_duplicates(mut self, capacity: usize) -> Self {
+        Arc::get_mut(&mut self.inner).unwrap().duplicates_capacity = capacity;
+        self
+    }
+
+    /// Apdex score of an endpoint between 0 and 1, if it had requests.
+    pub fn apdex(&self, method: &str, route: &str) -> Option<f64> {
+        let key = RouteKey {
+            method: method.to_string(),
+            route: route.to_string(),
+        };
+        let routes = self.inner.routes.lock().unwrap();
+        routes.get(&key)?.apdex.score()
+    }
+
+    /// Apdex score of all endpoints together, each request judged by the threshold of its route.
+    pub fn global_apdex(&self) -> Option<f64> {
+        let routes = self.inner.routes.lock().unwrap();
+        let total = routes
+            .values()
+            .fold(ApdexCounts::default(), |total, metrics| {
+                total.add(metrics.apdex)
+            });
+        total.score()
+    }
+
+    /// Latency at quantile `quantile` (e.g. `0.99`) of an endpoint, if it had requests.
+    pub fn percentile(&self, method: &str, route: &str, quantile: f64) -> Option<Duration> {
+        let key = RouteKey {
+            method: method.to_string(),
+            route: route.to_string(),
+        };
+        let routes = self.inner.routes.lock().unwrap();
+        let histogram = routes
+            .get(&key)
+            .map(|metrics| &metrics.latency)
+            .filter(|histogram| !histogram.is_empty())?;
+        Some(Duration::from_micros(histogram.value_at_quantile(quantile)))
+    }
+
+    /// Latency summary of an endpoint, if it had requests.
+    pub fn latency(&self, method: &str, route: &str) -> Option<LatencySummary> {
+        let key = RouteKey {
+            method: method.to_string(),
+            route: route.to_string(),
+        };
+        let routes = self.inner.routes.lock().unwrap();
+        routes
+            .get(&key)
+            .map(|metrics| &metrics.latency)
+            .filter(|histogram| !histogram.is_empty())
+            .map(summary)
+    }
+
+    /// Cache hits and misses of an endpoint, if it had requests.
+    pub fn cache(&self, method: &str, route: &str) -> Option<CacheCounts> {
+        let key = RouteKey {
+            method: method.to_string(),
+            route: route.to_string(),
+        };
+        let routes = self.inner.routes.lock().unwrap();
+        routes.get(&key).map(|metrics| metrics.cache)
+    }
+
+    /// Requests of an endpoint repeating a recent fingerprint, if it had requests, see
+    /// [MetricsCollector::count_duplicates].
+    pub fn duplicates(&self, method: &str, route: &str) -> Option<u64> {
+        let key = RouteKey {
+            method: method.to_string(),
+            route: route.to_string(),
+        };
+        let routes = self.inner.routes.lock().unwrap();
+        routes.get(&key).map(|metrics| metrics.duplicates)
+    }
+
+    /// Request body size summary of an endpoint, if it had requests.
+    pub fn request_sizes(&self, method: &str, route: &str) -> Option<SizeSummary> {
+        self.sizes(method, route, |metrics| &metrics.request_size)
+    }
+
+    /// Response body size summary of an endpoint, if it had responses of known size.
+    pub fn response_sizes(&self, method: &str, route: &str) -> Option<SizeSummary> {
+        self.sizes(method, route, |metrics| &metrics.response_size)
+    }
+
+    /// Latency, server errors and body sizes of requests of `consumer`, if it had requests.
+    pub fn consumer(&self, consumer: &str) -> Option<ConsumerSummary> {
+        let consumers = self.inner.consumers.lock().unwrap();
+        consumers.get(consumer).map(ConsumerMetrics::summary)
+    }
+
+    /// Summaries of all consumers with requests, sorted by consumer.
+    pub fn consumers(&self) -> Vec<(String, ConsumerSummary)> {
+        let consumers = self.inner.consumers.lock().unwrap();
+        let mut summaries: Vec<_> = consumers
+            .iter()
+            .map(|(consumer, metrics)| (consumer.clone(), metrics.summary()))
+            .collect();
+        summaries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        summaries
+    }
+
+    /// Latency summaries of all endpoints with requests.
+    pub fn summaries(&self) -> Vec<(RouteKey, LatencySummary)> {
+        let routes = self.inner.routes.lock().unwrap();
+        let mut summaries: Vec<_> = routes
+            .iter()
+            .filter(|(_, metrics)| !metrics.latency.is_empty())
+            .map(|(key, metrics)| (key.clone(), summary(&metrics.latency)))
+            .collect();
+        summaries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        summaries
+    }
+
+    /// Up to `n` endpoints with the highest p99 latency, slowest first.
+    pub fn slowest(&self, n: usize) -> Vec<(RouteKey, LatencySummary)> {
+        let mut summaries = self.summaries();
+        summaries.sort_by_key(|(_, summary)| std::cmp::Reverse(summary.p99));
+        summaries.truncate(n);
+        summaries
+    }
+
+    /// Copy of all recorded histograms and Apdex counts.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let routes = self.inner.routes.lock().unwrap();
+        let mut snapshot = MetricsSnapshot {
+            routes: routes
+                .iter()
+                .map(|(key, metrics)| RouteSnapshot {
+                    method: key.method.clone(),
+                    route: key.route.clone(),
+                    latency: HistogramSnapshot::of(&metrics.latency),
+                    request_size: HistogramSnapshot::of(&metrics.request_size),
+                    response_size: HistogramSnapshot::of(&metrics.response_size),
+                    apdex: metrics.apdex,
+                    cache: metrics.cache,
+                    duplicates: metrics.duplicates,
+                })
+                .collect(),
+            consumers: self
+                .inner
+                .consumers
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(consumer, metrics)| ConsumerSnapshot {
+                    consumer: consumer.clone(),
+                    latency: HistogramSnapshot::of(&metrics.latency),
+                    server_errors: metrics.server_errors,
+                    request_bytes: metrics.request_bytes,
+                    response_bytes: metrics.response_bytes,
+                })
+                .collect(),
+        };
+        snapshot
+            .routes
+            .sort_by(|a, b| (&a.method, &a.route).cmp(&(&b.method, &b.route)));
+        snapshot
+            .consumers
+            .sort_by(|a, b| a.consumer.cmp(&b.consumer));
+        snapshot
+    }
+
+    /// Adds histograms and Apdex counts of `snapshot` to this collector, e.g. snapshots of other workers or
+    /// processes into an empty collector, to query all of them together. Endpoints over `max_routes` are merged
+    /// under [OTHER_ROUTE].
+    pub fn merge(&self, snapshot: &MetricsSnapshot) {
+        for route in &snapshot.routes {
+            let key = RouteKey {
+                method: route.method.clone(),
+                route: route.route.clone(),
+            };
+            self.with_route(key, |metrics, _| {
+                route.latency.record_into(&mut metrics.latency);
+                route.request_size.record_into(&mut metrics.request_size);
+                route.response_size.record_into(&mut metrics.response_size);
+                metrics.apdex = metrics.apdex.add(route.apdex);
+                metrics.cache = metrics.cache.add(route.cache);
+                metrics.duplicates += route.duplicates;
+            });
+        }
+        for consumer in &snapshot.consumers {
+            self.with_consumer(&consumer.consumer, |metrics| {
+                consumer.latency.record_into(&mut metrics.latency);
+                metrics.server_errors += consumer.server_errors;
+                metrics.request_bytes += consumer.request_bytes;
+                metrics.response_bytes += consumer.response_bytes;
+            });
+        }
+    }
+
+    /// Clears recorded latencies, starting a new window.
+    pub fn reset(&self) {
+        self.inner.routes.lock().unwrap().clear();
+        self.inner.consumers.lock().unwrap().clear();
+        *self.inner.fingerprints.lock().unwrap() = Default::default();
+    }
+
+    fn sizes<F: Fn(&RouteMetrics) -> &Histogram<u64>>(
+        &self,
+        method: &str,
+        route: &str,
+        histogram: F,
+    ) -> Option<SizeSummary> {
+        let key = RouteKey {
+            method: method.to_string(),
+            route: route.to_string(),
+        };
+        let routes = self.inner.routes.lock().unwrap();
+        let histogram = routes
+            .get(&key)
+            .map(histogram)
+            .filter(|histogram| !histogram.is_empty())?;
+        Some(SizeSummary {
+            count: histogram.len(),
+            p50: histogram.value_at_quantile(0.5),
+            p95: histogram.value_at_quantile(0.95),
+            p99: histogram.value_at_quantile(0.99),
+            max: histogram.max(),
+        })
+    }
+
+    /// Whether `fingerprint` is among recent ones, remembering it otherwise.
+    fn is_duplicate(&self, fingerprint: Fingerprint) -> bool {
+        let capacity = self.inner.duplicates_capacity;
+        if capacity == 0 {
+            return false;
+        }
+        let mut fingerprints = self.inner.fingerprints.lock().unwrap();
+        let (recent, order) = &mut *fingerprints;
+        if recent.contains(&fingerprint) {
+            return true;
+        }
+        if order.len() >= capacity {
+            if let Some(oldest) = order.pop_front() {
+                recent.remove(&oldest);
+            }
+        }
+        recent.insert(fingerprint);
+        order.push_back(fingerprint);
+        false
+    }
+
+    fn with_route<F: FnOnce(&mut RouteMetrics, Duration)>(&self, key: RouteKey, f: F) {
+        let mut routes = self.inner.routes.lock().unwrap();
+        let key = if routes.len() >= self.inner.max_routes && !routes.contains_key(&key) {
+            RouteKey {
+                method: key.method,
+                route: OTHER_ROUTE.to_string(),
+            }
+        } else {
+            key
+        };
+        let threshold = self
+            .inner
+            .apdex_route_thresholds
+            .get(&key.route)
+            .copied()
+            .unwrap_or(self.inner.apdex_threshold);
+        let significant_figures = self.inner.significant_figures;
+        let metrics = routes.entry(key).or_insert_with(|| RouteMetrics {
+            latency: Histogram::new(significant_figures).unwrap(),
+            request_size: Histogram::new(significant_figures).unwrap(),
+            response_size: Histogram::new(significant_figures).unwrap(),
+            apdex: ApdexCounts::default(),
+            cache: CacheCounts::default(),
+            duplicates: 0,
+        });
+        f(metrics, threshold);
+    }
+
+    fn with_consumer<F: FnOnce(&mut ConsumerMetrics)>(&self, consumer: &str, f: F) {
+        let mut consumers = self.inner.consumers.lock().unwrap();
+        let consumer =
+            if consumers.len() >= self.inner.max_consumers && !consumers.contains_key(consumer) {
+                OTHER_CONSUMER
+            } else {
+                consumer
+            };
+        if !consumers.contains_key(consumer) {
+            consumers.insert(
+                consumer.to_string(),
+                ConsumerMetrics {
+                    latency: Histogram::new(self.inner.significant_figures).unwrap(),
+                    server_errors: 0,
+                    request_bytes: 0,
+                    response_bytes: 0,
+                },
+            );
+        }
+        f(consumers.get_mut(consumer).unwrap());
+    }
+}
+
+impl ConsumerMetrics {
+    fn summary(&self) -> ConsumerSummary {
+        ConsumerSummary {
+            latency: summary(&self.latency),
+            server_errors: self.server_errors,
+            request_bytes: self.request_bytes,
+            response_bytes: self.response_bytes,
+        }
+    }
+}
+
+impl RouteMetrics {
+    // auto resizing histograms only fail for values beyond `u64::MAX / 2`
+    fn record_latency(&mut self, elapsed: Duration) {
+        let _ = self
+            .latency
+            .record(elapsed.as_micros().min(u64::MAX as u128 / 2) as u64);
+    }
+
+    fn record_sizes(&mut self, request_size: u64, response_size: Option<u64>) {
+        let _ = self.request_size.record(request_size.min(u64::MAX / 2));
+        if let Some(response_size) = response_size {
+            let _ = self.response_size.record(response_size.min(u64::MAX / 2));
+        }
+    }
+}
+
+fn summary(histogram: &Histogram<u64>) -> LatencySummary {
+    LatencySummary {
+        count: histogram.len(),
+        p50: Duration::from_micros(histogram.value_at_quantile(0.5)),
+        p95: Duration::from_micros(histogram.value_at_quantile(0.95)),
+        p99: Duration::from_micros(histogram.value_at_quantile(0.99)),
+        max: Duration::from_micros(histogram.max()),
+    }
+}
+
+/// Request body size from `Content-Length`, so the body doesn't have to be buffered, or of the body buffered for
+/// other observers.
+fn request_size(data: &RequestStartData) -> u64 {
+    data.req
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok()?.parse().ok())
+        .unwrap_or(data.body.len() as u64)
+}
+
+impl Observer for MetricsCollector {
+    fn on_request_started(&self, data: RequestStartData) {
+        let route = crate::normalize::route(data.req);
+        self.inner
+            .pending
+            .lock()
+            .unwrap()
+            .insert(data.request_id, (route, request_size(&data)));
+    }
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        let (route, request_size) =
+            match self.inner.pending.lock().unwrap().remove(&data.request_id) {
+                Some(pending) => pending,
+                None => return,
+            };
+        let key = RouteKey {
+            method: data.method.to_string(),
+            route,
+        };
+        let (elapsed, status, response_size) = (data.elapsed, data.status, data.response_size);
+        if let Some(consumer) = &data.consumer {
+            self.with_consumer(consumer, |metrics| {
+                // auto resizing histograms only fail for values beyond `u64::MAX / 2`
+                let _ = metrics
+                    .latency
+                    .record(elapsed.as_micros().min(u64::MAX as u128 / 2) as u64);
+                metrics.server_errors += status.is_server_error() as u64;
+                metrics.request_bytes += request_size;
+                metrics.response_bytes += response_size.unwrap_or(0);
+            });
+        }
+        let hit = data.cache.and_then(|cache| cache.hit);
+        let duplicate = data
+            .fingerprint
+            .is_some_and(|fingerprint| self.is_duplicate(fingerprint));
+        self.with_route(key, |metrics, threshold| {
+            metrics.record_latency(elapsed);
+            metrics.record_sizes(request_size, response_size);
+            metrics.cache.record(hit);
+            metrics.duplicates += duplicate as u64;
+            metrics
+                .apdex
+                .record(elapsed, threshold, status.is_server_error());
+        });
+    }
+
+    fn on_request_cancelled(&self, data: RequestCancelledData) {
+        self.inner.pending.lock().unwrap().remove(&data.request_id);
+    }
+
+    fn capture_body(&self, _req: &ServiceRequest) -> bool {
+        false
+    }
+}
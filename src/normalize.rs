@@ -0,0 +1,96 @@
+//! [PathNormalizer] groups unmatched paths into a bounded set of routes.
+use std::sync::Arc;
+
+use actix_web::dev::ServiceRequest;
+use actix_web::HttpMessage;
+use regex::Regex;
+
+/// Replaces dynamic path segments with placeholders, e.g. `/users/42` with `/users/:id`, so that paths of requests
+/// without a matched route pattern (404s, proxied or catch-all routes) don't blow up the cardinality of stats,
+/// metrics and log labels.
+///
+/// Every path segment is checked against rules in order, the first rule matching a segment replaces it with its
+/// placeholder. Custom rules are checked before the default ones:
+/// * numbers - `:id`
+/// * UUIDs - `:uuid`
+/// * hexadecimal hashes of 16 or more characters - `:hash`
+///
+/// Enable with [RequestHook::normalize_paths](crate::RequestHook::normalize_paths). Matched route patterns are
+/// used as they are.
+///
+/// ```
+/// use actix_request_hook::normalize::PathNormalizer;
+///
+/// let normalizer = PathNormalizer::default().rule("^[a-z]{2}-[A-Z]{2}$", ":locale");
+/// assert_eq!(normalizer.normalize("/en-US/users/42"), "/:locale/users/:id");
+/// ```
+#[derive(Clone, Debug)]
+pub struct PathNormalizer {
+    rules: Arc<Vec<(Regex, String)>>,
+}
+
+impl Default for PathNormalizer {
+    fn default() -> Self {
+        Self {
+            rules: Arc::new(vec![
+                (Regex::new(r"^\d+$").unwrap(), ":id".to_string()),
+                (
+                    Regex::new(
+                        r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$",
+                    )
+                    .unwrap(),
+                    ":uuid".to_string(),
+                ),
+                (
+                    Regex::new(r"^[0-9a-fA-F]{16,}$").unwrap(),
+                    ":hash".to_string(),
+                ),
+            ]),
+        }
+    }
+}
+
+impl PathNormalizer {
+    /// Normalizer without the default rules, only with rules added by [PathNormalizer::rule].
+    pub fn empty() -> Self {
+        Self {
+            rules: Arc::new(Vec::new()),
+        }
+    }
+
+    /// Replaces path segments matching `pattern` with `placeholder`, before rules added earlier and the default
+    /// rules. Pattern is matched against a single segment, without slashes, so anchor it to match whole segments.
+    pub fn rule<T: Into<String>>(mut self, pattern: &str, placeholder: T) -> Self {
+        let rule = (Regex::new(pattern).unwrap(), placeholder.into());
+        Arc::make_mut(&mut self.rules).insert(0, rule);
+        self
+    }
+
+    /// Path with every dynamic segment replaced by its placeholder.
+    pub fn normalize(&self, path: &str) -> String {
+        path.split('/')
+            .map(|segment| {
+                self.rules
+                    .iter()
+                    .find(|(regex, _)| !segment.is_empty() && regex.is_match(segment))
+                    .map_or(segment, |(_, placeholder)| placeholder.as_str())
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+/// Route of a request for grouping: matched route pattern, or path normalized by the hook's [PathNormalizer], or
+/// the path as it is if the hook doesn't normalize paths.
+///
+/// Built-in observers group by this route, custom observers can do the same from
+/// [RequestStartData::req](crate::observer::RequestStartData).
+pub fn route(req: &ServiceRequest) -> String {
+    if let Some(pattern) = req.match_pattern() {
+        return pattern;
+    }
+    match req.extensions().get::<PathNormalizer>() {
+        Some(normalizer) => normalizer.normalize(req.path()),
+        None => req.path().to_string(),
+    }
+}
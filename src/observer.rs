@@ -1,11 +1,26 @@
 //! [`Observer`] trait and function implementations.
+use std::fmt;
+use std::future::ready;
+use std::sync::Arc;
 use std::time::Duration;
 
 use actix_web::dev::ServiceRequest;
+use actix_web::http::header::HeaderMap;
 use actix_web::http::StatusCode;
-use actix_web::web::BytesMut;
+use actix_web::web::{Bytes, BytesMut};
+use actix_web::HttpResponse;
+use futures_util::future::LocalBoxFuture;
 use uuid::Uuid;
 
+use crate::attributes::{HookAttributes, HookResponseAttributes};
+use crate::baggage::Baggage;
+use crate::cache::CacheSignals;
+use crate::client::ClientGeo;
+use crate::fingerprint::Fingerprint;
+use crate::render::RenderedBody;
+use crate::security::SecurityFlags;
+use crate::util::{BodyLen, RedactedHeaders};
+
 /// Request start arguments container
 ///
 /// # Properties
@@ -14,12 +29,217 @@ use uuid::Uuid;
 /// * `request_id` - unique identifier of a request, identifies connection between request start and end.
 /// * `uri` - uri of request.
 /// * `method` - http method of request.
+/// * `origin` - worker and instance which observed the request.
+/// * `body_sizes` - encoded and decoded size of the body, if the hook decoded it, see
+///   [RequestHook::decompress_body](crate::RequestHook::decompress_body).
+/// * `body_truncated` - whether reading the body stopped at
+///   [RequestHook::max_body_size](crate::RequestHook::max_body_size), e.g. of a chunked upload, so `body` holds
+///   only its first bytes.
+/// * `grpc` - service and method of a gRPC request, without a status yet.
+/// * `sampled` - whether the sample rate of [HookSettings](crate::settings::HookSettings) picked the request, or
+///   the caller's decision, see [RequestHook::upstream_sampling](crate::RequestHook::upstream_sampling).
+/// * `force_sampled` - whether the request is observed regardless of the sample rate, see
+///   [RequestHook::force_sample_header](crate::RequestHook::force_sample_header) and [Observer::force_sample].
+/// * `baggage` - entries of W3C `baggage` headers of the request, see [Baggage].
+/// * `retry_of` - id of the first request with the same idempotency key, if this one is a retry, see
+///   [RequestHook::detect_retries](crate::RequestHook::detect_retries).
+/// * `fingerprint` - hash of the request's method, route, selected headers and body, see [Fingerprint].
+/// * `geo` - client IP with its country and autonomous system, see [ClientGeo].
+/// * `security_flags` - suspicious traits of the request, e.g. a path traversal attempt, see [SecurityFlags].
+/// * `rendered_body` - body rendered as text for sinks, unless it's empty, see
+///   [RequestHook::render_bodies](crate::RequestHook::render_bodies).
+/// * `operation_id` - `operationId` of the request's operation in the OpenAPI document, see
+///   [RequestHook::openapi](crate::RequestHook::openapi).
+/// * `deprecated` - whether the request's route is deprecated, see
+///   [RequestHook::deprecate_route](crate::RequestHook::deprecate_route).
+/// * `consumer` - consumer of the request, e.g. a customer identified by API key, see
+///   [ConsumerResolver](crate::consumer::ConsumerResolver).
+///
+/// `uri` and `method` are allocated once per request and shared by start and end data of all observers, cloning
+/// them only bumps a reference count.
+///
+/// Displays as `GET /users [id]`. Debug output redacts credential headers and shows only the length of the body,
+/// same for other event data.
 pub struct RequestStartData<'l> {
     pub req: &'l ServiceRequest,
     pub request_id: Uuid,
-    pub uri: String,
-    pub method: String,
+    pub uri: Arc<str>,
+    pub method: Arc<str>,
     pub body: BytesMut,
+    pub origin: Origin,
+    pub body_sizes: Option<BodySizes>,
+    pub body_truncated: bool,
+    pub grpc: Option<GrpcCall>,
+    pub sampled: bool,
+    pub force_sampled: bool,
+    pub baggage: Option<Baggage>,
+    pub retry_of: Option<Uuid>,
+    pub fingerprint: Option<Fingerprint>,
+    pub geo: Option<ClientGeo>,
+    pub security_flags: SecurityFlags,
+    pub rendered_body: Option<RenderedBody>,
+    pub operation_id: Option<Arc<str>>,
+    pub deprecated: bool,
+    pub consumer: Option<Arc<str>>,
+}
+
+/// Sizes of a request body sent with a `Content-Encoding`, e.g. gzip, which the hook decoded for observers, see
+/// [RequestHook::decompress_body](crate::RequestHook::decompress_body). Both count captured bytes only, at most
+/// [RequestHook::max_body_size](crate::RequestHook::max_body_size) each.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BodySizes {
+    /// Bytes as received, encoded.
+    pub raw: usize,
+    /// Bytes after decoding.
+    pub decoded: usize,
+}
+
+/// gRPC call of a request with an `application/grpc` content type, grpc-web included, e.g. of a tonic service
+/// behind actix. Its path is `/{service}/{method}`.
+///
+/// # Properties
+///
+/// * `service` - fully qualified name of the service, e.g. `helloworld.Greeter`.
+/// * `method` - name of the method, e.g. `SayHello`.
+/// * `status` - `grpc-status` code of the response, `0` meaning OK. Actix doesn't expose HTTP/2 trailers, so it's
+///   only known for responses carrying it in their headers, i.e. trailers-only responses such as most errors.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GrpcCall {
+    pub service: Arc<str>,
+    pub method: Arc<str>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub status: Option<u32>,
+}
+
+/// How a request deviates from the [OpenApi](crate::openapi::OpenApi) document of the hook, in the `api_deviations` field of end data, see
+/// [RequestHook::openapi](crate::RequestHook::openapi).
+///
+/// With `serde` feature enabled, it is (de)serialized with a `kind` tag, e.g.
+/// `{"kind": "unexpected_status", "status": 418}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", rename_all = "snake_case"))]
+pub enum ApiDeviation {
+    /// No operation of the document matches the method and path of the request.
+    UndocumentedEndpoint,
+    /// Request lacks a required query or header parameter of its operation.
+    MissingParameter { name: Arc<str> },
+    /// Response status isn't among the documented responses of the operation, and it has no `default` response.
+    UnexpectedStatus { status: u16 },
+}
+
+impl fmt::Display for ApiDeviation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiDeviation::UndocumentedEndpoint => f.write_str("undocumented endpoint"),
+            ApiDeviation::MissingParameter { name } => write!(f, "missing parameter `{}`", name),
+            ApiDeviation::UnexpectedStatus { status } => write!(f, "unexpected status {}", status),
+        }
+    }
+}
+
+/// Fault a chaos interceptor injected into a request, in the `chaos` field of end data, see
+/// [ChaosObserver](crate::observers::chaos::ChaosObserver). With `chaos` feature enabled, an interceptor injects one by
+/// inserting it into extensions of the request in [Observer::intercept]: the hook delays the handler of a request with
+/// [ChaosInjection::Latency], and tags the response of one with [ChaosInjection::Error].
+///
+/// With `serde` feature enabled, it is (de)serialized with a `kind` tag, e.g. `{"kind": "latency", "delay_us": 500000}`
+/// or `{"kind": "error", "status": 503}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", rename_all = "snake_case"))]
+pub enum ChaosInjection {
+    /// Handler was called `delay` late.
+    Latency {
+        #[cfg_attr(
+            feature = "serde",
+            serde(rename = "delay_us", with = "crate::util::serde_micros")
+        )]
+        delay: Duration,
+    },
+    /// Request was answered with `status` instead of calling the handler.
+    Error {
+        #[cfg_attr(feature = "serde", serde(with = "crate::util::serde_status"))]
+        status: StatusCode,
+    },
+}
+
+impl fmt::Display for ChaosInjection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChaosInjection::Latency { delay } => write!(f, "latency {}ms", delay.as_millis()),
+            ChaosInjection::Error { status } => write!(f, "error {}", status.as_u16()),
+        }
+    }
+}
+
+/// Worker and service instance which observed a request, so events of deployments with several workers and
+/// replicas can be told apart.
+///
+/// # Properties
+///
+/// * `worker` - name of the worker thread, e.g. `actix-server worker 0`, or its id if it has no name.
+/// * `instance` - id of the service instance, e.g. host or pod name, see
+///   [RequestHook::instance_id](crate::RequestHook::instance_id).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Origin {
+    pub worker: Arc<str>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub instance: Option<Arc<str>>,
+}
+
+impl fmt::Debug for RequestStartData<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestStartData")
+            .field("request_id", &self.request_id)
+            .field("uri", &self.uri)
+            .field("method", &self.method)
+            .field("headers", &RedactedHeaders(self.req.headers()))
+            .field("body", &BodyLen(self.body.len()))
+            .field("origin", &self.origin)
+            .field("body_sizes", &self.body_sizes)
+            .field("body_truncated", &self.body_truncated)
+            .field("grpc", &self.grpc)
+            .field("sampled", &self.sampled)
+            .field("force_sampled", &self.force_sampled)
+            .field("baggage", &self.baggage)
+            .field("retry_of", &self.retry_of)
+            .field("fingerprint", &self.fingerprint)
+            .field("geo", &self.geo)
+            .field("security_flags", &self.security_flags)
+            .field("rendered_body", &self.rendered_body)
+            .field("operation_id", &self.operation_id)
+            .field("deprecated", &self.deprecated)
+            .field("consumer", &self.consumer)
+            .finish()
+    }
+}
+
+impl fmt::Display for RequestStartData<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} [{}]", self.method, self.uri, self.request_id)
+    }
+}
+
+impl Origin {
+    /// Origin of requests observed by the current thread of `instance`.
+    pub fn current(instance: Option<Arc<str>>) -> Self {
+        let thread = std::thread::current();
+        let worker = match thread.name() {
+            Some(name) => name.into(),
+            None => format!("{:?}", thread.id()).into(),
+        };
+        Self { worker, instance }
+    }
 }
 
 /// Request end arguments container
@@ -31,12 +251,483 @@ pub struct RequestStartData<'l> {
 /// * `uri` - uri of request.
 /// * `method` - http method of request.
 /// * `status` - http status code of response.
+/// * `response_size` - size of response body in bytes, unless it is streamed or the handler failed.
+/// * `request_body` - request body as read by the handler, only with [RequestHook::tee_body](crate::RequestHook::tee_body).
+/// * `request_body_sizes` - encoded and decoded size of `request_body`, if the hook decoded it, see
+///   [RequestHook::decompress_body](crate::RequestHook::decompress_body).
+/// * `headers_elapsed` - elapsed time until the response headers were ready, if the end was deferred until the
+///   response body was sent, see [RequestHook::defer_end](crate::RequestHook::defer_end). `elapsed` then lasts
+///   until the body ended.
+/// * `grpc` - service, method and status of a gRPC request, see [GrpcCall].
+/// * `generated_by_framework` - whether actix answered the request instead of a handler, e.g. with `404 Not Found`
+///   of an unknown path or `405 Method Not Allowed` of a `HEAD` or `OPTIONS` request to a route without them, so
+///   error rates can leave them out.
+/// * `baggage` - entries of W3C `baggage` headers of the request, see [Baggage].
+/// * `retry_of` - id of the first request with the same idempotency key, if this one is a retry, see
+///   [RequestHook::detect_retries](crate::RequestHook::detect_retries).
+/// * `fingerprint` - hash of the request's method, route, selected headers and body, see [Fingerprint].
+/// * `geo` - client IP with its country and autonomous system, see [ClientGeo].
+/// * `security_flags` - suspicious traits of the request, e.g. a path traversal attempt, see [SecurityFlags].
+/// * `error_category` - category of the error the request failed with, e.g. `db_timeout`, see
+///   [RequestHook::error_classifier](crate::RequestHook::error_classifier).
+/// * `cache` - cache related headers of the response and whether it was a cache hit, see [CacheSignals].
+/// * `rendered_request_body` - `request_body` rendered as text for sinks, unless it's empty, see
+///   [RequestHook::render_bodies](crate::RequestHook::render_bodies).
+/// * `operation_id` - `operationId` of the request's operation in the OpenAPI document, see
+///   [RequestHook::openapi](crate::RequestHook::openapi).
+/// * `api_deviations` - how the request deviates from the OpenAPI document, e.g. an unexpected status, see
+///   [ApiDeviation].
+/// * `chaos` - fault a chaos interceptor injected into the request, see [ChaosInjection].
+/// * `deprecated` - whether the request's route is deprecated, see
+///   [RequestHook::deprecate_route](crate::RequestHook::deprecate_route).
+/// * `consumer` - consumer of the request, e.g. a customer identified by API key, see
+///   [ConsumerResolver](crate::consumer::ConsumerResolver).
+/// * `attributes` - business context middleware or the handler added to the request, see [HookAttributes].
+/// * `response_attributes` - values the handler attached to its response, e.g. the number of database queries, see
+///   [HookResponseAttributes].
+/// * `response` - response headers and body, only if an observer asked for it with [Observer::capture_response].
+/// * `outcome` - whether the handler completed or panicked.
+/// * `origin` - worker and instance which observed the request.
+/// * `deadline_exceeded` - whether the request ran past its deadline, see
+///   [RequestHook::soft_timeout](crate::RequestHook::soft_timeout).
+///
+/// With `serde` feature enabled, `elapsed` and `headers_elapsed` are (de)serialized as `elapsed_us` and
+/// `headers_elapsed_us` microseconds, and `status` as a number.
+///
+/// Displays as `GET /users 200 12ms [id]`, followed by `panicked`, `deadline exceeded` or the injected fault, e.g.
+/// `chaos latency 500ms`, if so.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RequestEndData {
+    pub request_id: Uuid,
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "elapsed_us", with = "crate::util::serde_micros")
+    )]
+    pub elapsed: Duration,
+    pub uri: Arc<str>,
+    pub method: Arc<str>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde_status"))]
+    pub status: StatusCode,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub response_size: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub request_body: Option<Bytes>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub response: Option<CapturedResponse>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Outcome::is_completed")
+    )]
+    pub outcome: Outcome,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "is_false"))]
+    pub deadline_exceeded: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub origin: Origin,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub request_body_sizes: Option<BodySizes>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            rename = "headers_elapsed_us",
+            default,
+            skip_serializing_if = "Option::is_none",
+            with = "crate::util::serde_micros_opt"
+        )
+    )]
+    pub headers_elapsed: Option<Duration>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub grpc: Option<GrpcCall>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "is_false"))]
+    pub generated_by_framework: bool,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub baggage: Option<Baggage>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub retry_of: Option<Uuid>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub fingerprint: Option<Fingerprint>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub geo: Option<ClientGeo>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "SecurityFlags::is_empty")
+    )]
+    pub security_flags: SecurityFlags,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub error_category: Option<Arc<str>>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub cache: Option<CacheSignals>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub rendered_request_body: Option<RenderedBody>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub operation_id: Option<Arc<str>>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub api_deviations: Vec<ApiDeviation>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub chaos: Option<ChaosInjection>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "is_false"))]
+    pub deprecated: bool,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub consumer: Option<Arc<str>>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub attributes: Option<HookAttributes>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub response_attributes: Option<HookResponseAttributes>,
+}
+
+impl fmt::Debug for RequestEndData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestEndData")
+            .field("request_id", &self.request_id)
+            .field("elapsed", &self.elapsed)
+            .field("uri", &self.uri)
+            .field("method", &self.method)
+            .field("status", &self.status)
+            .field("response_size", &self.response_size)
+            .field(
+                "request_body",
+                &self.request_body.as_ref().map(|body| BodyLen(body.len())),
+            )
+            .field("response", &self.response)
+            .field("outcome", &self.outcome)
+            .field("deadline_exceeded", &self.deadline_exceeded)
+            .field("origin", &self.origin)
+            .field("request_body_sizes", &self.request_body_sizes)
+            .field("headers_elapsed", &self.headers_elapsed)
+            .field("grpc", &self.grpc)
+            .field("generated_by_framework", &self.generated_by_framework)
+            .field("baggage", &self.baggage)
+            .field("retry_of", &self.retry_of)
+            .field("fingerprint", &self.fingerprint)
+            .field("geo", &self.geo)
+            .field("security_flags", &self.security_flags)
+            .field("error_category", &self.error_category)
+            .field("cache", &self.cache)
+            .field("rendered_request_body", &self.rendered_request_body)
+            .field("operation_id", &self.operation_id)
+            .field("api_deviations", &self.api_deviations)
+            .field("chaos", &self.chaos)
+            .field("deprecated", &self.deprecated)
+            .field("consumer", &self.consumer)
+            .field("attributes", &self.attributes)
+            .field("response_attributes", &self.response_attributes)
+            .finish()
+    }
+}
+
+impl fmt::Display for RequestEndData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}ms [{}]",
+            self.method,
+            self.uri,
+            self.status.as_u16(),
+            self.elapsed.as_millis(),
+            self.request_id
+        )?;
+        if !self.outcome.is_completed() {
+            write!(f, " panicked")?;
+        }
+        if self.deadline_exceeded {
+            write!(f, " deadline exceeded")?;
+        }
+        if let Some(chaos) = &self.chaos {
+            write!(f, " chaos {}", chaos)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+pub(crate) fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// How the handler of an ended request finished.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Outcome {
+    /// Handler returned a response or an error.
+    #[default]
+    Completed,
+    /// Handler panicked with the message, the request ended with 500 Internal Server Error.
+    Panicked(String),
+}
+
+impl Outcome {
+    pub fn is_completed(&self) -> bool {
+        *self == Outcome::Completed
+    }
+}
+
+/// Request cancelled arguments container, for a request which started but never ended, because its future was
+/// dropped, e.g. by a client disconnecting, a timeout middleware or a server shutdown.
+///
+/// # Properties
+///
+/// * `request_id` - unique identifier of a request, same as in its [RequestStartData].
+/// * `elapsed` - elapsed time between request start and cancellation.
+/// * `uri` - uri of request.
+/// * `method` - http method of request.
+/// * `origin` - worker and instance which observed the request.
+///
+/// Displays as `GET /users cancelled 12ms [id]`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RequestCancelledData {
+    pub request_id: Uuid,
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "elapsed_us", with = "crate::util::serde_micros")
+    )]
+    pub elapsed: Duration,
+    pub uri: Arc<str>,
+    pub method: Arc<str>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub origin: Origin,
+}
+
+impl fmt::Display for RequestCancelledData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} cancelled {}ms [{}]",
+            self.method,
+            self.uri,
+            self.elapsed.as_millis(),
+            self.request_id
+        )
+    }
+}
+
+/// Request still running past its deadline, see [RequestHook::soft_timeout](crate::RequestHook::soft_timeout) and
+/// [RequestHook::deadline_header](crate::RequestHook::deadline_header).
+///
+/// # Properties
+///
+/// * `request_id` - unique identifier of a request, same as in its [RequestStartData].
+/// * `deadline` - time the request had, from its start.
+/// * `uri` - uri of request.
+/// * `method` - http method of request.
+///
+/// Displays as `GET /users deadline 500ms exceeded [id]`. With `serde` feature enabled, `deadline` is
+/// (de)serialized as `deadline_us` microseconds.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeadlineExceededData {
+    pub request_id: Uuid,
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "deadline_us", with = "crate::util::serde_micros")
+    )]
+    pub deadline: Duration,
+    pub uri: Arc<str>,
+    pub method: Arc<str>,
+}
+
+impl fmt::Display for DeadlineExceededData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} deadline {}ms exceeded [{}]",
+            self.method,
+            self.uri,
+            self.deadline.as_millis(),
+            self.request_id
+        )
+    }
+}
+
+/// Request upgrading its connection to another protocol, e.g. a WebSocket handshake, see [Observer::on_upgrade].
+///
+/// # Properties
+///
+/// * `handshake` - end data of the handshake, with its status, e.g. `101 Switching Protocols` if the upgrade was
+///   accepted, and the time until it was answered.
+/// * `protocol` - protocol of the `Upgrade` header, lowercase, e.g. `websocket`.
+/// * `duration` - time the upgraded connection was open, from the handshake until it closed, only with
+///   [RequestHook::track_upgrades](crate::RequestHook::track_upgrades).
+///
+/// Displays as `GET /ws 101 2ms [id] upgraded to websocket`, followed by `for 90s` with a duration. With `serde`
+/// feature enabled, `duration` is (de)serialized as `duration_us` microseconds.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UpgradeData {
+    pub handshake: RequestEndData,
+    pub protocol: Arc<str>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            rename = "duration_us",
+            default,
+            skip_serializing_if = "Option::is_none",
+            with = "crate::util::serde_micros_opt"
+        )
+    )]
+    pub duration: Option<Duration>,
+}
+
+impl fmt::Display for UpgradeData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} upgraded to {}", self.handshake, self.protocol)?;
+        if let Some(duration) = self.duration {
+            write!(f, " for {:?}", duration)?;
+        }
+        Ok(())
+    }
+}
+
+/// Response headers and body, captured for observers which asked for it with [Observer::capture_response]. `rendered`
+/// is the body rendered as text, unless it's empty, see [RequestHook::render_bodies](crate::RequestHook::render_bodies).
+#[derive(Clone)]
+pub struct CapturedResponse {
+    pub headers: HeaderMap,
+    pub body: Bytes,
+    pub rendered: Option<RenderedBody>,
+}
+
+impl fmt::Debug for CapturedResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CapturedResponse")
+            .field("headers", &RedactedHeaders(&self.headers))
+            .field("body", &BodyLen(self.body.len()))
+            .field("rendered", &self.rendered)
+            .finish()
+    }
+}
+
+/// Context of a request which took longer than the threshold of
+/// [RequestHook::on_slow_request](crate::RequestHook::on_slow_request).
+///
+/// # Properties
+///
+/// * `route` - matched route pattern, or normalized path if no route matched.
+/// * `headers` - request headers.
+/// * `body_prefix` - first [SLOW_REQUEST_BODY_PREFIX] bytes of request body, empty if body capture is disabled
+///   in [HookSettings](crate::settings::HookSettings).
+///
+/// Displays as `GET /users 200 1200ms [id]`.
+#[derive(Clone)]
+pub struct SlowRequestData {
     pub request_id: Uuid,
     pub elapsed: Duration,
-    pub uri: String,
-    pub method: String,
+    pub uri: Arc<str>,
+    pub method: Arc<str>,
+    pub route: String,
     pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body_prefix: Bytes,
+}
+
+impl fmt::Debug for SlowRequestData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlowRequestData")
+            .field("request_id", &self.request_id)
+            .field("elapsed", &self.elapsed)
+            .field("uri", &self.uri)
+            .field("method", &self.method)
+            .field("route", &self.route)
+            .field("status", &self.status)
+            .field("headers", &RedactedHeaders(&self.headers))
+            .field("body_prefix", &BodyLen(self.body_prefix.len()))
+            .finish()
+    }
+}
+
+impl fmt::Display for SlowRequestData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}ms [{}]",
+            self.method,
+            self.uri,
+            self.status.as_u16(),
+            self.elapsed.as_millis(),
+            self.request_id
+        )
+    }
+}
+
+/// Maximum length of [SlowRequestData::body_prefix].
+pub const SLOW_REQUEST_BODY_PREFIX: usize = 4096;
+
+/// Request which started but neither ended nor was cancelled within the age of
+/// [RequestHook::on_orphaned_request](crate::RequestHook::on_orphaned_request), e.g. because its future was leaked
+/// or is never woken up again.
+///
+/// # Properties
+///
+/// * `age` - time since the request started.
+///
+/// Displays as `GET /users orphaned 30000ms [id]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrphanedRequestData {
+    pub request_id: Uuid,
+    pub age: Duration,
+    pub uri: Arc<str>,
+    pub method: Arc<str>,
+}
+
+impl fmt::Display for OrphanedRequestData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} orphaned {}ms [{}]",
+            self.method,
+            self.uri,
+            self.age.as_millis(),
+            self.request_id
+        )
+    }
 }
 
 /// An Observer is notified before a request is passed for processing, and after processing into a response.
@@ -63,4 +754,82 @@ pub trait Observer {
 
     /// Fired after handler call. See [RequestEndData] for available arguments.
     fn on_request_ended(&self, data: RequestEndData);
+
+    /// Fired instead of [Observer::on_request_ended] when a started request is dropped before it ends, so every
+    /// start is followed by exactly one end or cancellation. See [RequestCancelledData] for available arguments.
+    fn on_request_cancelled(&self, _data: RequestCancelledData) {}
+
+    /// Fired once the request runs past its deadline, while the handler is still running, see
+    /// [DeadlineExceededData]. The request ends with [RequestEndData::deadline_exceeded] set.
+    fn on_deadline_exceeded(&self, _data: DeadlineExceededData) {}
+
+    /// Fired instead of [Observer::on_request_ended] for requests asking to upgrade their connection with
+    /// `Connection: Upgrade`, e.g. WebSocket handshakes, whose elapsed time says little about the connection. See
+    /// [UpgradeData] for available arguments. By default, passes the handshake to [Observer::on_request_ended].
+    fn on_upgrade(&self, data: UpgradeData) {
+        self.on_request_ended(data.handshake)
+    }
+
+    /// Whether response headers and body of `req` should be captured into [RequestEndData::response].
+    /// Capturing buffers the whole response body before it is sent, so it is off by default. Only bodies of a known
+    /// size up to [RequestHook::max_body_size](crate::RequestHook::max_body_size) are captured, streamed responses,
+    /// e.g. server-sent events, are not.
+    fn capture_response(&self, _req: &ServiceRequest) -> bool {
+        false
+    }
+
+    /// Whether this observer reads the request body of `req` from [RequestStartData::body]. The hook only buffers
+    /// a request body if at least one observer of the request asks for it, otherwise the payload passes through
+    /// to the handler untouched and observers receive an empty body. Observers ignoring the body should return
+    /// false, so large uploads keep streaming.
+    fn capture_body(&self, _req: &ServiceRequest) -> bool {
+        true
+    }
+
+    /// Whether to observe `req` regardless of the sample rate, e.g. for requests of an account under
+    /// investigation. Called for every observed request before sampling, forced requests are sampled for all
+    /// observers and have [RequestStartData::force_sampled] set.
+    fn force_sample(&self, _req: &ServiceRequest) -> bool {
+        false
+    }
+
+    /// Interceptor: returns a response to answer `req` with instead of calling the handler, e.g. a 429 when a
+    /// client exceeded its rate limit. Called for every observed request, regardless of sampling, before
+    /// [Observer::on_request_started]. The first observer returning a response wins, and observers still receive
+    /// start and end of the intercepted request.
+    fn intercept(&self, _req: &ServiceRequest) -> Option<HttpResponse> {
+        None
+    }
+
+    /// Interceptor of request bodies: returns a response to answer `req` with instead of calling the handler, once
+    /// its `body` was read, e.g. a 400 for a body breaking the API contract. Called for every observed request
+    /// whose body the hook captured and no interceptor answered, regardless of sampling, before
+    /// [Observer::on_request_started]. `body` is decoded but not scrubbed. Bodies teed with
+    /// [RequestHook::tee_body](crate::RequestHook::tee_body) are read by the handler, so they are never passed here.
+    fn intercept_body(&self, _req: &ServiceRequest, _body: &[u8]) -> Option<HttpResponse> {
+        None
+    }
+
+    /// Fired periodically with time since the previous tick, while the hook is running with
+    /// [RequestHook::tick_interval](crate::RequestHook::tick_interval), e.g. to flush buffers or emit summaries of
+    /// a window without spawning a task. Ticks are per worker, observers registered with
+    /// [SharedRequestHook](crate::shared::SharedRequestHook) are ticked once for all workers instead, see
+    /// [SharedRequestHook::tick_interval](crate::shared::SharedRequestHook::tick_interval).
+    fn on_tick(&self, _elapsed: Duration) {}
+
+    /// Fired once when the hook shuts down, e.g. to flush buffered events, returning a future completing once
+    /// they were sent. [RequestHook::shutdown](crate::RequestHook::shutdown) awaits it. A hook dropped without
+    /// being shut down, i.e. when actix drops the app of a worker after the server's stop signal and graceful
+    /// shutdown, spawns it instead, so it only completes if it finishes before the worker's runtime stops.
+    /// Observers registered with [SharedRequestHook](crate::shared::SharedRequestHook) are shut down once for all
+    /// workers, see [SharedRequestHook::shutdown](crate::shared::SharedRequestHook::shutdown).
+    fn on_shutdown(&self) -> LocalBoxFuture<'static, ()> {
+        Box::pin(ready(()))
+    }
+
+    /// Name of the observer, e.g. in [RequestHook::observer_names](crate::RequestHook::observer_names). Type
+    /// name by default.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
 }
@@ -0,0 +1,128 @@
+//! [`ActorObserver`] forwards request events to an actix actor.
+use std::cell::Cell;
+
+use actix::dev::{SendError, ToEnvelope};
+use actix::{Actor, Addr, Handler, Message, Recipient};
+
+use crate::event::{RequestEvent, RequestStartEvent};
+use crate::observer::{
+    DeadlineExceededData, Observer, RequestCancelledData, RequestEndData, RequestStartData,
+    UpgradeData,
+};
+
+type ErrorFn = Box<dyn Fn(&SendError<RequestEvent>)>;
+
+impl Message for RequestEvent {
+    type Result = ();
+}
+
+/// Sends every [RequestEvent] as a message to an actor, which implements `Handler<RequestEvent>`.
+///
+/// Messages are sent with `try_send`, so a full mailbox (see `Context::set_mailbox_capacity`) applies backpressure
+/// by dropping events instead of blocking the request. Dropped events are counted and reported to `on_error`.
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use actix::{Actor, Context, Handler};
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::event::RequestEvent;
+/// use actix_request_hook::observers::actor::ActorObserver;
+///
+/// struct Sink;
+///
+/// impl Actor for Sink {
+///     type Context = Context<Self>;
+/// }
+///
+/// impl Handler<RequestEvent> for Sink {
+///     type Result = ();
+///
+///     fn handle(&mut self, event: RequestEvent, _ctx: &mut Context<Self>) {
+///         println!("{:?}", event);
+///     }
+/// }
+///
+/// # actix::System::new().block_on(async {
+/// let addr = Sink.start();
+/// let request_hook = RequestHook::new().register(Rc::new(ActorObserver::new(addr)));
+/// # });
+/// ```
+pub struct ActorObserver {
+    recipient: Recipient<RequestEvent>,
+    started: bool,
+    dropped: Cell<u64>,
+    on_error: Option<ErrorFn>,
+}
+
+impl ActorObserver {
+    /// Forwards events to actor at `addr`.
+    pub fn new<A>(addr: Addr<A>) -> Self
+    where
+        A: Actor + Handler<RequestEvent>,
+        A::Context: ToEnvelope<A, RequestEvent>,
+    {
+        Self::recipient(addr.recipient())
+    }
+
+    /// Forwards events to `recipient`, e.g. an actor behind a supervisor.
+    pub fn recipient(recipient: Recipient<RequestEvent>) -> Self {
+        Self {
+            recipient,
+            started: true,
+            dropped: Cell::new(0),
+            on_error: None,
+        }
+    }
+
+    /// Forwards only [RequestEvent::Ended] and [RequestEvent::Cancelled] events.
+    pub fn ended_only(mut self) -> Self {
+        self.started = false;
+        self
+    }
+
+    /// Called when an event is dropped because the mailbox is full or the actor has stopped.
+    pub fn on_error<F: 'static + Fn(&SendError<RequestEvent>)>(mut self, callback: F) -> Self {
+        self.on_error = Some(Box::new(callback));
+        self
+    }
+
+    /// Number of events dropped so far.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.get()
+    }
+
+    fn send(&self, event: RequestEvent) {
+        if let Err(err) = self.recipient.try_send(event) {
+            self.dropped.set(self.dropped.get() + 1);
+            if let Some(callback) = &self.on_error {
+                callback(&err);
+            }
+        }
+    }
+}
+
+impl Observer for ActorObserver {
+    fn on_request_started(&self, data: RequestStartData) {
+        if self.started {
+            self.send(RequestEvent::Started(RequestStartEvent::from(&data)));
+        }
+    }
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        self.send(RequestEvent::Ended(data));
+    }
+
+    fn on_request_cancelled(&self, data: RequestCancelledData) {
+        self.send(RequestEvent::Cancelled(data));
+    }
+
+    fn on_deadline_exceeded(&self, data: DeadlineExceededData) {
+        if self.started {
+            self.send(RequestEvent::DeadlineExceeded(data));
+        }
+    }
+
+    fn on_upgrade(&self, data: UpgradeData) {
+        self.send(RequestEvent::Upgrade(data));
+    }
+}
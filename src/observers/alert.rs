@@ -0,0 +1,203 @@
+//! [`ErrorBurstAlert`] posts an alert to a Slack or Discord webhook when server errors pile up.
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use actix_web::dev::ServiceRequest;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Client;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::observer::{Observer, RequestCancelledData, RequestEndData, RequestStartData};
+
+type ErrorFn = Rc<dyn Fn(&reqwest::Error)>;
+
+/// Maximum number of errors listed in an alert message.
+const MAX_LISTED: usize = 10;
+
+enum Format {
+    Slack,
+    Discord,
+}
+
+struct ServerError {
+    at: Instant,
+    request_id: Uuid,
+    method: String,
+    route: String,
+    status: u16,
+}
+
+/// Posts an alert when at least `threshold` 5xx responses happen within `window`, 10 within 1 minute by default.
+///
+/// Alerts list request ids and route patterns of the latest errors. After an alert, no other alert is posted
+/// until `cooldown` passes, 5 minutes by default.
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use std::time::Duration;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::observers::alert::ErrorBurstAlert;
+///
+/// let alert = ErrorBurstAlert::slack("https://hooks.slack.com/services/T000/B000/XXXX")
+///     .service_name("orders")
+///     .threshold(20, Duration::from_secs(60))
+///     .cooldown(Duration::from_secs(600));
+/// let request_hook = RequestHook::new().register(Rc::new(alert));
+/// ```
+pub struct ErrorBurstAlert {
+    client: Client,
+    url: String,
+    format: Format,
+    service_name: Option<String>,
+    threshold: usize,
+    window: Duration,
+    cooldown: Duration,
+    on_error: Option<ErrorFn>,
+    routes: RefCell<HashMap<Uuid, String>>,
+    errors: RefCell<VecDeque<ServerError>>,
+    last_alert: Cell<Option<Instant>>,
+}
+
+impl ErrorBurstAlert {
+    /// Creates an alert posting to a Slack incoming webhook.
+    pub fn slack<T: Into<String>>(webhook_url: T) -> Self {
+        Self::new(webhook_url.into(), Format::Slack)
+    }
+
+    /// Creates an alert posting to a Discord webhook.
+    pub fn discord<T: Into<String>>(webhook_url: T) -> Self {
+        Self::new(webhook_url.into(), Format::Discord)
+    }
+
+    fn new(url: String, format: Format) -> Self {
+        Self {
+            client: Client::new(),
+            url,
+            format,
+            service_name: None,
+            threshold: 10,
+            window: Duration::from_secs(60),
+            cooldown: Duration::from_secs(300),
+            on_error: None,
+            routes: RefCell::new(HashMap::new()),
+            errors: RefCell::new(VecDeque::new()),
+            last_alert: Cell::new(None),
+        }
+    }
+
+    /// Service name mentioned in alert messages.
+    pub fn service_name<T: Into<String>>(mut self, service_name: T) -> Self {
+        self.service_name = Some(service_name.into());
+        self
+    }
+
+    /// Alerts when `threshold` server errors happen within `window`.
+    pub fn threshold(mut self, threshold: usize, window: Duration) -> Self {
+        self.threshold = threshold;
+        self.window = window;
+        self
+    }
+
+    /// Minimum time between two alerts.
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Called when an alert could not be posted.
+    pub fn on_error<F: 'static + Fn(&reqwest::Error)>(mut self, callback: F) -> Self {
+        self.on_error = Some(Rc::new(callback));
+        self
+    }
+
+    fn message(&self, errors: &VecDeque<ServerError>) -> String {
+        let service = match &self.service_name {
+            Some(service_name) => format!(" in {}", service_name),
+            None => String::new(),
+        };
+        let mut message = format!(
+            "{} server errors{} within {}s",
+            errors.len(),
+            service,
+            self.window.as_secs()
+        );
+        for error in errors.iter().rev().take(MAX_LISTED) {
+            message.push_str(&format!(
+                "\n• {} {} {} [{}]",
+                error.method, error.route, error.status, error.request_id
+            ));
+        }
+        message
+    }
+
+    fn alert(&self, message: String) {
+        let payload = match self.format {
+            Format::Slack => json!({ "text": message }),
+            Format::Discord => json!({ "content": message }),
+        };
+        let request = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(payload.to_string());
+        let on_error = self.on_error.clone();
+        actix_web::rt::spawn(async move {
+            if let Err(err) = request.send().await.and_then(|res| res.error_for_status()) {
+                if let Some(callback) = on_error {
+                    callback(&err);
+                }
+            }
+        });
+    }
+}
+
+impl Observer for ErrorBurstAlert {
+    fn on_request_started(&self, data: RequestStartData) {
+        let route = crate::normalize::route(data.req);
+        self.routes.borrow_mut().insert(data.request_id, route);
+    }
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        let route = self.routes.borrow_mut().remove(&data.request_id);
+        if !data.status.is_server_error() {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut errors = self.errors.borrow_mut();
+        errors.push_back(ServerError {
+            at: now,
+            request_id: data.request_id,
+            method: data.method.to_string(),
+            route: route.unwrap_or_else(|| data.uri.to_string()),
+            status: data.status.as_u16(),
+        });
+        while errors
+            .front()
+            .is_some_and(|error| now.duration_since(error.at) > self.window)
+        {
+            errors.pop_front();
+        }
+
+        let cooled_down = self
+            .last_alert
+            .get()
+            .is_none_or(|last_alert| now.duration_since(last_alert) >= self.cooldown);
+        if errors.len() >= self.threshold && cooled_down {
+            self.last_alert.set(Some(now));
+            self.alert(self.message(&errors));
+            errors.clear();
+        }
+    }
+
+    fn on_request_cancelled(&self, data: RequestCancelledData) {
+        self.routes.borrow_mut().remove(&data.request_id);
+    }
+
+    fn capture_body(&self, _req: &ServiceRequest) -> bool {
+        false
+    }
+}
@@ -0,0 +1,194 @@
+//! [`AmqpObserver`] publishes request end events to an AMQP (RabbitMQ) exchange.
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::time::Duration;
+
+use actix_web::dev::ServiceRequest;
+use lapin::options::BasicPublishOptions;
+use lapin::{BasicProperties, Channel, Connection, ConnectionProperties};
+
+use crate::observer::{Observer, RequestEndData, RequestStartData};
+use crate::util::status_class;
+
+type RoutingKeyFn = Rc<dyn Fn(&RequestEndData) -> String>;
+type ErrorFn = Rc<dyn Fn(&lapin::Error)>;
+
+/// Publishes every ended request as a JSON serialized [RequestEndData] to an AMQP exchange.
+///
+/// Routing key defaults to lowercase method and status class, e.g. `get.2xx`, so queues can bind to `*.5xx`.
+/// Events are buffered locally while the broker is unreachable, connection is re-established with exponential
+/// backoff. When the buffer is full the oldest events are dropped.
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::observers::amqp::AmqpObserver;
+///
+/// let amqp = AmqpObserver::new("amqp://127.0.0.1:5672/%2f", "requests").buffer_capacity(50_000);
+/// let request_hook = RequestHook::new().register(Rc::new(amqp));
+/// ```
+pub struct AmqpObserver {
+    publisher: Rc<Publisher>,
+    routing_key: RoutingKeyFn,
+}
+
+struct Publisher {
+    uri: String,
+    exchange: String,
+    capacity: usize,
+    max_backoff: Duration,
+    on_error: Option<ErrorFn>,
+    state: RefCell<PublisherState>,
+}
+
+#[derive(Default)]
+struct PublisherState {
+    channel: Option<Channel>,
+    buffer: VecDeque<(String, Vec<u8>)>,
+    publishing: bool,
+}
+
+impl AmqpObserver {
+    /// Creates an observer publishing to `exchange` on broker at `uri`. Connection is opened on first event.
+    pub fn new<U: Into<String>, E: Into<String>>(uri: U, exchange: E) -> Self {
+        Self {
+            publisher: Rc::new(Publisher {
+                uri: uri.into(),
+                exchange: exchange.into(),
+                capacity: 10_000,
+                max_backoff: Duration::from_secs(30),
+                on_error: None,
+                state: RefCell::new(PublisherState::default()),
+            }),
+            routing_key: Rc::new(routing_key),
+        }
+    }
+
+    /// Derives routing key from an event.
+    pub fn routing_key<F: 'static + Fn(&RequestEndData) -> String>(
+        mut self,
+        routing_key: F,
+    ) -> Self {
+        self.routing_key = Rc::new(routing_key);
+        self
+    }
+
+    /// Maximum number of events buffered while the broker is unreachable, 10000 by default.
+    pub fn buffer_capacity(mut self, capacity: usize) -> Self {
+        self.publisher_mut().capacity = capacity;
+        self
+    }
+
+    /// Maximum delay between reconnect attempts, 30s by default.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.publisher_mut().max_backoff = max_backoff;
+        self
+    }
+
+    /// Called on connection and publish errors.
+    pub fn on_error<F: 'static + Fn(&lapin::Error)>(mut self, callback: F) -> Self {
+        self.publisher_mut().on_error = Some(Rc::new(callback));
+        self
+    }
+
+    fn publisher_mut(&mut self) -> &mut Publisher {
+        Rc::get_mut(&mut self.publisher).unwrap()
+    }
+}
+
+/// Default routing key, lowercase method and status class, e.g. `post.5xx`.
+pub(crate) fn routing_key(data: &RequestEndData) -> String {
+    format!(
+        "{}.{}",
+        data.method.to_lowercase(),
+        status_class(data.status)
+    )
+}
+
+impl Publisher {
+    fn report(&self, err: &lapin::Error) {
+        if let Some(callback) = &self.on_error {
+            callback(err);
+        }
+    }
+
+    async fn channel(&self) -> lapin::Result<Channel> {
+        let channel = self.state.borrow().channel.clone();
+        match channel {
+            Some(channel) if channel.status().connected() => Ok(channel),
+            _ => {
+                let connection =
+                    Connection::connect(&self.uri, ConnectionProperties::default()).await?;
+                let channel = connection.create_channel().await?;
+                self.state.borrow_mut().channel = Some(channel.clone());
+                Ok(channel)
+            }
+        }
+    }
+
+    async fn publish(&self, routing_key: &str, payload: &[u8]) -> lapin::Result<()> {
+        let channel = self.channel().await?;
+        channel
+            .basic_publish(
+                &self.exchange,
+                routing_key,
+                BasicPublishOptions::default(),
+                payload,
+                BasicProperties::default().with_content_type("application/json".into()),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Drains buffer, reconnecting with exponential backoff until the broker accepts events again.
+    async fn drain(self: Rc<Self>) {
+        let mut backoff = Duration::from_millis(100);
+        loop {
+            let next = self.state.borrow_mut().buffer.pop_front();
+            let (routing_key, payload) = match next {
+                Some(message) => message,
+                None => break,
+            };
+            match self.publish(&routing_key, &payload).await {
+                Ok(()) => backoff = Duration::from_millis(100),
+                Err(err) => {
+                    self.report(&err);
+                    {
+                        let mut state = self.state.borrow_mut();
+                        state.channel = None;
+                        if state.buffer.len() < self.capacity {
+                            state.buffer.push_front((routing_key, payload));
+                        }
+                    }
+                    actix_web::rt::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(self.max_backoff);
+                }
+            }
+        }
+        self.state.borrow_mut().publishing = false;
+    }
+}
+
+impl Observer for AmqpObserver {
+    fn on_request_started(&self, _data: RequestStartData) {}
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        let routing_key = (self.routing_key)(&data);
+        let payload = serde_json::to_vec(&data).unwrap();
+
+        let mut state = self.publisher.state.borrow_mut();
+        if state.buffer.len() >= self.publisher.capacity {
+            state.buffer.pop_front();
+        }
+        state.buffer.push_back((routing_key, payload));
+        if !state.publishing {
+            state.publishing = true;
+            actix_web::rt::spawn(self.publisher.clone().drain());
+        }
+    }
+
+    fn capture_body(&self, _req: &ServiceRequest) -> bool {
+        false
+    }
+}
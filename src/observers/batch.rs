@@ -0,0 +1,101 @@
+//! Size and time based batching shared by sink observers.
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+
+use futures_util::future::LocalBoxFuture;
+
+type SinkFn<T> = Box<dyn Fn(Vec<T>) -> Pin<Box<dyn Future<Output = ()>>>>;
+
+/// Collects items and spawns `sink` with them once `max_size` items are collected or `interval` passes.
+///
+/// Timer is started lazily with the first item, so batchers can be created outside of actix runtime.
+pub(crate) struct Batcher<T> {
+    inner: Rc<Inner<T>>,
+}
+
+struct Inner<T> {
+    buffer: RefCell<Vec<T>>,
+    max_size: usize,
+    interval: Duration,
+    ticking: Cell<bool>,
+    sink: SinkFn<T>,
+}
+
+impl<T: 'static> Batcher<T> {
+    pub fn new<F, Fut>(max_size: usize, interval: Duration, sink: F) -> Self
+    where
+        F: 'static + Fn(Vec<T>) -> Fut,
+        Fut: 'static + Future<Output = ()>,
+    {
+        Self {
+            inner: Rc::new(Inner {
+                buffer: RefCell::new(Vec::with_capacity(max_size)),
+                max_size,
+                interval,
+                ticking: Cell::new(false),
+                sink: Box::new(move |batch| Box::pin(sink(batch))),
+            }),
+        }
+    }
+
+    pub fn push(&self, item: T) {
+        let full = {
+            let mut buffer = self.inner.buffer.borrow_mut();
+            buffer.push(item);
+            buffer.len() >= self.inner.max_size
+        };
+        if full {
+            self.inner.spawn_flush();
+        }
+        if !self.inner.ticking.replace(true) {
+            actix_web::rt::spawn(tick(Rc::downgrade(&self.inner), self.inner.interval));
+        }
+    }
+
+    /// Takes collected items right away, returning `sink` sending them, e.g. to await on shutdown.
+    pub fn flush(&self) -> LocalBoxFuture<'static, ()> {
+        match self.inner.flush() {
+            Some(send) => send,
+            None => Box::pin(std::future::ready(())),
+        }
+    }
+
+    /// Takes collected items without passing them to `sink`.
+    #[cfg(feature = "webhook")]
+    pub fn take(&self) -> Vec<T> {
+        std::mem::take(&mut *self.inner.buffer.borrow_mut())
+    }
+}
+
+impl<T> Inner<T> {
+    fn flush(&self) -> Option<Pin<Box<dyn Future<Output = ()>>>> {
+        let batch = std::mem::take(&mut *self.buffer.borrow_mut());
+        if batch.is_empty() {
+            None
+        } else {
+            Some((self.sink)(batch))
+        }
+    }
+
+    fn spawn_flush(&self) {
+        if let Some(send) = self.flush() {
+            actix_web::rt::spawn(send);
+        }
+    }
+}
+
+/// Flushes batcher every `interval`, until the batcher is dropped.
+async fn tick<T>(inner: Weak<Inner<T>>, interval: Duration) {
+    let mut interval = actix_web::rt::time::interval(interval);
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        match inner.upgrade() {
+            Some(inner) => inner.spawn_flush(),
+            None => break,
+        }
+    }
+}
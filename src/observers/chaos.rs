@@ -0,0 +1,156 @@
+//! [`ChaosObserver`] injects latency and error responses into a sample of requests, as an interceptor of the hook.
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::dev::ServiceRequest;
+use actix_web::http::StatusCode;
+use actix_web::{HttpMessage, HttpResponse};
+use uuid::Uuid;
+
+use crate::extract::RequestStart;
+pub use crate::observer::ChaosInjection;
+use crate::observer::{Observer, RequestEndData, RequestStartData};
+
+/// Fault injected into a fraction of requests to a route.
+struct Rule {
+    route: String,
+    fraction: f64,
+    injection: ChaosInjection,
+}
+
+/// Injects faults into a sampled fraction of requests of matched routes, to test how clients and dashboards cope with
+/// a slow or failing service: [ChaosObserver::latency] delays their handler, and [ChaosObserver::error] answers them
+/// with an error status instead of calling it, through [Observer::intercept].
+///
+/// Every injection is recorded in the `chaos` field of end data of the request, see [ChaosInjection], so events of
+/// injected faults can be told apart from real ones in later analysis. Requests are sampled by request id, and rules
+/// matching the same route take disjoint fractions of its requests, in the order they were added, so a request gets
+/// at most one fault. Observer is a cloneable handle, register the same observer with hooks of all workers and
+/// switch injections off for the whole process with [ChaosObserver::set_enabled].
+///
+/// ```
+/// use std::rc::Rc;
+/// use std::time::Duration;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::observers::chaos::ChaosObserver;
+/// use actix_web::http::StatusCode;
+///
+/// let chaos = ChaosObserver::new()
+///     .latency("/users/{id}", 0.1, Duration::from_millis(500))
+///     .error("/orders", 0.01, StatusCode::SERVICE_UNAVAILABLE);
+/// let request_hook = RequestHook::new().register(Rc::new(chaos.clone()));
+/// // later, e.g. from an admin endpoint
+/// chaos.set_enabled(false);
+/// ```
+#[derive(Clone)]
+pub struct ChaosObserver {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    rules: Vec<Rule>,
+    enabled: AtomicBool,
+    injected: AtomicU64,
+}
+
+impl Default for ChaosObserver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChaosObserver {
+    /// Enabled observer without rules.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                rules: Vec::new(),
+                enabled: AtomicBool::new(true),
+                injected: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Delays the handler of a `fraction`, between `0.0` and `1.0`, of requests to `route` by `delay`. `route` is a
+    /// route pattern such as `/users/{id}`, or `*` for all routes.
+    pub fn latency<T: Into<String>>(self, route: T, fraction: f64, delay: Duration) -> Self {
+        self.rule(route.into(), fraction, ChaosInjection::Latency { delay })
+    }
+
+    /// Answers a `fraction`, between `0.0` and `1.0`, of requests to `route` with an empty `status` response instead
+    /// of calling the handler. `route` is a route pattern such as `/users/{id}`, or `*` for all routes.
+    pub fn error<T: Into<String>>(self, route: T, fraction: f64, status: StatusCode) -> Self {
+        self.rule(route.into(), fraction, ChaosInjection::Error { status })
+    }
+
+    /// Switches injections on or off, e.g. at the end of an experiment.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.inner.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Number of requests a fault was injected into.
+    pub fn injected(&self) -> u64 {
+        self.inner.injected.load(Ordering::Relaxed)
+    }
+
+    fn rule(mut self, route: String, fraction: f64, injection: ChaosInjection) -> Self {
+        Arc::get_mut(&mut self.inner).unwrap().rules.push(Rule {
+            route,
+            fraction,
+            injection,
+        });
+        self
+    }
+
+    /// Fault to inject into `req`, if it's sampled by a rule of its route.
+    fn injection(&self, req: &ServiceRequest) -> Option<ChaosInjection> {
+        if self.inner.rules.is_empty() || !self.is_enabled() {
+            return None;
+        }
+        let point = chaos_point(&req.extensions().get::<RequestStart>()?.request_id());
+        let route = crate::normalize::route(req);
+        let mut threshold = 0.0;
+        self.inner
+            .rules
+            .iter()
+            .filter(|rule| rule.route == "*" || rule.route == route)
+            .find(|rule| {
+                threshold += rule.fraction;
+                point < threshold
+            })
+            .map(|rule| rule.injection.clone())
+    }
+}
+
+impl Observer for ChaosObserver {
+    fn on_request_started(&self, _data: RequestStartData) {}
+
+    fn on_request_ended(&self, _data: RequestEndData) {}
+
+    fn intercept(&self, req: &ServiceRequest) -> Option<HttpResponse> {
+        let injection = self.injection(req)?;
+        self.inner.injected.fetch_add(1, Ordering::Relaxed);
+        req.extensions_mut().insert(injection.clone());
+        match injection {
+            ChaosInjection::Latency { .. } => None,
+            ChaosInjection::Error { status } => Some(HttpResponse::build(status).finish()),
+        }
+    }
+
+    fn capture_body(&self, _req: &ServiceRequest) -> bool {
+        false
+    }
+}
+
+/// Maps request id to `[0, 1)`, from other bits than samplers of observers use, so faults are injected independently
+/// of e.g. which requests are recorded. The two top bits of the second half are the variant of the id, not random.
+fn chaos_point(request_id: &Uuid) -> f64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&request_id.as_bytes()[8..]);
+    ((u64::from_be_bytes(bytes) << 2) >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -0,0 +1,168 @@
+//! [`ClickhouseObserver`] inserts request end events into ClickHouse over its HTTP interface.
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_web::dev::ServiceRequest;
+use futures_util::future::LocalBoxFuture;
+use reqwest::Client;
+use serde::Serialize;
+
+use crate::observer::{Observer, RequestEndData, RequestStartData};
+use crate::observers::batch::Batcher;
+
+type ErrorFn = Rc<dyn Fn(&reqwest::Error)>;
+
+/// Row in `JSONEachRow` format, a serialized [RequestEndData] with insert `timestamp` in unix milliseconds.
+#[derive(Serialize)]
+struct Row {
+    timestamp: u64,
+    #[serde(flatten)]
+    data: RequestEndData,
+}
+
+/// Inserts ended requests into a ClickHouse table in batches, with `INSERT ... FORMAT JSONEachRow`.
+///
+/// Suggested table:
+/// ```sql
+/// CREATE TABLE requests (
+///     timestamp DateTime64(3),
+///     request_id UUID,
+///     method LowCardinality(String),
+///     uri String,
+///     status UInt16,
+///     elapsed_us UInt64
+/// ) ENGINE = MergeTree ORDER BY timestamp
+/// ```
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use std::time::Duration;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::observers::clickhouse::ClickhouseObserver;
+///
+/// let clickhouse = ClickhouseObserver::builder("http://localhost:8123", "requests")
+///     .credentials("default", "secret")
+///     .batch(10_000, Duration::from_secs(5))
+///     .build()
+///     .unwrap();
+/// let request_hook = RequestHook::new().register(Rc::new(clickhouse));
+/// ```
+pub struct ClickhouseObserver {
+    batcher: Batcher<Row>,
+}
+
+impl ClickhouseObserver {
+    /// Starts configuring an observer inserting into `table` of ClickHouse HTTP interface at `url`.
+    pub fn builder<U: Into<String>, T: Into<String>>(
+        url: U,
+        table: T,
+    ) -> ClickhouseObserverBuilder {
+        ClickhouseObserverBuilder {
+            url: url.into(),
+            table: table.into(),
+            credentials: None,
+            max_batch: 1000,
+            interval: Duration::from_secs(1),
+            on_error: None,
+        }
+    }
+}
+
+/// Configuration for [ClickhouseObserver].
+pub struct ClickhouseObserverBuilder {
+    url: String,
+    table: String,
+    credentials: Option<(String, String)>,
+    max_batch: usize,
+    interval: Duration,
+    on_error: Option<ErrorFn>,
+}
+
+impl ClickhouseObserverBuilder {
+    /// Authenticates with `X-ClickHouse-User` and `X-ClickHouse-Key` headers.
+    pub fn credentials<U: Into<String>, P: Into<String>>(mut self, user: U, password: P) -> Self {
+        self.credentials = Some((user.into(), password.into()));
+        self
+    }
+
+    /// Inserts a batch when `max_batch` events are collected or `interval` passes, 1000 events and 1s by default.
+    pub fn batch(mut self, max_batch: usize, interval: Duration) -> Self {
+        self.max_batch = max_batch;
+        self.interval = interval;
+        self
+    }
+
+    /// Called when a batch fails to insert.
+    pub fn on_error<F: 'static + Fn(&reqwest::Error)>(mut self, callback: F) -> Self {
+        self.on_error = Some(Rc::new(callback));
+        self
+    }
+
+    /// Creates the HTTP client.
+    pub fn build(self) -> Result<ClickhouseObserver, reqwest::Error> {
+        let inserter = Rc::new(Inserter {
+            client: Client::builder().build()?,
+            url: self.url,
+            query: format!("INSERT INTO {} FORMAT JSONEachRow", self.table),
+            credentials: self.credentials,
+            on_error: self.on_error,
+        });
+        let batcher = Batcher::new(self.max_batch, self.interval, move |batch| {
+            inserter.clone().insert(batch)
+        });
+        Ok(ClickhouseObserver { batcher })
+    }
+}
+
+struct Inserter {
+    client: Client,
+    url: String,
+    query: String,
+    credentials: Option<(String, String)>,
+    on_error: Option<ErrorFn>,
+}
+
+impl Inserter {
+    async fn insert(self: Rc<Self>, batch: Vec<Row>) {
+        let mut body = Vec::new();
+        for row in &batch {
+            serde_json::to_writer(&mut body, row).unwrap();
+            body.push(b'\n');
+        }
+        let mut request = self
+            .client
+            .post(&self.url)
+            .query(&[("query", &self.query)])
+            .body(body);
+        if let Some((user, password)) = &self.credentials {
+            request = request
+                .header("X-ClickHouse-User", user)
+                .header("X-ClickHouse-Key", password);
+        }
+        if let Err(err) = request.send().await.and_then(|res| res.error_for_status()) {
+            if let Some(callback) = &self.on_error {
+                callback(&err);
+            }
+        }
+    }
+}
+
+impl Observer for ClickhouseObserver {
+    fn on_request_started(&self, _data: RequestStartData) {}
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        self.batcher.push(Row { timestamp, data });
+    }
+
+    fn capture_body(&self, _req: &ServiceRequest) -> bool {
+        false
+    }
+
+    fn on_shutdown(&self) -> LocalBoxFuture<'static, ()> {
+        self.batcher.flush()
+    }
+}
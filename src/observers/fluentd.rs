@@ -0,0 +1,263 @@
+//! [`FluentdObserver`] ships request end events to fluentd or fluent-bit over the forward protocol.
+use std::cell::RefCell;
+use std::io;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_web::dev::ServiceRequest;
+use futures_util::future::LocalBoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use uuid::Uuid;
+
+use crate::observer::{Observer, RequestEndData, RequestStartData};
+use crate::observers::batch::Batcher;
+
+type ErrorFn = Rc<dyn Fn(&io::Error)>;
+
+trait Connection: AsyncRead + AsyncWrite + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> Connection for T {}
+
+/// Where forward messages are sent.
+enum Address {
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(String),
+}
+
+/// Options of a forward mode message.
+#[derive(Serialize)]
+struct ForwardOptions<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chunk: Option<&'a str>,
+    size: usize,
+}
+
+#[derive(Deserialize)]
+struct AckResponse {
+    ack: String,
+}
+
+/// Sends ended requests to fluentd's `in_forward` input, as MessagePack encoded forward mode messages
+/// `[tag, [[time, record], ...], options]` in batches.
+///
+/// With [`require_ack`](FluentdObserverBuilder::require_ack) every message carries a chunk id, and is resent
+/// on a new connection until fluentd acknowledges it or retries are exhausted.
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::observers::fluentd::FluentdObserver;
+///
+/// let fluentd = FluentdObserver::tcp("127.0.0.1:24224", "app.requests")
+///     .require_ack()
+///     .build();
+/// let request_hook = RequestHook::new().register(Rc::new(fluentd));
+/// ```
+pub struct FluentdObserver {
+    batcher: Batcher<(u64, Value)>,
+}
+
+impl FluentdObserver {
+    /// Starts configuring an observer forwarding events tagged with `tag` over TCP, e.g. to `127.0.0.1:24224`.
+    pub fn tcp<A: Into<String>, T: Into<String>>(addr: A, tag: T) -> FluentdObserverBuilder {
+        FluentdObserverBuilder::new(Address::Tcp(addr.into()), tag.into())
+    }
+
+    /// Starts configuring an observer forwarding events tagged with `tag` over a Unix domain socket.
+    #[cfg(unix)]
+    pub fn unix<P: Into<String>, T: Into<String>>(path: P, tag: T) -> FluentdObserverBuilder {
+        FluentdObserverBuilder::new(Address::Unix(path.into()), tag.into())
+    }
+}
+
+/// Configuration for [FluentdObserver].
+pub struct FluentdObserverBuilder {
+    address: Address,
+    tag: String,
+    require_ack: bool,
+    ack_timeout: Duration,
+    max_retries: u32,
+    max_batch: usize,
+    interval: Duration,
+    on_error: Option<ErrorFn>,
+}
+
+impl FluentdObserverBuilder {
+    fn new(address: Address, tag: String) -> Self {
+        Self {
+            address,
+            tag,
+            require_ack: false,
+            ack_timeout: Duration::from_secs(5),
+            max_retries: 3,
+            max_batch: 500,
+            interval: Duration::from_secs(1),
+            on_error: None,
+        }
+    }
+
+    /// Requests an acknowledgement for every message, for at-least-once delivery.
+    pub fn require_ack(mut self) -> Self {
+        self.require_ack = true;
+        self
+    }
+
+    /// How long to wait for an acknowledgement before the message is resent, 5s by default.
+    pub fn ack_timeout(mut self, ack_timeout: Duration) -> Self {
+        self.ack_timeout = ack_timeout;
+        self
+    }
+
+    /// Number of times a failed message is resent, 3 by default.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sends a message when `max_batch` events are collected or `interval` passes, 500 events and 1s by default.
+    pub fn batch(mut self, max_batch: usize, interval: Duration) -> Self {
+        self.max_batch = max_batch;
+        self.interval = interval;
+        self
+    }
+
+    /// Called on connection, write and acknowledgement errors.
+    pub fn on_error<F: 'static + Fn(&io::Error)>(mut self, callback: F) -> Self {
+        self.on_error = Some(Rc::new(callback));
+        self
+    }
+
+    /// Creates the observer. Connection is opened on first message.
+    pub fn build(self) -> FluentdObserver {
+        let forwarder = Rc::new(Forwarder {
+            address: self.address,
+            tag: self.tag,
+            require_ack: self.require_ack,
+            ack_timeout: self.ack_timeout,
+            max_retries: self.max_retries,
+            on_error: self.on_error,
+            connection: RefCell::new(None),
+        });
+        let batcher = Batcher::new(self.max_batch, self.interval, move |batch| {
+            forwarder.clone().forward(batch)
+        });
+        FluentdObserver { batcher }
+    }
+}
+
+struct Forwarder {
+    address: Address,
+    tag: String,
+    require_ack: bool,
+    ack_timeout: Duration,
+    max_retries: u32,
+    on_error: Option<ErrorFn>,
+    connection: RefCell<Option<Box<dyn Connection>>>,
+}
+
+impl Forwarder {
+    async fn connect(&self) -> io::Result<Box<dyn Connection>> {
+        Ok(match &self.address {
+            Address::Tcp(addr) => Box::new(actix_web::rt::net::TcpStream::connect(addr).await?),
+            #[cfg(unix)]
+            Address::Unix(path) => Box::new(actix_web::rt::net::UnixStream::connect(path).await?),
+        })
+    }
+
+    /// Sends message on an open connection, or a new one, and waits for the acknowledgement of `chunk`.
+    async fn send(&self, message: &[u8], chunk: Option<&str>) -> io::Result<()> {
+        let connection = self.connection.borrow_mut().take();
+        let mut connection = match connection {
+            Some(connection) => connection,
+            None => self.connect().await?,
+        };
+        connection.write_all(message).await?;
+        connection.flush().await?;
+        if let Some(chunk) = chunk {
+            let ack = actix_web::rt::time::timeout(self.ack_timeout, read_ack(&mut connection))
+                .await
+                .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "fluentd ack timed out"))??;
+            if ack != chunk {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("fluentd acknowledged chunk {}, expected {}", ack, chunk),
+                ));
+            }
+        }
+        *self.connection.borrow_mut() = Some(connection);
+        Ok(())
+    }
+
+    async fn forward(self: Rc<Self>, batch: Vec<(u64, Value)>) {
+        let chunk = if self.require_ack {
+            Some(Uuid::new_v4().to_simple().to_string())
+        } else {
+            None
+        };
+        let options = ForwardOptions {
+            chunk: chunk.as_deref(),
+            size: batch.len(),
+        };
+        let message = rmp_serde::to_vec_named(&(&self.tag, batch, options)).unwrap();
+
+        let mut backoff = Duration::from_millis(100);
+        for attempt in 0..=self.max_retries {
+            match self.send(&message, chunk.as_deref()).await {
+                Ok(()) => break,
+                Err(err) => {
+                    if let Some(callback) = &self.on_error {
+                        callback(&err);
+                    }
+                    if attempt < self.max_retries {
+                        actix_web::rt::time::sleep(backoff).await;
+                        backoff *= 2;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Reads a MessagePack `{"ack": chunk}` response.
+async fn read_ack(connection: &mut Box<dyn Connection>) -> io::Result<String> {
+    let mut response = Vec::new();
+    let mut buffer = [0; 256];
+    loop {
+        let read = connection.read(&mut buffer).await?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        response.extend_from_slice(&buffer[..read]);
+        match rmp_serde::from_slice::<AckResponse>(&response) {
+            Ok(response) => return Ok(response.ack),
+            Err(rmp_serde::decode::Error::InvalidMarkerRead(_))
+            | Err(rmp_serde::decode::Error::InvalidDataRead(_)) => continue,
+            Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+        }
+    }
+}
+
+impl Observer for FluentdObserver {
+    fn on_request_started(&self, _data: RequestStartData) {}
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        // serialized through JSON, so the request id is a string and not MessagePack binary
+        self.batcher
+            .push((time, serde_json::to_value(&data).unwrap()));
+    }
+
+    fn capture_body(&self, _req: &ServiceRequest) -> bool {
+        false
+    }
+
+    fn on_shutdown(&self) -> LocalBoxFuture<'static, ()> {
+        self.batcher.flush()
+    }
+}
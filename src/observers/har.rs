@@ -0,0 +1,271 @@
+//! [`HarRecorder`] writes captured request/response pairs as HAR 1.2 files.
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use actix_web::dev::ServiceRequest;
+use actix_web::http::header::{HeaderMap, CONTENT_TYPE};
+use actix_web::web;
+use base64::Engine;
+use regex::RegexSet;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::observer::{Observer, RequestCancelledData, RequestEndData, RequestStartData};
+use crate::util::{rfc3339, SENSITIVE_HEADERS};
+
+type ErrorFn = Rc<dyn Fn(&io::Error)>;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Request part of a HAR entry, kept until the request ends.
+struct PendingEntry {
+    started: SystemTime,
+    http_version: String,
+    request: Value,
+}
+
+/// Records full request/response pairs (headers and bodies) into `<dir>/<request id>.har` files, in HAR 1.2 format,
+/// which can be imported into browser devtools.
+///
+/// Without [HarRecorder::path_regex] and [HarRecorder::when_header] every request is recorded, otherwise only
+/// requests matching either of them. Bodies of recorded requests are captured and recorded responses are buffered,
+/// see [Observer::capture_response]. Files are written on actix's blocking thread pool. `Authorization`, `Proxy-Authorization`, `Cookie` and `Set-Cookie` header values are redacted and bodies are
+/// truncated to 64KiB by default.
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::observers::har::HarRecorder;
+///
+/// let har = HarRecorder::new("/tmp/har")
+///     .path_regex("^/api/orders")
+///     .when_header("x-record-har", "1")
+///     .redact_header("x-api-key");
+/// let request_hook = RequestHook::new().register(Rc::new(har));
+/// ```
+pub struct HarRecorder {
+    dir: PathBuf,
+    paths: Option<RegexSet>,
+    header: Option<(String, String)>,
+    redact_headers: HashSet<String>,
+    max_body_size: usize,
+    on_error: Option<ErrorFn>,
+    pending: RefCell<HashMap<Uuid, PendingEntry>>,
+}
+
+impl HarRecorder {
+    /// Creates a recorder writing HAR files into existing directory `dir`.
+    pub fn new<T: Into<PathBuf>>(dir: T) -> Self {
+        Self {
+            dir: dir.into(),
+            paths: None,
+            header: None,
+            redact_headers: SENSITIVE_HEADERS
+                .iter()
+                .map(|header| header.to_string())
+                .collect(),
+            max_body_size: 64 * 1024,
+            on_error: None,
+            pending: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Records requests with paths matching regex.
+    pub fn path_regex<T: Into<String>>(mut self, path: T) -> Self {
+        let mut patterns = self
+            .paths
+            .as_ref()
+            .map(|paths| paths.patterns().to_vec())
+            .unwrap_or_default();
+        patterns.push(path.into());
+        self.paths = Some(RegexSet::new(patterns).unwrap());
+        self
+    }
+
+    /// Records requests which have header `name` set to `value`.
+    pub fn when_header<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.header = Some((name.into(), value.into()));
+        self
+    }
+
+    /// Replaces value of header `name` with `[REDACTED]`, in both requests and responses.
+    pub fn redact_header<T: Into<String>>(mut self, name: T) -> Self {
+        self.redact_headers.insert(name.into().to_lowercase());
+        self
+    }
+
+    /// Truncates recorded request and response bodies to `max_body_size` bytes.
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Called when a HAR file could not be written.
+    pub fn on_error<F: 'static + Fn(&io::Error)>(mut self, callback: F) -> Self {
+        self.on_error = Some(Rc::new(callback));
+        self
+    }
+
+    fn records(&self, req: &ServiceRequest) -> bool {
+        let path_matches = self.paths.as_ref().map(|paths| paths.is_match(req.path()));
+        let header_matches = self.header.as_ref().map(|(name, value)| {
+            req.headers()
+                .get(name.as_str())
+                .is_some_and(|header| header == value.as_str())
+        });
+        match (path_matches, header_matches) {
+            (None, None) => true,
+            (path, header) => path.unwrap_or(false) || header.unwrap_or(false),
+        }
+    }
+
+    fn headers(&self, headers: &HeaderMap) -> Value {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if self.redact_headers.contains(name.as_str()) {
+                    REDACTED.into()
+                } else {
+                    String::from_utf8_lossy(value.as_bytes())
+                };
+                json!({ "name": name.as_str(), "value": value })
+            })
+            .collect()
+    }
+
+    /// HAR content of a body, base64 encoded if it is not valid UTF-8.
+    fn content(&self, headers: &HeaderMap, body: &[u8]) -> Value {
+        let mime_type = headers
+            .get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .unwrap_or_default();
+        let truncated = &body[..body.len().min(self.max_body_size)];
+        let mut content = json!({ "size": body.len(), "mimeType": mime_type });
+        match std::str::from_utf8(truncated) {
+            Ok(text) => content["text"] = text.into(),
+            Err(_) => {
+                content["text"] = base64::engine::general_purpose::STANDARD
+                    .encode(truncated)
+                    .into();
+                content["encoding"] = "base64".into();
+            }
+        }
+        content
+    }
+
+    fn request(&self, data: &RequestStartData) -> Value {
+        let req = data.req;
+        let connection_info = req.connection_info();
+        let query: Vec<(String, String)> = web::Query::from_query(req.query_string())
+            .map(web::Query::into_inner)
+            .unwrap_or_default();
+        let mut request = json!({
+            "method": &*data.method,
+            "url": format!("{}://{}{}", connection_info.scheme(), connection_info.host(), data.uri),
+            "httpVersion": http_version(req),
+            "cookies": [],
+            "headers": self.headers(req.headers()),
+            "queryString": query
+                .into_iter()
+                .map(|(name, value)| json!({ "name": name, "value": value }))
+                .collect::<Vec<_>>(),
+            "headersSize": -1,
+            "bodySize": data.body.len(),
+        });
+        if !data.body.is_empty() {
+            let content = self.content(req.headers(), &data.body);
+            request["postData"] =
+                json!({ "mimeType": content["mimeType"], "text": content["text"] });
+        }
+        request
+    }
+
+    fn response(&self, data: &RequestEndData, http_version: String) -> Value {
+        let (headers, content, body_size) = match &data.response {
+            Some(response) => (
+                self.headers(&response.headers),
+                self.content(&response.headers, &response.body),
+                response.body.len(),
+            ),
+            None => (json!([]), json!({ "size": 0, "mimeType": "" }), 0),
+        };
+        json!({
+            "status": data.status.as_u16(),
+            "statusText": data.status.canonical_reason().unwrap_or_default(),
+            "httpVersion": http_version,
+            "cookies": [],
+            "headers": headers,
+            "content": content,
+            "redirectURL": "",
+            "headersSize": -1,
+            "bodySize": body_size,
+        })
+    }
+}
+
+impl Observer for HarRecorder {
+    fn on_request_started(&self, data: RequestStartData) {
+        if self.records(data.req) {
+            let entry = PendingEntry {
+                started: SystemTime::now(),
+                http_version: http_version(data.req),
+                request: self.request(&data),
+            };
+            self.pending.borrow_mut().insert(data.request_id, entry);
+        }
+    }
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        let entry = match self.pending.borrow_mut().remove(&data.request_id) {
+            Some(entry) => entry,
+            None => return,
+        };
+        let time = data.elapsed.as_secs_f64() * 1000.0;
+        let har = json!({
+            "log": {
+                "version": "1.2",
+                "creator": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+                "entries": [{
+                    "startedDateTime": rfc3339(entry.started),
+                    "time": time,
+                    "request": entry.request,
+                    "response": self.response(&data, entry.http_version),
+                    "cache": {},
+                    "timings": { "send": 0, "wait": time, "receive": 0 },
+                }],
+            }
+        });
+        let path = self.dir.join(format!("{}.har", data.request_id));
+        let content = serde_json::to_vec_pretty(&har).unwrap();
+        let on_error = self.on_error.clone();
+        actix_web::rt::spawn(async move {
+            let written = web::block(move || std::fs::write(path, content))
+                .await
+                .unwrap_or_else(|err| Err(io::Error::other(err)));
+            if let (Err(err), Some(callback)) = (written, on_error) {
+                callback(&err);
+            }
+        });
+    }
+
+    fn on_request_cancelled(&self, data: RequestCancelledData) {
+        self.pending.borrow_mut().remove(&data.request_id);
+    }
+
+    fn capture_response(&self, req: &ServiceRequest) -> bool {
+        self.records(req)
+    }
+
+    fn capture_body(&self, req: &ServiceRequest) -> bool {
+        self.records(req)
+    }
+}
+
+/// HTTP version of `req` as written in HAR files, e.g. `HTTP/1.1`.
+fn http_version(req: &ServiceRequest) -> String {
+    format!("{:?}", req.version())
+}
@@ -0,0 +1,229 @@
+//! [`HoneycombObserver`] sends one wide event per request to Honeycomb.
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime};
+
+use actix_web::dev::ServiceRequest;
+use actix_web::http::header::{CONTENT_LENGTH, USER_AGENT};
+use futures_util::future::LocalBoxFuture;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Client;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+use crate::observer::{Observer, RequestCancelledData, RequestEndData, RequestStartData};
+use crate::observers::batch::Batcher;
+use crate::util::{parse_traceparent, rfc3339};
+
+type ErrorFn = Rc<dyn Fn(&reqwest::Error)>;
+/// Start time and fields collected when request started.
+type Pending = (SystemTime, Map<String, Value>);
+
+/// Sends one wide event per ended request to Honeycomb's batch events API.
+///
+/// Event fields are flattened: `request_id`, `name`, `duration_ms`, `http.method`, `http.uri`, `http.route`,
+/// `http.status_code`, `http.host`, `http.user_agent`, `http.request_body_size`, `client.ip` and `service.name`.
+/// Trace ids are taken from W3C `traceparent` header when present, so events join traces in Honeycomb:
+/// `trace.trace_id`, `trace.parent_id` and `trace.span_id` derived from the request id.
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::observers::honeycomb::HoneycombObserver;
+///
+/// let honeycomb = HoneycombObserver::builder("api-key", "requests")
+///     .service_name("orders")
+///     .build()
+///     .unwrap();
+/// let request_hook = RequestHook::new().register(Rc::new(honeycomb));
+/// ```
+pub struct HoneycombObserver {
+    service_name: Option<String>,
+    pending: RefCell<HashMap<Uuid, Pending>>,
+    batcher: Batcher<Value>,
+}
+
+impl HoneycombObserver {
+    /// Starts configuring an observer sending events to `dataset` with `api_key`.
+    pub fn builder<K: Into<String>, D: Into<String>>(
+        api_key: K,
+        dataset: D,
+    ) -> HoneycombObserverBuilder {
+        HoneycombObserverBuilder {
+            api_key: api_key.into(),
+            dataset: dataset.into(),
+            api_host: "https://api.honeycomb.io".to_string(),
+            service_name: None,
+            max_batch: 500,
+            interval: Duration::from_secs(1),
+            on_error: None,
+        }
+    }
+}
+
+/// Configuration for [HoneycombObserver].
+pub struct HoneycombObserverBuilder {
+    api_key: String,
+    dataset: String,
+    api_host: String,
+    service_name: Option<String>,
+    max_batch: usize,
+    interval: Duration,
+    on_error: Option<ErrorFn>,
+}
+
+impl HoneycombObserverBuilder {
+    /// Overrides API host, `https://api.honeycomb.io` by default, e.g. for EU region or a refinery proxy.
+    pub fn api_host<T: Into<String>>(mut self, api_host: T) -> Self {
+        self.api_host = api_host.into();
+        self
+    }
+
+    /// Sets `service.name` field of every event.
+    pub fn service_name<T: Into<String>>(mut self, service_name: T) -> Self {
+        self.service_name = Some(service_name.into());
+        self
+    }
+
+    /// Sends a batch when `max_batch` events are collected or `interval` passes, 500 events and 1s by default.
+    pub fn batch(mut self, max_batch: usize, interval: Duration) -> Self {
+        self.max_batch = max_batch;
+        self.interval = interval;
+        self
+    }
+
+    /// Called when a batch could not be sent.
+    pub fn on_error<F: 'static + Fn(&reqwest::Error)>(mut self, callback: F) -> Self {
+        self.on_error = Some(Rc::new(callback));
+        self
+    }
+
+    /// Creates the HTTP client.
+    pub fn build(self) -> Result<HoneycombObserver, reqwest::Error> {
+        let sender = Rc::new(Sender {
+            client: Client::builder().build()?,
+            url: format!(
+                "{}/1/batch/{}",
+                self.api_host.trim_end_matches('/'),
+                self.dataset
+            ),
+            api_key: self.api_key,
+            on_error: self.on_error,
+        });
+        let batcher = Batcher::new(self.max_batch, self.interval, move |batch| {
+            sender.clone().send(batch)
+        });
+        Ok(HoneycombObserver {
+            service_name: self.service_name,
+            pending: RefCell::new(HashMap::new()),
+            batcher,
+        })
+    }
+}
+
+struct Sender {
+    client: Client,
+    url: String,
+    api_key: String,
+    on_error: Option<ErrorFn>,
+}
+
+impl Sender {
+    async fn send(self: Rc<Self>, batch: Vec<Value>) {
+        let request = self
+            .client
+            .post(&self.url)
+            .header("X-Honeycomb-Team", &self.api_key)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Value::from(batch).to_string());
+        if let Err(err) = request.send().await.and_then(|res| res.error_for_status()) {
+            if let Some(callback) = &self.on_error {
+                callback(&err);
+            }
+        }
+    }
+}
+
+impl Observer for HoneycombObserver {
+    fn on_request_started(&self, data: RequestStartData) {
+        let req = data.req;
+        let connection_info = req.connection_info();
+        let header = |name| {
+            req.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        let mut fields = Map::new();
+        fields.insert("http.host".into(), connection_info.host().into());
+        let body_size = header(CONTENT_LENGTH.as_str())
+            .and_then(|length| length.parse().ok())
+            .unwrap_or(data.body.len() as u64);
+        fields.insert("http.request_body_size".into(), body_size.into());
+        if let Some(route) = req.match_pattern() {
+            fields.insert("http.route".into(), route.into());
+        }
+        if let Some(user_agent) = header(USER_AGENT.as_str()) {
+            fields.insert("http.user_agent".into(), user_agent.into());
+        }
+        if let Some(ip) = connection_info.realip_remote_addr() {
+            fields.insert("client.ip".into(), ip.into());
+        }
+        if let Some(service_name) = &self.service_name {
+            fields.insert("service.name".into(), service_name.as_str().into());
+        }
+
+        let span_id = data.request_id.to_simple().to_string()[..16].to_string();
+        match header("traceparent").and_then(parse_traceparent) {
+            Some((trace_id, parent_id, _)) => {
+                fields.insert("trace.trace_id".into(), trace_id.into());
+                fields.insert("trace.parent_id".into(), parent_id.into());
+            }
+            None => {
+                let trace_id = data.request_id.to_simple().to_string();
+                fields.insert("trace.trace_id".into(), trace_id.into());
+            }
+        }
+        fields.insert("trace.span_id".into(), span_id.into());
+
+        self.pending
+            .borrow_mut()
+            .insert(data.request_id, (SystemTime::now(), fields));
+    }
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        let (started, mut fields) = self
+            .pending
+            .borrow_mut()
+            .remove(&data.request_id)
+            .unwrap_or_else(|| (SystemTime::now() - data.elapsed, Map::new()));
+        let name = match fields.get("http.route").and_then(Value::as_str) {
+            Some(route) => format!("{} {}", data.method, route),
+            None => data.method.to_string(),
+        };
+        fields.insert("name".into(), name.into());
+        fields.insert("request_id".into(), data.request_id.to_string().into());
+        fields.insert(
+            "duration_ms".into(),
+            (data.elapsed.as_secs_f64() * 1000.0).into(),
+        );
+        fields.insert("http.method".into(), (&*data.method).into());
+        fields.insert("http.uri".into(), (&*data.uri).into());
+        fields.insert("http.status_code".into(), data.status.as_u16().into());
+
+        self.batcher
+            .push(json!({ "time": rfc3339(started), "data": fields }));
+    }
+
+    fn on_request_cancelled(&self, data: RequestCancelledData) {
+        self.pending.borrow_mut().remove(&data.request_id);
+    }
+
+    fn capture_body(&self, _req: &ServiceRequest) -> bool {
+        false
+    }
+
+    fn on_shutdown(&self) -> LocalBoxFuture<'static, ()> {
+        self.batcher.flush()
+    }
+}
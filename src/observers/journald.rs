@@ -0,0 +1,110 @@
+//! [`JournaldObserver`] writes request end events to the systemd journal.
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::path::PathBuf;
+
+use actix_web::dev::ServiceRequest;
+
+use crate::observer::{Observer, RequestEndData, RequestStartData};
+
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Writes an entry to journald for every ended request, using journald's native protocol.
+///
+/// Entries carry `REQUEST_ID`, `HTTP_METHOD`, `HTTP_URI`, `HTTP_STATUS` and `ELAPSED_US` fields, so they can be
+/// filtered with `journalctl`, e.g. `journalctl HTTP_STATUS=500`.
+/// Priority is `err` for 5xx responses, `warning` for 4xx and `info` for everything else.
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::observers::journald::JournaldObserver;
+///
+/// let journald = JournaldObserver::new().unwrap().identifier("my-service");
+/// let request_hook = RequestHook::new().register(Rc::new(journald));
+/// ```
+pub struct JournaldObserver {
+    socket: UnixDatagram,
+    path: PathBuf,
+    identifier: Option<String>,
+}
+
+impl JournaldObserver {
+    /// Creates an observer writing to the default journald socket.
+    pub fn new() -> io::Result<Self> {
+        Ok(Self {
+            socket: UnixDatagram::unbound()?,
+            path: PathBuf::from(JOURNALD_SOCKET),
+            identifier: None,
+        })
+    }
+
+    /// Sets `SYSLOG_IDENTIFIER` of written entries.
+    pub fn identifier<T: Into<String>>(mut self, identifier: T) -> Self {
+        self.identifier = Some(identifier.into());
+        self
+    }
+
+    /// Overrides journald socket path, `/run/systemd/journal/socket` by default.
+    pub fn socket_path<T: Into<PathBuf>>(mut self, path: T) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    fn entry(&self, data: &RequestEndData) -> Vec<u8> {
+        let priority = if data.status.is_server_error() {
+            "3"
+        } else if data.status.is_client_error() {
+            "4"
+        } else {
+            "6"
+        };
+        let elapsed_us = data.elapsed.as_micros().to_string();
+        let message = format!(
+            "{} {} {} {}ms",
+            data.method,
+            data.uri,
+            data.status.as_u16(),
+            data.elapsed.as_millis()
+        );
+
+        let mut entry = Vec::new();
+        append_field(&mut entry, "MESSAGE", &message);
+        append_field(&mut entry, "PRIORITY", priority);
+        if let Some(identifier) = &self.identifier {
+            append_field(&mut entry, "SYSLOG_IDENTIFIER", identifier);
+        }
+        append_field(&mut entry, "REQUEST_ID", &data.request_id.to_string());
+        append_field(&mut entry, "HTTP_METHOD", &data.method);
+        append_field(&mut entry, "HTTP_URI", &data.uri);
+        append_field(&mut entry, "HTTP_STATUS", data.status.as_str());
+        append_field(&mut entry, "ELAPSED_US", &elapsed_us);
+        entry
+    }
+}
+
+/// Appends a field in journald's native format. Values containing a newline are length-prefixed.
+fn append_field(entry: &mut Vec<u8>, key: &str, value: &str) {
+    entry.extend_from_slice(key.as_bytes());
+    if value.contains('\n') {
+        entry.push(b'\n');
+        entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        entry.push(b'=');
+    }
+    entry.extend_from_slice(value.as_bytes());
+    entry.push(b'\n');
+}
+
+impl Observer for JournaldObserver {
+    fn on_request_started(&self, _data: RequestStartData) {}
+
+    /// Journal write failures are ignored, logging must never affect the response.
+    fn on_request_ended(&self, data: RequestEndData) {
+        let _ = self.socket.send_to(&self.entry(&data), &self.path);
+    }
+
+    fn capture_body(&self, _req: &ServiceRequest) -> bool {
+        false
+    }
+}
@@ -0,0 +1,168 @@
+//! [`KafkaObserver`] publishes request end events to a Kafka topic.
+use std::future::ready;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::dev::ServiceRequest;
+use futures_util::future::LocalBoxFuture;
+use rdkafka::config::ClientConfig;
+use rdkafka::error::{KafkaError, KafkaResult};
+use rdkafka::message::{DeliveryResult, Message};
+use rdkafka::producer::{BaseRecord, Producer, ProducerContext, ThreadedProducer};
+use rdkafka::ClientContext;
+
+use crate::observer::{Observer, RequestEndData, RequestStartData};
+
+type KeyFn = Rc<dyn Fn(&RequestEndData) -> String>;
+type DeliveryErrorFn = Arc<dyn Fn(&KafkaError, &[u8]) + Send + Sync>;
+
+/// Publishes every ended request as a JSON serialized [RequestEndData] to a Kafka topic.
+///
+/// Messages are keyed by request id unless configured otherwise with [KafkaObserverBuilder::key_by].
+/// Batching is done by librdkafka, see [KafkaObserverBuilder::batch].
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use std::time::Duration;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::observers::kafka::KafkaObserver;
+///
+/// let kafka = KafkaObserver::builder("localhost:9092", "requests")
+///     .batch(Duration::from_millis(100), 1000)
+///     .on_delivery_error(|err, _payload| eprintln!("request event not delivered: {}", err))
+///     .build()
+///     .unwrap();
+/// let request_hook = RequestHook::new().register(Rc::new(kafka));
+/// ```
+pub struct KafkaObserver {
+    producer: ThreadedProducer<DeliveryContext>,
+    topic: String,
+    messages: Messages,
+}
+
+impl KafkaObserver {
+    /// Starts configuring an observer publishing to `topic` on `brokers` (comma separated `host:port` list).
+    pub fn builder<B: Into<String>, T: Into<String>>(brokers: B, topic: T) -> KafkaObserverBuilder {
+        let mut config = ClientConfig::new();
+        config.set("bootstrap.servers", brokers);
+        KafkaObserverBuilder {
+            config,
+            topic: topic.into(),
+            messages: Messages {
+                key: Rc::new(|data| data.request_id.to_string()),
+            },
+            on_delivery_error: None,
+        }
+    }
+}
+
+/// Keys and serializes events into messages, apart from the producer sending them.
+struct Messages {
+    key: KeyFn,
+}
+
+impl Messages {
+    /// Key and JSON payload of the message of `data`.
+    fn message(&self, data: &RequestEndData) -> (String, Vec<u8>) {
+        ((self.key)(data), serde_json::to_vec(data).unwrap())
+    }
+}
+
+/// Configuration for [KafkaObserver].
+pub struct KafkaObserverBuilder {
+    config: ClientConfig,
+    topic: String,
+    messages: Messages,
+    on_delivery_error: Option<DeliveryErrorFn>,
+}
+
+impl KafkaObserverBuilder {
+    /// Sets any librdkafka producer property, e.g. `compression.type` or `security.protocol`.
+    pub fn set<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.config.set(key, value);
+        self
+    }
+
+    /// Waits up to `linger` for up to `max_messages` events before sending them as one batch.
+    pub fn batch(self, linger: Duration, max_messages: usize) -> Self {
+        self.set("linger.ms", linger.as_millis().to_string())
+            .set("batch.num.messages", max_messages.to_string())
+    }
+
+    /// Derives message key from an event, e.g. a tenant. Request id is used by default.
+    pub fn key_by<F: 'static + Fn(&RequestEndData) -> String>(mut self, key: F) -> Self {
+        self.messages.key = Rc::new(key);
+        self
+    }
+
+    /// Called with the error and serialized event, for events that could not be queued or delivered.
+    pub fn on_delivery_error<F>(mut self, callback: F) -> Self
+    where
+        F: 'static + Fn(&KafkaError, &[u8]) + Send + Sync,
+    {
+        self.on_delivery_error = Some(Arc::new(callback));
+        self
+    }
+
+    /// Creates the producer.
+    pub fn build(self) -> KafkaResult<KafkaObserver> {
+        let producer = self.config.create_with_context(DeliveryContext {
+            on_delivery_error: self.on_delivery_error,
+        })?;
+        Ok(KafkaObserver {
+            producer,
+            topic: self.topic,
+            messages: self.messages,
+        })
+    }
+}
+
+/// Producer context forwarding delivery failures to [KafkaObserverBuilder::on_delivery_error].
+pub struct DeliveryContext {
+    on_delivery_error: Option<DeliveryErrorFn>,
+}
+
+impl ClientContext for DeliveryContext {}
+
+impl ProducerContext for DeliveryContext {
+    type DeliveryOpaque = ();
+
+    fn delivery(
+        &self,
+        delivery_result: &DeliveryResult<'_>,
+        _delivery_opaque: Self::DeliveryOpaque,
+    ) {
+        if let (Err((err, message)), Some(callback)) = (delivery_result, &self.on_delivery_error) {
+            callback(err, message.payload().unwrap_or_default());
+        }
+    }
+}
+
+impl Observer for KafkaObserver {
+    fn on_request_started(&self, _data: RequestStartData) {}
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        let (key, payload) = self.messages.message(&data);
+        let record = BaseRecord::to(&self.topic).key(&key).payload(&payload);
+        if let Err((err, _record)) = self.producer.send(record) {
+            if let Some(callback) = &self.producer.context().on_delivery_error {
+                callback(&err, &payload);
+            }
+        }
+    }
+
+    fn capture_body(&self, _req: &ServiceRequest) -> bool {
+        false
+    }
+
+    /// Waits up to 5 seconds for queued events to be delivered.
+    fn on_shutdown(&self) -> LocalBoxFuture<'static, ()> {
+        if let Err(err) = self.producer.flush(Duration::from_secs(5)) {
+            if let Some(callback) = &self.producer.context().on_delivery_error {
+                callback(&err, &[]);
+            }
+        }
+        Box::pin(ready(()))
+    }
+}
@@ -0,0 +1,241 @@
+//! [`LokiObserver`] pushes request end events to Grafana Loki.
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_web::dev::ServiceRequest;
+use futures_util::future::LocalBoxFuture;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Client;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::observer::{Observer, RequestCancelledData, RequestEndData, RequestStartData};
+use crate::observers::batch::Batcher;
+use crate::util::status_class;
+
+type TenantFn = Box<dyn Fn(&ServiceRequest) -> Option<String>>;
+type ErrorFn = Rc<dyn Fn(&reqwest::Error)>;
+type Labels = BTreeMap<String, String>;
+
+/// Log line with its labels and timestamp in unix nanoseconds.
+struct Entry {
+    labels: Labels,
+    timestamp: String,
+    line: String,
+}
+
+/// Pushes ended requests as JSON log lines to Loki's `/loki/api/v1/push`, in batches.
+///
+/// Every line is labeled with configured static labels, and optionally with `route` (route pattern),
+/// `status_class` (e.g. `5xx`) and `tenant`. Keep label values low in cardinality, see Loki best practices.
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::observers::loki::LokiObserver;
+///
+/// let loki = LokiObserver::builder("http://localhost:3100")
+///     .label("service", "orders")
+///     .route_label()
+///     .status_class_label()
+///     .build()
+///     .unwrap();
+/// let request_hook = RequestHook::new().register(Rc::new(loki));
+/// ```
+pub struct LokiObserver {
+    labels: Labels,
+    route_label: bool,
+    status_class_label: bool,
+    tenant: Option<TenantFn>,
+    pending: RefCell<HashMap<Uuid, Labels>>,
+    batcher: Batcher<Entry>,
+}
+
+impl LokiObserver {
+    /// Starts configuring an observer pushing to Loki at `url`, e.g. `http://localhost:3100`.
+    pub fn builder<T: Into<String>>(url: T) -> LokiObserverBuilder {
+        LokiObserverBuilder {
+            url: url.into(),
+            org_id: None,
+            labels: BTreeMap::new(),
+            route_label: false,
+            status_class_label: false,
+            tenant: None,
+            max_batch: 1000,
+            interval: Duration::from_secs(1),
+            on_error: None,
+        }
+    }
+}
+
+/// Configuration for [LokiObserver].
+pub struct LokiObserverBuilder {
+    url: String,
+    org_id: Option<String>,
+    labels: Labels,
+    route_label: bool,
+    status_class_label: bool,
+    tenant: Option<TenantFn>,
+    max_batch: usize,
+    interval: Duration,
+    on_error: Option<ErrorFn>,
+}
+
+impl LokiObserverBuilder {
+    /// Adds a static label to every line, e.g. `service` or `env`.
+    pub fn label<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.labels.insert(name.into(), value.into());
+        self
+    }
+
+    /// Labels lines with `route`, the matched route pattern (e.g. `/users/{id}`) or normalized path if no route matched.
+    pub fn route_label(mut self) -> Self {
+        self.route_label = true;
+        self
+    }
+
+    /// Labels lines with `status_class`, e.g. `2xx`.
+    pub fn status_class_label(mut self) -> Self {
+        self.status_class_label = true;
+        self
+    }
+
+    /// Labels lines with `tenant` resolved from request, if any.
+    pub fn tenant_label<F: 'static + Fn(&ServiceRequest) -> Option<String>>(
+        mut self,
+        tenant: F,
+    ) -> Self {
+        self.tenant = Some(Box::new(tenant));
+        self
+    }
+
+    /// Sends `X-Scope-OrgID` header, for multi-tenant Loki installations.
+    pub fn org_id<T: Into<String>>(mut self, org_id: T) -> Self {
+        self.org_id = Some(org_id.into());
+        self
+    }
+
+    /// Pushes a batch when `max_batch` lines are collected or `interval` passes, 1000 lines and 1s by default.
+    pub fn batch(mut self, max_batch: usize, interval: Duration) -> Self {
+        self.max_batch = max_batch;
+        self.interval = interval;
+        self
+    }
+
+    /// Called when a push fails.
+    pub fn on_error<F: 'static + Fn(&reqwest::Error)>(mut self, callback: F) -> Self {
+        self.on_error = Some(Rc::new(callback));
+        self
+    }
+
+    /// Creates the HTTP client.
+    pub fn build(self) -> Result<LokiObserver, reqwest::Error> {
+        let pusher = Rc::new(Pusher {
+            client: Client::builder().build()?,
+            url: format!("{}/loki/api/v1/push", self.url.trim_end_matches('/')),
+            org_id: self.org_id,
+            on_error: self.on_error,
+        });
+        let batcher = Batcher::new(self.max_batch, self.interval, move |batch| {
+            pusher.clone().push(batch)
+        });
+        Ok(LokiObserver {
+            labels: self.labels,
+            route_label: self.route_label,
+            status_class_label: self.status_class_label,
+            tenant: self.tenant,
+            pending: RefCell::new(HashMap::new()),
+            batcher,
+        })
+    }
+}
+
+struct Pusher {
+    client: Client,
+    url: String,
+    org_id: Option<String>,
+    on_error: Option<ErrorFn>,
+}
+
+impl Pusher {
+    async fn push(self: Rc<Self>, batch: Vec<Entry>) {
+        let mut streams: BTreeMap<Labels, Vec<[String; 2]>> = BTreeMap::new();
+        for entry in batch {
+            streams
+                .entry(entry.labels)
+                .or_default()
+                .push([entry.timestamp, entry.line]);
+        }
+        let streams: Vec<_> = streams
+            .into_iter()
+            .map(|(labels, values)| json!({ "stream": labels, "values": values }))
+            .collect();
+
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(json!({ "streams": streams }).to_string());
+        if let Some(org_id) = &self.org_id {
+            request = request.header("X-Scope-OrgID", org_id);
+        }
+        if let Err(err) = request.send().await.and_then(|res| res.error_for_status()) {
+            if let Some(callback) = &self.on_error {
+                callback(&err);
+            }
+        }
+    }
+}
+
+impl Observer for LokiObserver {
+    fn on_request_started(&self, data: RequestStartData) {
+        let mut labels = Labels::new();
+        if self.route_label {
+            let route = crate::normalize::route(data.req);
+            labels.insert("route".to_string(), route);
+        }
+        if let Some(tenant) = self.tenant.as_ref().and_then(|tenant| tenant(data.req)) {
+            labels.insert("tenant".to_string(), tenant);
+        }
+        if !labels.is_empty() {
+            self.pending.borrow_mut().insert(data.request_id, labels);
+        }
+    }
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        let mut labels = self.labels.clone();
+        if let Some(request_labels) = self.pending.borrow_mut().remove(&data.request_id) {
+            labels.extend(request_labels);
+        }
+        if self.status_class_label {
+            labels.insert(
+                "status_class".to_string(),
+                status_class(data.status).to_string(),
+            );
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos()
+            .to_string();
+        self.batcher.push(Entry {
+            labels,
+            timestamp,
+            line: serde_json::to_string(&data).unwrap(),
+        });
+    }
+
+    fn on_request_cancelled(&self, data: RequestCancelledData) {
+        self.pending.borrow_mut().remove(&data.request_id);
+    }
+
+    fn capture_body(&self, _req: &ServiceRequest) -> bool {
+        false
+    }
+
+    fn on_shutdown(&self) -> LocalBoxFuture<'static, ()> {
+        self.batcher.flush()
+    }
+}
@@ -0,0 +1,127 @@
+//! [`NatsObserver`] publishes request end events to a NATS subject.
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use actix_web::dev::ServiceRequest;
+use actix_web::web::Bytes;
+use async_nats::{Client, PublishError};
+use uuid::Uuid;
+
+use crate::observer::{Observer, RequestCancelledData, RequestEndData, RequestStartData};
+
+type SubjectFn = Rc<dyn Fn(&RequestEndData) -> String>;
+type ErrorFn = Rc<dyn Fn(&PublishError)>;
+
+/// Publishes every ended request as a JSON serialized [RequestEndData] to a NATS subject.
+///
+/// Publishing is spawned on the actix runtime, so request latency is unaffected by NATS round trips.
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::observers::nats::NatsObserver;
+///
+/// # async fn setup() {
+/// let client = async_nats::connect("localhost:4222").await.unwrap();
+/// // "/users/42" of route "/users/{id}" is published to "requests.users.{id}"
+/// let nats = NatsObserver::new(client, "requests").subject_per_route();
+/// let request_hook = RequestHook::new().register(Rc::new(nats));
+/// # }
+/// ```
+pub struct NatsObserver {
+    client: Client,
+    subject: String,
+    subject_by: Option<SubjectFn>,
+    per_route: bool,
+    on_error: Option<ErrorFn>,
+    routes: RefCell<HashMap<Uuid, String>>,
+}
+
+impl NatsObserver {
+    /// Creates an observer publishing to `subject` with a connected `client`.
+    pub fn new<T: Into<String>>(client: Client, subject: T) -> Self {
+        Self {
+            client,
+            subject: subject.into(),
+            subject_by: None,
+            per_route: false,
+            on_error: None,
+            routes: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Publishes each route to its own subject, made of the configured subject and segments of the route pattern, or
+    /// normalized path if no route matched, see [route](crate::normalize::route).
+    pub fn subject_per_route(mut self) -> Self {
+        self.subject_by = None;
+        self.per_route = true;
+        self
+    }
+
+    /// Derives subject from an event, overrides configured subject.
+    pub fn subject_by<F: 'static + Fn(&RequestEndData) -> String>(mut self, subject_by: F) -> Self {
+        self.subject_by = Some(Rc::new(subject_by));
+        self.per_route = false;
+        self
+    }
+
+    /// Called when an event could not be published.
+    pub fn on_error<F: 'static + Fn(&PublishError)>(mut self, callback: F) -> Self {
+        self.on_error = Some(Rc::new(callback));
+        self
+    }
+}
+
+/// Appends path segments of `uri`, e.g. a route pattern, to `subject`. NATS wildcards and whitespace in segments are replaced with `_`.
+pub(crate) fn route_subject(subject: &str, uri: &str) -> String {
+    let path = uri.split(['?', '#']).next().unwrap_or_default();
+    path.split('/').filter(|segment| !segment.is_empty()).fold(
+        subject.to_string(),
+        |mut subject, segment| {
+            subject.push('.');
+            subject.extend(segment.chars().map(|c| match c {
+                '.' | '*' | '>' => '_',
+                c if c.is_whitespace() => '_',
+                c => c,
+            }));
+            subject
+        },
+    )
+}
+
+impl Observer for NatsObserver {
+    fn on_request_started(&self, data: RequestStartData) {
+        if self.per_route {
+            let route = crate::normalize::route(data.req);
+            self.routes.borrow_mut().insert(data.request_id, route);
+        }
+    }
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        let route = self.routes.borrow_mut().remove(&data.request_id);
+        let subject = match (&self.subject_by, route) {
+            (Some(subject_by), _) => subject_by(&data),
+            (None, Some(route)) => route_subject(&self.subject, &route),
+            (None, None) => self.subject.clone(),
+        };
+        let payload = Bytes::from(serde_json::to_vec(&data).unwrap());
+        let client = self.client.clone();
+        let on_error = self.on_error.clone();
+        actix_web::rt::spawn(async move {
+            if let Err(err) = client.publish(subject, payload).await {
+                if let Some(callback) = on_error {
+                    callback(&err);
+                }
+            }
+        });
+    }
+
+    fn on_request_cancelled(&self, data: RequestCancelledData) {
+        self.routes.borrow_mut().remove(&data.request_id);
+    }
+
+    fn capture_body(&self, _req: &ServiceRequest) -> bool {
+        false
+    }
+}
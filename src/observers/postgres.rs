@@ -0,0 +1,209 @@
+//! [`PostgresAuditObserver`] inserts request end events into a PostgreSQL table.
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::future::ready;
+use std::rc::Rc;
+use std::time::Duration;
+
+use actix_web::dev::ServiceRequest;
+use futures_util::future::LocalBoxFuture;
+use tokio_postgres::{Client, Error, Statement};
+
+use crate::observer::{Observer, RequestEndData, RequestStartData};
+use crate::observers::batch::Batcher;
+
+type ErrorFn = Rc<dyn Fn(&Error)>;
+
+/// Inserts ended requests into a PostgreSQL table in batches, with a single prepared statement per batch.
+///
+/// Table must have `request_id UUID, method TEXT, uri TEXT, status SMALLINT, elapsed_us BIGINT` columns.
+/// Batches are inserted one at a time. While the database is slow, up to `max_pending` batches wait in memory,
+/// older batches are dropped after that and counted in [PostgresAuditObserver::dropped].
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::observers::postgres::PostgresAuditObserver;
+///
+/// # async fn setup() {
+/// let (client, connection) = tokio_postgres::connect("host=localhost user=postgres", tokio_postgres::NoTls)
+///     .await
+///     .unwrap();
+/// actix_web::rt::spawn(connection);
+/// let audit = PostgresAuditObserver::new(client).table("audit.requests");
+/// let request_hook = RequestHook::new().register(Rc::new(audit));
+/// # }
+/// ```
+pub struct PostgresAuditObserver {
+    writer: Rc<Writer>,
+    max_batch: usize,
+    interval: Duration,
+    batcher: RefCell<Option<Batcher<RequestEndData>>>,
+}
+
+struct Writer {
+    client: Client,
+    table: String,
+    max_pending: usize,
+    on_error: Option<ErrorFn>,
+    statement: RefCell<Option<Statement>>,
+    pending: RefCell<VecDeque<Vec<RequestEndData>>>,
+    writing: Cell<bool>,
+    dropped: Cell<u64>,
+}
+
+impl PostgresAuditObserver {
+    /// Creates an observer inserting into `request_audit` table with a connected `client`.
+    pub fn new(client: Client) -> Self {
+        Self {
+            writer: Rc::new(Writer {
+                client,
+                table: "request_audit".to_string(),
+                max_pending: 100,
+                on_error: None,
+                statement: RefCell::new(None),
+                pending: RefCell::new(VecDeque::new()),
+                writing: Cell::new(false),
+                dropped: Cell::new(0),
+            }),
+            max_batch: 500,
+            interval: Duration::from_secs(1),
+            batcher: RefCell::new(None),
+        }
+    }
+
+    /// Table to insert into, optionally schema qualified.
+    pub fn table<T: Into<String>>(mut self, table: T) -> Self {
+        self.writer_mut().table = table.into();
+        self
+    }
+
+    /// Inserts a batch when `max_batch` events are collected or `interval` passes, 500 events and 1s by default.
+    pub fn batch(mut self, max_batch: usize, interval: Duration) -> Self {
+        self.max_batch = max_batch;
+        self.interval = interval;
+        self
+    }
+
+    /// Maximum number of batches waiting for insert, 100 by default.
+    pub fn max_pending(mut self, max_pending: usize) -> Self {
+        self.writer_mut().max_pending = max_pending;
+        self
+    }
+
+    /// Called when a batch fails to insert.
+    pub fn on_error<F: 'static + Fn(&Error)>(mut self, callback: F) -> Self {
+        self.writer_mut().on_error = Some(Rc::new(callback));
+        self
+    }
+
+    /// Number of events dropped because too many batches were waiting for insert.
+    pub fn dropped(&self) -> u64 {
+        self.writer.dropped.get()
+    }
+
+    fn writer_mut(&mut self) -> &mut Writer {
+        Rc::get_mut(&mut self.writer).unwrap()
+    }
+}
+
+/// Insert statement taking one array parameter per column, so a whole batch is a single statement execution.
+pub(crate) fn insert_statement(table: &str) -> String {
+    format!(
+        "INSERT INTO {} (request_id, method, uri, status, elapsed_us) \
+        SELECT request_id::uuid, method, uri, status, elapsed_us \
+        FROM UNNEST($1::text[], $2::text[], $3::text[], $4::int2[], $5::int8[]) \
+        AS t (request_id, method, uri, status, elapsed_us)",
+        table
+    )
+}
+
+impl Writer {
+    fn enqueue(self: Rc<Self>, batch: Vec<RequestEndData>) {
+        {
+            let mut pending = self.pending.borrow_mut();
+            if pending.len() >= self.max_pending {
+                if let Some(dropped) = pending.pop_front() {
+                    self.dropped.set(self.dropped.get() + dropped.len() as u64);
+                }
+            }
+            pending.push_back(batch);
+        }
+        if !self.writing.replace(true) {
+            actix_web::rt::spawn(self.write_pending());
+        }
+    }
+
+    async fn write_pending(self: Rc<Self>) {
+        loop {
+            let next = self.pending.borrow_mut().pop_front();
+            let batch = match next {
+                Some(batch) => batch,
+                None => break,
+            };
+            if let Err(err) = self.insert(&batch).await {
+                self.statement.borrow_mut().take();
+                if let Some(callback) = &self.on_error {
+                    callback(&err);
+                }
+            }
+        }
+        self.writing.set(false);
+    }
+
+    async fn insert(&self, batch: &[RequestEndData]) -> Result<u64, Error> {
+        let prepared = self.statement.borrow().clone();
+        let statement = match prepared {
+            Some(statement) => statement,
+            None => {
+                let statement = self.client.prepare(&insert_statement(&self.table)).await?;
+                *self.statement.borrow_mut() = Some(statement.clone());
+                statement
+            }
+        };
+        let ids: Vec<String> = batch
+            .iter()
+            .map(|data| data.request_id.to_string())
+            .collect();
+        let methods: Vec<&str> = batch.iter().map(|data| &*data.method).collect();
+        let uris: Vec<&str> = batch.iter().map(|data| &*data.uri).collect();
+        let statuses: Vec<i16> = batch
+            .iter()
+            .map(|data| data.status.as_u16() as i16)
+            .collect();
+        let elapsed: Vec<i64> = batch
+            .iter()
+            .map(|data| data.elapsed.as_micros() as i64)
+            .collect();
+        self.client
+            .execute(&statement, &[&ids, &methods, &uris, &statuses, &elapsed])
+            .await
+    }
+}
+
+impl Observer for PostgresAuditObserver {
+    fn on_request_started(&self, _data: RequestStartData) {}
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        let mut batcher = self.batcher.borrow_mut();
+        let batcher = batcher.get_or_insert_with(|| {
+            let writer = self.writer.clone();
+            Batcher::new(self.max_batch, self.interval, move |batch| {
+                writer.clone().enqueue(batch);
+                async {}
+            })
+        });
+        batcher.push(data);
+    }
+
+    fn capture_body(&self, _req: &ServiceRequest) -> bool {
+        false
+    }
+
+    fn on_shutdown(&self) -> LocalBoxFuture<'static, ()> {
+        match &*self.batcher.borrow() {
+            Some(batcher) => batcher.flush(),
+            None => Box::pin(ready(())),
+        }
+    }
+}
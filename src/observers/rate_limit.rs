@@ -0,0 +1,157 @@
+//! [`RateLimitObserver`] rejects clients exceeding a request rate, as an interceptor of the hook.
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::dev::ServiceRequest;
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::HttpResponse;
+
+use crate::observer::{Observer, RequestEndData, RequestStartData};
+use crate::util::LruMap;
+
+type KeyFn = Box<dyn Fn(&ServiceRequest) -> Option<String> + Send + Sync>;
+
+/// Tokens of a key, refilled continuously up to the limit.
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Allows `limit` requests per `window` for every key, client IP by default, and answers further requests with
+/// `429 Too Many Requests` and a `Retry-After` header, through [Observer::intercept].
+///
+/// Rates are tracked as token buckets: a key can burst up to `limit` requests, and regains capacity steadily over
+/// `window`. Rejected requests are still observed by all observers, with status 429. Limiter is a cloneable handle,
+/// register the same limiter with hooks of all workers for process wide limits.
+///
+/// ```
+/// use std::rc::Rc;
+/// use std::time::Duration;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::observers::rate_limit::RateLimitObserver;
+///
+/// let rate_limit = RateLimitObserver::new(100, Duration::from_secs(60)).key(|req| {
+///     req.headers()
+///         .get("X-Api-Key")
+///         .and_then(|key| key.to_str().ok())
+///         .map(str::to_string)
+/// });
+/// let request_hook = RequestHook::new().register(Rc::new(rate_limit.clone()));
+/// ```
+#[derive(Clone)]
+pub struct RateLimitObserver {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    limit: u32,
+    window: Duration,
+    key: KeyFn,
+    dry_run: bool,
+    limited: AtomicU64,
+    buckets: Mutex<LruMap<String, Bucket>>,
+}
+
+impl RateLimitObserver {
+    /// Allows `limit` requests per `window` per client IP, with up to 10000 tracked clients. A `limit` of 0
+    /// rejects every request, with a `Retry-After` of `window`.
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                limit,
+                window,
+                key: Box::new(|req| req.peer_addr().map(|addr| addr.ip().to_string())),
+                dry_run: false,
+                limited: AtomicU64::new(0),
+                buckets: Mutex::new(LruMap::new(10_000)),
+            }),
+        }
+    }
+
+    /// Limits requests per key resolved from request, e.g. a tenant or an API key. Requests without a key are
+    /// not limited.
+    pub fn key<F: 'static + Fn(&ServiceRequest) -> Option<String> + Send + Sync>(
+        mut self,
+        key: F,
+    ) -> Self {
+        self.inner_mut().key = Box::new(key);
+        self
+    }
+
+    /// Limits requests per route pattern, or normalized path if no route matched, see
+    /// [route](crate::normalize::route).
+    pub fn per_route(self) -> Self {
+        self.key(|req| Some(crate::normalize::route(req)))
+    }
+
+    /// Maximum number of tracked keys. When exceeded, the key which was idle the longest is forgotten.
+    pub fn max_keys(mut self, max_keys: usize) -> Self {
+        self.inner_mut().buckets = Mutex::new(LruMap::new(max_keys));
+        self
+    }
+
+    /// Only counts requests over the limit in [RateLimitObserver::limited], without rejecting them, e.g. to
+    /// tune limits against real traffic first.
+    pub fn dry_run(mut self) -> Self {
+        self.inner_mut().dry_run = true;
+        self
+    }
+
+    /// Number of requests over the limit, rejected unless in dry run.
+    pub fn limited(&self) -> u64 {
+        self.inner.limited.load(Ordering::Relaxed)
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Arc::get_mut(&mut self.inner).unwrap()
+    }
+
+    /// Takes a token of `key`, or returns how long until one is available.
+    fn acquire(&self, key: String) -> Result<(), Duration> {
+        let inner = &self.inner;
+        if inner.limit == 0 {
+            return Err(inner.window);
+        }
+        let limit = inner.limit as f64;
+        let now = Instant::now();
+        let mut buckets = inner.buckets.lock().unwrap();
+        let bucket = buckets.get_or_insert_with(key, || Bucket {
+            tokens: limit,
+            updated: now,
+        });
+        let refill = now.duration_since(bucket.updated).as_secs_f64() / inner.window.as_secs_f64();
+        bucket.tokens = (bucket.tokens + refill * limit).min(limit);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(inner.window.mul_f64((1.0 - bucket.tokens) / limit))
+    }
+}
+
+impl Observer for RateLimitObserver {
+    fn on_request_started(&self, _data: RequestStartData) {}
+
+    fn on_request_ended(&self, _data: RequestEndData) {}
+
+    fn intercept(&self, req: &ServiceRequest) -> Option<HttpResponse> {
+        let key = (self.inner.key)(req)?;
+        let retry_after = self.acquire(key).err()?;
+        self.inner.limited.fetch_add(1, Ordering::Relaxed);
+        if self.inner.dry_run {
+            return None;
+        }
+        let seconds = retry_after.as_secs() + (retry_after.subsec_nanos() > 0) as u64;
+        Some(
+            HttpResponse::TooManyRequests()
+                .insert_header((RETRY_AFTER, seconds.max(1).to_string()))
+                .finish(),
+        )
+    }
+
+    fn capture_body(&self, _req: &ServiceRequest) -> bool {
+        false
+    }
+}
@@ -0,0 +1,215 @@
+//! [`Recorder`] persists sampled requests, [`Replayer`] sends them to an app under test.
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse};
+use actix_web::test::{self, TestRequest};
+use actix_web::web::Bytes;
+use actix_web::HttpMessage;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::extract::RequestStart;
+use crate::observer::{Observer, RequestEndData, RequestStartData};
+use crate::util::sample_point;
+
+type ErrorFn = Rc<dyn Fn(&io::Error)>;
+
+/// A recorded request, one JSON object per line in a recording file. Body is base64 encoded.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub uri: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    /// Decoded request body.
+    pub fn body(&self) -> Bytes {
+        base64::engine::general_purpose::STANDARD
+            .decode(&self.body)
+            .unwrap_or_default()
+            .into()
+    }
+
+    /// Builds a test request, e.g. for [actix_web::test::call_service].
+    pub fn to_test_request(&self) -> TestRequest {
+        let method = self.method.parse().unwrap_or_default();
+        self.headers.iter().fold(
+            TestRequest::default()
+                .method(method)
+                .uri(&self.uri)
+                .set_payload(self.body()),
+            |req, (name, value)| req.append_header((name.as_str(), value.as_str())),
+        )
+    }
+}
+
+/// Appends a sampled fraction of requests to a recording file, which [Replayer] can send to an app in tests.
+///
+/// Sampling is decided from request id, so it costs nothing for requests which are not recorded.
+/// `Authorization` and `Cookie` headers are not recorded by default.
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::observers::recorder::Recorder;
+///
+/// let recorder = Recorder::new("requests.jsonl").sample_rate(0.01);
+/// let request_hook = RequestHook::new().register(Rc::new(recorder));
+/// ```
+pub struct Recorder {
+    path: PathBuf,
+    sample_rate: f64,
+    skip_headers: HashSet<String>,
+    on_error: Option<ErrorFn>,
+}
+
+impl Recorder {
+    /// Creates a recorder appending all requests to file at `path`.
+    pub fn new<T: Into<PathBuf>>(path: T) -> Self {
+        Self {
+            path: path.into(),
+            sample_rate: 1.0,
+            skip_headers: ["authorization", "cookie"]
+                .iter()
+                .map(|header| header.to_string())
+                .collect(),
+            on_error: None,
+        }
+    }
+
+    /// Fraction of requests to record, between `0.0` and `1.0`.
+    pub fn sample_rate(mut self, sample_rate: f64) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    /// Doesn't record header `name`.
+    pub fn skip_header<T: Into<String>>(mut self, name: T) -> Self {
+        self.skip_headers.insert(name.into().to_lowercase());
+        self
+    }
+
+    /// Records header `name`, e.g. `authorization` to replay authenticated requests.
+    pub fn keep_header<T: Into<String>>(mut self, name: T) -> Self {
+        self.skip_headers.remove(&name.into().to_lowercase());
+        self
+    }
+
+    /// Called when a request could not be written.
+    pub fn on_error<F: 'static + Fn(&io::Error)>(mut self, callback: F) -> Self {
+        self.on_error = Some(Rc::new(callback));
+        self
+    }
+
+    fn is_sampled(&self, request_id: &Uuid) -> bool {
+        sample_point(request_id) < self.sample_rate
+    }
+
+    fn write(&self, request: &RecordedRequest) -> io::Result<()> {
+        let mut line = serde_json::to_vec(request)?;
+        line.push(b'\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(&line)
+    }
+}
+
+impl Observer for Recorder {
+    fn on_request_started(&self, data: RequestStartData) {
+        if !self.is_sampled(&data.request_id) {
+            return;
+        }
+        let request = RecordedRequest {
+            method: data.method.to_string(),
+            uri: data.uri.to_string(),
+            headers: data
+                .req
+                .headers()
+                .iter()
+                .filter(|(name, _)| !self.skip_headers.contains(name.as_str()))
+                .map(|(name, value)| {
+                    let value = String::from_utf8_lossy(value.as_bytes());
+                    (name.to_string(), value.to_string())
+                })
+                .collect(),
+            body: base64::engine::general_purpose::STANDARD.encode(&data.body),
+        };
+        if let Err(err) = self.write(&request) {
+            if let Some(callback) = &self.on_error {
+                callback(&err);
+            }
+        }
+    }
+
+    fn on_request_ended(&self, _data: RequestEndData) {}
+
+    fn capture_body(&self, req: &ServiceRequest) -> bool {
+        req.extensions()
+            .get::<RequestStart>()
+            .is_none_or(|start| self.is_sampled(&start.request_id()))
+    }
+}
+
+/// Replays requests recorded by [Recorder], e.g. for regression tests against real traffic shapes.
+///
+/// ```no_run
+/// use actix_request_hook::observers::recorder::Replayer;
+/// use actix_web::{test, web, App, HttpResponse};
+///
+/// #[actix_web::test]
+/// async fn test_recorded_traffic() {
+///     let app = test::init_service(App::new().default_service(web::to(HttpResponse::Ok))).await;
+///     let replayer = Replayer::open("requests.jsonl").unwrap();
+///     for (request, res) in replayer.replay(&app).await {
+///         assert!(!res.status().is_server_error(), "{} {}", request.method, request.uri);
+///     }
+/// }
+/// ```
+pub struct Replayer {
+    requests: Vec<RecordedRequest>,
+}
+
+impl Replayer {
+    /// Reads a recording file. Lines which are not recorded requests are skipped.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut requests = Vec::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            if let Ok(request) = serde_json::from_str(&line?) {
+                requests.push(request);
+            }
+        }
+        Ok(Self { requests })
+    }
+
+    /// Recorded requests, in recording order.
+    pub fn requests(&self) -> &[RecordedRequest] {
+        &self.requests
+    }
+
+    /// Calls `app` with every recorded request, in recording order.
+    pub async fn replay<'a, S, B>(
+        &'a self,
+        app: &S,
+    ) -> Vec<(&'a RecordedRequest, ServiceResponse<B>)>
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+        B: MessageBody,
+    {
+        let mut responses = Vec::with_capacity(self.requests.len());
+        for request in &self.requests {
+            let res = test::call_service(app, request.to_test_request().to_request()).await;
+            responses.push((request, res));
+        }
+        responses
+    }
+}
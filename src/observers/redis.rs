@@ -0,0 +1,128 @@
+//! [`RedisObserver`] adds request end events to a Redis stream or publishes them to a channel.
+use std::rc::Rc;
+
+use actix_web::dev::ServiceRequest;
+use redis::aio::MultiplexedConnection;
+use redis::{Cmd, RedisError};
+
+use crate::observer::{Observer, RequestEndData, RequestStartData};
+
+type ErrorFn = Rc<dyn Fn(&RedisError)>;
+
+/// Where [RedisObserver] sends events.
+pub(crate) enum Target {
+    Stream { key: String, max_len: Option<usize> },
+    Channel(String),
+}
+
+impl Target {
+    pub(crate) fn command(&self, data: &RequestEndData) -> Cmd {
+        match self {
+            Target::Stream { key, max_len } => {
+                let mut cmd = redis::cmd("XADD");
+                cmd.arg(key);
+                if let Some(max_len) = max_len {
+                    cmd.arg("MAXLEN").arg("~").arg(*max_len);
+                }
+                cmd.arg("*")
+                    .arg("request_id")
+                    .arg(data.request_id.to_string())
+                    .arg("method")
+                    .arg(&*data.method)
+                    .arg("uri")
+                    .arg(&*data.uri)
+                    .arg("status")
+                    .arg(data.status.as_u16())
+                    .arg("elapsed_us")
+                    .arg(data.elapsed.as_micros() as u64);
+                cmd
+            }
+            Target::Channel(channel) => {
+                let mut cmd = redis::cmd("PUBLISH");
+                cmd.arg(channel).arg(serde_json::to_vec(data).unwrap());
+                cmd
+            }
+        }
+    }
+}
+
+/// Sends every ended request to Redis, either as a stream entry (`XADD`) or a JSON message (`PUBLISH`).
+///
+/// Stream entries have `request_id`, `method`, `uri`, `status` and `elapsed_us` fields. Commands are spawned on the
+/// actix runtime, so request latency is unaffected by Redis round trips.
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::observers::redis::RedisObserver;
+///
+/// # async fn setup() {
+/// let client = redis::Client::open("redis://127.0.0.1/").unwrap();
+/// let connection = client.get_multiplexed_tokio_connection().await.unwrap();
+/// let redis = RedisObserver::stream(connection, "requests").max_len(10_000);
+/// let request_hook = RequestHook::new().register(Rc::new(redis));
+/// # }
+/// ```
+pub struct RedisObserver {
+    connection: MultiplexedConnection,
+    target: Target,
+    on_error: Option<ErrorFn>,
+}
+
+impl RedisObserver {
+    /// Creates an observer adding events to stream `key`.
+    pub fn stream<T: Into<String>>(connection: MultiplexedConnection, key: T) -> Self {
+        Self {
+            connection,
+            target: Target::Stream {
+                key: key.into(),
+                max_len: None,
+            },
+            on_error: None,
+        }
+    }
+
+    /// Creates an observer publishing JSON serialized events to `channel`.
+    pub fn channel<T: Into<String>>(connection: MultiplexedConnection, channel: T) -> Self {
+        Self {
+            connection,
+            target: Target::Channel(channel.into()),
+            on_error: None,
+        }
+    }
+
+    /// Caps stream at approximately `max_len` entries (`MAXLEN ~`). Ignored for channels.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        if let Target::Stream { max_len: limit, .. } = &mut self.target {
+            *limit = Some(max_len);
+        }
+        self
+    }
+
+    /// Called when a command fails.
+    pub fn on_error<F: 'static + Fn(&RedisError)>(mut self, callback: F) -> Self {
+        self.on_error = Some(Rc::new(callback));
+        self
+    }
+}
+
+impl Observer for RedisObserver {
+    fn on_request_started(&self, _data: RequestStartData) {}
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        let cmd = self.target.command(&data);
+        let mut connection = self.connection.clone();
+        let on_error = self.on_error.clone();
+        actix_web::rt::spawn(async move {
+            if let Err(err) = cmd.exec_async(&mut connection).await {
+                if let Some(callback) = on_error {
+                    callback(&err);
+                }
+            }
+        });
+    }
+
+    fn capture_body(&self, _req: &ServiceRequest) -> bool {
+        false
+    }
+}
@@ -0,0 +1,228 @@
+//! [`SchemaObserver`] validates JSON request bodies against JSON Schemas of their routes, as an API contract monitor.
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use actix_web::dev::ServiceRequest;
+use actix_web::http::Method;
+use actix_web::{HttpMessage, HttpResponse};
+use jsonschema::JSONSchema;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::extract::RequestStart;
+use crate::observer::{Observer, RequestEndData, RequestStartData};
+
+type ViolationFn = Box<dyn Fn(&SchemaViolation) + Send + Sync>;
+
+/// Request body which broke the JSON Schema of its route, passed to
+/// [SchemaObserver::on_violation].
+///
+/// # Properties
+///
+/// * `request_id` - id of the request, same as in events of observers.
+/// * `method` - http method of the request.
+/// * `route` - matched route pattern of the request, e.g. `/users/{id}`.
+/// * `errors` - what broke the schema, e.g. `/age: "ten" is not of type "integer"`, or that the body isn't JSON at
+///   all. At most [SchemaObserver::max_errors] of them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchemaViolation {
+    pub request_id: Uuid,
+    pub method: Method,
+    pub route: String,
+    pub errors: Vec<String>,
+}
+
+struct RouteSchema {
+    schema: JSONSchema,
+    violations: AtomicU64,
+}
+
+/// Validates captured JSON request bodies against JSON Schemas registered per method and route pattern, turning the
+/// hook into a lightweight API contract monitor. Violations are counted per route and passed to
+/// [SchemaObserver::on_violation], e.g. to log them, and with [SchemaObserver::reject] answered with
+/// `400 Bad Request` listing the errors, through [Observer::intercept_body].
+///
+/// Only bodies of routes with a schema are captured for it. Bodies aren't validated when the hook doesn't capture
+/// them, e.g. of requests the sample rate skipped, with [RequestHook::tee_body](crate::RequestHook::tee_body), a
+/// body capture disabled in [HookSettings](crate::settings::HookSettings), or a `Content-Length` over
+/// [RequestHook::max_body_size](crate::RequestHook::max_body_size). Longer bodies sent without one are truncated
+/// and reported as invalid JSON. Observer is a cloneable handle, register the same observer with hooks of all
+/// workers for process wide counts.
+///
+/// ```
+/// use std::rc::Rc;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::observers::schema::SchemaObserver;
+/// use actix_web::http::Method;
+/// use serde_json::json;
+///
+/// let schema = SchemaObserver::new()
+///     .schema(Method::POST, "/users", &json!({
+///         "type": "object",
+///         "required": ["name"],
+///         "properties": {"name": {"type": "string"}, "age": {"type": "integer"}},
+///     }))
+///     .on_violation(|violation| eprintln!("{} {}: {:?}", violation.method, violation.route, violation.errors));
+/// let request_hook = RequestHook::new().register(Rc::new(schema.clone()));
+/// ```
+#[derive(Clone)]
+pub struct SchemaObserver {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    schemas: HashMap<(Method, String), RouteSchema>,
+    on_violation: Option<ViolationFn>,
+    reject: bool,
+    max_errors: usize,
+    violations: AtomicU64,
+}
+
+impl Default for SchemaObserver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SchemaObserver {
+    /// Observer without schemas, reporting at most 10 errors per violation.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                schemas: HashMap::new(),
+                on_violation: None,
+                reject: false,
+                max_errors: 10,
+                violations: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Validates bodies of `method` requests to `route`, a route pattern such as `/users/{id}`, against `schema`.
+    /// Drafts 4, 6, 7, 2019-09 and 2020-12 are supported, detected by `$schema`, else draft 7. References to remote
+    /// schemas aren't resolved.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `schema` isn't a valid JSON Schema.
+    pub fn schema<T: Into<String>>(mut self, method: Method, route: T, schema: &Value) -> Self {
+        let schema = JSONSchema::compile(schema)
+            .unwrap_or_else(|err| panic!("invalid JSON Schema: {}", err));
+        self.inner_mut().schemas.insert(
+            (method, route.into()),
+            RouteSchema {
+                schema,
+                violations: AtomicU64::new(0),
+            },
+        );
+        self
+    }
+
+    /// Calls `f` with every body breaking its schema.
+    pub fn on_violation<F: 'static + Fn(&SchemaViolation) + Send + Sync>(mut self, f: F) -> Self {
+        self.inner_mut().on_violation = Some(Box::new(f));
+        self
+    }
+
+    /// Answers requests whose body breaks its schema with `400 Bad Request` and a JSON body listing the errors,
+    /// e.g. `{"errors": ["/age: \"ten\" is not of type \"integer\""]}`, instead of calling the handler.
+    pub fn reject(mut self) -> Self {
+        self.inner_mut().reject = true;
+        self
+    }
+
+    /// Maximum number of errors reported per violation.
+    pub fn max_errors(mut self, max_errors: usize) -> Self {
+        self.inner_mut().max_errors = max_errors;
+        self
+    }
+
+    /// Number of bodies which broke their schema.
+    pub fn violations(&self) -> u64 {
+        self.inner.violations.load(Ordering::Relaxed)
+    }
+
+    /// Number of bodies of `method` requests to `route` which broke its schema.
+    pub fn violations_of(&self, method: &Method, route: &str) -> u64 {
+        self.inner
+            .schemas
+            .get(&(method.clone(), route.to_string()))
+            .map_or(0, |schema| schema.violations.load(Ordering::Relaxed))
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Arc::get_mut(&mut self.inner).unwrap()
+    }
+
+    fn schema_of(&self, req: &ServiceRequest) -> Option<(String, &RouteSchema)> {
+        if self.inner.schemas.is_empty() {
+            return None;
+        }
+        let route = crate::normalize::route(req);
+        let schema = self
+            .inner
+            .schemas
+            .get(&(req.method().clone(), route.clone()))?;
+        Some((route, schema))
+    }
+
+    /// Errors of `body` against `schema`, empty if it is valid.
+    fn errors(&self, schema: &JSONSchema, body: &[u8]) -> Vec<String> {
+        let instance = match serde_json::from_slice::<Value>(body) {
+            Ok(instance) => instance,
+            Err(err) => return vec![format!("invalid JSON: {}", err)],
+        };
+        let errors = match schema.validate(&instance) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors
+                .take(self.inner.max_errors)
+                .map(|error| {
+                    let path = error.instance_path.to_string();
+                    if path.is_empty() {
+                        error.to_string()
+                    } else {
+                        format!("{}: {}", path, error)
+                    }
+                })
+                .collect(),
+        };
+        errors
+    }
+}
+
+impl Observer for SchemaObserver {
+    fn on_request_started(&self, _data: RequestStartData) {}
+
+    fn on_request_ended(&self, _data: RequestEndData) {}
+
+    fn intercept_body(&self, req: &ServiceRequest, body: &[u8]) -> Option<HttpResponse> {
+        let (route, schema) = self.schema_of(req)?;
+        let errors = self.errors(&schema.schema, body);
+        if errors.is_empty() {
+            return None;
+        }
+        schema.violations.fetch_add(1, Ordering::Relaxed);
+        self.inner.violations.fetch_add(1, Ordering::Relaxed);
+        let violation = SchemaViolation {
+            request_id: req
+                .extensions()
+                .get::<RequestStart>()
+                .map(RequestStart::request_id)
+                .unwrap_or_default(),
+            method: req.method().clone(),
+            route,
+            errors,
+        };
+        if let Some(on_violation) = &self.inner.on_violation {
+            on_violation(&violation);
+        }
+        self.inner
+            .reject
+            .then(|| HttpResponse::BadRequest().json(json!({ "errors": violation.errors })))
+    }
+
+    fn capture_body(&self, req: &ServiceRequest) -> bool {
+        self.schema_of(req).is_some()
+    }
+}
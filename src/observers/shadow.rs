@@ -0,0 +1,255 @@
+//! [`ShadowObserver`] mirrors a sample of requests to a secondary deployment, e.g. a new version of the service.
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::fmt;
+use std::rc::Rc;
+use std::time::Duration;
+
+use actix_web::dev::ServiceRequest;
+use actix_web::http::header;
+use actix_web::HttpMessage;
+use awc::error::SendRequestError;
+use awc::Client;
+use uuid::Uuid;
+
+use crate::extract::RequestStart;
+use crate::observer::{Observer, RequestEndData, RequestStartData};
+use crate::util::sample_point;
+
+type ErrorFn = Rc<dyn Fn(&ShadowError)>;
+
+/// Header carrying the request id of the original request on mirrored requests, so the shadow deployment can tell
+/// them apart from real traffic.
+pub const X_SHADOW_REQUEST: &str = "x-shadow-request";
+
+/// Headers of a connection rather than a request, never mirrored.
+const HOP_BY_HOP: [&str; 9] = [
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "host",
+];
+
+/// Error reported to [ShadowObserverBuilder::on_error].
+#[derive(Debug)]
+pub struct ShadowError(pub SendRequestError);
+
+impl fmt::Display for ShadowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "shadow request failed: {}", self.0)
+    }
+}
+
+impl std::error::Error for ShadowError {}
+
+/// Counters of a [ShadowObserver].
+#[derive(Default)]
+struct Counts {
+    in_flight: Cell<usize>,
+    sent: Cell<u64>,
+    failed: Cell<u64>,
+    dropped: Cell<u64>,
+}
+
+/// Re-sends a sample of requests, method, path with query, headers and body, to the same path of a secondary base
+/// URL, fire-and-forget, to test a new version of a service against real traffic. Responses of the shadow are
+/// ignored, and clients only ever see responses of the app.
+///
+/// Requests are mirrored when they start, from a spawned task, sampled by request id like
+/// [Recorder](crate::observers::recorder::Recorder). They carry their original request id in an
+/// [X_SHADOW_REQUEST] header, and their body as observers receive it, i.e. decoded and scrubbed if the hook does
+/// so. Requests with a body the hook didn't capture in full aren't mirrored, e.g. one larger than
+/// [RequestHook::max_body_size](crate::RequestHook::max_body_size), by `Content-Length` or cut while reading a
+/// chunked body, or teed with [RequestHook::tee_body](crate::RequestHook::tee_body), and neither are requests beyond
+/// [ShadowObserverBuilder::max_in_flight] while the shadow is slow. Counters are per worker.
+///
+/// Only requests the hook samples reach observers, so [ShadowObserverBuilder::sample_rate] applies on top of the
+/// sample rate of [HookSettings](crate::settings::HookSettings), e.g. `0.5` of a hook sampling `0.1` mirrors 5% of
+/// requests. Keep the hook's rate at `1.0` to mirror exactly the shadow's rate.
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use std::time::Duration;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::observers::shadow::ShadowObserver;
+///
+/// let shadow = ShadowObserver::builder("http://users-v2.internal:8080")
+///     .sample_rate(0.05)
+///     .skip_header("authorization")
+///     .timeout(Duration::from_secs(2))
+///     .build();
+/// let request_hook = RequestHook::new().register(Rc::new(shadow));
+/// ```
+pub struct ShadowObserver {
+    client: Client,
+    base_url: String,
+    sample_rate: f64,
+    skip_headers: HashSet<String>,
+    max_in_flight: usize,
+    on_error: Option<ErrorFn>,
+    counts: Rc<Counts>,
+}
+
+impl ShadowObserver {
+    /// Starts configuring an observer mirroring requests to `base_url`, e.g. `http://users-v2:8080`.
+    pub fn builder<T: Into<String>>(base_url: T) -> ShadowObserverBuilder {
+        ShadowObserverBuilder {
+            base_url: base_url.into(),
+            sample_rate: 1.0,
+            skip_headers: HashSet::new(),
+            timeout: Duration::from_secs(5),
+            max_in_flight: 64,
+            on_error: None,
+        }
+    }
+
+    /// Number of requests the shadow answered, with any status.
+    pub fn sent(&self) -> u64 {
+        self.counts.sent.get()
+    }
+
+    /// Number of mirrored requests which failed, e.g. timed out or couldn't connect.
+    pub fn failed(&self) -> u64 {
+        self.counts.failed.get()
+    }
+
+    /// Number of sampled requests which weren't mirrored, because their body wasn't captured or too many mirrored
+    /// requests were in flight.
+    pub fn dropped(&self) -> u64 {
+        self.counts.dropped.get()
+    }
+
+    fn is_sampled(&self, request_id: &Uuid) -> bool {
+        sample_point(request_id) < self.sample_rate
+    }
+}
+
+/// Configuration for [ShadowObserver].
+pub struct ShadowObserverBuilder {
+    base_url: String,
+    sample_rate: f64,
+    skip_headers: HashSet<String>,
+    timeout: Duration,
+    max_in_flight: usize,
+    on_error: Option<ErrorFn>,
+}
+
+impl ShadowObserverBuilder {
+    /// Fraction of sampled requests to mirror, between `0.0` and `1.0`, all by default. Multiplies with the sample
+    /// rate of the hook, see [ShadowObserver].
+    pub fn sample_rate(mut self, sample_rate: f64) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    /// Doesn't mirror header `name`, e.g. `authorization`. Hop-by-hop headers and `Host` are never mirrored.
+    pub fn skip_header<T: Into<String>>(mut self, name: T) -> Self {
+        self.skip_headers.insert(name.into().to_lowercase());
+        self
+    }
+
+    /// Time a mirrored request may take before it fails, 5s by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Maximum number of mirrored requests in flight per worker, 64 by default. Further requests aren't mirrored
+    /// until some complete, so a slow shadow doesn't pile up tasks.
+    pub fn max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = max_in_flight;
+        self
+    }
+
+    /// Called on every failed mirrored request.
+    pub fn on_error<F: 'static + Fn(&ShadowError)>(mut self, callback: F) -> Self {
+        self.on_error = Some(Rc::new(callback));
+        self
+    }
+
+    /// Creates the HTTP client.
+    pub fn build(self) -> ShadowObserver {
+        ShadowObserver {
+            client: Client::builder().timeout(self.timeout).finish(),
+            base_url: self.base_url.trim_end_matches('/').to_string(),
+            sample_rate: self.sample_rate,
+            skip_headers: self.skip_headers,
+            max_in_flight: self.max_in_flight,
+            on_error: self.on_error,
+            counts: Rc::new(Counts::default()),
+        }
+    }
+}
+
+impl Observer for ShadowObserver {
+    fn on_request_started(&self, data: RequestStartData) {
+        if !self.is_sampled(&data.request_id) {
+            return;
+        }
+        let counts = &self.counts;
+        let headers = data.req.headers();
+        let uncaptured = data.body.is_empty()
+            && (headers.contains_key(header::TRANSFER_ENCODING)
+                || headers
+                    .get(header::CONTENT_LENGTH)
+                    .is_some_and(|length| length != "0"));
+        if data.body_truncated || uncaptured || counts.in_flight.get() >= self.max_in_flight {
+            counts.dropped.set(counts.dropped.get() + 1);
+            return;
+        }
+        let path = data
+            .req
+            .uri()
+            .path_and_query()
+            .map_or("/", |path| path.as_str());
+        let mut request = self
+            .client
+            .request(
+                data.req.method().clone(),
+                format!("{}{}", self.base_url, path),
+            )
+            .insert_header((X_SHADOW_REQUEST, data.request_id.to_string()));
+        for (name, value) in headers {
+            let name = name.as_str();
+            if HOP_BY_HOP.contains(&name)
+                || name == "content-length"
+                // the body was decoded
+                || (name == "content-encoding" && data.body_sizes.is_some())
+                || self.skip_headers.contains(name)
+            {
+                continue;
+            }
+            request = request.append_header((name, value.clone()));
+        }
+        let body = data.body.freeze();
+
+        counts.in_flight.set(counts.in_flight.get() + 1);
+        let counts = counts.clone();
+        let on_error = self.on_error.clone();
+        actix_web::rt::spawn(async move {
+            match request.send_body(body).await {
+                Ok(_) => counts.sent.set(counts.sent.get() + 1),
+                Err(err) => {
+                    counts.failed.set(counts.failed.get() + 1);
+                    if let Some(callback) = on_error {
+                        callback(&ShadowError(err));
+                    }
+                }
+            }
+            counts.in_flight.set(counts.in_flight.get() - 1);
+        });
+    }
+
+    fn on_request_ended(&self, _data: RequestEndData) {}
+
+    fn capture_body(&self, req: &ServiceRequest) -> bool {
+        req.extensions()
+            .get::<RequestStart>()
+            .is_none_or(|start| self.is_sampled(&start.request_id()))
+    }
+}
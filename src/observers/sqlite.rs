@@ -0,0 +1,230 @@
+//! [`SqliteAuditObserver`] writes request audit rows into a SQLite database.
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::ready;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::dev::ServiceRequest;
+use futures_util::future::LocalBoxFuture;
+use rusqlite::{params, Connection};
+use uuid::Uuid;
+
+use crate::observer::{Observer, RequestCancelledData, RequestEndData, RequestStartData};
+use crate::util::sha256_hex;
+
+/// Schema migrations, applied in order. `PRAGMA user_version` holds the number of applied migrations.
+const MIGRATIONS: &[&str] = &["CREATE TABLE request_audit (
+        id TEXT PRIMARY KEY,
+        timestamp INTEGER NOT NULL,
+        method TEXT NOT NULL,
+        path TEXT NOT NULL,
+        user TEXT,
+        status INTEGER NOT NULL,
+        elapsed_us INTEGER NOT NULL,
+        body_sha256 TEXT
+    );
+    CREATE INDEX request_audit_timestamp ON request_audit (timestamp);"];
+
+type UserFn = Box<dyn Fn(&ServiceRequest) -> Option<String>>;
+
+/// Rows queued for the writer thread, beyond which further rows are dropped.
+const QUEUE_CAPACITY: usize = 10_000;
+
+/// Request data captured at start, written together with end data.
+struct Pending {
+    timestamp: i64,
+    path: String,
+    user: Option<String>,
+    body_sha256: Option<String>,
+}
+
+/// Row of `request_audit`, queued for the writer thread.
+struct Row {
+    id: String,
+    timestamp: i64,
+    method: String,
+    path: String,
+    user: Option<String>,
+    status: u16,
+    elapsed_us: i64,
+    body_sha256: Option<String>,
+}
+
+/// Thread writing queued rows, until its sender is dropped.
+struct Writer {
+    sender: SyncSender<Row>,
+    thread: JoinHandle<()>,
+}
+
+/// Writes a row into `request_audit` table for every ended request.
+///
+/// Rows hold request id, start timestamp (unix milliseconds), method, path, user, status, elapsed microseconds and
+/// SHA-256 of request body. Database is switched to WAL mode and schema is migrated on open.
+/// Rows are written on a dedicated thread, every row queued meanwhile in one transaction, so inserts never block
+/// the worker. Up to 10000 rows are queued, further rows are dropped. On [Observer::on_shutdown], the worker waits
+/// for queued rows to be written.
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::observers::sqlite::SqliteAuditObserver;
+///
+/// let audit = SqliteAuditObserver::open("audit.db").unwrap().user_by(|req| {
+///     req.headers()
+///         .get("x-user")
+///         .and_then(|user| user.to_str().ok())
+///         .map(String::from)
+/// });
+/// let request_hook = RequestHook::new().register(Rc::new(audit));
+/// ```
+pub struct SqliteAuditObserver {
+    connection: Arc<Mutex<Connection>>,
+    user_by: Option<UserFn>,
+    pending: RefCell<HashMap<Uuid, Pending>>,
+    writer: RefCell<Option<Writer>>,
+}
+
+impl SqliteAuditObserver {
+    /// Opens or creates database file at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> rusqlite::Result<Self> {
+        Self::new(Connection::open(path)?)
+    }
+
+    /// Uses an already opened connection, e.g. an in-memory database.
+    pub fn new(connection: Connection) -> rusqlite::Result<Self> {
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        migrate(&connection)?;
+        let connection = Arc::new(Mutex::new(connection));
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_CAPACITY);
+        let thread = {
+            let connection = connection.clone();
+            std::thread::Builder::new()
+                .name("request-hook-sqlite".to_string())
+                .spawn(move || write_rows(&connection, receiver))
+                .expect("failed to spawn sqlite thread")
+        };
+        Ok(Self {
+            connection,
+            user_by: None,
+            pending: RefCell::new(HashMap::new()),
+            writer: RefCell::new(Some(Writer { sender, thread })),
+        })
+    }
+
+    /// Resolves user of a request, e.g. from a header or request extensions.
+    pub fn user_by<F: 'static + Fn(&ServiceRequest) -> Option<String>>(
+        mut self,
+        user_by: F,
+    ) -> Self {
+        self.user_by = Some(Box::new(user_by));
+        self
+    }
+
+    /// Underlying connection, locked until the guard is dropped, e.g. for querying audit history. Rows of
+    /// recently ended requests might still be queued.
+    pub fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap()
+    }
+}
+
+/// Writes rows until every sender is dropped, rows received meanwhile in one transaction.
+fn write_rows(connection: &Mutex<Connection>, receiver: Receiver<Row>) {
+    while let Ok(row) = receiver.recv() {
+        let mut connection = connection.lock().unwrap();
+        let _ = insert(
+            &mut connection,
+            std::iter::once(row).chain(receiver.try_iter()),
+        );
+    }
+}
+
+fn insert(connection: &mut Connection, rows: impl Iterator<Item = Row>) -> rusqlite::Result<()> {
+    let transaction = connection.transaction()?;
+    {
+        let mut statement = transaction.prepare_cached(
+            "INSERT INTO request_audit (id, timestamp, method, path, user, status, elapsed_us, body_sha256)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )?;
+        for row in rows {
+            statement.execute(params![
+                row.id,
+                row.timestamp,
+                row.method,
+                row.path,
+                row.user,
+                row.status,
+                row.elapsed_us,
+                row.body_sha256,
+            ])?;
+        }
+    }
+    transaction.commit()
+}
+
+fn migrate(connection: &Connection) -> rusqlite::Result<()> {
+    let version: usize = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        connection.execute_batch(&format!(
+            "BEGIN; {} PRAGMA user_version = {}; COMMIT;",
+            migration,
+            index + 1
+        ))?;
+    }
+    Ok(())
+}
+
+impl Observer for SqliteAuditObserver {
+    fn on_request_started(&self, data: RequestStartData) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as i64;
+        let pending = Pending {
+            timestamp,
+            path: data.req.path().to_string(),
+            user: self.user_by.as_ref().and_then(|user_by| user_by(data.req)),
+            body_sha256: if data.body.is_empty() {
+                None
+            } else {
+                Some(sha256_hex(&data.body))
+            },
+        };
+        self.pending.borrow_mut().insert(data.request_id, pending);
+    }
+
+    /// Write failures are ignored, auditing must never affect the response.
+    fn on_request_ended(&self, data: RequestEndData) {
+        let pending = match self.pending.borrow_mut().remove(&data.request_id) {
+            Some(pending) => pending,
+            None => return,
+        };
+        if let Some(writer) = &*self.writer.borrow() {
+            let _ = writer.sender.try_send(Row {
+                id: data.request_id.to_string(),
+                timestamp: pending.timestamp,
+                method: data.method.to_string(),
+                path: pending.path,
+                user: pending.user,
+                status: data.status.as_u16(),
+                elapsed_us: data.elapsed.as_micros() as i64,
+                body_sha256: pending.body_sha256,
+            });
+        }
+    }
+
+    fn on_request_cancelled(&self, data: RequestCancelledData) {
+        self.pending.borrow_mut().remove(&data.request_id);
+    }
+
+    fn on_shutdown(&self) -> LocalBoxFuture<'static, ()> {
+        if let Some(writer) = self.writer.borrow_mut().take() {
+            drop(writer.sender);
+            writer.thread.join().ok();
+        }
+        Box::pin(ready(()))
+    }
+}
@@ -0,0 +1,255 @@
+//! [`WebhookObserver`] posts batches of request end events to an HTTP endpoint.
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::future::ready;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Duration;
+
+use actix_web::dev::ServiceRequest;
+use futures_util::future::LocalBoxFuture;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Client;
+
+use crate::observer::{Observer, RequestEndData, RequestStartData};
+use crate::observers::batch::Batcher;
+
+type ErrorFn = Rc<dyn Fn(&WebhookError)>;
+
+/// Error reported to [WebhookObserverBuilder::on_error].
+#[derive(Debug)]
+pub enum WebhookError {
+    /// Request failed or endpoint responded with an error status.
+    Http(reqwest::Error),
+    /// Overflow file could not be written or read.
+    Overflow(io::Error),
+}
+
+impl fmt::Display for WebhookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebhookError::Http(err) => write!(f, "webhook request failed: {}", err),
+            WebhookError::Overflow(err) => write!(f, "webhook overflow file failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for WebhookError {}
+
+/// Posts ended requests as a JSON array of [RequestEndData] to an HTTP endpoint.
+///
+/// Failed posts are retried with exponential backoff. Batches that still fail are appended to an overflow file
+/// (one JSON event per line) if configured, and re-sent after the next successful post. On shutdown, events not
+/// posted yet are appended to the overflow file, or posted if there is none.
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use std::time::Duration;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::observers::webhook::WebhookObserver;
+///
+/// let webhook = WebhookObserver::builder("https://audit.example.com/events")
+///     .bearer_token("secret")
+///     .batch(500, Duration::from_secs(5))
+///     .overflow_file("/var/spool/my-service/events.jsonl")
+///     .build()
+///     .unwrap();
+/// let request_hook = RequestHook::new().register(Rc::new(webhook));
+/// ```
+pub struct WebhookObserver {
+    batcher: Batcher<RequestEndData>,
+    sender: Rc<Sender>,
+}
+
+impl WebhookObserver {
+    /// Starts configuring an observer posting to `url`.
+    pub fn builder<T: Into<String>>(url: T) -> WebhookObserverBuilder {
+        WebhookObserverBuilder {
+            url: url.into(),
+            bearer_token: None,
+            max_batch: 100,
+            interval: Duration::from_secs(1),
+            max_retries: 3,
+            overflow_file: None,
+            on_error: None,
+        }
+    }
+}
+
+/// Configuration for [WebhookObserver].
+pub struct WebhookObserverBuilder {
+    url: String,
+    bearer_token: Option<String>,
+    max_batch: usize,
+    interval: Duration,
+    max_retries: u32,
+    overflow_file: Option<PathBuf>,
+    on_error: Option<ErrorFn>,
+}
+
+impl WebhookObserverBuilder {
+    /// Sends `Authorization: Bearer <token>` header.
+    pub fn bearer_token<T: Into<String>>(mut self, token: T) -> Self {
+        self.bearer_token = Some(token.into());
+        self
+    }
+
+    /// Posts a batch when `max_batch` events are collected or `interval` passes, 100 events and 1s by default.
+    pub fn batch(mut self, max_batch: usize, interval: Duration) -> Self {
+        self.max_batch = max_batch;
+        self.interval = interval;
+        self
+    }
+
+    /// Number of retries after a failed post, 3 by default. Backoff starts at 100ms and doubles with each retry.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Appends batches which failed all retries to `path`. Without it, such batches are dropped.
+    pub fn overflow_file<T: Into<PathBuf>>(mut self, path: T) -> Self {
+        self.overflow_file = Some(path.into());
+        self
+    }
+
+    /// Called on every failed post and overflow file error.
+    pub fn on_error<F: 'static + Fn(&WebhookError)>(mut self, callback: F) -> Self {
+        self.on_error = Some(Rc::new(callback));
+        self
+    }
+
+    /// Creates the HTTP client.
+    pub fn build(self) -> Result<WebhookObserver, reqwest::Error> {
+        let sender = Rc::new(Sender {
+            client: Client::builder().build()?,
+            url: self.url,
+            bearer_token: self.bearer_token,
+            max_retries: self.max_retries,
+            overflow_file: self.overflow_file,
+            on_error: self.on_error,
+        });
+        let batch_sender = sender.clone();
+        let batcher = Batcher::new(self.max_batch, self.interval, move |batch| {
+            batch_sender.clone().send(batch)
+        });
+        Ok(WebhookObserver { batcher, sender })
+    }
+}
+
+struct Sender {
+    client: Client,
+    url: String,
+    bearer_token: Option<String>,
+    max_retries: u32,
+    overflow_file: Option<PathBuf>,
+    on_error: Option<ErrorFn>,
+}
+
+impl Sender {
+    fn report(&self, err: WebhookError) {
+        if let Some(callback) = &self.on_error {
+            callback(&err);
+        }
+    }
+
+    async fn send(self: Rc<Self>, batch: Vec<RequestEndData>) {
+        if self.deliver(serde_json::to_vec(&batch).unwrap()).await {
+            self.replay_overflow().await;
+        } else {
+            self.overflow(&batch);
+        }
+    }
+
+    /// Posts body, retrying with exponential backoff. Returns whether the endpoint accepted it.
+    async fn deliver(&self, body: Vec<u8>) -> bool {
+        let mut backoff = Duration::from_millis(100);
+        for attempt in 0..=self.max_retries {
+            let mut request = self
+                .client
+                .post(&self.url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.clone());
+            if let Some(token) = &self.bearer_token {
+                request = request.bearer_auth(token);
+            }
+            match request.send().await.and_then(|res| res.error_for_status()) {
+                Ok(_) => return true,
+                Err(err) => self.report(WebhookError::Http(err)),
+            }
+            if attempt < self.max_retries {
+                actix_web::rt::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+        }
+        false
+    }
+
+    fn overflow(&self, batch: &[RequestEndData]) {
+        let path = match &self.overflow_file {
+            Some(path) => path,
+            None => return,
+        };
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| {
+                batch.iter().try_for_each(|event| {
+                    serde_json::to_writer(&mut file, event)?;
+                    file.write_all(b"\n")
+                })
+            });
+        if let Err(err) = written {
+            self.report(WebhookError::Overflow(err));
+        }
+    }
+
+    /// Re-sends events from the overflow file as a single batch.
+    async fn replay_overflow(&self) {
+        let path = match &self.overflow_file {
+            Some(path) if path.exists() => path,
+            _ => return,
+        };
+        let content = match fs::read_to_string(path).and_then(|content| {
+            fs::remove_file(path)?;
+            Ok(content)
+        }) {
+            Ok(content) => content,
+            Err(err) => return self.report(WebhookError::Overflow(err)),
+        };
+        let batch: Vec<RequestEndData> = content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        if !batch.is_empty() && !self.deliver(serde_json::to_vec(&batch).unwrap()).await {
+            self.overflow(&batch);
+        }
+    }
+}
+
+impl Observer for WebhookObserver {
+    fn on_request_started(&self, _data: RequestStartData) {}
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        self.batcher.push(data);
+    }
+
+    fn capture_body(&self, _req: &ServiceRequest) -> bool {
+        false
+    }
+
+    fn on_shutdown(&self) -> LocalBoxFuture<'static, ()> {
+        if self.sender.overflow_file.is_some() {
+            // spooled synchronously, a post might not finish before the worker stops
+            let batch = self.batcher.take();
+            if !batch.is_empty() {
+                self.sender.overflow(&batch);
+            }
+            Box::pin(ready(()))
+        } else {
+            self.batcher.flush()
+        }
+    }
+}
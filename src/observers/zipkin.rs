@@ -0,0 +1,287 @@
+//! [`ZipkinObserver`] reports a Zipkin v2 SERVER span per request.
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_web::dev::ServiceRequest;
+use futures_util::future::LocalBoxFuture;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Client;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+use crate::observer::{Observer, RequestCancelledData, RequestEndData, RequestStartData};
+use crate::observers::batch::Batcher;
+
+type ErrorFn = Rc<dyn Fn(&reqwest::Error)>;
+
+/// Trace context propagated with B3 headers.
+#[derive(Debug, PartialEq)]
+pub(crate) struct B3Context {
+    pub trace_id: String,
+    pub span_id: String,
+    pub parent_id: Option<String>,
+    /// `None` when upstream deferred the sampling decision.
+    pub sampled: Option<bool>,
+}
+
+impl B3Context {
+    /// Reads single `b3` header, or multiple `X-B3-*` headers.
+    pub(crate) fn from_request(req: &ServiceRequest) -> Option<Self> {
+        let header = |name| {
+            req.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        if let Some(b3) = header("b3") {
+            return Self::parse_single(b3);
+        }
+        let sampled = match header("x-b3-flags") {
+            Some("1") => Some("d"),
+            _ => header("x-b3-sampled"),
+        };
+        Self::new(
+            header("x-b3-traceid")?,
+            header("x-b3-spanid")?,
+            header("x-b3-parentspanid"),
+            sampled,
+        )
+    }
+
+    /// Parses `{trace_id}-{span_id}-{sampled}-{parent_id}`, where last two parts are optional.
+    fn parse_single(b3: &str) -> Option<Self> {
+        let mut parts = b3.trim().split('-');
+        let trace_id = parts.next()?;
+        let span_id = parts.next()?;
+        let sampled = parts.next();
+        let parent_id = parts.next();
+        Self::new(trace_id, span_id, parent_id, sampled)
+    }
+
+    fn new(
+        trace_id: &str,
+        span_id: &str,
+        parent_id: Option<&str>,
+        sampled: Option<&str>,
+    ) -> Option<Self> {
+        let is_id = |value: &str, lengths: &[usize]| {
+            lengths.contains(&value.len()) && value.bytes().all(|byte| byte.is_ascii_hexdigit())
+        };
+        if !is_id(trace_id, &[16, 32]) || !is_id(span_id, &[16]) {
+            return None;
+        }
+        let parent_id = parent_id.filter(|parent_id| is_id(parent_id, &[16]));
+        let sampled = match sampled {
+            Some("1" | "d" | "true") => Some(true),
+            Some("0" | "false") => Some(false),
+            _ => None,
+        };
+        Some(Self {
+            trace_id: trace_id.to_lowercase(),
+            span_id: span_id.to_lowercase(),
+            parent_id: parent_id.map(str::to_lowercase),
+            sampled,
+        })
+    }
+}
+
+/// Span data collected when request started.
+struct Pending {
+    context: Option<B3Context>,
+    timestamp: u128,
+    route: Option<String>,
+    remote_ip: Option<String>,
+}
+
+/// Reports a `SERVER` span per request to Zipkin's `/api/v2/spans`, as v2 JSON in batches.
+///
+/// Incoming B3 headers (single `b3` or `X-B3-*`) are honored: the span joins the caller's trace and span id,
+/// and requests explicitly not sampled upstream are not reported. Requests without B3 headers start a new trace.
+/// Spans are tagged with `http.method`, `http.path`, `http.route`, `http.status_code` and `error` for 5xx responses.
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::observers::zipkin::ZipkinObserver;
+///
+/// let zipkin = ZipkinObserver::builder("http://localhost:9411", "orders")
+///     .build()
+///     .unwrap();
+/// let request_hook = RequestHook::new().register(Rc::new(zipkin));
+/// ```
+pub struct ZipkinObserver {
+    service_name: String,
+    pending: RefCell<HashMap<Uuid, Pending>>,
+    batcher: Batcher<Value>,
+}
+
+impl ZipkinObserver {
+    /// Starts configuring an observer reporting to Zipkin at `endpoint` (e.g. `http://localhost:9411`),
+    /// as local service `service_name`.
+    pub fn builder<E: Into<String>, S: Into<String>>(
+        endpoint: E,
+        service_name: S,
+    ) -> ZipkinObserverBuilder {
+        ZipkinObserverBuilder {
+            endpoint: endpoint.into(),
+            service_name: service_name.into(),
+            max_batch: 100,
+            interval: Duration::from_secs(1),
+            on_error: None,
+        }
+    }
+}
+
+/// Configuration for [ZipkinObserver].
+pub struct ZipkinObserverBuilder {
+    endpoint: String,
+    service_name: String,
+    max_batch: usize,
+    interval: Duration,
+    on_error: Option<ErrorFn>,
+}
+
+impl ZipkinObserverBuilder {
+    /// Reports a batch when `max_batch` spans are collected or `interval` passes, 100 spans and 1s by default.
+    pub fn batch(mut self, max_batch: usize, interval: Duration) -> Self {
+        self.max_batch = max_batch;
+        self.interval = interval;
+        self
+    }
+
+    /// Called when spans could not be reported.
+    pub fn on_error<F: 'static + Fn(&reqwest::Error)>(mut self, callback: F) -> Self {
+        self.on_error = Some(Rc::new(callback));
+        self
+    }
+
+    /// Creates the HTTP client.
+    pub fn build(self) -> Result<ZipkinObserver, reqwest::Error> {
+        let reporter = Rc::new(Reporter {
+            client: Client::builder().build()?,
+            url: format!("{}/api/v2/spans", self.endpoint.trim_end_matches('/')),
+            on_error: self.on_error,
+        });
+        let batcher = Batcher::new(self.max_batch, self.interval, move |batch| {
+            reporter.clone().report(batch)
+        });
+        Ok(ZipkinObserver {
+            service_name: self.service_name,
+            pending: RefCell::new(HashMap::new()),
+            batcher,
+        })
+    }
+}
+
+struct Reporter {
+    client: Client,
+    url: String,
+    on_error: Option<ErrorFn>,
+}
+
+impl Reporter {
+    async fn report(self: Rc<Self>, batch: Vec<Value>) {
+        let request = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Value::from(batch).to_string());
+        if let Err(err) = request.send().await.and_then(|res| res.error_for_status()) {
+            if let Some(callback) = &self.on_error {
+                callback(&err);
+            }
+        }
+    }
+}
+
+impl Observer for ZipkinObserver {
+    fn on_request_started(&self, data: RequestStartData) {
+        let context = B3Context::from_request(data.req);
+        if context.as_ref().and_then(|context| context.sampled) == Some(false) {
+            return;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros();
+        let remote_ip = data.req.peer_addr().map(|addr| addr.ip().to_string());
+        self.pending.borrow_mut().insert(
+            data.request_id,
+            Pending {
+                context,
+                timestamp,
+                route: data.req.match_pattern(),
+                remote_ip,
+            },
+        );
+    }
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        let pending = match self.pending.borrow_mut().remove(&data.request_id) {
+            Some(pending) => pending,
+            None => return,
+        };
+        let name = match &pending.route {
+            Some(route) => format!("{} {}", data.method, route),
+            None => data.method.to_string(),
+        };
+        let mut tags = Map::new();
+        tags.insert("http.method".into(), (&*data.method).into());
+        tags.insert(
+            "http.path".into(),
+            data.uri.split(['?', '#']).next().unwrap_or_default().into(),
+        );
+        if let Some(route) = pending.route {
+            tags.insert("http.route".into(), route.into());
+        }
+        tags.insert(
+            "http.status_code".into(),
+            data.status.as_u16().to_string().into(),
+        );
+        if data.status.is_server_error() {
+            tags.insert("error".into(), data.status.as_u16().to_string().into());
+        }
+
+        let mut span = json!({
+            "name": name.to_lowercase(),
+            "kind": "SERVER",
+            "timestamp": pending.timestamp as u64,
+            "duration": (data.elapsed.as_micros() as u64).max(1),
+            "localEndpoint": { "serviceName": self.service_name },
+            "tags": tags,
+        });
+        let request_id = data.request_id.to_simple().to_string();
+        match pending.context {
+            Some(context) => {
+                span["traceId"] = context.trace_id.into();
+                span["id"] = context.span_id.into();
+                span["shared"] = true.into();
+                if let Some(parent_id) = context.parent_id {
+                    span["parentId"] = parent_id.into();
+                }
+            }
+            None => {
+                span["traceId"] = request_id[..].into();
+                span["id"] = request_id[..16].into();
+            }
+        }
+        if let Some(ip) = pending.remote_ip {
+            let key = if ip.contains(':') { "ipv6" } else { "ipv4" };
+            span["remoteEndpoint"] = json!({ key: ip });
+        }
+        self.batcher.push(span);
+    }
+
+    fn on_request_cancelled(&self, data: RequestCancelledData) {
+        self.pending.borrow_mut().remove(&data.request_id);
+    }
+
+    fn capture_body(&self, _req: &ServiceRequest) -> bool {
+        false
+    }
+
+    fn on_shutdown(&self) -> LocalBoxFuture<'static, ()> {
+        self.batcher.flush()
+    }
+}
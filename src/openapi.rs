@@ -0,0 +1,317 @@
+//! [OpenApi] documents resolving the operation of requests, and [ApiDeviation]s of requests from them.
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use actix_web::dev::ServiceRequest;
+use actix_web::http::header::HeaderName;
+use actix_web::http::{Method, StatusCode};
+use serde_json::Value;
+
+pub use crate::observer::ApiDeviation;
+use crate::util::percent_decode;
+
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Error of loading an [OpenApi] document, e.g. one without `paths`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpenApiError(pub String);
+
+impl fmt::Display for OpenApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid OpenAPI document: {}", self.0)
+    }
+}
+
+impl std::error::Error for OpenApiError {}
+
+#[derive(Clone, Debug)]
+enum Segment {
+    Literal(String),
+    Param,
+}
+
+#[derive(Debug)]
+enum Parameter {
+    Query(Arc<str>),
+    Header(HeaderName),
+}
+
+/// Operation of an [OpenApi] document, a method of a path template such as `GET /users/{id}`.
+#[derive(Debug)]
+pub struct Operation {
+    method: Method,
+    path: String,
+    segments: Vec<Segment>,
+    operation_id: Option<Arc<str>>,
+    required: Vec<Parameter>,
+    responses: Vec<String>,
+}
+
+impl Operation {
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// Path template, e.g. `/users/{id}`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// `operationId`, e.g. `getUser`.
+    pub fn operation_id(&self) -> Option<&Arc<str>> {
+        self.operation_id.as_ref()
+    }
+
+    /// Whether `status` is a documented response, by its code, its range, e.g. `4XX`, or a `default` response.
+    /// Operations without documented responses expect any status.
+    pub fn expects(&self, status: StatusCode) -> bool {
+        let code = status.as_str();
+        self.responses.is_empty()
+            || self.responses.iter().any(|response| {
+                // compared as bytes, keys of the document aren't necessarily ASCII
+                let bytes = response.as_bytes();
+                response == "default"
+                    || response == code
+                    || (bytes.len() == 3
+                        && bytes[1..].eq_ignore_ascii_case(b"XX")
+                        && bytes[0] == code.as_bytes()[0])
+            })
+    }
+
+    /// Required query and header parameters `req` lacks.
+    pub(crate) fn missing_parameters(&self, req: &ServiceRequest) -> Vec<ApiDeviation> {
+        self.required
+            .iter()
+            .filter_map(|parameter| {
+                let name: Arc<str> = match parameter {
+                    Parameter::Query(name) if !has_query_param(req.query_string(), name) => {
+                        name.clone()
+                    }
+                    Parameter::Header(name) if !req.headers().contains_key(name) => {
+                        name.as_str().into()
+                    }
+                    _ => return None,
+                };
+                Some(ApiDeviation::MissingParameter { name })
+            })
+            .collect()
+    }
+
+    /// Number of literal segments of a template matching `segments`, none if it doesn't match.
+    fn literals_matching(&self, segments: &[&str]) -> Option<usize> {
+        if self.segments.len() != segments.len() {
+            return None;
+        }
+        let mut literals = 0;
+        for (template, segment) in self.segments.iter().zip(segments) {
+            match template {
+                Segment::Literal(literal) if literal == segment => literals += 1,
+                Segment::Literal(_) => return None,
+                Segment::Param if segment.is_empty() => return None,
+                Segment::Param => {}
+            }
+        }
+        Some(literals)
+    }
+}
+
+/// OpenAPI 3 or Swagger 2 document of an app, resolving the operation of each request, see
+/// [RequestHook::openapi](crate::RequestHook::openapi). Requests match path templates of the document by their
+/// path, concrete paths winning over templated ones, e.g. `/users/me` over `/users/{id}`. Only JSON documents are
+/// parsed, deserialize YAML documents into a [Value] with a YAML library first and load it with
+/// [OpenApi::from_value].
+///
+/// Required query and header parameters of operations are checked, including local `$ref`s, e.g. to
+/// `#/components/parameters`, cookie parameters aren't. Paths are matched as they are requested, prefixes of
+/// `servers` aren't stripped.
+///
+/// ```
+/// use actix_request_hook::openapi::OpenApi;
+/// use actix_web::http::{Method, StatusCode};
+///
+/// let api = OpenApi::from_json(r#"{
+///     "openapi": "3.0.3",
+///     "paths": {
+///         "/users/{id}": {
+///             "get": {"operationId": "getUser", "responses": {"200": {}, "404": {}}}
+///         }
+///     }
+/// }"#).unwrap();
+/// let operation = api.find(&Method::GET, "/users/7").unwrap();
+/// assert_eq!(operation.operation_id().map(|id| &**id), Some("getUser"));
+/// assert!(!operation.expects(StatusCode::IM_A_TEAPOT));
+/// assert!(api.find(&Method::DELETE, "/users/7").is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct OpenApi {
+    operations: Vec<Arc<Operation>>,
+}
+
+impl FromStr for OpenApi {
+    type Err = OpenApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_json(s)
+    }
+}
+
+impl OpenApi {
+    /// Loads a JSON document.
+    pub fn from_json(json: &str) -> Result<Self, OpenApiError> {
+        let document: Value =
+            serde_json::from_str(json).map_err(|err| OpenApiError(err.to_string()))?;
+        Self::from_value(&document)
+    }
+
+    /// Loads a document deserialized into a [Value].
+    pub fn from_value(document: &Value) -> Result<Self, OpenApiError> {
+        if document
+            .get("openapi")
+            .or_else(|| document.get("swagger"))
+            .is_none()
+        {
+            return Err(OpenApiError("missing `openapi` version".to_string()));
+        }
+        let paths = document
+            .get("paths")
+            .and_then(Value::as_object)
+            .ok_or_else(|| OpenApiError("missing `paths`".to_string()))?;
+        let mut operations = Vec::new();
+        for (path, item) in paths {
+            if !path.starts_with('/') {
+                return Err(OpenApiError(format!(
+                    "path `{}` doesn't start with `/`",
+                    path
+                )));
+            }
+            let segments = path
+                .split('/')
+                .skip(1)
+                .map(|segment| {
+                    if segment.starts_with('{') && segment.ends_with('}') {
+                        Segment::Param
+                    } else {
+                        Segment::Literal(segment.to_string())
+                    }
+                })
+                .collect::<Vec<_>>();
+            let shared = parameters(document, item)?;
+            for method in METHODS.iter() {
+                let Some(operation) = item.get(*method) else {
+                    continue;
+                };
+                let mut required = shared.clone();
+                for (name, location, is_required) in parameters(document, operation)? {
+                    required.retain(|(other, other_location, _)| {
+                        (other, other_location) != (&name, &location)
+                    });
+                    required.push((name, location, is_required));
+                }
+                let required = required
+                    .into_iter()
+                    .filter(|(_, _, is_required)| *is_required)
+                    .filter_map(|(name, location, _)| match location.as_str() {
+                        "query" => Some(Ok(Parameter::Query(name.into()))),
+                        "header" => Some(
+                            HeaderName::from_str(&name)
+                                .map(Parameter::Header)
+                                .map_err(|_| {
+                                    OpenApiError(format!("invalid header parameter `{}`", name))
+                                }),
+                        ),
+                        _ => None,
+                    })
+                    .collect::<Result<_, _>>()?;
+                let responses = operation
+                    .get("responses")
+                    .and_then(Value::as_object)
+                    .map(|responses| responses.keys().cloned().collect())
+                    .unwrap_or_default();
+                operations.push(Arc::new(Operation {
+                    method: Method::from_str(&method.to_ascii_uppercase()).unwrap(),
+                    path: path.clone(),
+                    segments: segments.clone(),
+                    operation_id: operation
+                        .get("operationId")
+                        .and_then(Value::as_str)
+                        .map(Into::into),
+                    required,
+                    responses,
+                }));
+            }
+        }
+        Ok(Self { operations })
+    }
+
+    /// Operation of `method` requests to `path`, none if the document doesn't describe them.
+    pub fn find(&self, method: &Method, path: &str) -> Option<&Operation> {
+        self.position(method, path)
+            .map(|position| &*self.operations[position])
+    }
+
+    /// Operations of the document.
+    pub fn operations(&self) -> impl Iterator<Item = &Operation> {
+        self.operations.iter().map(|operation| &**operation)
+    }
+
+    /// Operation of `req`, see [OpenApi::find].
+    pub(crate) fn operation(&self, req: &ServiceRequest) -> Option<Arc<Operation>> {
+        self.position(req.method(), req.path())
+            .map(|position| self.operations[position].clone())
+    }
+
+    fn position(&self, method: &Method, path: &str) -> Option<usize> {
+        let segments: Vec<_> = path.split('/').skip(1).collect();
+        self.operations
+            .iter()
+            .enumerate()
+            .filter(|(_, operation)| operation.method == method)
+            .filter_map(|(position, operation)| {
+                Some((position, operation.literals_matching(&segments)?))
+            })
+            .max_by_key(|(position, literals)| (*literals, std::cmp::Reverse(*position)))
+            .map(|(position, _)| position)
+    }
+}
+
+/// Name, location and whether it's required, of `parameters` of a path item or operation.
+fn parameters(document: &Value, item: &Value) -> Result<Vec<(String, String, bool)>, OpenApiError> {
+    let Some(parameters) = item.get("parameters").and_then(Value::as_array) else {
+        return Ok(Vec::new());
+    };
+    parameters
+        .iter()
+        .map(|parameter| {
+            let parameter = match parameter.get("$ref").and_then(Value::as_str) {
+                Some(reference) => reference
+                    .strip_prefix('#')
+                    .and_then(|pointer| document.pointer(pointer))
+                    .ok_or_else(|| OpenApiError(format!("unresolved reference `{}`", reference)))?,
+                None => parameter,
+            };
+            let field = |name: &str| {
+                parameter
+                    .get(name)
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+                    .ok_or_else(|| OpenApiError(format!("parameter without `{}`", name)))
+            };
+            let required = parameter
+                .get("required")
+                .and_then(Value::as_bool)
+                .unwrap_or_default();
+            Ok((field("name")?, field("in")?, required))
+        })
+        .collect()
+}
+
+/// Whether `query` has a parameter `name`, e.g. `page` of `page=2&size=10`.
+fn has_query_param(query: &str, name: &str) -> bool {
+    query
+        .split('&')
+        .map(|pair| pair.split_once('=').map_or(pair, |(key, _)| key))
+        .any(|key| key == name || (key.contains('%') && percent_decode(key) == name))
+}
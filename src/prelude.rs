@@ -0,0 +1,80 @@
+//! Re-exports of types most apps need, `use actix_request_hook::prelude::*;` to import them at once. Built-in
+//! observers are included with their cargo features.
+pub use crate::attributes::{AttributeValue, HookAttributes, HookResponseAttributes};
+pub use crate::baggage::Baggage;
+pub use crate::blocking::{Blocking, BlockingObserver, Overflow};
+pub use crate::builder::{BuildError, RequestHookBuilder};
+pub use crate::cache::{CacheRule, CacheSignals};
+pub use crate::classify::ErrorClassifier;
+pub use crate::client::{Cidr, ClientGeo, GeoResolver};
+pub use crate::collector::Collector;
+pub use crate::combinators::ObserverExt;
+pub use crate::consumer::{ConsumerResolver, HeaderConsumer, QueryConsumer};
+pub use crate::current::CurrentRequest;
+pub use crate::event::{RequestEvent, RequestStartEvent};
+pub use crate::fingerprint::Fingerprint;
+pub use crate::observer::{
+    ApiDeviation, BodySizes, CapturedResponse, ChaosInjection, DeadlineExceededData, GrpcCall,
+    Observer, Origin, OrphanedRequestData, Outcome, RequestCancelledData, RequestEndData,
+    RequestStartData, SlowRequestData, UpgradeData,
+};
+pub use crate::render::{BodyEncoding, RenderedBody};
+pub use crate::retry::RetryDetector;
+pub use crate::security::{SecurityDetectors, SecurityFlags};
+pub use crate::settings::{HookSettings, UpstreamSampling};
+pub use crate::shared::SharedRequestHook;
+pub use crate::stats::HookStats;
+pub use crate::RequestHook;
+/// Type of request ids.
+pub use uuid::Uuid;
+
+#[cfg(feature = "geoip")]
+pub use crate::client::MaxMindResolver;
+#[cfg(feature = "awc")]
+pub use crate::extract::CorrelateRequest;
+#[cfg(feature = "actor")]
+pub use crate::observers::actor::ActorObserver;
+#[cfg(feature = "alert")]
+pub use crate::observers::alert::ErrorBurstAlert;
+#[cfg(feature = "amqp")]
+pub use crate::observers::amqp::AmqpObserver;
+#[cfg(feature = "chaos")]
+pub use crate::observers::chaos::ChaosObserver;
+#[cfg(feature = "clickhouse")]
+pub use crate::observers::clickhouse::ClickhouseObserver;
+#[cfg(feature = "fluentd")]
+pub use crate::observers::fluentd::FluentdObserver;
+#[cfg(feature = "har")]
+pub use crate::observers::har::HarRecorder;
+#[cfg(feature = "honeycomb")]
+pub use crate::observers::honeycomb::HoneycombObserver;
+#[cfg(all(unix, feature = "journald"))]
+pub use crate::observers::journald::JournaldObserver;
+#[cfg(feature = "kafka")]
+pub use crate::observers::kafka::KafkaObserver;
+#[cfg(feature = "loki")]
+pub use crate::observers::loki::LokiObserver;
+#[cfg(feature = "nats")]
+pub use crate::observers::nats::NatsObserver;
+#[cfg(feature = "postgres")]
+pub use crate::observers::postgres::PostgresAuditObserver;
+#[cfg(feature = "rate-limit")]
+pub use crate::observers::rate_limit::RateLimitObserver;
+#[cfg(feature = "recorder")]
+pub use crate::observers::recorder::{Recorder, Replayer};
+#[cfg(feature = "redis")]
+pub use crate::observers::redis::RedisObserver;
+#[cfg(feature = "schema")]
+pub use crate::observers::schema::SchemaObserver;
+#[cfg(feature = "shadow")]
+pub use crate::observers::shadow::ShadowObserver;
+#[cfg(feature = "sqlite")]
+pub use crate::observers::sqlite::SqliteAuditObserver;
+#[cfg(feature = "webhook")]
+pub use crate::observers::webhook::WebhookObserver;
+#[cfg(feature = "zipkin")]
+pub use crate::observers::zipkin::ZipkinObserver;
+#[cfg(feature = "openapi")]
+pub use crate::openapi::OpenApi;
+#[cfg(feature = "quota")]
+pub use crate::quota::{BillingWindow, QuotaLedger};
@@ -0,0 +1,110 @@
+//! Protobuf messages for request events, defined in `proto/request_hook.proto` (package `actix_request_hook.v1`).
+//!
+//! Messages are prost-build output checked in to the crate, so building doesn't require `protoc`.
+//! Regenerate them when the schema changes, and only add fields with new numbers.
+//!
+//! ```
+//! use actix_request_hook::proto::{request_event, RequestEnded, RequestEvent};
+//! use prost::Message;
+//!
+//! let event = RequestEvent {
+//!     event: Some(request_event::Event::Ended(RequestEnded {
+//!         status: 200,
+//!         ..Default::default()
+//!     })),
+//! };
+//! let decoded = RequestEvent::decode(event.encode_to_vec().as_slice()).unwrap();
+//! assert_eq!(decoded, event);
+//! ```
+use crate::observer::{RequestEndData, RequestStartData};
+
+/// Request before it is passed to a handler.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RequestStarted {
+    /// Hyphenated UUID, same for start and end event of a request.
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub uri: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub method: ::prost::alloc::string::String,
+    #[prost(bytes = "vec", tag = "4")]
+    pub body: ::prost::alloc::vec::Vec<u8>,
+}
+
+/// Request after it was processed into a response.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RequestEnded {
+    /// Hyphenated UUID, same for start and end event of a request.
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub uri: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub method: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "4")]
+    pub status: u32,
+    #[prost(uint64, tag = "5")]
+    pub elapsed_us: u64,
+    /// Size of response body in bytes, unset if it was streamed or the handler failed.
+    #[prost(uint64, optional, tag = "6")]
+    pub response_size: ::core::option::Option<u64>,
+}
+
+/// Envelope for a stream of both event kinds.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RequestEvent {
+    #[prost(oneof = "request_event::Event", tags = "1, 2")]
+    pub event: ::core::option::Option<request_event::Event>,
+}
+
+/// Nested message and enum types in `RequestEvent`.
+pub mod request_event {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Event {
+        #[prost(message, tag = "1")]
+        Started(super::RequestStarted),
+        #[prost(message, tag = "2")]
+        Ended(super::RequestEnded),
+    }
+}
+
+impl From<&RequestStartData<'_>> for RequestStarted {
+    fn from(data: &RequestStartData<'_>) -> Self {
+        Self {
+            request_id: data.request_id.to_string(),
+            uri: data.uri.to_string(),
+            method: data.method.to_string(),
+            body: data.body.to_vec(),
+        }
+    }
+}
+
+impl From<&RequestEndData> for RequestEnded {
+    fn from(data: &RequestEndData) -> Self {
+        Self {
+            request_id: data.request_id.to_string(),
+            uri: data.uri.to_string(),
+            method: data.method.to_string(),
+            status: data.status.as_u16().into(),
+            elapsed_us: data.elapsed.as_micros() as u64,
+            response_size: data.response_size,
+        }
+    }
+}
+
+impl From<RequestStarted> for RequestEvent {
+    fn from(started: RequestStarted) -> Self {
+        Self {
+            event: Some(request_event::Event::Started(started)),
+        }
+    }
+}
+
+impl From<RequestEnded> for RequestEvent {
+    fn from(ended: RequestEnded) -> Self {
+        Self {
+            event: Some(request_event::Event::Ended(ended)),
+        }
+    }
+}
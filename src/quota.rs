@@ -0,0 +1,347 @@
+//! [QuotaLedger] accounts requests and bytes of consumers per billing window, e.g. for usage based billing.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_web::dev::ServiceRequest;
+use actix_web::http::header::CONTENT_LENGTH;
+
+pub use crate::consumer::OTHER_CONSUMER;
+use crate::util::{civil_from_days, days_from_civil};
+
+type FlushFn = Box<dyn Fn(&[QuotaUsage]) + Send + Sync>;
+
+/// Billing period of a [QuotaLedger], in UTC.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BillingWindow {
+    /// Windows of a fixed length in whole seconds, aligned to the UNIX epoch, e.g. an hour or a day starting at
+    /// midnight UTC.
+    Every(Duration),
+    /// Calendar months, starting on the first at midnight UTC.
+    Month,
+}
+
+impl BillingWindow {
+    /// Start and end of the window containing `time`.
+    pub fn bounds(&self, time: SystemTime) -> (SystemTime, SystemTime) {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let (start, end) = match self {
+            BillingWindow::Every(length) => {
+                let length = length.as_secs().max(1);
+                let start = secs / length * length;
+                (start, start + length)
+            }
+            BillingWindow::Month => {
+                let (year, month, _) = civil_from_days((secs / 86_400) as i64);
+                let (next_year, next_month) = if month == 12 {
+                    (year + 1, 1)
+                } else {
+                    (year, month + 1)
+                };
+                (
+                    days_from_civil(year, month, 1) as u64 * 86_400,
+                    days_from_civil(next_year, next_month, 1) as u64 * 86_400,
+                )
+            }
+        };
+        (
+            UNIX_EPOCH + Duration::from_secs(start),
+            UNIX_EPOCH + Duration::from_secs(end),
+        )
+    }
+}
+
+/// Requests and body bytes of a consumer within a billing window, from [QuotaLedger].
+///
+/// With `serde` feature enabled, `window_start` and `window_end` are (de)serialized as UNIX timestamps in seconds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QuotaUsage {
+    pub consumer: String,
+    #[cfg_attr(feature = "serde", serde(with = "unix_secs"))]
+    pub window_start: SystemTime,
+    #[cfg_attr(feature = "serde", serde(with = "unix_secs"))]
+    pub window_end: SystemTime,
+    pub requests: u64,
+    pub request_bytes: u64,
+    /// Bytes of responses of known size, streamed responses aren't counted.
+    pub response_bytes: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Counters {
+    requests: u64,
+    request_bytes: u64,
+    response_bytes: u64,
+}
+
+struct Window {
+    start: SystemTime,
+    end: SystemTime,
+    consumers: HashMap<String, Counters>,
+}
+
+impl Window {
+    fn new(window: BillingWindow, time: SystemTime) -> Self {
+        let (start, end) = window.bounds(time);
+        Self {
+            start,
+            end,
+            consumers: HashMap::new(),
+        }
+    }
+
+    /// Usage of every consumer, sorted by consumer.
+    fn usage(&self) -> Vec<QuotaUsage> {
+        let mut usage: Vec<_> = self
+            .consumers
+            .iter()
+            .map(|(consumer, counters)| self.usage_of(consumer, counters))
+            .collect();
+        usage.sort_by(|a, b| a.consumer.cmp(&b.consumer));
+        usage
+    }
+
+    fn usage_of(&self, consumer: &str, counters: &Counters) -> QuotaUsage {
+        QuotaUsage {
+            consumer: consumer.to_string(),
+            window_start: self.start,
+            window_end: self.end,
+            requests: counters.requests,
+            request_bytes: counters.request_bytes,
+            response_bytes: counters.response_bytes,
+        }
+    }
+}
+
+/// Current window and closed windows not taken yet, oldest first.
+struct Ledger {
+    current: Window,
+    closed: Vec<Window>,
+}
+
+/// Counts requests, request bytes and response bytes of every consumer per [BillingWindow], so usage based billing
+/// can be driven by the hook: [QuotaLedger::snapshot] is usage of the current window so far, e.g. to show customers
+/// their usage, and [QuotaLedger::take_closed] hands usage of ended windows over once, e.g. to a billing system.
+/// With [QuotaLedger::on_flush], ended windows are flushed to a callback on ticks of the hook instead, see
+/// [RequestHook::tick_interval](crate::RequestHook::tick_interval).
+///
+/// Consumers are resolved by the hook, see [RequestHook::consumer_resolver](crate::RequestHook::consumer_resolver),
+/// requests without one aren't counted. The hook records every request, regardless of sampling, see
+/// [RequestHook::quota_ledger](crate::RequestHook::quota_ledger). Requests count towards the window they end in,
+/// with request bytes from `Content-Length`, or of the body captured for observers. Windows are in wall clock time,
+/// and held in memory: up to `max_closed_windows` ended windows which weren't taken are kept, older ones are
+/// dropped. Ledger is a cloneable handle, pass the same ledger to hooks of all workers for process wide usage.
+///
+/// ```
+/// use std::time::Duration;
+/// use actix_request_hook::consumer::HeaderConsumer;
+/// use actix_request_hook::quota::{BillingWindow, QuotaLedger};
+/// use actix_request_hook::RequestHook;
+///
+/// let ledger = QuotaLedger::new(BillingWindow::Month)
+///     .on_flush(|usage| {
+///         for usage in usage {
+///             println!("{} made {} requests", usage.consumer, usage.requests);
+///         }
+///     });
+/// let request_hook = RequestHook::new()
+///     .consumer_resolver(HeaderConsumer::new("X-Consumer-Id"))
+///     .tick_interval(Duration::from_secs(60))
+///     .quota_ledger(ledger.clone());
+/// ```
+#[derive(Clone)]
+pub struct QuotaLedger {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    window: BillingWindow,
+    max_consumers: usize,
+    max_closed_windows: usize,
+    on_flush: Option<FlushFn>,
+    ledger: Mutex<Ledger>,
+}
+
+impl QuotaLedger {
+    /// Creates a ledger of `window`s, counting up to 10000 consumers per window and keeping up to 12 ended windows.
+    pub fn new(window: BillingWindow) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                window,
+                max_consumers: 10_000,
+                max_closed_windows: 12,
+                on_flush: None,
+                ledger: Mutex::new(Ledger {
+                    current: Window::new(window, SystemTime::now()),
+                    closed: Vec::new(),
+                }),
+            }),
+        }
+    }
+
+    /// Maximum number of distinct consumers counted per window, further consumers are counted under
+    /// [OTHER_CONSUMER].
+    pub fn max_consumers(mut self, max_consumers: usize) -> Self {
+        Arc::get_mut(&mut self.inner).unwrap().max_consumers = max_consumers;
+        self
+    }
+
+    /// Maximum number of ended windows kept until they are taken.
+    pub fn max_closed_windows(mut self, max_closed_windows: usize) -> Self {
+        Arc::get_mut(&mut self.inner).unwrap().max_closed_windows = max_closed_windows;
+        self
+    }
+
+    /// Calls `f` with usage of windows which ended, on ticks of the hook or [QuotaLedger::flush], instead of
+    /// waiting for [QuotaLedger::take_closed].
+    pub fn on_flush<F: 'static + Fn(&[QuotaUsage]) + Send + Sync>(mut self, f: F) -> Self {
+        Arc::get_mut(&mut self.inner).unwrap().on_flush = Some(Box::new(f));
+        self
+    }
+
+    /// Usage of `consumer` in the current window, if it had requests.
+    pub fn usage(&self, consumer: &str) -> Option<QuotaUsage> {
+        let mut ledger = self.inner.ledger.lock().unwrap();
+        self.rotate(&mut ledger, SystemTime::now());
+        let current = &ledger.current;
+        current
+            .consumers
+            .get(consumer)
+            .map(|counters| current.usage_of(consumer, counters))
+    }
+
+    /// Usage of every consumer in the current window so far, sorted by consumer.
+    pub fn snapshot(&self) -> Vec<QuotaUsage> {
+        let mut ledger = self.inner.ledger.lock().unwrap();
+        self.rotate(&mut ledger, SystemTime::now());
+        ledger.current.usage()
+    }
+
+    /// Usage of every consumer in windows which ended since the last call, oldest window first, then by consumer.
+    pub fn take_closed(&self) -> Vec<QuotaUsage> {
+        self.take_closed_at(SystemTime::now())
+    }
+
+    /// Passes usage of windows which ended to the callback of [QuotaLedger::on_flush], if any. The hook calls it on
+    /// every tick, see [RequestHook::tick_interval](crate::RequestHook::tick_interval).
+    pub fn flush(&self) {
+        if let Some(on_flush) = &self.inner.on_flush {
+            let closed = self.take_closed();
+            if !closed.is_empty() {
+                on_flush(&closed);
+            }
+        }
+    }
+
+    /// Usage of a request of `consumer` until it ends, request bytes by `Content-Length`, or the captured `body`
+    /// length. None for requests without a consumer.
+    pub(crate) fn start(
+        &self,
+        consumer: Option<&Arc<str>>,
+        req: &ServiceRequest,
+        body_len: usize,
+    ) -> Option<PendingUsage> {
+        let request_bytes = req
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok()?.parse().ok())
+            .unwrap_or(body_len as u64);
+        Some(PendingUsage {
+            ledger: self.clone(),
+            consumer: consumer?.clone(),
+            request_bytes,
+        })
+    }
+
+    pub(crate) fn take_closed_at(&self, now: SystemTime) -> Vec<QuotaUsage> {
+        let mut ledger = self.inner.ledger.lock().unwrap();
+        self.rotate(&mut ledger, now);
+        ledger
+            .closed
+            .drain(..)
+            .flat_map(|window| window.usage())
+            .collect()
+    }
+
+    pub(crate) fn record_at(
+        &self,
+        consumer: &str,
+        now: SystemTime,
+        request_bytes: u64,
+        response_bytes: u64,
+    ) {
+        let mut ledger = self.inner.ledger.lock().unwrap();
+        self.rotate(&mut ledger, now);
+        let consumers = &mut ledger.current.consumers;
+        let consumer =
+            if consumers.len() >= self.inner.max_consumers && !consumers.contains_key(consumer) {
+                OTHER_CONSUMER
+            } else {
+                consumer
+            };
+        if !consumers.contains_key(consumer) {
+            consumers.insert(consumer.to_string(), Counters::default());
+        }
+        let counters = consumers.get_mut(consumer).unwrap();
+        counters.requests += 1;
+        counters.request_bytes += request_bytes;
+        counters.response_bytes += response_bytes;
+    }
+
+    /// Closes the current window if `now` is past its end.
+    fn rotate(&self, ledger: &mut Ledger, now: SystemTime) {
+        if now < ledger.current.end {
+            return;
+        }
+        let current = std::mem::replace(&mut ledger.current, Window::new(self.inner.window, now));
+        if !current.consumers.is_empty() {
+            ledger.closed.push(current);
+        }
+        let excess = ledger
+            .closed
+            .len()
+            .saturating_sub(self.inner.max_closed_windows);
+        ledger.closed.drain(..excess);
+    }
+}
+
+/// Usage of a started request, recorded in its ledger once the request ended.
+pub(crate) struct PendingUsage {
+    ledger: QuotaLedger,
+    consumer: Arc<str>,
+    request_bytes: u64,
+}
+
+impl PendingUsage {
+    pub(crate) fn end(self, response_bytes: Option<u64>) {
+        self.ledger.record_at(
+            &self.consumer,
+            SystemTime::now(),
+            self.request_bytes,
+            response_bytes.unwrap_or(0),
+        );
+    }
+}
+
+/// (De)serializes [SystemTime] as whole seconds since the UNIX epoch.
+#[cfg(feature = "serde")]
+mod unix_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    pub fn serialize<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        serializer.serialize_u64(secs)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+        u64::deserialize(deserializer).map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+    }
+}
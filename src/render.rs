@@ -0,0 +1,104 @@
+//! [RenderedBody], captured bodies rendered safely as text for sinks, see [BodyEncoding].
+use std::fmt::{self, Write};
+use std::sync::Arc;
+
+use base64::Engine;
+
+use crate::util::BodyLen;
+
+/// How captured bodies are rendered as text, see [RequestHook::render_bodies](crate::RequestHook::render_bodies).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum BodyEncoding {
+    /// UTF-8, with invalid sequences replaced by `U+FFFD`.
+    Utf8Lossy,
+    /// Standard base64 with padding, e.g. for binary bodies.
+    Base64,
+    /// Hex dump of up to `max_bytes` bytes, pairs separated by spaces, e.g. `7b 22 69 64`.
+    Hex { max_bytes: usize },
+    /// UTF-8 if the body is valid UTF-8, else base64.
+    Auto,
+}
+
+/// Captured body rendered as text with a [BodyEncoding], so sink observers don't each render arbitrary bytes
+/// themselves. The bytes stay available alongside it.
+///
+/// # Properties
+///
+/// * `encoding` - encoding of `text`, never [BodyEncoding::Auto], which resolves to UTF-8 or base64.
+/// * `text` - rendered body.
+/// * `truncated` - whether `text` covers only the start of the body, see [BodyEncoding::Hex].
+///
+/// ```
+/// use actix_request_hook::render::{BodyEncoding, RenderedBody};
+///
+/// let rendered = RenderedBody::render(b"\x00\xff", BodyEncoding::Auto);
+/// assert_eq!(rendered.encoding, BodyEncoding::Base64);
+/// assert_eq!(&*rendered.text, "AP8=");
+/// ```
+///
+/// Debug output shows the length of `text` only, the body may hold credentials or personal data.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RenderedBody {
+    pub encoding: BodyEncoding,
+    pub text: Arc<str>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "crate::observer::is_false")
+    )]
+    pub truncated: bool,
+}
+
+impl RenderedBody {
+    /// Renders `body` with `encoding`.
+    pub fn render(body: &[u8], encoding: BodyEncoding) -> Self {
+        let (encoding, text, truncated) = match encoding {
+            BodyEncoding::Auto => match std::str::from_utf8(body) {
+                Ok(text) => (BodyEncoding::Utf8Lossy, text.into(), false),
+                Err(_) => (
+                    BodyEncoding::Base64,
+                    base64::engine::general_purpose::STANDARD.encode(body),
+                    false,
+                ),
+            },
+            BodyEncoding::Utf8Lossy => (encoding, String::from_utf8_lossy(body).into(), false),
+            BodyEncoding::Base64 => (
+                encoding,
+                base64::engine::general_purpose::STANDARD.encode(body),
+                false,
+            ),
+            BodyEncoding::Hex { max_bytes } => {
+                let shown = &body[..body.len().min(max_bytes)];
+                (encoding, hex(shown), shown.len() < body.len())
+            }
+        };
+        Self {
+            encoding,
+            text: text.into(),
+            truncated,
+        }
+    }
+}
+
+impl fmt::Debug for RenderedBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RenderedBody")
+            .field("encoding", &self.encoding)
+            .field("text", &BodyLen(self.text.len()))
+            .field("truncated", &self.truncated)
+            .finish()
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len() * 3);
+    for (i, byte) in bytes.iter().enumerate() {
+        if i > 0 {
+            encoded.push(' ');
+        }
+        let _ = write!(encoded, "{:02x}", byte);
+    }
+    encoded
+}
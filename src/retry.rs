@@ -0,0 +1,92 @@
+//! [RetryDetector], telling retried requests from organic traffic by their idempotency keys.
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use actix_web::dev::ServiceRequest;
+use actix_web::http::header::HeaderName;
+use uuid::Uuid;
+
+/// Name of the header clients send to make retries of a request safe, see the IETF `Idempotency-Key` draft.
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
+/// Remembers idempotency keys of recent requests, so a request repeating the key, method and path of an earlier one
+/// is a retry: its events have `retry_of` set to the id of the first request with that key, and dashboards can tell
+/// client retry storms from organic traffic. Register it with
+/// [RequestHook::detect_retries](crate::RequestHook::detect_retries).
+///
+/// Keys are kept up to a capacity, the oldest are forgotten first. Detector is a cloneable handle, share one across
+/// workers of an `HttpServer`, as retries rarely reach the same worker:
+///
+/// ```
+/// use actix_request_hook::retry::RetryDetector;
+/// use actix_request_hook::RequestHook;
+///
+/// let retries = RetryDetector::new(10_000);
+/// // in the app factory of every worker
+/// let request_hook = RequestHook::new().detect_retries(retries.clone());
+/// ```
+#[derive(Clone)]
+pub struct RetryDetector {
+    header: HeaderName,
+    capacity: usize,
+    keys: Arc<Mutex<RecentKeys>>,
+}
+
+#[derive(Default)]
+struct RecentKeys {
+    first: HashMap<String, Uuid>,
+    order: VecDeque<String>,
+}
+
+impl RetryDetector {
+    /// Creates a detector of requests with an `Idempotency-Key` header, remembering up to `capacity` keys.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            header: HeaderName::from_static(IDEMPOTENCY_KEY),
+            capacity,
+            keys: Arc::new(Mutex::new(RecentKeys::default())),
+        }
+    }
+
+    /// Reads keys from header `name` instead of `Idempotency-Key`, e.g. `x-request-key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` isn't a valid header name.
+    pub fn header(mut self, name: &str) -> Self {
+        self.header = HeaderName::from_str(name).unwrap();
+        self
+    }
+
+    /// Id of the first request with the key, method and path of `req`, remembering `request_id` for its key if
+    /// it's the first one. Requests without a key are never retries.
+    pub(crate) fn retry_of(&self, req: &ServiceRequest, request_id: Uuid) -> Option<Uuid> {
+        if self.capacity == 0 {
+            return None;
+        }
+        let key = req.headers().get(&self.header)?.to_str().ok()?;
+        let key = format!("{} {} {}", req.method(), req.path(), key);
+        let mut keys = self.keys.lock().unwrap();
+        if let Some(first) = keys.first.get(&key) {
+            return Some(*first);
+        }
+        if keys.order.len() >= self.capacity {
+            if let Some(oldest) = keys.order.pop_front() {
+                keys.first.remove(&oldest);
+            }
+        }
+        keys.first.insert(key.clone(), request_id);
+        keys.order.push_back(key);
+        None
+    }
+
+    /// Number of remembered keys.
+    pub fn len(&self) -> usize {
+        self.keys.lock().unwrap().order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,152 @@
+//! [BodyScrubber], redacting fields of JSON bodies before observers see them.
+use actix_web::http::header::HeaderValue;
+use actix_web::web::Bytes;
+use serde_json::Value;
+
+/// Value replacing scrubbed fields by default.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Segment of a field path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    /// `*`, any single key.
+    Any,
+    /// `**`, any number of keys, none included.
+    AnyDepth,
+}
+
+/// Redacts fields of captured JSON request and response bodies before observers see them, so body capture can be
+/// enabled under GDPR or PCI constraints. Register it with [RequestHook::scrub_bodies](crate::RequestHook::scrub_bodies).
+///
+/// Fields are selected by paths from the root of the body, `$.password` or `$.card.number`, where `*` matches any
+/// single key and `**` any number of keys, e.g. `**.ssn` matches `ssn` at any depth. The leading `$.` is optional.
+/// Arrays are transparent, `$.users.email` matches `email` of every element of a `users` array. Keys are compared
+/// exactly.
+///
+/// Bodies which aren't JSON are left as they are, unless their content type is JSON: then they are truncated or
+/// malformed, can't be scrubbed, and are dropped entirely so nothing leaks. Handlers always read the original body.
+///
+/// ```
+/// use actix_request_hook::scrub::BodyScrubber;
+/// use actix_request_hook::RequestHook;
+///
+/// let request_hook = RequestHook::new().scrub_bodies(
+///     BodyScrubber::new()
+///         .field("$.password")
+///         .field("$.card.number")
+///         .field("**.ssn"),
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct BodyScrubber {
+    paths: Vec<Vec<Segment>>,
+    replacement: Value,
+}
+
+impl Default for BodyScrubber {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BodyScrubber {
+    /// Creates a scrubber without fields, replacing them with `"[REDACTED]"` once added.
+    pub fn new() -> Self {
+        Self {
+            paths: Vec::new(),
+            replacement: Value::String(REDACTED.to_string()),
+        }
+    }
+
+    /// Redacts fields at `path`, e.g. `$.card.number` or `**.ssn`.
+    pub fn field<T: AsRef<str>>(mut self, path: T) -> Self {
+        let path = path.as_ref();
+        let path = path
+            .strip_prefix("$.")
+            .or_else(|| path.strip_prefix('$'))
+            .unwrap_or(path);
+        self.paths.push(
+            path.split('.')
+                .filter(|segment| !segment.is_empty())
+                .map(|segment| match segment {
+                    "*" => Segment::Any,
+                    "**" => Segment::AnyDepth,
+                    key => Segment::Key(key.to_string()),
+                })
+                .collect(),
+        );
+        self
+    }
+
+    /// Replaces redacted fields with `replacement` instead of `"[REDACTED]"`, e.g. `null`.
+    pub fn replacement<T: Into<Value>>(mut self, replacement: T) -> Self {
+        self.replacement = replacement.into();
+        self
+    }
+
+    /// Scrubbed `body` of a `content_type`, see [BodyScrubber].
+    pub(crate) fn scrub(&self, content_type: Option<&HeaderValue>, body: Bytes) -> Bytes {
+        if body.is_empty() || self.paths.is_empty() {
+            return body;
+        }
+        match serde_json::from_slice::<Value>(&body) {
+            Ok(mut value) => {
+                let mut path = Vec::new();
+                self.scrub_value(&mut value, &mut path);
+                serde_json::to_vec(&value)
+                    .map(Bytes::from)
+                    .unwrap_or_default()
+            }
+            Err(_) if is_json(content_type) => Bytes::new(),
+            Err(_) => body,
+        }
+    }
+
+    fn scrub_value<'v>(&self, value: &'v mut Value, path: &mut Vec<&'v str>) {
+        match value {
+            Value::Object(object) => {
+                for (key, value) in object.iter_mut() {
+                    path.push(key);
+                    if self.paths.iter().any(|pattern| matches(pattern, path)) {
+                        *value = self.replacement.clone();
+                    } else {
+                        self.scrub_value(value, path);
+                    }
+                    path.pop();
+                }
+            }
+            Value::Array(values) => {
+                for value in values {
+                    self.scrub_value(value, path);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether `path` of keys matches `pattern`.
+fn matches(pattern: &[Segment], path: &[&str]) -> bool {
+    match (pattern.first(), path.first()) {
+        (None, None) => true,
+        (Some(Segment::AnyDepth), _) => {
+            matches(&pattern[1..], path) || (!path.is_empty() && matches(pattern, &path[1..]))
+        }
+        (Some(Segment::Any), Some(_)) => matches(&pattern[1..], &path[1..]),
+        (Some(Segment::Key(key)), Some(segment)) => {
+            key == segment && matches(&pattern[1..], &path[1..])
+        }
+        _ => false,
+    }
+}
+
+fn is_json(content_type: Option<&HeaderValue>) -> bool {
+    content_type
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| {
+            let mime = content_type.split(';').next().unwrap_or_default().trim();
+            mime.eq_ignore_ascii_case("application/json")
+                || mime.to_ascii_lowercase().ends_with("+json")
+        })
+}
@@ -0,0 +1,225 @@
+//! [SecurityDetectors] flagging suspicious requests with [SecurityFlags], pre-classified signals for SIEM observers.
+use std::fmt;
+
+use actix_web::dev::ServiceRequest;
+use actix_web::http::Method;
+use regex::RegexSet;
+
+use crate::util::percent_decode;
+
+/// Suspicious traits of a request found by [SecurityDetectors], in the `security_flags` field of events. Flags are
+/// signals for a SIEM or alerting to correlate, not verdicts, the request is handled as usual.
+///
+/// Displays as names of its flags joined by `|`, e.g. `path_traversal|sql_injection`, and is (de)serialized as its
+/// bits with `serde` feature enabled.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct SecurityFlags(u32);
+
+impl SecurityFlags {
+    /// Path with `../` segments or their encodings, or a null byte, e.g. `/static/..%2f..%2fetc/passwd`.
+    pub const PATH_TRAVERSAL: SecurityFlags = SecurityFlags(1);
+    /// Header longer than [SecurityDetectors::max_header_size].
+    pub const OVERSIZED_HEADER: SecurityFlags = SecurityFlags(1 << 1);
+    /// Query string looking like SQL injection, e.g. `id=1' OR '1'='1`.
+    pub const SQL_INJECTION: SecurityFlags = SecurityFlags(1 << 2);
+    /// Method apps rarely serve, e.g. `TRACE`, `CONNECT` or WebDAV methods, see
+    /// [SecurityDetectors::allowed_methods].
+    pub const ABNORMAL_METHOD: SecurityFlags = SecurityFlags(1 << 3);
+
+    const NAMES: [(SecurityFlags, &'static str); 4] = [
+        (Self::PATH_TRAVERSAL, "path_traversal"),
+        (Self::OVERSIZED_HEADER, "oversized_header"),
+        (Self::SQL_INJECTION, "sql_injection"),
+        (Self::ABNORMAL_METHOD, "abnormal_method"),
+    ];
+
+    /// No flags.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// All flags.
+    pub const fn all() -> Self {
+        Self(0b1111)
+    }
+
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Flags of `bits`, dropping unknown ones.
+    pub const fn from_bits_truncate(bits: u32) -> Self {
+        Self(bits & Self::all().0)
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether all of `other` are set.
+    pub const fn contains(&self, other: SecurityFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: SecurityFlags) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: SecurityFlags) {
+        self.0 &= !other.0;
+    }
+
+    /// Names of set flags, e.g. `sql_injection`.
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        Self::NAMES
+            .iter()
+            .filter(move |(flag, _)| self.contains(*flag))
+            .map(|(_, name)| *name)
+    }
+}
+
+impl std::ops::BitOr for SecurityFlags {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl fmt::Display for SecurityFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, name) in self.names().enumerate() {
+            if i > 0 {
+                f.write_str("|")?;
+            }
+            f.write_str(name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Encodings of `..` and separators of path traversal, matched against the lowercased path and query.
+const TRAVERSAL_PATTERNS: [&str; 10] = [
+    "../", "..\\", "/..", "%2e%2e", "%2e.", ".%2e", "%252e", "%c0%ae", "%00", "\0",
+];
+
+/// SQL injection fragments, matched against the percent decoded, lowercased query string.
+const SQL_INJECTION_PATTERNS: [&str; 8] = [
+    r"'\s*(or|and)\s+'?[\w-]+'?\s*(=|<|>|like)",
+    r"\bunion\b.+\bselect\b",
+    r";\s*(drop|delete|insert|update|alter|truncate|exec)\b",
+    r"('|\d)\s*(--|#)",
+    r"/\*.*\*/",
+    r"\b(sleep|benchmark|pg_sleep)\s*\(",
+    r"\bwaitfor\s+delay\b",
+    r"\b(or|and)\s+\d+\s*=\s*\d+",
+];
+
+/// Detectors of suspicious requests, flagging them with [SecurityFlags]. Enable them with
+/// [RequestHook::security_detectors](crate::RequestHook::security_detectors).
+///
+/// All detectors are on by default, with headers up to 8 KiB and methods `GET`, `HEAD`, `POST`, `PUT`, `PATCH`,
+/// `DELETE` and `OPTIONS` allowed.
+///
+/// ```
+/// use actix_request_hook::security::{SecurityDetectors, SecurityFlags};
+/// use actix_request_hook::RequestHook;
+///
+/// let request_hook = RequestHook::new().security_detectors(
+///     SecurityDetectors::new()
+///         .without(SecurityFlags::ABNORMAL_METHOD)
+///         .max_header_size(16 * 1024),
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct SecurityDetectors {
+    enabled: SecurityFlags,
+    max_header_size: usize,
+    allowed_methods: Vec<Method>,
+    sql_injection: RegexSet,
+}
+
+impl Default for SecurityDetectors {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SecurityDetectors {
+    pub fn new() -> Self {
+        Self {
+            enabled: SecurityFlags::all(),
+            max_header_size: 8 * 1024,
+            allowed_methods: vec![
+                Method::GET,
+                Method::HEAD,
+                Method::POST,
+                Method::PUT,
+                Method::PATCH,
+                Method::DELETE,
+                Method::OPTIONS,
+            ],
+            sql_injection: RegexSet::new(SQL_INJECTION_PATTERNS).unwrap(),
+        }
+    }
+
+    /// Turns off detectors of `flags`.
+    pub fn without(mut self, flags: SecurityFlags) -> Self {
+        self.enabled.remove(flags);
+        self
+    }
+
+    /// Flags requests with a header, name and value, longer than `bytes`.
+    pub fn max_header_size(mut self, bytes: usize) -> Self {
+        self.max_header_size = bytes;
+        self
+    }
+
+    /// Methods which aren't flagged as abnormal, replacing the defaults.
+    pub fn allowed_methods<I: IntoIterator<Item = Method>>(mut self, methods: I) -> Self {
+        self.allowed_methods = methods.into_iter().collect();
+        self
+    }
+
+    /// Flags of `req`.
+    pub(crate) fn detect(&self, req: &ServiceRequest) -> SecurityFlags {
+        let mut flags = SecurityFlags::empty();
+        let uri = req.uri();
+        if self.enabled.contains(SecurityFlags::PATH_TRAVERSAL) {
+            let path_and_query = uri
+                .path_and_query()
+                .map_or(uri.path(), |path_and_query| path_and_query.as_str())
+                .to_ascii_lowercase();
+            if TRAVERSAL_PATTERNS
+                .iter()
+                .any(|pattern| path_and_query.contains(pattern))
+            {
+                flags.insert(SecurityFlags::PATH_TRAVERSAL);
+            }
+        }
+        if self.enabled.contains(SecurityFlags::OVERSIZED_HEADER)
+            && req
+                .headers()
+                .iter()
+                .any(|(name, value)| name.as_str().len() + value.len() > self.max_header_size)
+        {
+            flags.insert(SecurityFlags::OVERSIZED_HEADER);
+        }
+        if self.enabled.contains(SecurityFlags::SQL_INJECTION) {
+            if let Some(query) = uri.query() {
+                let query = percent_decode(&query.replace('+', " ")).to_lowercase();
+                if self.sql_injection.is_match(&query) {
+                    flags.insert(SecurityFlags::SQL_INJECTION);
+                }
+            }
+        }
+        if self.enabled.contains(SecurityFlags::ABNORMAL_METHOD)
+            && !self.allowed_methods.contains(req.method())
+        {
+            flags.insert(SecurityFlags::ABNORMAL_METHOD);
+        }
+        flags
+    }
+}
@@ -0,0 +1,102 @@
+//! [HookSettings], runtime switches of a [RequestHook](crate::RequestHook).
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+/// Cloneable handle to settings which can be changed while the server is running, from
+/// [RequestHook::settings](crate::RequestHook::settings).
+///
+/// Clones share the same settings, so a handle can flip sampling of all workers at once, e.g. from an admin handler.
+///
+/// ```
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::settings::HookSettings;
+///
+/// let settings = HookSettings::default();
+/// let request_hook = RequestHook::new().with_settings(settings.clone());
+/// // later, under load
+/// settings.set_sample_rate(0.1);
+/// settings.set_capture_body(false);
+/// ```
+#[derive(Clone, Debug)]
+pub struct HookSettings {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    /// Bits of an `f64`.
+    sample_rate: AtomicU64,
+    capture_body: AtomicBool,
+}
+
+impl Default for HookSettings {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                sample_rate: AtomicU64::new(1f64.to_bits()),
+                capture_body: AtomicBool::new(true),
+            }),
+        }
+    }
+}
+
+impl HookSettings {
+    /// Share of requests passed to observers and event subscribers, between 0 and 1.
+    pub fn sample_rate(&self) -> f64 {
+        f64::from_bits(self.inner.sample_rate.load(Ordering::Relaxed))
+    }
+
+    /// Sets share of requests passed to observers and event subscribers, clamped between 0 and 1, all by default.
+    /// Sampling is decided per request id, so start and end of a request are both observed or both skipped.
+    /// [HookStats](crate::stats::HookStats) still count every request.
+    pub fn set_sample_rate(&self, sample_rate: f64) {
+        let sample_rate = if sample_rate.is_nan() {
+            0.0
+        } else {
+            sample_rate.clamp(0.0, 1.0)
+        };
+        self.inner
+            .sample_rate
+            .store(sample_rate.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Whether request bodies are buffered and passed to observers.
+    pub fn capture_body(&self) -> bool {
+        self.inner.capture_body.load(Ordering::Relaxed)
+    }
+
+    /// Enables or disables buffering request bodies, enabled by default. Disabled, the payload passes through to
+    /// the handler untouched and observers receive an empty body.
+    pub fn set_capture_body(&self, capture_body: bool) {
+        self.inner
+            .capture_body
+            .store(capture_body, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_sampled(&self, request_id: Uuid) -> bool {
+        let sample_rate = self.sample_rate();
+        if sample_rate >= 1.0 {
+            return true;
+        }
+        // lowest 62 bits of a v4 uuid are random, above them are variant bits
+        let bits = request_id.as_u128() as u64 & ((1 << 62) - 1);
+        (bits as f64) < sample_rate * (1u64 << 62) as f64
+    }
+}
+
+/// How the hook's sampling treats the decision of the caller, passed in the flags of a W3C `traceparent` header or
+/// in B3 headers, see [RequestHook::upstream_sampling](crate::RequestHook::upstream_sampling).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UpstreamSampling {
+    /// Samples by the sample rate only.
+    #[default]
+    Ignore,
+    /// Samples requests the caller sampled and skips those it didn't, so request logs match traces across the call
+    /// chain. Requests without a decision are sampled by the sample rate.
+    Respect,
+    /// Same as [UpstreamSampling::Respect], except that skipped requests answered with a server error still reach
+    /// observers, once they ended. Their start is delivered right before their end, without a body.
+    AlwaysOnError,
+}
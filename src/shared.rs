@@ -0,0 +1,331 @@
+//! [SharedRequestHook], one hook for all workers of an `HttpServer`.
+use std::future::ready;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{Error, HttpResponse};
+use futures_util::future::{join_all, LocalBoxFuture};
+
+use crate::observer::{
+    DeadlineExceededData, Observer, RequestCancelledData, RequestEndData, RequestStartData,
+    UpgradeData,
+};
+use crate::settings::HookSettings;
+use crate::stats::HookStats;
+use crate::{RequestHook, RequestHookMiddleware};
+
+type ConfigureFn = Arc<dyn Fn(RequestHook) -> RequestHook + Send + Sync>;
+
+/// Hook created once, e.g. in `main`, and cloned into the app factory of `HttpServer::new`, which runs once per
+/// worker. Observers registered with [SharedRequestHook::register] are shared by every worker, so state-bearing
+/// observers, e.g. counters or limiters, see all traffic instead of a worker's share. They must be `Send + Sync`.
+///
+/// Every worker still runs a [RequestHook] of its own, set up by [SharedRequestHook::configure], which may register
+/// observers bound to a worker's thread too. [HookStats], [HookSettings] and, with `events` feature, the event
+/// channel are shared by all workers. Shared observers are ticked and shut down once for all workers, see
+/// [SharedRequestHook::tick_interval] and [SharedRequestHook::shutdown].
+///
+/// ```no_run
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use std::sync::Arc;
+/// use actix_web::{web, App, HttpResponse, HttpServer};
+/// use actix_request_hook::observer::{Observer, RequestEndData, RequestStartData};
+/// use actix_request_hook::shared::SharedRequestHook;
+///
+/// #[derive(Default)]
+/// struct ServerErrors(AtomicU64);
+///
+/// impl Observer for ServerErrors {
+///     fn on_request_started(&self, _data: RequestStartData) {}
+///
+///     fn on_request_ended(&self, data: RequestEndData) {
+///         if data.status.is_server_error() {
+///             self.0.fetch_add(1, Ordering::Relaxed);
+///         }
+///     }
+/// }
+///
+/// #[actix_web::main]
+/// async fn main() -> std::io::Result<()> {
+///     let errors = Arc::new(ServerErrors::default());
+///     let request_hook = SharedRequestHook::new()
+///         .configure(|hook| hook.exclude("/health"))
+///         .register(errors.clone());
+///     let server = HttpServer::new({
+///         let request_hook = request_hook.clone();
+///         move || {
+///             App::new()
+///                 .wrap(request_hook.clone())
+///                 .route("/", web::get().to(HttpResponse::Ok))
+///         }
+///     })
+///     .bind("127.0.0.1:8080")?
+///     .run()
+///     .await;
+///     request_hook.shutdown().await;
+///     server
+/// }
+/// ```
+#[derive(Clone)]
+pub struct SharedRequestHook {
+    configure: ConfigureFn,
+    observers: Vec<Arc<SharedEntry>>,
+    tick_interval: Option<Duration>,
+    ticking: Arc<AtomicBool>,
+    stats: HookStats,
+    settings: HookSettings,
+    #[cfg(feature = "events")]
+    events: tokio::sync::broadcast::Sender<crate::event::RequestEvent>,
+}
+
+impl Default for SharedRequestHook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SharedRequestHook {
+    pub fn new() -> Self {
+        Self {
+            configure: Arc::new(|hook| hook),
+            observers: Vec::new(),
+            tick_interval: None,
+            ticking: Arc::new(AtomicBool::new(false)),
+            stats: HookStats::default(),
+            settings: HookSettings::default(),
+            #[cfg(feature = "events")]
+            events: tokio::sync::broadcast::channel(1024).0,
+        }
+    }
+
+    /// Sets up the [RequestHook] of every worker with `configure`, e.g. exclusions, limits and observers which
+    /// aren't `Send`, registered per worker.
+    pub fn configure<F: 'static + Fn(RequestHook) -> RequestHook + Send + Sync>(
+        mut self,
+        configure: F,
+    ) -> Self {
+        self.configure = Arc::new(configure);
+        self
+    }
+
+    /// Registers an [Observer] shared by all workers, notified after observers registered per worker.
+    pub fn register<T: 'static + Observer + Send + Sync>(mut self, observer: Arc<T>) -> Self {
+        self.observers.push(Arc::new(SharedEntry {
+            observer,
+            workers: AtomicUsize::new(0),
+            shut_down: AtomicBool::new(false),
+        }));
+        self
+    }
+
+    /// Registers observers shared by all workers, see [SharedRequestHook::register].
+    pub fn register_all<I: IntoIterator<Item = Arc<dyn Observer + Send + Sync>>>(
+        mut self,
+        observers: I,
+    ) -> Self {
+        self.observers.extend(observers.into_iter().map(|observer| {
+            Arc::new(SharedEntry {
+                observer,
+                workers: AtomicUsize::new(0),
+                shut_down: AtomicBool::new(false),
+            })
+        }));
+        self
+    }
+
+    /// Calls [Observer::on_tick] of shared observers each `interval`, once for all workers, from a task started by
+    /// the first worker's middleware. Workers tick observers registered per worker on their own, see
+    /// [RequestHook::tick_interval].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn tick_interval(mut self, interval: Duration) -> Self {
+        assert!(!interval.is_zero(), "tick interval must be greater than 0");
+        self.tick_interval = Some(interval);
+        self
+    }
+
+    /// Fires [Observer::on_shutdown] of shared observers and waits until they complete, e.g. after `server.await`.
+    /// Without it, they are shut down once the hook of the last worker is dropped, which only completes work
+    /// spawned from there if it finishes before that worker's runtime stops. Observers are shut down only once.
+    pub async fn shutdown(&self) {
+        join_all(self.observers.iter().map(|entry| entry.shutdown())).await;
+    }
+
+    /// Shares `stats` by the hooks of all workers, instead of counters created with this hook.
+    pub fn with_stats(mut self, stats: HookStats) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Shares `settings` by the hooks of all workers, instead of settings created with this hook.
+    pub fn with_settings(mut self, settings: HookSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Same as [RequestHook::event_sender], for events of all workers.
+    #[cfg(feature = "events")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "events")))]
+    pub fn event_sender(
+        mut self,
+        sender: tokio::sync::broadcast::Sender<crate::event::RequestEvent>,
+    ) -> Self {
+        self.events = sender;
+        self
+    }
+
+    /// Counters of requests of all workers.
+    pub fn stats(&self) -> HookStats {
+        self.stats.clone()
+    }
+
+    /// Settings of all workers.
+    pub fn settings(&self) -> HookSettings {
+        self.settings.clone()
+    }
+
+    /// Same as [RequestHook::event_receiver], for events of all workers.
+    #[cfg(feature = "events")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "events")))]
+    pub fn event_receiver(&self) -> tokio::sync::broadcast::Receiver<crate::event::RequestEvent> {
+        self.events.subscribe()
+    }
+
+    /// Builds the [RequestHook] of a worker.
+    pub fn build(&self) -> RequestHook {
+        let hook = RequestHook::new()
+            .with_stats(self.stats.clone())
+            .with_settings(self.settings.clone());
+        #[cfg(feature = "events")]
+        let hook = hook.event_sender(self.events.clone());
+        let hook = (self.configure)(hook);
+        self.observers.iter().fold(hook, |hook, entry| {
+            entry.workers.fetch_add(1, Ordering::SeqCst);
+            hook.register(Rc::new(SharedObserver(entry.clone())))
+        })
+    }
+}
+
+impl<S: 'static, B> Transform<S, ServiceRequest> for SharedRequestHook
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: 'static + MessageBody,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RequestHookMiddleware<S>;
+    type InitError = ();
+    type Future = <RequestHook as Transform<S, ServiceRequest>>::Future;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        if let Some(period) = self.tick_interval {
+            if !self.ticking.swap(true, Ordering::SeqCst) {
+                actix_web::rt::spawn(tick(self.observers.clone(), period));
+            }
+        }
+        self.build().new_transform(service)
+    }
+}
+
+/// Ticks shared observers until they are shut down.
+async fn tick(observers: Vec<Arc<SharedEntry>>, period: Duration) {
+    let mut interval =
+        actix_web::rt::time::interval_at(actix_web::rt::time::Instant::now() + period, period);
+    let mut last = Instant::now();
+    loop {
+        interval.tick().await;
+        let elapsed = last.elapsed();
+        last = Instant::now();
+        let mut running = false;
+        for entry in &observers {
+            if !entry.shut_down.load(Ordering::SeqCst) {
+                running = true;
+                entry.observer.on_tick(elapsed);
+            }
+        }
+        if !running {
+            break;
+        }
+    }
+}
+
+/// Observer registered with [SharedRequestHook::register], with the number of worker hooks notifying it.
+struct SharedEntry {
+    observer: Arc<dyn Observer + Send + Sync>,
+    workers: AtomicUsize,
+    shut_down: AtomicBool,
+}
+
+impl SharedEntry {
+    /// Fires [Observer::on_shutdown] unless the observer was shut down already.
+    fn shutdown(&self) -> LocalBoxFuture<'static, ()> {
+        if self.shut_down.swap(true, Ordering::SeqCst) {
+            Box::pin(ready(()))
+        } else {
+            self.observer.on_shutdown()
+        }
+    }
+}
+
+/// Observer shared by hooks of all workers. Ticks aren't forwarded, shutdown only by the hook of the last worker.
+struct SharedObserver(Arc<SharedEntry>);
+
+impl Observer for SharedObserver {
+    fn on_request_started(&self, data: RequestStartData) {
+        self.0.observer.on_request_started(data)
+    }
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        self.0.observer.on_request_ended(data)
+    }
+
+    fn on_request_cancelled(&self, data: RequestCancelledData) {
+        self.0.observer.on_request_cancelled(data)
+    }
+
+    fn on_deadline_exceeded(&self, data: DeadlineExceededData) {
+        self.0.observer.on_deadline_exceeded(data)
+    }
+
+    fn on_upgrade(&self, data: UpgradeData) {
+        self.0.observer.on_upgrade(data)
+    }
+
+    fn capture_response(&self, req: &ServiceRequest) -> bool {
+        self.0.observer.capture_response(req)
+    }
+
+    fn capture_body(&self, req: &ServiceRequest) -> bool {
+        self.0.observer.capture_body(req)
+    }
+
+    fn force_sample(&self, req: &ServiceRequest) -> bool {
+        self.0.observer.force_sample(req)
+    }
+
+    fn intercept(&self, req: &ServiceRequest) -> Option<HttpResponse> {
+        self.0.observer.intercept(req)
+    }
+
+    fn intercept_body(&self, req: &ServiceRequest, body: &[u8]) -> Option<HttpResponse> {
+        self.0.observer.intercept_body(req, body)
+    }
+
+    fn on_shutdown(&self) -> LocalBoxFuture<'static, ()> {
+        if self.0.workers.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.shutdown()
+        } else {
+            Box::pin(ready(()))
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.0.observer.name()
+    }
+}
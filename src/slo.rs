@@ -0,0 +1,297 @@
+//! [SloMonitor] computes error budget burn rates of service level objectives.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::dev::ServiceRequest;
+use uuid::Uuid;
+
+pub use crate::budget::{Indicator, Objective};
+use crate::clock::{Clock, MonotonicClock};
+use crate::observer::{Observer, RequestCancelledData, RequestEndData, RequestStartData};
+use crate::util::path_matches;
+
+type BurnFn = Box<dyn Fn(&BurnAlert) + Send + Sync>;
+
+/// Burn rate alert of multiwindow alerting: error budget is burning faster than `threshold` over both
+/// a long and a short window.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BurnRule {
+    pub severity: Severity,
+    pub threshold: f64,
+    pub long_window: Duration,
+    pub short_window: Duration,
+}
+
+/// Severity of a [BurnRule].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// Budget is exhausted within days if burning continues, e.g. page someone.
+    Fast,
+    /// Budget is exhausted within the objective period if burning continues, e.g. open a ticket.
+    Slow,
+}
+
+/// Passed to [SloMonitor::on_burn] when a burn rule starts firing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BurnAlert {
+    pub objective: String,
+    pub severity: Severity,
+    pub threshold: f64,
+    pub long_window_burn_rate: f64,
+    pub short_window_burn_rate: f64,
+}
+
+/// Requests ended within one minute.
+#[derive(Clone, Copy, Debug, Default)]
+struct Bucket {
+    minute: u64,
+    total: u64,
+    bad: u64,
+}
+
+struct ObjectiveState {
+    objective: Objective,
+    buckets: Vec<Bucket>,
+    /// Whether each burn rule is firing.
+    firing: Vec<bool>,
+}
+
+/// Tracks good and bad requests of [Objective]s and calls back when error budget burns too fast.
+///
+/// Burn rate is the ratio of bad requests relative to the error budget, `1 - target`: a burn rate of 1 spends
+/// exactly the budget over the objective period. Defaults follow multiwindow alerting of the SRE workbook:
+/// fast burn above 14.4 over 1 hour and 5 minutes, slow burn above 6 over 6 hours and 30 minutes. Rules alert
+/// once when they start firing, and again only after they stopped.
+///
+/// Objective paths are matched against routes, or paths of requests without a matched route, normalized if
+/// [RequestHook::normalize_paths](crate::RequestHook::normalize_paths) is on, same as error budgets of
+/// [HookStats](crate::stats::HookStats), so both count the same requests of an objective.
+///
+/// Monitor is an [Observer] and a cloneable handle, register the same monitor with hooks of all workers.
+///
+/// ```
+/// use std::rc::Rc;
+/// use std::time::Duration;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::slo::{Objective, SloMonitor};
+///
+/// let slo = SloMonitor::new()
+///     .objective(Objective::latency("/api/**", Duration::from_millis(300), 0.999).named("api-latency"))
+///     .objective(Objective::availability("/**", 0.9995))
+///     .on_burn(|alert| eprintln!("{} burning {:?}: {}", alert.objective, alert.severity, alert.long_window_burn_rate));
+/// let request_hook = RequestHook::new().register(Rc::new(slo.clone()));
+/// ```
+#[derive(Clone)]
+pub struct SloMonitor {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    clock: Arc<dyn Clock>,
+    started: Instant,
+    rules: Vec<BurnRule>,
+    on_burn: Option<BurnFn>,
+    objectives: Mutex<Vec<ObjectiveState>>,
+    /// Route of started requests.
+    pending: Mutex<HashMap<Uuid, String>>,
+}
+
+impl Default for SloMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SloMonitor {
+    /// Creates a monitor with default fast and slow burn rules and no objectives.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                clock: Arc::new(MonotonicClock),
+                started: Instant::now(),
+                rules: vec![
+                    BurnRule {
+                        severity: Severity::Fast,
+                        threshold: 14.4,
+                        long_window: Duration::from_secs(3600),
+                        short_window: Duration::from_secs(300),
+                    },
+                    BurnRule {
+                        severity: Severity::Slow,
+                        threshold: 6.0,
+                        long_window: Duration::from_secs(6 * 3600),
+                        short_window: Duration::from_secs(1800),
+                    },
+                ],
+                on_burn: None,
+                objectives: Mutex::new(Vec::new()),
+                pending: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Adds an objective.
+    pub fn objective(mut self, objective: Objective) -> Self {
+        self.inner_mut()
+            .objectives
+            .get_mut()
+            .unwrap()
+            .push(ObjectiveState {
+                objective,
+                buckets: Vec::new(),
+                firing: Vec::new(),
+            });
+        self
+    }
+
+    /// Replaces default burn rules.
+    pub fn rules(mut self, rules: Vec<BurnRule>) -> Self {
+        self.inner_mut().rules = rules;
+        self
+    }
+
+    /// Called when a burn rule of an objective starts firing.
+    pub fn on_burn<F: 'static + Fn(&BurnAlert) + Send + Sync>(mut self, callback: F) -> Self {
+        self.inner_mut().on_burn = Some(Box::new(callback));
+        self
+    }
+
+    /// Reads time of burn windows from `clock` instead of the monotonic system clock, e.g. a
+    /// [MockClock](crate::clock::MockClock) to test windows deterministically.
+    pub fn clock<C: 'static + Clock>(mut self, clock: C) -> Self {
+        let inner = self.inner_mut();
+        inner.started = clock.now();
+        inner.clock = Arc::new(clock);
+        self
+    }
+
+    /// Burn rate of objective named `objective` over last `window`, rounded to minutes.
+    pub fn burn_rate(&self, objective: &str, window: Duration) -> Option<f64> {
+        let now = self.minute();
+        let objectives = self.inner.objectives.lock().unwrap();
+        let state = objectives
+            .iter()
+            .find(|state| state.objective.name == objective)?;
+        Some(state.burn_rate(now, window))
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Arc::get_mut(&mut self.inner).unwrap()
+    }
+
+    fn minute(&self) -> u64 {
+        let inner = &self.inner;
+        inner
+            .clock
+            .now()
+            .saturating_duration_since(inner.started)
+            .as_secs()
+            / 60
+    }
+
+    fn retained_minutes(&self) -> usize {
+        let longest = self
+            .inner
+            .rules
+            .iter()
+            .map(|rule| rule.long_window.max(rule.short_window))
+            .max()
+            .unwrap_or_default();
+        (longest.as_secs() / 60).max(1) as usize
+    }
+}
+
+impl ObjectiveState {
+    fn record(&mut self, minute: u64, retained: usize, bad: bool) {
+        if self.buckets.len() != retained {
+            self.buckets = vec![Bucket::default(); retained];
+        }
+        let bucket = &mut self.buckets[(minute % retained as u64) as usize];
+        if bucket.minute != minute || bucket.total == 0 {
+            *bucket = Bucket {
+                minute,
+                ..Bucket::default()
+            };
+        }
+        bucket.total += 1;
+        bucket.bad += bad as u64;
+    }
+
+    fn burn_rate(&self, now: u64, window: Duration) -> f64 {
+        let minutes = (window.as_secs() / 60).max(1);
+        let (total, bad) = self
+            .buckets
+            .iter()
+            .filter(|bucket| bucket.total > 0 && now.saturating_sub(bucket.minute) < minutes)
+            .fold((0, 0), |(total, bad), bucket| {
+                (total + bucket.total, bad + bucket.bad)
+            });
+        let budget = 1.0 - self.objective.target;
+        if total == 0 || budget <= 0.0 {
+            return 0.0;
+        }
+        bad as f64 / total as f64 / budget
+    }
+}
+
+impl Observer for SloMonitor {
+    fn on_request_started(&self, data: RequestStartData) {
+        let route = crate::normalize::route(data.req);
+        self.inner
+            .pending
+            .lock()
+            .unwrap()
+            .insert(data.request_id, route);
+    }
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        let route = match self.inner.pending.lock().unwrap().remove(&data.request_id) {
+            Some(route) => route,
+            None => return,
+        };
+        let now = self.minute();
+        let retained = self.retained_minutes();
+        let mut alerts = Vec::new();
+        {
+            let mut objectives = self.inner.objectives.lock().unwrap();
+            for state in objectives.iter_mut() {
+                if !path_matches(&state.objective.path, &route) {
+                    continue;
+                }
+                let bad = !state.objective.is_good(data.status, data.elapsed);
+                state.record(now, retained, bad);
+                state.firing.resize(self.inner.rules.len(), false);
+                for (index, rule) in self.inner.rules.iter().enumerate() {
+                    let long_window_burn_rate = state.burn_rate(now, rule.long_window);
+                    let short_window_burn_rate = state.burn_rate(now, rule.short_window);
+                    let firing = long_window_burn_rate > rule.threshold
+                        && short_window_burn_rate > rule.threshold;
+                    if firing && !state.firing[index] {
+                        alerts.push(BurnAlert {
+                            objective: state.objective.name.clone(),
+                            severity: rule.severity,
+                            threshold: rule.threshold,
+                            long_window_burn_rate,
+                            short_window_burn_rate,
+                        });
+                    }
+                    state.firing[index] = firing;
+                }
+            }
+        }
+        if let Some(callback) = &self.inner.on_burn {
+            for alert in &alerts {
+                callback(alert);
+            }
+        }
+    }
+
+    fn on_request_cancelled(&self, data: RequestCancelledData) {
+        self.inner.pending.lock().unwrap().remove(&data.request_id);
+    }
+
+    fn capture_body(&self, _req: &ServiceRequest) -> bool {
+        false
+    }
+}
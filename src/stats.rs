@@ -0,0 +1,922 @@
+//! [HookStats], live counters of a [RequestHook](crate::RequestHook).
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use actix_web::dev::ServiceRequest;
+use actix_web::http::StatusCode;
+use uuid::Uuid;
+
+use crate::budget::{ErrorBudget, ErrorBudgets, Objective};
+use crate::circuit::{CircuitBreakerConfig, CircuitState, CircuitStateChange, Circuits};
+use crate::clock::{Clock, MonotonicClock};
+use crate::hll::HyperLogLog;
+use crate::observer::OrphanedRequestData;
+
+/// Cloneable handle to counters of observed requests, from [RequestHook::stats](crate::RequestHook::stats).
+///
+/// Clones share the same counters, so a handle can be passed to handlers (e.g. as `web::Data`) or observers.
+/// Excluded paths are not counted.
+///
+/// ```
+/// use std::time::Duration;
+/// use actix_request_hook::RequestHook;
+/// use actix_request_hook::stats::HookStats;
+///
+/// // one handle shared by the hooks of all workers
+/// let stats = HookStats::default();
+/// let request_hook = RequestHook::new().with_stats(stats.clone());
+/// assert_eq!(stats.in_flight(), 0);
+/// assert_eq!(stats.snapshot(Duration::from_secs(60)).requests, 0);
+/// assert!(stats.slowest().is_empty());
+/// assert!(stats.status_counts().is_empty());
+/// assert!(stats.tenants().is_empty());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct HookStats {
+    inner: Arc<StatsInner>,
+}
+
+#[derive(Debug)]
+struct StatsInner {
+    in_flight: AtomicUsize,
+    /// Whether requests in flight are registered, once a hook watches for orphaned requests.
+    track_orphans: AtomicBool,
+    requests: Mutex<HashMap<Uuid, TrackedRequest>>,
+    orphaned: AtomicUsize,
+    clock: Arc<dyn Clock>,
+    started: Instant,
+    window: Duration,
+    apdex_threshold: Duration,
+    buckets: Mutex<Vec<Bucket>>,
+    slowest_capacity: usize,
+    slowest: Mutex<Vec<SlowRequest>>,
+    max_routes: usize,
+    status_counts: RwLock<HashMap<(String, String), Arc<StatusCounters>>>,
+    tenant_resolver: Option<Resolver>,
+    max_tenants: usize,
+    tenants: Mutex<Tenants>,
+    client_resolver: Option<Resolver>,
+    clients: Mutex<Vec<ClientSlot>>,
+    deprecated: Mutex<HashMap<DeprecatedKey, (u64, SystemTime)>>,
+    circuits: Option<Circuits>,
+    error_budgets: Option<ErrorBudgets>,
+}
+
+/// Seconds of a slot of distinct clients, see [StatsSnapshot::unique_clients].
+const CLIENT_SLOT_SECS: u64 = 10;
+
+/// Clients of requests ended within a slot of [CLIENT_SLOT_SECS].
+#[derive(Clone, Debug, Default)]
+struct ClientSlot {
+    slot: u64,
+    clients: HyperLogLog,
+}
+
+/// Request in flight, in the registry of [HookStats::orphaned] requests.
+#[derive(Debug)]
+struct TrackedRequest {
+    uri: Arc<str>,
+    method: Arc<str>,
+    started: Instant,
+    orphaned: bool,
+}
+
+/// Method, route and tenant of requests to a deprecated route.
+type DeprecatedKey = (String, String, Option<String>);
+
+type ResolverFn = Box<dyn Fn(&ServiceRequest) -> Option<String> + Send + Sync>;
+
+struct Resolver(ResolverFn);
+
+impl std::fmt::Debug for Resolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Resolver")
+    }
+}
+
+/// Counters of recently active tenants, least recently used are evicted first.
+#[derive(Debug, Default)]
+struct Tenants {
+    tick: u64,
+    counters: HashMap<String, TenantCounters>,
+}
+
+#[derive(Debug, Default)]
+struct TenantCounters {
+    last_used: u64,
+    requests: u64,
+    errors: u64,
+    latency: LatencyHistogram,
+}
+
+impl Default for StatsInner {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(300))
+    }
+}
+
+impl StatsInner {
+    fn new(window: Duration) -> Self {
+        Self {
+            in_flight: AtomicUsize::new(0),
+            track_orphans: AtomicBool::new(false),
+            requests: Mutex::new(HashMap::new()),
+            orphaned: AtomicUsize::new(0),
+            clock: Arc::new(MonotonicClock),
+            started: Instant::now(),
+            window,
+            apdex_threshold: Duration::from_millis(500),
+            buckets: Mutex::new(vec![Bucket::default(); window.as_secs().max(1) as usize]),
+            slowest_capacity: 10,
+            slowest: Mutex::new(Vec::new()),
+            max_routes: 500,
+            status_counts: RwLock::new(HashMap::new()),
+            tenant_resolver: None,
+            max_tenants: 100,
+            tenants: Mutex::new(Tenants::default()),
+            client_resolver: None,
+            clients: Mutex::new(vec![
+                ClientSlot::default();
+                (window.as_secs() / CLIENT_SLOT_SECS + 2) as usize
+            ]),
+            deprecated: Mutex::new(HashMap::new()),
+            circuits: None,
+            error_budgets: None,
+        }
+    }
+}
+
+/// Requests ended within one second.
+#[derive(Clone, Debug, Default)]
+struct Bucket {
+    second: u64,
+    requests: u64,
+    errors: u64,
+    elapsed_us: u64,
+    satisfied: u64,
+    tolerating: u64,
+}
+
+/// Throughput, error rate, latency and Apdex score of requests ended within a window, from [HookStats::snapshot].
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatsSnapshot {
+    /// Window the snapshot covers, at most the window [HookStats] retains.
+    pub window: Duration,
+    /// Number of ended requests.
+    pub requests: u64,
+    /// Ended requests per second.
+    pub requests_per_second: f64,
+    /// Share of ended requests with a 5xx status, between 0 and 1.
+    pub error_rate: f64,
+    /// Average elapsed time of ended requests.
+    pub average_latency: Duration,
+    /// Requests in flight when the snapshot was taken.
+    pub in_flight: usize,
+    /// Requests in flight when the snapshot was taken which are orphaned, see [HookStats::orphaned].
+    pub orphaned: usize,
+    /// Apdex score of ended requests between 0 and 1, if there were any.
+    pub apdex: Option<f64>,
+    /// Approximate number of distinct clients of ended requests, within about 3%, see
+    /// [HookStats::client_resolver]. Clients are counted in slots of 10 seconds, the window is rounded up to them.
+    pub unique_clients: u64,
+}
+
+/// Route under which status counts are aggregated once `max_routes` distinct routes are counted.
+pub const OTHER_ROUTE: &str = "<other>";
+
+/// Responses per status class, one counter for each of 1xx to 5xx.
+#[derive(Debug, Default)]
+struct StatusCounters([AtomicU64; 5]);
+
+/// Responses of a route per status class, from [HookStats::status_counts].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RouteStatusCounts {
+    pub method: String,
+    /// Matched route pattern, or normalized path if no route matched.
+    pub route: String,
+    pub informational: u64,
+    pub success: u64,
+    pub redirection: u64,
+    pub client_error: u64,
+    pub server_error: u64,
+}
+
+/// Requests of a tenant, from [HookStats::tenants].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TenantStats {
+    pub tenant: String,
+    pub requests: u64,
+    /// Share of requests with a 5xx status, between 0 and 1.
+    pub error_rate: f64,
+    /// 95th percentile of elapsed time, within 12.5%.
+    pub p95: Duration,
+}
+
+/// Tenant under which requests to a deprecated route are counted once `max_tenants` distinct tenants of it are.
+pub const OTHER_TENANT: &str = "<other>";
+
+/// Requests of a tenant to a deprecated route, from [HookStats::deprecated_usage].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeprecatedUsage {
+    pub method: String,
+    /// Route pattern marked with [RequestHook::deprecate_route](crate::RequestHook::deprecate_route).
+    pub route: String,
+    /// Tenant of the requests, none without a [HookStats::tenant_resolver] or if it resolved none.
+    pub tenant: Option<String>,
+    pub requests: u64,
+    /// When the last of the requests ended.
+    pub last_used: SystemTime,
+}
+
+/// Recent slow request, from [HookStats::slowest].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SlowRequest {
+    pub request_id: Uuid,
+    pub method: String,
+    /// Matched route pattern, or normalized path if no route matched.
+    pub route: String,
+    pub status: StatusCode,
+    pub elapsed: Duration,
+    pub ended_at: SystemTime,
+}
+
+impl HookStats {
+    /// Creates counters retaining ended requests for `window` (rounded to seconds), 5 minutes by default.
+    /// Snapshots can cover any window up to that.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            inner: Arc::new(StatsInner::new(window)),
+        }
+    }
+
+    /// Apdex threshold `T` of [StatsSnapshot::apdex], 500ms by default. Requests within `T` are satisfied, within
+    /// `4T` tolerating, slower requests and server errors frustrated.
+    pub fn apdex_threshold(mut self, threshold: Duration) -> Self {
+        Arc::get_mut(&mut self.inner).unwrap().apdex_threshold = threshold;
+        self
+    }
+
+    /// Number of slowest requests kept in [HookStats::slowest], 10 by default. Zero disables tracking.
+    pub fn slowest_capacity(mut self, capacity: usize) -> Self {
+        Arc::get_mut(&mut self.inner).unwrap().slowest_capacity = capacity;
+        self
+    }
+
+    /// Maximum number of distinct method and route pairs with status counts, 500 by default. Further routes are
+    /// counted under [OTHER_ROUTE].
+    pub fn max_routes(mut self, max_routes: usize) -> Self {
+        Arc::get_mut(&mut self.inner).unwrap().max_routes = max_routes;
+        self
+    }
+
+    /// Resolves tenant of a request, e.g. from a header or an API key, and keeps request counts, error rate
+    /// and p95 latency per tenant. Requests without a tenant are not counted per tenant.
+    pub fn tenant_resolver<F: 'static + Fn(&ServiceRequest) -> Option<String> + Send + Sync>(
+        mut self,
+        resolver: F,
+    ) -> Self {
+        Arc::get_mut(&mut self.inner).unwrap().tenant_resolver = Some(Resolver(Box::new(resolver)));
+        self
+    }
+
+    /// Resolves the client of a request counted in [StatsSnapshot::unique_clients], e.g. a user id, instead of the
+    /// IP address of the peer. Requests without a client are not counted.
+    pub fn client_resolver<F: 'static + Fn(&ServiceRequest) -> Option<String> + Send + Sync>(
+        mut self,
+        resolver: F,
+    ) -> Self {
+        Arc::get_mut(&mut self.inner).unwrap().client_resolver = Some(Resolver(Box::new(resolver)));
+        self
+    }
+
+    /// Maximum number of tenants with counters, 100 by default. Counters of the least recently active tenant
+    /// are dropped to make room for a new one.
+    pub fn max_tenants(mut self, max_tenants: usize) -> Self {
+        Arc::get_mut(&mut self.inner).unwrap().max_tenants = max_tenants;
+        self
+    }
+
+    /// Reads time of windows, circuits and error budgets from `clock` instead of the monotonic system clock, e.g.
+    /// a [MockClock](crate::clock::MockClock) to test windows deterministically.
+    pub fn clock<C: 'static + Clock>(mut self, clock: C) -> Self {
+        let inner = Arc::get_mut(&mut self.inner).unwrap();
+        inner.started = clock.now();
+        inner.clock = Arc::new(clock);
+        self
+    }
+
+    /// Tracks a circuit breaker state per method and route, opening on consecutive server errors or a high error
+    /// rate, see [CircuitBreakerConfig]. Stats only signal the state, e.g. for an [Observer::intercept]
+    /// (crate::observer::Observer::intercept) shedding load of open routes:
+    ///
+    /// ```
+    /// use actix_web::dev::ServiceRequest;
+    /// use actix_web::HttpResponse;
+    /// use actix_request_hook::circuit::{CircuitBreakerConfig, CircuitState};
+    /// use actix_request_hook::observer::{Observer, RequestEndData, RequestStartData};
+    /// use actix_request_hook::stats::HookStats;
+    ///
+    /// struct LoadShedder(HookStats);
+    ///
+    /// impl Observer for LoadShedder {
+    ///     fn on_request_started(&self, _data: RequestStartData) {}
+    ///     fn on_request_ended(&self, _data: RequestEndData) {}
+    ///
+    ///     fn intercept(&self, req: &ServiceRequest) -> Option<HttpResponse> {
+    ///         let route = req.match_pattern()?;
+    ///         let state = self.0.circuit_state(req.method().as_str(), &route);
+    ///         (state == CircuitState::Open).then(|| HttpResponse::ServiceUnavailable().finish())
+    ///     }
+    /// }
+    ///
+    /// let stats = HookStats::default().circuit_breaker(CircuitBreakerConfig::default());
+    /// let shedder = LoadShedder(stats.clone());
+    /// ```
+    pub fn circuit_breaker(mut self, config: CircuitBreakerConfig) -> Self {
+        let inner = Arc::get_mut(&mut self.inner).unwrap();
+        let on_state_change = inner
+            .circuits
+            .take()
+            .and_then(|circuits| circuits.on_state_change);
+        let mut circuits = Circuits::new(config);
+        circuits.on_state_change = on_state_change;
+        inner.circuits = Some(circuits);
+        self
+    }
+
+    /// Called when a route's circuit changes state. Enables the circuit breaker with default config, unless
+    /// [HookStats::circuit_breaker] configured it.
+    pub fn on_circuit_state_change<F: 'static + Fn(&CircuitStateChange) + Send + Sync>(
+        mut self,
+        callback: F,
+    ) -> Self {
+        let inner = Arc::get_mut(&mut self.inner).unwrap();
+        inner
+            .circuits
+            .get_or_insert_with(|| Circuits::new(CircuitBreakerConfig::default()))
+            .on_state_change = Some(Box::new(callback));
+        self
+    }
+
+    /// Tracks remaining error budget of `objective` over rolling windows of 1 hour, 6 hours, 24 hours and 30 days,
+    /// e.g. for deploy gating to query [HookStats::error_budget]. Objective paths are matched against routes,
+    /// or paths of requests without a matched route. Counts take about 50KB of memory per objective.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use actix_request_hook::budget::Objective;
+    /// use actix_request_hook::stats::HookStats;
+    ///
+    /// let stats = HookStats::default()
+    ///     .error_budget_objective(Objective::availability("/api/**", 0.999).named("api"));
+    /// let budget = stats.error_budget("api", Duration::from_secs(3600)).unwrap();
+    /// assert!(!budget.is_exhausted());
+    /// ```
+    pub fn error_budget_objective(mut self, objective: Objective) -> Self {
+        Arc::get_mut(&mut self.inner)
+            .unwrap()
+            .error_budgets
+            .get_or_insert_with(ErrorBudgets::default)
+            .add(objective);
+        self
+    }
+
+    /// Error budget of every objective over each of [ERROR_BUDGET_WINDOWS](crate::budget::ERROR_BUDGET_WINDOWS),
+    /// in order of objectives and windows.
+    pub fn error_budgets(&self) -> Vec<ErrorBudget> {
+        self.inner
+            .error_budgets
+            .as_ref()
+            .map_or_else(Vec::new, |budgets| budgets.budgets(self.minute()))
+    }
+
+    /// Error budget of objective named `objective` over last `window`, rounded to minutes up to a day, and to
+    /// hours beyond, at most 30 days.
+    pub fn error_budget(&self, objective: &str, window: Duration) -> Option<ErrorBudget> {
+        self.inner
+            .error_budgets
+            .as_ref()?
+            .budget(self.minute(), objective, window)
+    }
+
+    /// Number of requests which started, and haven't ended or been cancelled yet.
+    pub fn in_flight(&self) -> usize {
+        self.inner.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Number of requests in flight which were reported as orphaned, because they neither ended nor were cancelled
+    /// within the age of [RequestHook::on_orphaned_request](crate::RequestHook::on_orphaned_request). Requests
+    /// leave the gauge if they end after all, a gauge which only grows points at leaked request futures.
+    pub fn orphaned(&self) -> usize {
+        self.inner.orphaned.load(Ordering::Relaxed)
+    }
+
+    /// Throughput, error rate, average latency and Apdex score of requests ended within last `window`, e.g. 1 minute.
+    pub fn snapshot(&self, window: Duration) -> StatsSnapshot {
+        let now = self.now();
+        let buckets = self.inner.buckets.lock().unwrap();
+        let seconds = window.as_secs().clamp(1, buckets.len() as u64);
+        let unique_clients = self.unique_clients(now, seconds);
+        let total = buckets
+            .iter()
+            .filter(|bucket| bucket.requests > 0 && now - bucket.second < seconds)
+            .fold(Bucket::default(), |total, bucket| Bucket {
+                second: now,
+                requests: total.requests + bucket.requests,
+                errors: total.errors + bucket.errors,
+                elapsed_us: total.elapsed_us + bucket.elapsed_us,
+                satisfied: total.satisfied + bucket.satisfied,
+                tolerating: total.tolerating + bucket.tolerating,
+            });
+        let requests = total.requests;
+        StatsSnapshot {
+            window: Duration::from_secs(seconds),
+            requests,
+            requests_per_second: requests as f64 / seconds as f64,
+            error_rate: if requests == 0 {
+                0.0
+            } else {
+                total.errors as f64 / requests as f64
+            },
+            average_latency: Duration::from_micros(
+                total.elapsed_us.checked_div(requests).unwrap_or(0),
+            ),
+            in_flight: self.in_flight(),
+            orphaned: self.orphaned(),
+            apdex: (requests > 0).then(|| {
+                (total.satisfied as f64 + total.tolerating as f64 / 2.0) / requests as f64
+            }),
+            unique_clients,
+        }
+    }
+
+    /// Slowest requests ended within the retained window, slowest first.
+    ///
+    /// The leaderboard is bounded: a request is only kept if it was among the slowest when it ended, so after
+    /// slow entries expire, faster requests that ended alongside them are not backfilled.
+    pub fn slowest(&self) -> Vec<SlowRequest> {
+        let mut slowest = self.inner.slowest.lock().unwrap();
+        self.expire(&mut slowest);
+        slowest.clone()
+    }
+
+    /// Responses per status class of every method and route, since start or last reset.
+    pub fn status_counts(&self) -> Vec<RouteStatusCounts> {
+        let status_counts = self.inner.status_counts.read().unwrap();
+        let mut counts: Vec<_> = status_counts
+            .iter()
+            .map(|((method, route), counters)| {
+                let [informational, success, redirection, client_error, server_error] =
+                    [0, 1, 2, 3, 4].map(|class| counters.0[class].load(Ordering::Relaxed));
+                RouteStatusCounts {
+                    method: method.clone(),
+                    route: route.clone(),
+                    informational,
+                    success,
+                    redirection,
+                    client_error,
+                    server_error,
+                }
+            })
+            .collect();
+        counts.sort_by(|a, b| (&a.route, &a.method).cmp(&(&b.route, &b.method)));
+        counts
+    }
+
+    /// Clears status counts of all routes.
+    pub fn reset_status_counts(&self) {
+        self.inner.status_counts.write().unwrap().clear();
+    }
+
+    /// Requests of every tracked tenant since it became active, busiest first.
+    pub fn tenants(&self) -> Vec<TenantStats> {
+        let tenants = self.inner.tenants.lock().unwrap();
+        let mut stats: Vec<_> = tenants
+            .counters
+            .iter()
+            .map(|(tenant, counters)| counters.stats(tenant))
+            .collect();
+        stats.sort_by(|a, b| {
+            b.requests
+                .cmp(&a.requests)
+                .then_with(|| a.tenant.cmp(&b.tenant))
+        });
+        stats
+    }
+
+    /// Requests of `tenant`, if it is tracked.
+    pub fn tenant_stats(&self, tenant: &str) -> Option<TenantStats> {
+        let tenants = self.inner.tenants.lock().unwrap();
+        tenants
+            .counters
+            .get(tenant)
+            .map(|counters| counters.stats(tenant))
+    }
+
+    /// Requests to routes marked with [RequestHook::deprecate_route](crate::RequestHook::deprecate_route) per method
+    /// and tenant since start, sorted by route, method and tenant, e.g. to contact consumers still using a route
+    /// before removing it. Tenants beyond `max_tenants` of a route are counted under [OTHER_TENANT].
+    pub fn deprecated_usage(&self) -> Vec<DeprecatedUsage> {
+        let deprecated = self.inner.deprecated.lock().unwrap();
+        let mut usage: Vec<_> = deprecated
+            .iter()
+            .map(
+                |((method, route, tenant), (requests, last_used))| DeprecatedUsage {
+                    method: method.clone(),
+                    route: route.clone(),
+                    tenant: tenant.clone(),
+                    requests: *requests,
+                    last_used: *last_used,
+                },
+            )
+            .collect();
+        usage.sort_by(|a, b| {
+            (&a.route, &a.method, &a.tenant).cmp(&(&b.route, &b.method, &b.tenant))
+        });
+        usage
+    }
+
+    /// Circuit state of an endpoint, closed if circuit breaker is disabled or the endpoint had no requests.
+    pub fn circuit_state(&self, method: &str, route: &str) -> CircuitState {
+        self.inner
+            .circuits
+            .as_ref()
+            .map_or(CircuitState::Closed, |circuits| {
+                circuits.state(method, route, self.instant())
+            })
+    }
+
+    /// Method, route and state of every endpoint with an open or half open circuit, sorted by route and method.
+    pub fn open_circuits(&self) -> Vec<(String, String, CircuitState)> {
+        self.inner
+            .circuits
+            .as_ref()
+            .map_or_else(Vec::new, |circuits| circuits.not_closed(self.instant()))
+    }
+
+    /// Counts a started request until returned guard is dropped, which covers requests cancelled mid-flight.
+    /// Requests of `deprecated` routes are counted in [HookStats::deprecated_usage] when they end. Once orphans are
+    /// tracked, the request is registered by its id until then too.
+    pub(crate) fn track(
+        &self,
+        request_id: Uuid,
+        req: &ServiceRequest,
+        uri: &Arc<str>,
+        method: &Arc<str>,
+        deprecated: bool,
+    ) -> InFlightGuard {
+        self.inner.in_flight.fetch_add(1, Ordering::Relaxed);
+        let registered = self.inner.track_orphans.load(Ordering::Relaxed);
+        if registered {
+            self.inner.requests.lock().unwrap().insert(
+                request_id,
+                TrackedRequest {
+                    uri: uri.clone(),
+                    method: method.clone(),
+                    started: self.instant(),
+                    orphaned: false,
+                },
+            );
+        }
+        InFlightGuard {
+            stats: self.clone(),
+            request_id,
+            method: req.method().to_string(),
+            route: crate::normalize::route(req),
+            tenant: self
+                .inner
+                .tenant_resolver
+                .as_ref()
+                .and_then(|resolver| (resolver.0)(req)),
+            client: match &self.inner.client_resolver {
+                Some(resolver) => (resolver.0)(req).map(|client| HyperLogLog::hash(&client)),
+                None => req.peer_addr().map(|addr| HyperLogLog::hash(&addr.ip())),
+            },
+            deprecated,
+            registered,
+        }
+    }
+
+    fn count_client(&self, client: u64) {
+        let slot = self.now() / CLIENT_SLOT_SECS;
+        let mut clients = self.inner.clients.lock().unwrap();
+        let index = (slot % clients.len() as u64) as usize;
+        let entry = &mut clients[index];
+        if entry.slot != slot {
+            *entry = ClientSlot {
+                slot,
+                ..ClientSlot::default()
+            };
+        }
+        entry.clients.insert(client);
+    }
+
+    /// Distinct clients of slots overlapping last `seconds`.
+    fn unique_clients(&self, now: u64, seconds: u64) -> u64 {
+        let first = (now + 1).saturating_sub(seconds) / CLIENT_SLOT_SECS;
+        let mut merged = HyperLogLog::default();
+        for slot in self.inner.clients.lock().unwrap().iter() {
+            if slot.slot >= first && slot.slot * CLIENT_SLOT_SECS <= now {
+                merged.merge(&slot.clients);
+            }
+        }
+        merged.estimate()
+    }
+
+    /// Registers requests in flight from now on, so [HookStats::take_orphans] finds them.
+    pub(crate) fn track_orphans(&self) {
+        self.inner.track_orphans.store(true, Ordering::Relaxed);
+    }
+
+    /// Requests in flight for at least `max_age` which weren't taken before, oldest first.
+    pub(crate) fn take_orphans(&self, max_age: Duration) -> Vec<OrphanedRequestData> {
+        let now = self.instant();
+        let mut orphans: Vec<_> = self
+            .inner
+            .requests
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .filter(|(_, request)| {
+                !request.orphaned && now.saturating_duration_since(request.started) >= max_age
+            })
+            .map(|(request_id, request)| {
+                request.orphaned = true;
+                OrphanedRequestData {
+                    request_id: *request_id,
+                    age: now.saturating_duration_since(request.started),
+                    uri: request.uri.clone(),
+                    method: request.method.clone(),
+                }
+            })
+            .collect();
+        self.inner
+            .orphaned
+            .fetch_add(orphans.len(), Ordering::Relaxed);
+        orphans.sort_by_key(|orphan| std::cmp::Reverse(orphan.age));
+        orphans
+    }
+
+    fn count_deprecated(&self, method: &str, route: &str, tenant: Option<&str>) {
+        let mut deprecated = self.inner.deprecated.lock().unwrap();
+        let mut key = (
+            method.to_string(),
+            route.to_string(),
+            tenant.map(str::to_string),
+        );
+        if key.2.is_some() && !deprecated.contains_key(&key) {
+            let tenants = deprecated
+                .keys()
+                .filter(|(other_method, other_route, other_tenant)| {
+                    other_method == method && other_route == route && other_tenant.is_some()
+                })
+                .count();
+            if tenants >= self.inner.max_tenants {
+                key.2 = Some(OTHER_TENANT.to_string());
+            }
+        }
+        let (requests, last_used) = deprecated.entry(key).or_insert((0, SystemTime::now()));
+        *requests += 1;
+        *last_used = SystemTime::now();
+    }
+
+    fn count_tenant(&self, tenant: String, status: StatusCode, elapsed: Duration) {
+        if self.inner.max_tenants == 0 {
+            return;
+        }
+        let mut tenants = self.inner.tenants.lock().unwrap();
+        tenants.tick += 1;
+        let tick = tenants.tick;
+        if tenants.counters.len() >= self.inner.max_tenants
+            && !tenants.counters.contains_key(&tenant)
+        {
+            let least_recent = tenants
+                .counters
+                .iter()
+                .min_by_key(|(_, counters)| counters.last_used)
+                .map(|(tenant, _)| tenant.clone());
+            if let Some(least_recent) = least_recent {
+                tenants.counters.remove(&least_recent);
+            }
+        }
+        let counters = tenants.counters.entry(tenant).or_default();
+        counters.last_used = tick;
+        counters.requests += 1;
+        counters.errors += status.is_server_error() as u64;
+        counters.latency.record(elapsed);
+    }
+
+    fn count_status(&self, method: &str, route: &str, status: StatusCode) {
+        let class = match status.as_u16() / 100 {
+            class @ 1..=5 => class as usize - 1,
+            _ => return,
+        };
+        let key = (method.to_string(), route.to_string());
+        let counters = self.inner.status_counts.read().unwrap().get(&key).cloned();
+        let counters = match counters {
+            Some(counters) => counters,
+            None => {
+                let mut status_counts = self.inner.status_counts.write().unwrap();
+                let key = if status_counts.len() >= self.inner.max_routes
+                    && !status_counts.contains_key(&key)
+                {
+                    (key.0, OTHER_ROUTE.to_string())
+                } else {
+                    key
+                };
+                status_counts.entry(key).or_default().clone()
+            }
+        };
+        counters.0[class].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn expire(&self, slowest: &mut Vec<SlowRequest>) {
+        let now = SystemTime::now();
+        slowest.retain(|request| {
+            now.duration_since(request.ended_at)
+                .map_or(true, |age| age < self.inner.window)
+        });
+    }
+
+    fn record_slow(&self, request: SlowRequest) {
+        let mut slowest = self.inner.slowest.lock().unwrap();
+        self.expire(&mut slowest);
+        if slowest.len() >= self.inner.slowest_capacity
+            && slowest
+                .last()
+                .is_some_and(|last| last.elapsed >= request.elapsed)
+        {
+            return;
+        }
+        let index = slowest.partition_point(|slow| slow.elapsed >= request.elapsed);
+        slowest.insert(index, request);
+        slowest.truncate(self.inner.slowest_capacity);
+    }
+
+    fn now(&self) -> u64 {
+        self.instant()
+            .saturating_duration_since(self.inner.started)
+            .as_secs()
+    }
+
+    fn instant(&self) -> Instant {
+        self.inner.clock.now()
+    }
+
+    fn minute(&self) -> u64 {
+        self.now() / 60
+    }
+
+    fn record(&self, status: StatusCode, elapsed: Duration) {
+        let now = self.now();
+        let mut buckets = self.inner.buckets.lock().unwrap();
+        let index = (now % buckets.len() as u64) as usize;
+        let bucket = &mut buckets[index];
+        if bucket.second != now {
+            *bucket = Bucket {
+                second: now,
+                ..Bucket::default()
+            };
+        }
+        bucket.requests += 1;
+        bucket.errors += status.is_server_error() as u64;
+        bucket.elapsed_us += elapsed.as_micros() as u64;
+        if !status.is_server_error() {
+            let threshold = self.inner.apdex_threshold;
+            bucket.satisfied += (elapsed <= threshold) as u64;
+            bucket.tolerating += (elapsed > threshold && elapsed <= threshold * 4) as u64;
+        }
+    }
+}
+
+impl TenantCounters {
+    fn stats(&self, tenant: &str) -> TenantStats {
+        TenantStats {
+            tenant: tenant.to_string(),
+            requests: self.requests,
+            error_rate: if self.requests == 0 {
+                0.0
+            } else {
+                self.errors as f64 / self.requests as f64
+            },
+            p95: self.latency.quantile(0.95),
+        }
+    }
+}
+
+/// Log-linear histogram of latencies in microseconds, 8 buckets per power of two.
+#[derive(Debug, Default)]
+struct LatencyHistogram {
+    counts: Vec<u64>,
+}
+
+impl LatencyHistogram {
+    fn record(&mut self, elapsed: Duration) {
+        let micros = elapsed.as_micros().min(u64::MAX as u128) as u64;
+        let index = if micros < 8 {
+            micros as usize
+        } else {
+            let exponent = 63 - micros.leading_zeros() as usize;
+            (exponent - 2) * 8 + ((micros >> (exponent - 3)) & 7) as usize
+        };
+        if self.counts.len() <= index {
+            self.counts.resize(index + 1, 0);
+        }
+        self.counts[index] += 1;
+    }
+
+    /// Upper bound of the bucket containing `quantile`.
+    fn quantile(&self, quantile: f64) -> Duration {
+        let total: u64 = self.counts.iter().sum();
+        let rank = ((quantile * total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let micros = if index < 8 {
+                    index as u64
+                } else {
+                    let shift = index / 8 - 1;
+                    ((9 + (index % 8) as u64) << shift) - 1
+                };
+                return Duration::from_micros(micros);
+            }
+        }
+        Duration::ZERO
+    }
+}
+
+/// Decrements in-flight counter, and unregisters the request from orphan tracking, when dropped.
+pub(crate) struct InFlightGuard {
+    stats: HookStats,
+    request_id: Uuid,
+    method: String,
+    /// Matched route pattern, or normalized path if no route matched.
+    route: String,
+    tenant: Option<String>,
+    /// Hash of the client, see [HookStats::client_resolver].
+    client: Option<u64>,
+    deprecated: bool,
+    /// Whether the request is in the registry of orphans.
+    registered: bool,
+}
+
+impl InFlightGuard {
+    /// Records an ended request.
+    pub(crate) fn end(mut self, status: StatusCode, elapsed: Duration) {
+        self.stats.record(status, elapsed);
+        self.stats.count_status(&self.method, &self.route, status);
+        if let Some(circuits) = &self.stats.inner.circuits {
+            circuits.record(
+                &self.method,
+                &self.route,
+                status,
+                self.stats.inner.max_routes,
+                self.stats.instant(),
+            );
+        }
+        if let Some(budgets) = &self.stats.inner.error_budgets {
+            budgets.record(self.stats.minute(), &self.route, status, elapsed);
+        }
+        if self.deprecated {
+            self.stats
+                .count_deprecated(&self.method, &self.route, self.tenant.as_deref());
+        }
+        if let Some(tenant) = self.tenant.take() {
+            self.stats.count_tenant(tenant, status, elapsed);
+        }
+        if let Some(client) = self.client {
+            self.stats.count_client(client);
+        }
+        if self.stats.inner.slowest_capacity > 0 {
+            self.stats.record_slow(SlowRequest {
+                request_id: self.request_id,
+                method: std::mem::take(&mut self.method),
+                route: std::mem::take(&mut self.route),
+                status,
+                elapsed,
+                ended_at: SystemTime::now(),
+            });
+        }
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.stats.inner.in_flight.fetch_sub(1, Ordering::Relaxed);
+        if self.registered {
+            let request = self
+                .stats
+                .inner
+                .requests
+                .lock()
+                .unwrap()
+                .remove(&self.request_id);
+            if request.is_some_and(|request| request.orphaned) {
+                self.stats.inner.orphaned.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
@@ -0,0 +1,417 @@
+//! Helpers for testing the wiring of observers into an app: [RecordingObserver] records every event it receives,
+//! [assert_events](crate::assert_events) and [assert_paired](crate::assert_paired) check them. [MockObserver]
+//! misbehaves on chosen paths, to test how an app copes with slow or failing observers.
+//!
+//! ```
+//! use std::rc::Rc;
+//! use std::time::Duration;
+//! use actix_web::{test, web, App, HttpResponse};
+//! use actix_request_hook::test_utils::RecordingObserver;
+//! use actix_request_hook::{assert_events, assert_paired, RequestHook};
+//!
+//! # actix_web::rt::System::new().block_on(async {
+//! let recorder = RecordingObserver::new();
+//! let app = test::init_service(
+//!     App::new()
+//!         .wrap(RequestHook::new().exclude("/health").register(Rc::new(recorder.clone())))
+//!         .route("/health", web::get().to(HttpResponse::Ok))
+//!         .route("/hey", web::get().to(HttpResponse::Ok)),
+//! )
+//! .await;
+//! test::call_service(&app, test::TestRequest::get().uri("/health").to_request()).await;
+//! test::call_service(&app, test::TestRequest::get().uri("/hey").to_request()).await;
+//!
+//! recorder.wait_for(2, Duration::from_secs(1)).await;
+//! assert_events!(recorder, ["started GET /hey", "ended GET /hey 200"]);
+//! assert_paired!(recorder);
+//! # });
+//! ```
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use std::task::{Poll, Waker};
+use std::time::Duration;
+
+use futures_util::future::poll_fn;
+use uuid::Uuid;
+
+use crate::event::{RequestEvent, RequestStartEvent};
+use crate::observer::{
+    DeadlineExceededData, Observer, RequestCancelledData, RequestEndData, RequestStartData,
+    UpgradeData,
+};
+
+/// [Observer] storing every event it receives, in order. Clones share the same events, register a clone and
+/// inspect the original.
+#[derive(Clone, Default)]
+pub struct RecordingObserver {
+    inner: Rc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    events: RefCell<Vec<RequestEvent>>,
+    /// Tasks waiting for more events.
+    wakers: RefCell<Vec<Waker>>,
+}
+
+impl RecordingObserver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every event received so far.
+    pub fn events(&self) -> Vec<RequestEvent> {
+        self.inner.events.borrow().clone()
+    }
+
+    /// Start events received so far.
+    pub fn started(&self) -> Vec<RequestStartEvent> {
+        self.filter(|event| match event {
+            RequestEvent::Started(event) => Some(event.clone()),
+            _ => None,
+        })
+    }
+
+    /// End events received so far.
+    pub fn ended(&self) -> Vec<RequestEndData> {
+        self.filter(|event| match event {
+            RequestEvent::Ended(data) => Some(data.clone()),
+            _ => None,
+        })
+    }
+
+    /// Cancellations received so far.
+    pub fn cancelled(&self) -> Vec<RequestCancelledData> {
+        self.filter(|event| match event {
+            RequestEvent::Cancelled(data) => Some(data.clone()),
+            _ => None,
+        })
+    }
+
+    /// Number of events received so far.
+    pub fn len(&self) -> usize {
+        self.inner.events.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forgets events received so far.
+    pub fn clear(&self) {
+        self.inner.events.borrow_mut().clear();
+    }
+
+    /// Events as lines of `started {method} {uri}`, `ended {method} {uri} {status}`, `cancelled {method} {uri}`,
+    /// `deadline exceeded {method} {uri}` and `upgraded {method} {uri} {status} {protocol}`, as compared by
+    /// [assert_events](crate::assert_events).
+    pub fn summary(&self) -> Vec<String> {
+        self.inner
+            .events
+            .borrow()
+            .iter()
+            .map(|event| match event {
+                RequestEvent::Started(event) => format!("started {} {}", event.method, event.uri),
+                RequestEvent::Ended(data) => {
+                    format!(
+                        "ended {} {} {}",
+                        data.method,
+                        data.uri,
+                        data.status.as_u16()
+                    )
+                }
+                RequestEvent::Cancelled(data) => {
+                    format!("cancelled {} {}", data.method, data.uri)
+                }
+                RequestEvent::DeadlineExceeded(data) => {
+                    format!("deadline exceeded {} {}", data.method, data.uri)
+                }
+                RequestEvent::Upgrade(data) => {
+                    let handshake = &data.handshake;
+                    format!(
+                        "upgraded {} {} {} {}",
+                        handshake.method,
+                        handshake.uri,
+                        handshake.status.as_u16(),
+                        data.protocol
+                    )
+                }
+            })
+            .collect()
+    }
+
+    /// Waits until `count` events were received in total, e.g. for events of requests still running in the
+    /// background. Panics with the events received so far after `timeout`.
+    pub async fn wait_for(&self, count: usize, timeout: Duration) {
+        self.wait_until(
+            timeout,
+            || self.len() >= count,
+            || format!("{} events", count),
+        )
+        .await
+    }
+
+    /// Same as [RecordingObserver::wait_for], counting only end events, cancellations and upgrades.
+    pub async fn wait_for_ended(&self, count: usize, timeout: Duration) {
+        let ended = || {
+            self.inner
+                .events
+                .borrow()
+                .iter()
+                .filter(|event| {
+                    matches!(
+                        event,
+                        RequestEvent::Ended(_)
+                            | RequestEvent::Cancelled(_)
+                            | RequestEvent::Upgrade(_)
+                    )
+                })
+                .count()
+        };
+        self.wait_until(
+            timeout,
+            || ended() >= count,
+            || format!("{} ended requests", count),
+        )
+        .await
+    }
+
+    /// Checks that every request started once and then either ended or was cancelled once, in that order. Call it
+    /// once requests finished, requests still running are reported as never ended.
+    pub fn check_pairing(&self) -> Result<(), String> {
+        let mut open: HashMap<Uuid, String> = HashMap::new();
+        let mut closed: HashSet<Uuid> = HashSet::new();
+        for event in self.inner.events.borrow().iter() {
+            let (request_id, uri) = match event {
+                RequestEvent::Started(event) => {
+                    if open.contains_key(&event.request_id) || closed.contains(&event.request_id) {
+                        return Err(format!(
+                            "request {} {} started twice",
+                            event.request_id, event.uri
+                        ));
+                    }
+                    open.insert(event.request_id, event.uri.to_string());
+                    continue;
+                }
+                RequestEvent::Ended(data) => (data.request_id, &data.uri),
+                RequestEvent::Cancelled(data) => (data.request_id, &data.uri),
+                RequestEvent::Upgrade(data) => (data.handshake.request_id, &data.handshake.uri),
+                RequestEvent::DeadlineExceeded(_) => continue,
+            };
+            if closed.contains(&request_id) {
+                return Err(format!("request {} {} ended twice", request_id, uri));
+            }
+            if open.remove(&request_id).is_none() {
+                return Err(format!(
+                    "request {} {} ended without starting",
+                    request_id, uri
+                ));
+            }
+            closed.insert(request_id);
+        }
+        match open.into_iter().next() {
+            Some((request_id, uri)) => Err(format!("request {} {} never ended", request_id, uri)),
+            None => Ok(()),
+        }
+    }
+
+    fn filter<T, F: Fn(&RequestEvent) -> Option<T>>(&self, f: F) -> Vec<T> {
+        self.inner.events.borrow().iter().filter_map(f).collect()
+    }
+
+    fn record(&self, event: RequestEvent) {
+        self.inner.events.borrow_mut().push(event);
+        for waker in self.inner.wakers.borrow_mut().drain(..) {
+            waker.wake();
+        }
+    }
+
+    async fn wait_until<D: Fn() -> bool, E: Fn() -> String>(
+        &self,
+        timeout: Duration,
+        done: D,
+        expected: E,
+    ) {
+        let seen = poll_fn(|cx| {
+            if done() {
+                return Poll::Ready(());
+            }
+            self.inner.wakers.borrow_mut().push(cx.waker().clone());
+            Poll::Pending
+        });
+        if actix_web::rt::time::timeout(timeout, seen).await.is_err() {
+            panic!(
+                "expected {} within {:?}, received {:?}",
+                expected(),
+                timeout,
+                self.summary()
+            );
+        }
+    }
+}
+
+impl Observer for RecordingObserver {
+    fn on_request_started(&self, data: RequestStartData) {
+        self.record(RequestEvent::Started(RequestStartEvent::from(&data)));
+    }
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        self.record(RequestEvent::Ended(data));
+    }
+
+    fn on_request_cancelled(&self, data: RequestCancelledData) {
+        self.record(RequestEvent::Cancelled(data));
+    }
+
+    fn on_deadline_exceeded(&self, data: DeadlineExceededData) {
+        self.record(RequestEvent::DeadlineExceeded(data));
+    }
+
+    fn on_upgrade(&self, data: UpgradeData) {
+        self.record(RequestEvent::Upgrade(data));
+    }
+}
+
+/// What a [MockObserver] does when notified of a request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MockBehavior {
+    /// Records the event, the default.
+    Record,
+    /// Ignores the event.
+    Ignore,
+    /// Blocks the worker for a while, like an observer doing blocking I/O, and then records the event.
+    Sleep(Duration),
+    /// Panics with a message, without recording the event.
+    Panic(String),
+}
+
+/// [Observer] with scripted behavior per path, e.g. to check how an app with its own timeouts or panic handling
+/// copes with a slow or panicking observer. Events it records are in its [recorder](MockObserver::recorder).
+///
+/// ```
+/// use std::time::Duration;
+/// use actix_request_hook::test_utils::{MockBehavior, MockObserver};
+///
+/// let mock = MockObserver::new()
+///     .on_started("/slow", MockBehavior::Sleep(Duration::from_millis(100)))
+///     .on_ended("/boom", MockBehavior::Panic("sink failed".to_string()))
+///     .otherwise(MockBehavior::Ignore);
+/// ```
+pub struct MockObserver {
+    recorder: RecordingObserver,
+    started: HashMap<String, MockBehavior>,
+    ended: HashMap<String, MockBehavior>,
+    otherwise: MockBehavior,
+}
+
+impl Default for MockObserver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockObserver {
+    /// Mock recording every event.
+    pub fn new() -> Self {
+        Self {
+            recorder: RecordingObserver::new(),
+            started: HashMap::new(),
+            ended: HashMap::new(),
+            otherwise: MockBehavior::Record,
+        }
+    }
+
+    /// Behaves as `behavior` when requests of `path` start.
+    pub fn on_started<T: Into<String>>(mut self, path: T, behavior: MockBehavior) -> Self {
+        self.started.insert(path.into(), behavior);
+        self
+    }
+
+    /// Behaves as `behavior` when requests of `path` end, are cancelled or exceed their deadline.
+    pub fn on_ended<T: Into<String>>(mut self, path: T, behavior: MockBehavior) -> Self {
+        self.ended.insert(path.into(), behavior);
+        self
+    }
+
+    /// Behaves as `behavior` on paths without a scripted behavior, [MockBehavior::Record] by default.
+    pub fn otherwise(mut self, behavior: MockBehavior) -> Self {
+        self.otherwise = behavior;
+        self
+    }
+
+    /// Events recorded so far.
+    pub fn recorder(&self) -> &RecordingObserver {
+        &self.recorder
+    }
+
+    fn behave(&self, behavior: Option<&MockBehavior>, event: RequestEvent) {
+        match behavior.unwrap_or(&self.otherwise) {
+            MockBehavior::Record => self.recorder.record(event),
+            MockBehavior::Ignore => {}
+            MockBehavior::Sleep(duration) => {
+                std::thread::sleep(*duration);
+                self.recorder.record(event);
+            }
+            MockBehavior::Panic(message) => panic!("{}", message),
+        }
+    }
+}
+
+/// Path of a request uri, without query.
+fn path(uri: &str) -> &str {
+    uri.split('?').next().unwrap_or(uri)
+}
+
+impl Observer for MockObserver {
+    fn on_request_started(&self, data: RequestStartData) {
+        let behavior = self.started.get(data.req.path());
+        self.behave(
+            behavior,
+            RequestEvent::Started(RequestStartEvent::from(&data)),
+        );
+    }
+
+    fn on_request_ended(&self, data: RequestEndData) {
+        let behavior = self.ended.get(path(&data.uri));
+        self.behave(behavior, RequestEvent::Ended(data));
+    }
+
+    fn on_request_cancelled(&self, data: RequestCancelledData) {
+        let behavior = self.ended.get(path(&data.uri));
+        self.behave(behavior, RequestEvent::Cancelled(data));
+    }
+
+    fn on_deadline_exceeded(&self, data: DeadlineExceededData) {
+        let behavior = self.ended.get(path(&data.uri));
+        self.behave(behavior, RequestEvent::DeadlineExceeded(data));
+    }
+}
+
+/// Asserts the [summary](RecordingObserver::summary) of events of a [RecordingObserver], in order.
+///
+/// ```
+/// # use actix_request_hook::assert_events;
+/// # let recorder = actix_request_hook::test_utils::RecordingObserver::new();
+/// assert_events!(recorder, []);
+/// ```
+#[macro_export]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-utils")))]
+macro_rules! assert_events {
+    ($recorder:expr, [$($event:expr),* $(,)?]) => {{
+        let expected: Vec<String> = vec![$(String::from($event)),*];
+        assert_eq!($recorder.summary(), expected);
+    }};
+}
+
+/// Asserts that every request seen by a [RecordingObserver] started once and then ended or was cancelled once, see
+/// [RecordingObserver::check_pairing].
+#[macro_export]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-utils")))]
+macro_rules! assert_paired {
+    ($recorder:expr) => {{
+        if let Err(err) = $recorder.check_pairing() {
+            panic!("{}", err);
+        }
+    }};
+}
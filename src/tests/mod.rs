@@ -1,2 +1,131 @@
+#[cfg(feature = "actor")]
+mod test_actor;
+#[cfg(feature = "admin")]
+mod test_admin;
+#[cfg(feature = "alert")]
+mod test_alert;
+#[cfg(feature = "amqp")]
+mod test_amqp;
+#[cfg(feature = "anomaly")]
+mod test_anomaly;
+mod test_attributes;
+mod test_baggage;
+mod test_blocking;
+mod test_budget;
+mod test_builder;
+mod test_cache;
+#[cfg(feature = "chaos")]
+mod test_chaos;
+mod test_circuit;
+mod test_classify;
+#[cfg(feature = "clickhouse")]
+mod test_clickhouse;
+mod test_client;
+mod test_clock;
+mod test_collector;
+mod test_combinators;
+mod test_consumer;
+mod test_current;
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+mod test_encoding;
+#[cfg(feature = "events")]
+mod test_events;
+mod test_extract;
+mod test_fingerprint;
+#[cfg(feature = "fluentd")]
+mod test_fluentd;
+mod test_grpc;
+#[cfg(feature = "har")]
+mod test_har;
+#[cfg(feature = "honeycomb")]
+mod test_honeycomb;
+#[cfg(all(unix, feature = "journald"))]
+mod test_journald;
+#[cfg(feature = "kafka")]
+mod test_kafka;
+#[cfg(feature = "loki")]
+mod test_loki;
+#[cfg(feature = "metrics")]
+mod test_metrics;
+#[cfg(feature = "nats")]
+mod test_nats;
+mod test_normalize;
 mod test_observer;
+#[cfg(feature = "openapi")]
+mod test_openapi;
+#[cfg(feature = "postgres")]
+mod test_postgres;
+mod test_prelude;
+#[cfg(feature = "protobuf")]
+mod test_proto;
+#[cfg(feature = "quota")]
+mod test_quota;
+#[cfg(feature = "rate-limit")]
+mod test_rate_limit;
+#[cfg(feature = "recorder")]
+mod test_recorder;
+#[cfg(feature = "redis")]
+mod test_redis;
+mod test_render;
+mod test_retry;
+#[cfg(feature = "schema")]
+mod test_schema;
+#[cfg(feature = "scrub")]
+mod test_scrub;
+mod test_security;
 mod test_service;
+mod test_settings;
+#[cfg(feature = "shadow")]
+mod test_shadow;
+mod test_shared;
+#[cfg(feature = "slo")]
+mod test_slo;
+mod test_slow_request;
+#[cfg(feature = "sqlite")]
+mod test_sqlite;
+mod test_stats;
+#[cfg(feature = "test-utils")]
+mod test_test_utils;
+mod test_upgrade;
+#[cfg(feature = "webhook")]
+mod test_webhook;
+#[cfg(feature = "zipkin")]
+mod test_zipkin;
+
+/// End data of a completed `GET /hey` request which took 1.5ms, for tests to override fields of with struct update
+/// syntax, e.g. `RequestEndData { status: StatusCode::NOT_FOUND, ..end_data() }`.
+#[cfg(test)]
+pub(crate) fn end_data() -> crate::RequestEndData {
+    crate::RequestEndData {
+        request_id: uuid::Uuid::new_v4(),
+        elapsed: std::time::Duration::from_micros(1500),
+        uri: "/hey".into(),
+        method: "GET".into(),
+        status: actix_web::http::StatusCode::OK,
+        response_size: None,
+        request_body: None,
+        response: None,
+        outcome: crate::Outcome::Completed,
+        deadline_exceeded: false,
+        origin: crate::Origin::default(),
+        request_body_sizes: None,
+        headers_elapsed: None,
+        grpc: None,
+        generated_by_framework: false,
+        baggage: None,
+        error_category: None,
+        cache: None,
+        retry_of: None,
+        fingerprint: None,
+        geo: None,
+        security_flags: Default::default(),
+        rendered_request_body: None,
+        operation_id: None,
+        api_deviations: Vec::new(),
+        chaos: None,
+        deprecated: false,
+        consumer: None,
+        attributes: None,
+        response_attributes: None,
+    }
+}
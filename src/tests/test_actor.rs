@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod tests {
+    use crate::event::RequestEvent;
+    use crate::observers::actor::ActorObserver;
+    use crate::RequestHook;
+    use actix::{Actor, ActorContext, Context, Handler};
+    use actix_web::{test, web, App, HttpResponse};
+    use std::rc::Rc;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    struct Collector {
+        events: Arc<Mutex<Vec<RequestEvent>>>,
+        stop: bool,
+    }
+
+    impl Actor for Collector {
+        type Context = Context<Self>;
+
+        fn started(&mut self, ctx: &mut Context<Self>) {
+            if self.stop {
+                ctx.stop();
+            }
+        }
+    }
+
+    impl Handler<RequestEvent> for Collector {
+        type Result = ();
+
+        fn handle(&mut self, event: RequestEvent, _ctx: &mut Context<Self>) {
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_forwards_events_to_actor() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let addr = Collector {
+            events: events.clone(),
+            stop: false,
+        }
+        .start();
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(ActorObserver::new(addr.clone()))))
+                .wrap(RequestHook::new().register(Rc::new(ActorObserver::new(addr).ended_only())))
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        test::call_service(&app, test::TestRequest::default().to_request()).await;
+        actix_web::rt::time::sleep(Duration::from_millis(100)).await;
+
+        let events = events.lock().unwrap();
+        let started = events
+            .iter()
+            .filter(|event| matches!(event, RequestEvent::Started(_)))
+            .count();
+        assert_eq!(events.len(), 3);
+        assert_eq!(started, 1);
+    }
+
+    #[actix_web::test]
+    async fn test_counts_dropped_events() {
+        let addr = Collector {
+            events: Arc::new(Mutex::new(Vec::new())),
+            stop: true,
+        }
+        .start();
+        actix_web::rt::time::sleep(Duration::from_millis(100)).await;
+
+        let errors = Rc::new(Mutex::new(0));
+        let callback_errors = errors.clone();
+        let observer = Rc::new(
+            ActorObserver::new(addr).on_error(move |_| *callback_errors.lock().unwrap() += 1),
+        );
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(observer.clone()))
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        test::call_service(&app, test::TestRequest::default().to_request()).await;
+
+        assert_eq!(observer.dropped(), 2);
+        assert_eq!(*errors.lock().unwrap(), 2);
+    }
+}
@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests {
+    use crate::admin::{self, HookConfig, SettingsState, StatsState};
+    use crate::observer::{Observer, RequestEndData, RequestStartData};
+    use crate::RequestHook;
+    use actix_web::http::StatusCode;
+    use actix_web::{guard, test, web, App, HttpResponse};
+    use std::rc::Rc;
+
+    struct NoopObserver;
+
+    impl Observer for NoopObserver {
+        fn on_request_started(&self, _data: RequestStartData) {}
+
+        fn on_request_ended(&self, _data: RequestEndData) {}
+
+        fn name(&self) -> &str {
+            "noop"
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_admin_scope() {
+        let request_hook = RequestHook::new()
+            .exclude("/health")
+            .exclude_regex("^/static/")
+            .register(Rc::new(NoopObserver));
+        let settings = request_hook.settings();
+        let app = test::init_service(
+            App::new()
+                .service(admin::scope(
+                    "/__hook",
+                    &request_hook,
+                    guard::Header("x-admin-token", "secret"),
+                ))
+                .wrap(request_hook)
+                .route("/hello", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        test::call_service(&app, test::TestRequest::with_uri("/hello").to_request()).await;
+
+        let req = test::TestRequest::with_uri("/__hook/config").to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::NOT_FOUND
+        );
+
+        let req = test::TestRequest::with_uri("/__hook/config")
+            .insert_header(("x-admin-token", "secret"))
+            .to_request();
+        let config: HookConfig = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(config.exclude, vec!["/health"]);
+        assert_eq!(config.exclude_regex, vec!["^/static/"]);
+        assert_eq!(config.observers, vec!["noop"]);
+        assert_eq!(config.settings.sample_rate, Some(1.0));
+
+        let req = test::TestRequest::with_uri("/__hook/stats?window=10")
+            .insert_header(("x-admin-token", "secret"))
+            .to_request();
+        let stats: StatsState = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(stats.window_secs, 10);
+        // the hello request and two admin requests
+        assert_eq!(stats.requests, 3);
+        assert_eq!(stats.in_flight, 1);
+
+        let req = test::TestRequest::put()
+            .uri("/__hook/settings")
+            .insert_header(("x-admin-token", "secret"))
+            .set_json(serde_json::json!({"sample_rate": 0.25}))
+            .to_request();
+        let updated: SettingsState = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            updated,
+            SettingsState {
+                sample_rate: Some(0.25),
+                capture_body: Some(true),
+            }
+        );
+        assert_eq!(settings.sample_rate(), 0.25);
+
+        for sample_rate in [5.0, -1.0] {
+            let req = test::TestRequest::put()
+                .uri("/__hook/settings")
+                .insert_header(("x-admin-token", "secret"))
+                .set_json(serde_json::json!({"sample_rate": sample_rate, "capture_body": false}))
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        }
+        assert_eq!(settings.sample_rate(), 0.25);
+        assert!(settings.capture_body());
+    }
+}
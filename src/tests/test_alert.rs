@@ -0,0 +1,51 @@
+#[cfg(test)]
+mod tests {
+    use crate::observers::alert::ErrorBurstAlert;
+    use crate::RequestHook;
+    use actix_web::{test, web, App, HttpResponse, HttpServer};
+    use std::rc::Rc;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[actix_web::test]
+    async fn test_alerts_once_per_burst() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let server_received = received.clone();
+        let server = HttpServer::new(move || {
+            let received = server_received.clone();
+            App::new().default_service(web::to(move |body: web::Json<serde_json::Value>| {
+                received.lock().unwrap().push(body.into_inner());
+                async { HttpResponse::Ok().finish() }
+            }))
+        })
+        .workers(1)
+        .bind("127.0.0.1:0")
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let alert = ErrorBurstAlert::discord(format!("http://{}/webhook", addr))
+            .service_name("orders")
+            .threshold(2, Duration::from_secs(60));
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(alert)))
+                .route(
+                    "/users/{id}",
+                    web::get().to(HttpResponse::InternalServerError),
+                ),
+        )
+        .await;
+        for id in 0..3 {
+            let req = test::TestRequest::with_uri(&format!("/users/{}", id)).to_request();
+            test::call_service(&app, req).await;
+        }
+        actix_web::rt::time::sleep(Duration::from_millis(500)).await;
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let content = received[0]["content"].as_str().unwrap();
+        assert!(content.starts_with("2 server errors in orders within 60s\n"));
+        assert!(content.contains("• GET /users/{id} 500 ["));
+    }
+}
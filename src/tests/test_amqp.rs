@@ -0,0 +1,41 @@
+#[cfg(test)]
+mod tests {
+    use crate::observers::amqp::{routing_key, AmqpObserver};
+    use crate::tests::end_data;
+    use crate::{Observer, RequestEndData};
+    use actix_web::http::StatusCode;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    fn ended(method: &str, status: StatusCode) -> RequestEndData {
+        RequestEndData {
+            method: method.into(),
+            status,
+            ..end_data()
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_routing_key() {
+        assert_eq!(routing_key(&ended("GET", StatusCode::OK)), "get.2xx");
+        assert_eq!(
+            routing_key(&ended("POST", StatusCode::BAD_GATEWAY)),
+            "post.5xx"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_reports_unreachable_broker() {
+        let errors = Rc::new(RefCell::new(0));
+        let observer_errors = errors.clone();
+        let observer = AmqpObserver::new("amqp://127.0.0.1:1/%2f", "requests")
+            .max_backoff(Duration::from_secs(1))
+            .on_error(move |_err| *observer_errors.borrow_mut() += 1);
+
+        observer.on_request_ended(ended("GET", StatusCode::OK));
+        actix_web::rt::time::sleep(Duration::from_millis(500)).await;
+
+        assert!(*errors.borrow() > 0);
+    }
+}
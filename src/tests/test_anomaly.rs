@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod tests {
+    use crate::anomaly::{LatencyAnomaly, LatencyAnomalyDetector};
+    use crate::RequestHook;
+    use actix_web::{test, web, App, HttpResponse};
+    use std::rc::Rc;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    fn key() -> (String, String) {
+        ("GET".to_string(), "/users/{id}".to_string())
+    }
+
+    #[actix_web::test]
+    async fn test_latency_anomaly() {
+        let anomalies: Arc<Mutex<Vec<LatencyAnomaly>>> = Arc::new(Mutex::new(Vec::new()));
+        let callback_anomalies = anomalies.clone();
+        let detector = LatencyAnomalyDetector::new().on_latency_anomaly(move |anomaly| {
+            callback_anomalies.lock().unwrap().push(anomaly.clone())
+        });
+        for i in 0..100 {
+            let elapsed = Duration::from_millis(if i % 2 == 0 { 9 } else { 11 });
+            detector.observe(key(), Uuid::new_v4(), elapsed);
+        }
+        assert!(anomalies.lock().unwrap().is_empty());
+
+        let slow_request_id = Uuid::new_v4();
+        detector.observe(key(), slow_request_id, Duration::from_millis(100));
+        for _ in 0..4 {
+            detector.observe(key(), Uuid::new_v4(), Duration::from_millis(100));
+        }
+        {
+            let anomalies = anomalies.lock().unwrap();
+            assert_eq!(anomalies.len(), 1);
+            assert_eq!(anomalies[0].route, "/users/{id}");
+            assert_eq!(anomalies[0].request_id, slow_request_id);
+            assert!(anomalies[0].latency > Duration::from_millis(20));
+            assert!(anomalies[0].baseline < Duration::from_millis(12));
+            assert!(anomalies[0].z_score >= 3.0);
+        }
+        assert_eq!(detector.anomalous(), vec![key()]);
+
+        // baseline deviation widened by the slowdown narrows again
+        for _ in 0..300 {
+            detector.observe(key(), Uuid::new_v4(), Duration::from_millis(10));
+        }
+        assert!(detector.anomalous().is_empty());
+        for _ in 0..5 {
+            detector.observe(key(), Uuid::new_v4(), Duration::from_millis(200));
+        }
+        assert_eq!(anomalies.lock().unwrap().len(), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_warm_up_and_min_deviation() {
+        let anomalies = Arc::new(Mutex::new(0));
+        let callback_anomalies = anomalies.clone();
+        let detector = LatencyAnomalyDetector::new()
+            .min_deviation(Duration::from_millis(50))
+            .on_latency_anomaly(move |_| *callback_anomalies.lock().unwrap() += 1);
+        for _ in 0..10 {
+            detector.observe(key(), Uuid::new_v4(), Duration::from_millis(1));
+        }
+        // during warm up
+        detector.observe(key(), Uuid::new_v4(), Duration::from_secs(1));
+        for _ in 0..100 {
+            detector.observe(key(), Uuid::new_v4(), Duration::from_millis(1));
+        }
+        // many deviations, but only 20ms
+        for _ in 0..5 {
+            detector.observe(key(), Uuid::new_v4(), Duration::from_millis(21));
+        }
+        assert_eq!(*anomalies.lock().unwrap(), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_observer_route() {
+        let anomalies: Arc<Mutex<Vec<LatencyAnomaly>>> = Arc::new(Mutex::new(Vec::new()));
+        let callback_anomalies = anomalies.clone();
+        let detector = LatencyAnomalyDetector::new()
+            .min_samples(5)
+            .on_latency_anomaly(move |anomaly| {
+                callback_anomalies.lock().unwrap().push(anomaly.clone())
+            });
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(detector.clone())))
+                .route(
+                    "/users/{id}",
+                    web::get().to(|id: web::Path<String>| async move {
+                        if id.as_str() == "slow" {
+                            actix_web::rt::time::sleep(Duration::from_millis(200)).await;
+                        }
+                        HttpResponse::Ok().finish()
+                    }),
+                ),
+        )
+        .await;
+        for _ in 0..10 {
+            test::call_service(&app, test::TestRequest::with_uri("/users/1").to_request()).await;
+        }
+        test::call_service(
+            &app,
+            test::TestRequest::with_uri("/users/slow").to_request(),
+        )
+        .await;
+
+        let anomalies = anomalies.lock().unwrap();
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].method, "GET");
+        assert_eq!(anomalies[0].route, "/users/{id}");
+    }
+}
@@ -0,0 +1,153 @@
+#[cfg(test)]
+mod tests {
+    use crate::attributes::{AttributeValue, HookAttributes, HookResponseAttributes};
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::dev::{Service, ServiceRequest};
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App, HttpRequest, HttpResponse};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Default)]
+    struct AttributesObserver {
+        ended: RefCell<Vec<Option<HookAttributes>>>,
+        responses: RefCell<Vec<Option<HookResponseAttributes>>>,
+        capture_response: bool,
+    }
+
+    impl Observer for AttributesObserver {
+        fn on_request_started(&self, _data: RequestStartData) {}
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.ended.borrow_mut().push(data.attributes);
+            self.responses.borrow_mut().push(data.response_attributes);
+        }
+
+        fn capture_response(&self, _req: &ServiceRequest) -> bool {
+            self.capture_response
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_merges_attributes() {
+        let observer = Rc::new(AttributesObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(RequestHook::new().register(observer.clone()))
+                .wrap_fn(|req, srv| {
+                    if req.path() != "/health" {
+                        HookAttributes::set(&req, "tenant", "acme");
+                        HookAttributes::set(&req, "job_type", "unknown");
+                    }
+                    srv.call(req)
+                })
+                .route(
+                    "/orders/{id}",
+                    web::post().to(|req: HttpRequest, id: web::Path<u64>| async move {
+                        HookAttributes::set(&req, "order_id", id.to_string());
+                        HookAttributes::set(&req, "job_type", "checkout");
+                        HttpResponse::Ok().finish()
+                    }),
+                )
+                .route("/health", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        call_service(&app, TestRequest::post().uri("/orders/42").to_request()).await;
+        call_service(&app, TestRequest::get().uri("/health").to_request()).await;
+
+        let expected: HookAttributes = vec![
+            ("job_type", "checkout"),
+            ("order_id", "42"),
+            ("tenant", "acme"),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            expected.to_string(),
+            "job_type=checkout order_id=42 tenant=acme"
+        );
+        assert_eq!(*observer.ended.borrow(), vec![Some(expected), None]);
+    }
+
+    #[actix_web::test]
+    async fn test_copies_response_attributes() {
+        for capture_response in [false, true] {
+            let observer = Rc::new(AttributesObserver {
+                capture_response,
+                ..Default::default()
+            });
+            let app = init_service(
+                App::new()
+                    .wrap(RequestHook::new().register(observer.clone()))
+                    .route(
+                        "/users",
+                        web::get().to(|| async {
+                            let mut res = HttpResponse::Ok().body("[]");
+                            HookResponseAttributes::set(&mut res, "db_queries", 2);
+                            HookResponseAttributes::set(&mut res, "cache_hit", false);
+                            HookResponseAttributes::set(&mut res, "db_queries", 3);
+                            res
+                        }),
+                    )
+                    .route("/health", web::get().to(HttpResponse::Ok)),
+            )
+            .await;
+
+            call_service(&app, TestRequest::get().uri("/users").to_request()).await;
+            call_service(&app, TestRequest::get().uri("/health").to_request()).await;
+
+            let responses = observer.responses.borrow();
+            let attributes = responses[0].as_ref().unwrap();
+            assert_eq!(attributes.get("db_queries"), Some(&AttributeValue::Int(3)));
+            assert_eq!(attributes.to_string(), "cache_hit=false db_queries=3");
+            assert_eq!(responses[1], None);
+        }
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut attributes = HookAttributes::new();
+        assert!(attributes.is_empty());
+        assert_eq!(attributes.insert("order_id", "1"), None);
+        assert_eq!(attributes.insert("order_id", "2"), Some("1".to_string()));
+        assert_eq!(attributes.get("order_id"), Some("2"));
+        assert_eq!(
+            attributes.iter().collect::<Vec<_>>(),
+            vec![("order_id", "2")]
+        );
+        assert_eq!(attributes.len(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_attributes_serde() {
+        let attributes: HookAttributes = vec![("order_id", "42"), ("job_type", "checkout")]
+            .into_iter()
+            .collect();
+        let json = serde_json::to_string(&attributes).unwrap();
+        assert_eq!(json, r#"{"job_type":"checkout","order_id":"42"}"#);
+        assert_eq!(
+            serde_json::from_str::<HookAttributes>(&json).unwrap(),
+            attributes
+        );
+
+        let attributes: HookResponseAttributes = vec![
+            ("cache_hit", AttributeValue::from(true)),
+            ("db_queries", AttributeValue::from(3)),
+            ("db_time", AttributeValue::from(1.5)),
+            ("shard", AttributeValue::from("eu-1")),
+        ]
+        .into_iter()
+        .collect();
+        let json = serde_json::to_string(&attributes).unwrap();
+        assert_eq!(
+            json,
+            r#"{"cache_hit":true,"db_queries":3,"db_time":1.5,"shard":"eu-1"}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<HookResponseAttributes>(&json).unwrap(),
+            attributes
+        );
+    }
+}
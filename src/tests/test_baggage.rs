@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod tests {
+    use crate::baggage::Baggage;
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Default)]
+    struct BaggageObserver {
+        started: RefCell<Vec<Option<Baggage>>>,
+        ended: RefCell<Vec<Option<Baggage>>>,
+    }
+
+    impl Observer for BaggageObserver {
+        fn on_request_started(&self, data: RequestStartData) {
+            self.started.borrow_mut().push(data.baggage);
+        }
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.ended.borrow_mut().push(data.baggage);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_parse() {
+        let baggage = Baggage::parse(
+            " tier = gold ;ttl=60,broken,=empty,bad key=1,note=50%25%20off%zz,tier=platinum",
+        );
+        let entries: Vec<_> = baggage.iter().collect();
+        assert_eq!(entries, vec![("note", "50% off%zz"), ("tier", "platinum")]);
+        assert_eq!(baggage.to_string(), "note=50%25%20off%25zz,tier=platinum");
+        assert_eq!(Baggage::parse(&baggage.to_string()), baggage);
+
+        let many: Vec<_> = (0..100).map(|i| format!("k{}=v", i)).collect();
+        assert_eq!(Baggage::parse(&many.join(",")).len(), 64);
+        assert!(Baggage::parse("").is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_propagate_baggage() {
+        let observer = Rc::new(BaggageObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(observer.clone())
+                        .propagate_baggage(["tier"]),
+                )
+                .default_service(web::to(|baggage: Baggage| async move {
+                    HttpResponse::Ok().body(baggage.to_string())
+                })),
+        )
+        .await;
+
+        let req = TestRequest::default()
+            .insert_header(("baggage", "tier=gold"))
+            .append_header(("baggage", "experiment=b7"))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(read_body(res).await, "tier=gold");
+        let res = call_service(&app, TestRequest::default().to_request()).await;
+        assert_eq!(read_body(res).await, "");
+
+        let all = Some(Baggage::parse("experiment=b7,tier=gold"));
+        assert_eq!(*observer.started.borrow(), vec![all.clone(), None]);
+        assert_eq!(*observer.ended.borrow(), vec![all, None]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[actix_web::test]
+    async fn test_baggage_serde() {
+        let baggage = Baggage::parse("tier=gold,experiment=b7");
+        let json = serde_json::to_string(&baggage).unwrap();
+        assert_eq!(json, r#"{"experiment":"b7","tier":"gold"}"#);
+        assert_eq!(serde_json::from_str::<Baggage>(&json).unwrap(), baggage);
+    }
+}
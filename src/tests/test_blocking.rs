@@ -0,0 +1,237 @@
+#[cfg(test)]
+mod tests {
+    use crate::blocking::{Blocking, BlockingObserver, Overflow};
+    use crate::event::RequestStartEvent;
+    use crate::{RequestEndData, RequestHook};
+    use actix_web::{test, web, App, HttpResponse};
+    use std::rc::Rc;
+    use std::sync::{Arc, Condvar, Mutex};
+    use std::time::{Duration, Instant};
+
+    #[derive(Clone, Default)]
+    struct ThreadRecorder {
+        events: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl BlockingObserver for ThreadRecorder {
+        fn on_request_started(&self, event: RequestStartEvent) {
+            std::thread::sleep(Duration::from_millis(50));
+            let thread = std::thread::current();
+            self.events.lock().unwrap().push(format!(
+                "started {} on {:?}",
+                event.uri,
+                thread.name()
+            ));
+        }
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            let thread = std::thread::current();
+            self.events.lock().unwrap().push(format!(
+                "ended {} {} on {:?}",
+                data.uri,
+                data.status,
+                thread.name()
+            ));
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_blocking_observer_runs_off_worker() {
+        let recorder = ThreadRecorder::default();
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register_blocking(recorder.clone()))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let started = Instant::now();
+        test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+        // the slow start callback doesn't hold up the request
+        assert!(started.elapsed() < Duration::from_millis(50));
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while recorder.events.lock().unwrap().len() < 2 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(
+            *recorder.events.lock().unwrap(),
+            vec![
+                "started / on Some(\"request-hook-blocking\")",
+                "ended / 200 OK on Some(\"request-hook-blocking\")",
+            ]
+        );
+    }
+
+    /// Records events, and then waits until the gate is open.
+    #[derive(Clone, Default)]
+    struct GatedRecorder {
+        events: Arc<Mutex<Vec<String>>>,
+        gate: Arc<(Mutex<bool>, Condvar)>,
+    }
+
+    impl GatedRecorder {
+        fn open(&self) {
+            *self.gate.0.lock().unwrap() = true;
+            self.gate.1.notify_all();
+        }
+
+        fn wait_for(&self, count: usize) -> Vec<String> {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while self.events.lock().unwrap().len() < count && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            self.events.lock().unwrap().clone()
+        }
+
+        fn record(&self, event: String) {
+            self.events.lock().unwrap().push(event);
+            let (open, opened) = &*self.gate;
+            let _open = opened
+                .wait_while(open.lock().unwrap(), |open| !*open)
+                .unwrap();
+        }
+    }
+
+    impl BlockingObserver for GatedRecorder {
+        fn on_request_started(&self, event: RequestStartEvent) {
+            self.record(format!("started {}", event.uri));
+        }
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.record(format!("ended {}", data.uri));
+        }
+
+        fn on_shutdown(&self) {
+            self.events.lock().unwrap().push("shutdown".to_string());
+        }
+    }
+
+    /// Calls `/1`, `/2` and `/3` while the observer is stuck at start of `/1`, with room for two more events, then
+    /// opens the gate and stops the hook. Returns dropped and handled events.
+    async fn overflow(overflow: Overflow, recorder: &GatedRecorder) -> (u64, Vec<String>) {
+        let blocking = Rc::new(
+            Blocking::new(recorder.clone())
+                .capacity(2)
+                .overflow(overflow),
+        );
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(blocking.clone()))
+                .route("/{n}", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        test::call_service(&app, test::TestRequest::get().uri("/1").to_request()).await;
+        recorder.wait_for(1);
+        for uri in &["/2", "/3"] {
+            test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+        }
+        recorder.open();
+        drop(app);
+        let events = recorder.events.lock().unwrap().clone();
+        (blocking.dropped(), events)
+    }
+
+    #[actix_web::test]
+    async fn test_overflow_drop_newest() {
+        let (dropped, events) = overflow(Overflow::DropNewest, &GatedRecorder::default()).await;
+        assert_eq!(dropped, 3);
+        assert_eq!(
+            events,
+            vec!["started /1", "ended /1", "started /2", "shutdown"]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_overflow_drop_oldest() {
+        let (dropped, events) = overflow(Overflow::DropOldest, &GatedRecorder::default()).await;
+        assert_eq!(dropped, 3);
+        assert_eq!(
+            events,
+            vec!["started /1", "started /3", "ended /3", "shutdown"]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_overflow_block() {
+        let recorder = GatedRecorder::default();
+        let opener = recorder.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            opener.open();
+        });
+        let (dropped, events) = overflow(Overflow::Block, &recorder).await;
+        assert_eq!(dropped, 0);
+        assert_eq!(
+            events,
+            vec![
+                "started /1",
+                "ended /1",
+                "started /2",
+                "ended /2",
+                "started /3",
+                "ended /3",
+                "shutdown"
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_overflow_block_after_observer_panicked() {
+        struct Panicking;
+
+        impl BlockingObserver for Panicking {
+            fn on_request_started(&self, _event: RequestStartEvent) {
+                panic!("observer failed");
+            }
+
+            fn on_request_ended(&self, _data: RequestEndData) {}
+        }
+
+        let blocking = Blocking::new(Panicking)
+            .capacity(1)
+            .overflow(Overflow::Block);
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(blocking)))
+                .route("/{n}", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        // requests would wait for room forever, if the stopped thread didn't release them
+        for uri in &["/1", "/2", "/3"] {
+            let res =
+                test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+            assert!(res.status().is_success());
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_shutdown_waits_for_pending_events() {
+        let recorder = GatedRecorder::default();
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register_blocking(recorder.clone()))
+                .route("/{n}", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        for uri in &["/1", "/2"] {
+            test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+        }
+        let opener = recorder.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            opener.open();
+        });
+
+        drop(app);
+        assert_eq!(
+            *recorder.events.lock().unwrap(),
+            vec![
+                "started /1",
+                "ended /1",
+                "started /2",
+                "ended /2",
+                "shutdown"
+            ]
+        );
+    }
+}
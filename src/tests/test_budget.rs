@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests {
+    use crate::budget::{Objective, ERROR_BUDGET_WINDOWS};
+    use crate::stats::HookStats;
+    use crate::RequestHook;
+    use actix_web::{test, web, App, HttpResponse};
+    use std::time::Duration;
+
+    #[actix_web::test]
+    async fn test_error_budget() {
+        let stats = HookStats::default()
+            .error_budget_objective(Objective::availability("/api/**", 0.9).named("api"))
+            .error_budget_objective(Objective::latency("/**", Duration::from_secs(1), 0.99));
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().with_stats(stats.clone()))
+                .route("/api/users/{id}", web::get().to(HttpResponse::Ok))
+                .route(
+                    "/api/fail",
+                    web::get().to(HttpResponse::InternalServerError),
+                )
+                .route("/fail", web::get().to(HttpResponse::InternalServerError)),
+        )
+        .await;
+        let hour = Duration::from_secs(3600);
+        assert_eq!(stats.error_budget("api", hour).unwrap().remaining, 1.0);
+        assert!(stats.error_budget("other", hour).is_none());
+
+        for _ in 0..18 {
+            test::call_service(
+                &app,
+                test::TestRequest::with_uri("/api/users/1").to_request(),
+            )
+            .await;
+        }
+        test::call_service(&app, test::TestRequest::with_uri("/fail").to_request()).await;
+        test::call_service(&app, test::TestRequest::with_uri("/api/fail").to_request()).await;
+
+        let budget = stats.error_budget("api", hour).unwrap();
+        assert_eq!(budget.requests, 19);
+        assert_eq!(budget.bad, 1);
+        assert!((budget.remaining - (1.0 - 1.0 / 1.9)).abs() < 1e-9);
+        assert!(!budget.is_exhausted());
+
+        test::call_service(&app, test::TestRequest::with_uri("/api/fail").to_request()).await;
+        let budget = stats.error_budget("api", hour).unwrap();
+        assert!(budget.remaining < 0.0);
+        assert!(budget.is_exhausted());
+
+        let budgets = stats.error_budgets();
+        assert_eq!(budgets.len(), 2 * ERROR_BUDGET_WINDOWS.len());
+        let month = &budgets[ERROR_BUDGET_WINDOWS.len() - 1];
+        assert_eq!(month.window, Duration::from_secs(30 * 24 * 3600));
+        assert_eq!(month.requests, 20);
+        // latency objective covers every route, and every request was fast
+        assert_eq!(budgets[ERROR_BUDGET_WINDOWS.len()].objective, "/**");
+        assert_eq!(budgets[ERROR_BUDGET_WINDOWS.len()].requests, 21);
+        assert_eq!(budgets[ERROR_BUDGET_WINDOWS.len()].remaining, 1.0);
+    }
+
+    #[actix_web::test]
+    async fn test_no_objectives() {
+        let stats = HookStats::default();
+        assert!(stats.error_budgets().is_empty());
+        assert!(stats
+            .error_budget("api", Duration::from_secs(3600))
+            .is_none());
+    }
+}
@@ -0,0 +1,115 @@
+#[cfg(test)]
+mod tests {
+    use crate::builder::BuildError;
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::{test, web, App, HttpResponse};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[derive(Default)]
+    struct UriRecorder {
+        uris: Mutex<Vec<String>>,
+    }
+
+    impl Observer for UriRecorder {
+        fn on_request_started(&self, _data: RequestStartData) {}
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.uris.lock().unwrap().push(data.uri.to_string());
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_build_errors() {
+        let err = RequestHook::builder()
+            .exclude_regex("^/ok$")
+            .exclude_regex("^/(unclosed$")
+            .build()
+            .err()
+            .unwrap();
+        assert!(
+            matches!(&err, BuildError::InvalidRegex { pattern, .. } if pattern == "^/(unclosed$")
+        );
+        assert!(std::error::Error::source(&err).is_some());
+
+        for rate in [-0.1, 1.5, f64::NAN] {
+            let err = RequestHook::builder().sample_rate(rate).build().err();
+            assert!(matches!(err, Some(BuildError::InvalidSampleRate(_))));
+        }
+        let err = RequestHook::builder().max_body_size(0).build().err();
+        assert!(matches!(err, Some(BuildError::InvalidMaxBodySize(0))));
+        let err = RequestHook::builder()
+            .deadline_header("x deadline")
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "invalid header name `x deadline`");
+        let err = RequestHook::builder()
+            .force_sample_header("x debug", "secret")
+            .build()
+            .err();
+        assert!(matches!(err, Some(BuildError::InvalidHeaderName(_))));
+        let err = RequestHook::builder()
+            .fingerprint(["x-signature", "x sig"])
+            .build()
+            .err();
+        assert!(matches!(err, Some(BuildError::InvalidHeaderName(name)) if name == "x sig"));
+        let err = RequestHook::builder()
+            .include_ips(["10.0.0.0/8"])
+            .exclude_ips(["10.0.0.0/40"])
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "invalid CIDR `10.0.0.0/40`");
+        let err = RequestHook::builder()
+            .verbose_header("x-verbose", "short")
+            .build()
+            .err();
+        assert!(matches!(err, Some(BuildError::ShortVerboseToken(5))));
+        let err = RequestHook::builder()
+            .tick_interval(Duration::ZERO)
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "tick interval must be greater than 0");
+    }
+
+    #[actix_web::test]
+    async fn test_built_hook() {
+        let observer = Arc::new(UriRecorder::default());
+        let request_hook = RequestHook::builder()
+            .exclude_regex("^/a/\\d+$")
+            .sample_rate(1.0)
+            .register(observer.clone())
+            .build()
+            .unwrap();
+        assert_eq!(request_hook.build().excluded_patterns(), ["^/a/\\d+$"]);
+
+        // moved into the app factory of every worker, which shares observers
+        std::thread::spawn({
+            let request_hook = request_hook.clone();
+            move || drop(request_hook)
+        })
+        .join()
+        .unwrap();
+        let app = test::init_service(
+            App::new()
+                .service(
+                    web::scope("/a")
+                        .wrap(request_hook.clone())
+                        .route("/{n}", web::get().to(HttpResponse::Ok)),
+                )
+                .service(
+                    web::scope("/b")
+                        .wrap(request_hook)
+                        .route("/{n}", web::get().to(HttpResponse::Ok)),
+                )
+                .route("/{n}", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        for uri in ["/a/x", "/b/y", "/a/123"] {
+            test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+        }
+        assert_eq!(*observer.uris.lock().unwrap(), vec!["/a/x", "/b/y"]);
+    }
+}
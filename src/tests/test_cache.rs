@@ -0,0 +1,106 @@
+#[cfg(test)]
+mod tests {
+    use crate::cache::CacheSignals;
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Default)]
+    struct CacheObserver {
+        signals: RefCell<Vec<Option<CacheSignals>>>,
+    }
+
+    impl Observer for CacheObserver {
+        fn on_request_started(&self, _data: RequestStartData) {}
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.signals.borrow_mut().push(data.cache);
+        }
+    }
+
+    fn app_routes(cfg: &mut web::ServiceConfig) {
+        cfg.route(
+            "/cdn",
+            web::get().to(|| async {
+                HttpResponse::Ok()
+                    .insert_header(("cache-control", "public, max-age=60"))
+                    .insert_header(("etag", "\"v1\""))
+                    .insert_header(("x-cache", "hit from varnish"))
+                    .finish()
+            }),
+        )
+        .route(
+            "/aged",
+            web::get().to(|| async { HttpResponse::Ok().insert_header(("age", "12")).finish() }),
+        )
+        .route(
+            "/edge",
+            web::get().to(|| async {
+                HttpResponse::Ok()
+                    .insert_header(("x-cache", "TCP_MISS"))
+                    .finish()
+            }),
+        )
+        .route("/plain", web::get().to(HttpResponse::Ok));
+    }
+
+    async fn signals_of(
+        hook: RequestHook,
+        observer: Rc<CacheObserver>,
+    ) -> Vec<Option<CacheSignals>> {
+        let app = init_service(App::new().wrap(hook).configure(app_routes)).await;
+        for uri in &["/cdn", "/aged", "/edge", "/plain"] {
+            call_service(&app, TestRequest::with_uri(uri).to_request()).await;
+        }
+        let signals = observer.signals.borrow().clone();
+        signals
+    }
+
+    #[actix_web::test]
+    async fn test_cache_signals() {
+        let observer = Rc::new(CacheObserver::default());
+        let signals = signals_of(RequestHook::new().register(observer.clone()), observer).await;
+        assert_eq!(
+            signals,
+            vec![
+                Some(CacheSignals {
+                    cache_control: Some("public, max-age=60".into()),
+                    etag: Some("\"v1\"".into()),
+                    age: None,
+                    x_cache: Some("hit from varnish".into()),
+                    hit: Some(true),
+                }),
+                Some(CacheSignals {
+                    age: Some(12),
+                    hit: Some(true),
+                    ..CacheSignals::default()
+                }),
+                Some(CacheSignals {
+                    x_cache: Some("TCP_MISS".into()),
+                    ..CacheSignals::default()
+                }),
+                None,
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_cache_rule() {
+        let observer = Rc::new(CacheObserver::default());
+        let hook =
+            RequestHook::new()
+                .register(observer.clone())
+                .cache_rule(|signals: &CacheSignals| {
+                    let x_cache = signals.x_cache.as_deref()?.to_ascii_uppercase();
+                    Some(x_cache.contains("HIT"))
+                });
+        let hits: Vec<_> = signals_of(hook, observer)
+            .await
+            .into_iter()
+            .map(|signals| signals.and_then(|signals| signals.hit))
+            .collect();
+        assert_eq!(hits, vec![Some(true), None, Some(false), None]);
+    }
+}
@@ -0,0 +1,131 @@
+#[cfg(test)]
+mod tests {
+    use crate::observers::chaos::{ChaosInjection, ChaosObserver};
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::http::StatusCode;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[derive(Default)]
+    struct ChaosLog {
+        ended: RefCell<Vec<Option<ChaosInjection>>>,
+        elapsed: RefCell<Vec<Duration>>,
+    }
+
+    impl Observer for ChaosLog {
+        fn on_request_started(&self, _data: RequestStartData) {}
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.elapsed.borrow_mut().push(data.elapsed);
+            self.ended.borrow_mut().push(data.chaos);
+        }
+    }
+
+    async fn run(chaos: &ChaosObserver, uris: &[&str]) -> (Vec<StatusCode>, Rc<ChaosLog>, usize) {
+        let log = Rc::new(ChaosLog::default());
+        let handled = Rc::new(Cell::new(0));
+        let counter = handled.clone();
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(Rc::new(chaos.clone()))
+                        .register(log.clone()),
+                )
+                .app_data(web::Data::new(counter))
+                .route(
+                    "/users/{id}",
+                    web::get().to(|handled: web::Data<Rc<Cell<usize>>>| async move {
+                        handled.set(handled.get() + 1);
+                        HttpResponse::Ok().finish()
+                    }),
+                )
+                .route("/health", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let mut statuses = Vec::new();
+        for uri in uris {
+            let res = call_service(&app, TestRequest::get().uri(uri).to_request()).await;
+            statuses.push(res.status());
+        }
+        (statuses, log, handled.get())
+    }
+
+    #[actix_web::test]
+    async fn injects_errors_into_matched_routes() {
+        let chaos = ChaosObserver::new().error("/users/{id}", 1.0, StatusCode::SERVICE_UNAVAILABLE);
+        let (statuses, log, handled) = run(&chaos, &["/users/7", "/health"]).await;
+        assert_eq!(
+            statuses,
+            vec![StatusCode::SERVICE_UNAVAILABLE, StatusCode::OK]
+        );
+        assert_eq!(handled, 0);
+        assert_eq!(
+            *log.ended.borrow(),
+            vec![
+                Some(ChaosInjection::Error {
+                    status: StatusCode::SERVICE_UNAVAILABLE
+                }),
+                None
+            ]
+        );
+        assert_eq!(chaos.injected(), 1);
+    }
+
+    #[actix_web::test]
+    async fn delays_handlers() {
+        let delay = Duration::from_millis(50);
+        let chaos = ChaosObserver::new().latency("*", 1.0, delay);
+        let (statuses, log, handled) = run(&chaos, &["/health"]).await;
+        assert_eq!(statuses, vec![StatusCode::OK]);
+        assert_eq!(handled, 0);
+        assert!(log.elapsed.borrow()[0] >= delay);
+        assert_eq!(
+            *log.ended.borrow(),
+            vec![Some(ChaosInjection::Latency { delay })]
+        );
+    }
+
+    #[actix_web::test]
+    async fn rules_take_disjoint_fractions() {
+        let chaos = ChaosObserver::new()
+            .error("*", 0.5, StatusCode::INTERNAL_SERVER_ERROR)
+            .latency("/users/{id}", 0.5, Duration::ZERO);
+        let uris = vec!["/users/7"; 40];
+        let (statuses, log, handled) = run(&chaos, &uris).await;
+        let errors = statuses
+            .iter()
+            .filter(|status| **status == StatusCode::INTERNAL_SERVER_ERROR)
+            .count();
+        assert!(errors > 0 && handled > 0);
+        assert_eq!(errors + handled, 40);
+        assert!(log.ended.borrow().iter().all(Option::is_some));
+        assert_eq!(chaos.injected(), 40);
+    }
+
+    #[actix_web::test]
+    async fn disabled_observer_injects_nothing() {
+        let chaos = ChaosObserver::new().error("*", 1.0, StatusCode::BAD_GATEWAY);
+        chaos.set_enabled(false);
+        let (statuses, log, handled) = run(&chaos, &["/users/7", "/health"]).await;
+        assert_eq!(statuses, vec![StatusCode::OK, StatusCode::OK]);
+        assert_eq!(handled, 1);
+        assert_eq!(*log.ended.borrow(), vec![None, None]);
+        assert_eq!(chaos.injected(), 0);
+    }
+
+    #[test]
+    fn displays_injections_in_events() {
+        let delay = ChaosInjection::Latency {
+            delay: Duration::from_millis(500),
+        };
+        assert_eq!(delay.to_string(), "latency 500ms");
+        let error = ChaosInjection::Error {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+        };
+        assert_eq!(error.to_string(), "error 503");
+    }
+}
@@ -0,0 +1,155 @@
+#[cfg(test)]
+mod tests {
+    use crate::circuit::{CircuitBreakerConfig, CircuitState, CircuitStateChange};
+    use crate::stats::HookStats;
+    use crate::RequestHook;
+    use actix_web::{test, web, App, HttpResponse};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    fn status(code: web::Path<u16>) -> HttpResponse {
+        HttpResponse::build(actix_web::http::StatusCode::from_u16(*code).unwrap()).finish()
+    }
+
+    #[actix_web::test]
+    async fn test_consecutive_failures_open_circuit() {
+        let changes = Arc::new(Mutex::new(Vec::<CircuitStateChange>::new()));
+        let recorded = changes.clone();
+        let stats = HookStats::default()
+            .circuit_breaker(CircuitBreakerConfig {
+                consecutive_failures: 3,
+                open_for: Duration::from_millis(50),
+                ..CircuitBreakerConfig::default()
+            })
+            .on_circuit_state_change(move |change| recorded.lock().unwrap().push(change.clone()));
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().with_stats(stats.clone()))
+                .route(
+                    "/status/{code}",
+                    web::get().to(|code| async { status(code) }),
+                ),
+        )
+        .await;
+        for uri in &["/status/500", "/status/200", "/status/500", "/status/503"] {
+            test::call_service(&app, test::TestRequest::with_uri(uri).to_request()).await;
+        }
+        assert_eq!(
+            stats.circuit_state("GET", "/status/{code}"),
+            CircuitState::Closed
+        );
+        assert!(changes.lock().unwrap().is_empty());
+
+        test::call_service(
+            &app,
+            test::TestRequest::with_uri("/status/500").to_request(),
+        )
+        .await;
+        assert_eq!(
+            stats.circuit_state("GET", "/status/{code}"),
+            CircuitState::Open
+        );
+        assert_eq!(
+            stats.open_circuits(),
+            vec![(
+                "GET".to_string(),
+                "/status/{code}".to_string(),
+                CircuitState::Open
+            )]
+        );
+        {
+            let changes = changes.lock().unwrap();
+            assert_eq!(changes.len(), 1);
+            assert_eq!(changes[0].from, CircuitState::Closed);
+            assert_eq!(changes[0].to, CircuitState::Open);
+            assert_eq!(changes[0].consecutive_failures, 3);
+        }
+
+        actix_web::rt::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(
+            stats.circuit_state("GET", "/status/{code}"),
+            CircuitState::HalfOpen
+        );
+        // failed probe opens the circuit again
+        test::call_service(
+            &app,
+            test::TestRequest::with_uri("/status/500").to_request(),
+        )
+        .await;
+        assert_eq!(
+            stats.circuit_state("GET", "/status/{code}"),
+            CircuitState::Open
+        );
+
+        actix_web::rt::time::sleep(Duration::from_millis(60)).await;
+        test::call_service(
+            &app,
+            test::TestRequest::with_uri("/status/200").to_request(),
+        )
+        .await;
+        assert_eq!(
+            stats.circuit_state("GET", "/status/{code}"),
+            CircuitState::Closed
+        );
+        assert!(stats.open_circuits().is_empty());
+        let transitions: Vec<_> = changes
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|change| (change.from, change.to))
+            .collect();
+        assert_eq!(
+            transitions,
+            vec![
+                (CircuitState::Closed, CircuitState::Open),
+                (CircuitState::HalfOpen, CircuitState::Open),
+                (CircuitState::HalfOpen, CircuitState::Closed),
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_error_rate_opens_circuit() {
+        let stats = HookStats::default().circuit_breaker(CircuitBreakerConfig {
+            error_rate: 0.5,
+            min_requests: 4,
+            ..CircuitBreakerConfig::default()
+        });
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().with_stats(stats.clone()))
+                .route(
+                    "/status/{code}",
+                    web::get().to(|code| async { status(code) }),
+                ),
+        )
+        .await;
+        for uri in &["/status/500", "/status/200", "/status/500"] {
+            test::call_service(&app, test::TestRequest::with_uri(uri).to_request()).await;
+        }
+        assert_eq!(
+            stats.circuit_state("GET", "/status/{code}"),
+            CircuitState::Closed
+        );
+        test::call_service(
+            &app,
+            test::TestRequest::with_uri("/status/404").to_request(),
+        )
+        .await;
+        assert_eq!(
+            stats.circuit_state("GET", "/status/{code}"),
+            CircuitState::Open
+        );
+        assert_eq!(
+            stats.circuit_state("POST", "/status/{code}"),
+            CircuitState::Closed
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_disabled_circuit_is_closed() {
+        let stats = HookStats::default();
+        assert_eq!(stats.circuit_state("GET", "/"), CircuitState::Closed);
+        assert!(stats.open_circuits().is_empty());
+    }
+}
@@ -0,0 +1,112 @@
+#[cfg(test)]
+mod tests {
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::dev::Service;
+    use actix_web::error::JsonPayloadError;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App, HttpResponse, ResponseError};
+    use std::cell::RefCell;
+    use std::fmt;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    #[derive(Debug)]
+    struct DbTimeout;
+
+    impl fmt::Display for DbTimeout {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("query timed out")
+        }
+    }
+
+    impl ResponseError for DbTimeout {
+        fn status_code(&self) -> StatusCode {
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+    }
+
+    #[derive(Default)]
+    struct CategoryObserver {
+        categories: RefCell<Vec<Option<Arc<str>>>>,
+    }
+
+    impl Observer for CategoryObserver {
+        fn on_request_started(&self, _data: RequestStartData) {}
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.categories.borrow_mut().push(data.error_category);
+        }
+    }
+
+    async fn panicking() -> HttpResponse {
+        panic!("no response")
+    }
+
+    #[actix_web::test]
+    async fn test_error_classifier() {
+        let observer = Rc::new(CategoryObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(observer.clone())
+                        .error_classifier(|err: &actix_web::Error| {
+                            if err.as_error::<DbTimeout>().is_some() {
+                                Some("db_timeout")
+                            } else if err.as_error::<JsonPayloadError>().is_some() {
+                                Some("validation")
+                            } else if err.as_response_error().status_code().is_server_error() {
+                                Some("internal")
+                            } else {
+                                None
+                            }
+                        }),
+                )
+                .route(
+                    "/db",
+                    web::get().to(|| async { Err::<HttpResponse, _>(DbTimeout) }),
+                )
+                .route(
+                    "/json",
+                    web::post().to(|_: web::Json<Vec<u32>>| async { HttpResponse::Ok().finish() }),
+                )
+                .route(
+                    "/forbidden",
+                    web::get().to(|| async {
+                        Err::<HttpResponse, _>(actix_web::error::ErrorForbidden("no"))
+                    }),
+                )
+                .route("/panic", web::get().to(panicking))
+                .route("/ok", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let res = call_service(&app, TestRequest::with_uri("/db").to_request()).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let req = TestRequest::post()
+            .uri("/json")
+            .insert_header(("content-type", "application/json"))
+            .set_payload("{")
+            .to_request();
+        assert_eq!(
+            call_service(&app, req).await.status(),
+            StatusCode::BAD_REQUEST
+        );
+        call_service(&app, TestRequest::with_uri("/forbidden").to_request()).await;
+        call_service(&app, TestRequest::with_uri("/ok").to_request()).await;
+        let res = app.call(TestRequest::with_uri("/panic").to_request()).await;
+        assert!(res.is_err());
+
+        assert_eq!(
+            *observer.categories.borrow(),
+            vec![
+                Some("db_timeout".into()),
+                Some("validation".into()),
+                None,
+                None,
+                Some("internal".into()),
+            ]
+        );
+    }
+}
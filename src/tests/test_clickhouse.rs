@@ -0,0 +1,52 @@
+#[cfg(test)]
+mod tests {
+    use crate::observers::clickhouse::ClickhouseObserver;
+    use crate::tests::end_data;
+    use crate::{Observer, RequestEndData};
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[actix_web::test]
+    async fn test_inserts_json_each_row() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let server_received = received.clone();
+        let server = HttpServer::new(move || {
+            let received = server_received.clone();
+            App::new().default_service(web::to(move |req: HttpRequest, body: String| {
+                let user = req.headers().get("x-clickhouse-user").cloned();
+                received
+                    .lock()
+                    .unwrap()
+                    .push((req.query_string().to_string(), user, body));
+                async { HttpResponse::Ok().finish() }
+            }))
+        })
+        .workers(1)
+        .bind("127.0.0.1:0")
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let observer = ClickhouseObserver::builder(format!("http://{}", addr), "requests")
+            .credentials("default", "secret")
+            .batch(2, Duration::from_secs(60))
+            .build()
+            .unwrap();
+        for _ in 0..2 {
+            observer.on_request_ended(RequestEndData { ..end_data() });
+        }
+        actix_web::rt::time::sleep(Duration::from_millis(500)).await;
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let (query, user, body) = &received[0];
+        assert_eq!(query, "query=INSERT+INTO+requests+FORMAT+JSONEachRow");
+        assert_eq!(user.as_ref().unwrap(), "default");
+        assert_eq!(body.lines().count(), 2);
+        let row: serde_json::Value = serde_json::from_str(body.lines().next().unwrap()).unwrap();
+        assert!(row["timestamp"].is_u64());
+        assert_eq!(row["elapsed_us"], 1500);
+        assert_eq!(row["status"], 200);
+    }
+}
@@ -0,0 +1,147 @@
+#[cfg(test)]
+mod tests {
+    use crate::client::{Cidr, ClientGeo};
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+    use std::cell::RefCell;
+    use std::net::{IpAddr, SocketAddr};
+    use std::rc::Rc;
+
+    #[derive(Default)]
+    struct GeoObserver {
+        started: RefCell<Vec<Option<ClientGeo>>>,
+        ended: RefCell<Vec<Option<ClientGeo>>>,
+    }
+
+    impl Observer for GeoObserver {
+        fn on_request_started(&self, data: RequestStartData) {
+            self.started.borrow_mut().push(data.geo);
+        }
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.ended.borrow_mut().push(data.geo);
+        }
+    }
+
+    fn from(ip: &str) -> TestRequest {
+        TestRequest::default().peer_addr(SocketAddr::new(ip.parse().unwrap(), 40000))
+    }
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_cidr() {
+        let private: Cidr = "10.0.0.0/8".parse().unwrap();
+        assert!(private.contains(ip("10.1.2.3")));
+        assert!(private.contains(ip("::ffff:10.1.2.3")));
+        assert!(!private.contains(ip("11.0.0.1")));
+        assert!(!private.contains(ip("2001:db8::1")));
+        let docs: Cidr = "2001:db8::/32".parse().unwrap();
+        assert!(docs.contains(ip("2001:db8:ffff::1")));
+        assert!(!docs.contains(ip("2001:db9::1")));
+        let single: Cidr = "192.0.2.1".parse().unwrap();
+        assert_eq!(single.to_string(), "192.0.2.1/32");
+        assert!(!single.contains(ip("192.0.2.2")));
+        let any: Cidr = "::/0".parse().unwrap();
+        assert!(any.contains(ip("2001:db8::1")));
+        assert!("0.0.0.0/0".parse::<Cidr>().unwrap().contains(ip("8.8.8.8")));
+        for invalid in [
+            "10.0.0.0/33",
+            "10.0.0/8",
+            "example.com",
+            "::/129",
+            "10.0.0.0/x",
+        ] {
+            assert!(invalid.parse::<Cidr>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_ip_lists() {
+        let observer = Rc::new(GeoObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(observer.clone())
+                        .include_ips(["10.0.0.0/8", "2001:db8::/32"])
+                        .exclude_ips(["10.0.0.1"]),
+                )
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        for addr in ["10.0.0.2", "10.0.0.1", "192.0.2.1", "2001:db8::1"] {
+            let res = call_service(&app, from(addr).to_request()).await;
+            assert!(res.status().is_success());
+        }
+        call_service(&app, TestRequest::default().to_request()).await;
+        assert_eq!(observer.ended.borrow().len(), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_geo_resolver() {
+        let observer = Rc::new(GeoObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(observer.clone())
+                        .trust_forwarded_headers()
+                        .geo_resolver(|ip: IpAddr| {
+                            let mut geo = ClientGeo::new(ip);
+                            if ip.is_ipv4() {
+                                geo.country = Some("DE".into());
+                                geo.asn = Some(3320);
+                            }
+                            Some(geo)
+                        }),
+                )
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        let req = from("10.0.0.1").insert_header(("x-forwarded-for", "192.0.2.7, 10.0.0.1"));
+        call_service(&app, req.to_request()).await;
+        let req = from("10.0.0.1").insert_header(("forwarded", "for=\"[2001:db8::7]:4711\""));
+        call_service(&app, req.to_request()).await;
+        call_service(&app, TestRequest::default().to_request()).await;
+
+        let expected = vec![
+            Some(ClientGeo {
+                ip: ip("192.0.2.7"),
+                country: Some("DE".into()),
+                asn: Some(3320),
+                as_org: None,
+            }),
+            Some(ClientGeo::new(ip("2001:db8::7"))),
+            None,
+        ];
+        assert_eq!(*observer.started.borrow(), expected);
+        assert_eq!(*observer.ended.borrow(), expected);
+    }
+
+    #[actix_web::test]
+    async fn test_peer_addr_by_default() {
+        let observer = Rc::new(GeoObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(observer.clone())
+                        .exclude_ips(["192.0.2.0/24"])
+                        .geo_resolver(|ip| Some(ClientGeo::new(ip))),
+                )
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        // a client can't leave its requests unobserved by claiming another IP
+        let req = from("198.51.100.1").insert_header(("x-forwarded-for", "192.0.2.1"));
+        call_service(&app, req.to_request()).await;
+        assert_eq!(
+            *observer.ended.borrow(),
+            vec![Some(ClientGeo::new(ip("198.51.100.1")))]
+        );
+    }
+}
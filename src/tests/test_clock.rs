@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+    use crate::clock::{Clock, CoarseClock, MockClock};
+    use crate::stats::HookStats;
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::{test, web, App, HttpResponse};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[derive(Default)]
+    struct ElapsedObserver {
+        elapsed: RefCell<Vec<Duration>>,
+    }
+
+    impl Observer for ElapsedObserver {
+        fn on_request_started(&self, _data: RequestStartData) {}
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.elapsed.borrow_mut().push(data.elapsed);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_mock_clock_elapsed() {
+        let clock = MockClock::new();
+        let observer = Rc::new(ElapsedObserver::default());
+        let handler_clock = clock.clone();
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .clock(clock.clone())
+                        .register(observer.clone()),
+                )
+                .route(
+                    "/",
+                    web::get().to(move || {
+                        handler_clock.advance(Duration::from_millis(250));
+                        async { HttpResponse::Ok().finish() }
+                    }),
+                ),
+        )
+        .await;
+        test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+        test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+
+        assert_eq!(
+            *observer.elapsed.borrow(),
+            vec![Duration::from_millis(250), Duration::from_millis(250)]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_stats_windows_follow_clock() {
+        let clock = MockClock::new();
+        let stats = HookStats::new(Duration::from_secs(60)).clock(clock.clone());
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().with_stats(stats.clone()))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+        assert_eq!(stats.snapshot(Duration::from_secs(10)).requests, 1);
+
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(stats.snapshot(Duration::from_secs(10)).requests, 0);
+        assert_eq!(stats.snapshot(Duration::from_secs(60)).requests, 1);
+
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(stats.snapshot(Duration::from_secs(60)).requests, 0);
+    }
+
+    #[actix_web::test]
+    async fn test_coarse_clock_advances() {
+        let clock = CoarseClock::new(Duration::from_millis(1));
+        let start = clock.now();
+        std::thread::sleep(Duration::from_millis(20));
+        let now = clock.now();
+        assert!(now > start);
+        assert!(clock.now() >= now);
+    }
+}
@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod tests {
+    use crate::blocking::BlockingObserver;
+    use crate::collector::Collector;
+    use crate::event::RequestStartEvent;
+    use crate::RequestEndData;
+    use crate::RequestHook;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+    use std::rc::Rc;
+    use std::sync::{Arc, Mutex};
+
+    /// Records events, and names of the threads which observed them.
+    #[derive(Clone, Default)]
+    struct Recorder {
+        events: Arc<Mutex<Vec<String>>>,
+        threads: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Recorder {
+        fn record(&self, event: String) {
+            self.events.lock().unwrap().push(event);
+            let thread = std::thread::current()
+                .name()
+                .unwrap_or_default()
+                .to_string();
+            self.threads.lock().unwrap().push(thread);
+        }
+    }
+
+    impl BlockingObserver for Recorder {
+        fn on_request_started(&self, event: RequestStartEvent) {
+            self.record(format!("started {}", event.uri));
+        }
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.record(format!("ended {}", data.uri));
+        }
+
+        fn on_shutdown(&self) {
+            self.record("shutdown".to_string());
+        }
+    }
+
+    #[test]
+    fn test_collects_events_of_all_workers() {
+        let first = Recorder::default();
+        let second = Recorder::default();
+        let collector = Collector::new()
+            .register(first.clone())
+            .register(second.clone());
+
+        let workers: Vec<_> = (0..2)
+            .map(|worker| {
+                let collector = collector.clone();
+                std::thread::spawn(move || {
+                    actix_web::rt::System::new().block_on(async move {
+                        let app = init_service(
+                            App::new()
+                                .wrap(RequestHook::new().register(Rc::new(collector.observer())))
+                                .default_service(web::to(HttpResponse::Ok)),
+                        )
+                        .await;
+                        let uri = format!("/{}", worker);
+                        call_service(&app, TestRequest::get().uri(&uri).to_request()).await;
+                    })
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        // waits for the collector to handle pending events and shut down
+        drop(collector);
+
+        for recorder in [first, second] {
+            let events = recorder.events.lock().unwrap().clone();
+            assert_eq!(events.len(), 5);
+            for worker in 0..2 {
+                let started = events
+                    .iter()
+                    .position(|e| e == &format!("started /{}", worker));
+                let ended = events
+                    .iter()
+                    .position(|e| e == &format!("ended /{}", worker));
+                assert!(started.unwrap() < ended.unwrap());
+            }
+            assert_eq!(events.last().unwrap(), "shutdown");
+            assert!(recorder
+                .threads
+                .lock()
+                .unwrap()
+                .iter()
+                .all(|thread| thread == "request-hook-collector"));
+        }
+    }
+
+    #[test]
+    fn test_idle_collector() {
+        let recorder = Recorder::default();
+        let collector = Collector::new().register(recorder.clone());
+        let observer = collector.observer();
+        drop(collector);
+        drop(observer);
+        // the thread never started
+        assert!(recorder.events.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+    use crate::combinators::ObserverExt;
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::dev::ServiceRequest;
+    use actix_web::{test, web, App, HttpResponse};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Clone, Default)]
+    struct Recorder {
+        name: &'static str,
+        events: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Observer for Recorder {
+        fn on_request_started(&self, data: RequestStartData) {
+            self.events
+                .borrow_mut()
+                .push(format!("{} started {}", self.name, data.uri));
+        }
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.events
+                .borrow_mut()
+                .push(format!("{} ended {}", self.name, data.uri));
+        }
+
+        fn intercept(&self, req: &ServiceRequest) -> Option<HttpResponse> {
+            (req.path() == "/blocked").then(|| HttpResponse::Forbidden().finish())
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_combinators() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let api = Recorder {
+            name: "api",
+            events: events.clone(),
+        };
+        let all = Recorder {
+            name: "all",
+            events: events.clone(),
+        };
+        let sink = api
+            .filtered(|req| req.path().starts_with("/api"))
+            .mapped(|mut data| {
+                data.uri = data.uri.split('?').next().unwrap_or_default().into();
+                data
+            })
+            .chained(all);
+        assert_eq!(sink.name(), "api > all");
+
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(sink)))
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        for uri in ["/api/users?token=1", "/health"] {
+            test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+        }
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                "api started /api/users?token=1",
+                "all started /api/users?token=1",
+                "api ended /api/users",
+                "all ended /api/users?token=1",
+                "all started /health",
+                "all ended /health",
+            ]
+        );
+
+        // filtered out interceptor doesn't intercept, the chained one does
+        let res =
+            test::call_service(&app, test::TestRequest::get().uri("/blocked").to_request()).await;
+        assert_eq!(res.status(), 403);
+    }
+}
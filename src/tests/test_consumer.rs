@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+    use crate::consumer::{ConsumerResolver, HeaderConsumer, QueryConsumer};
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::dev::ServiceRequest;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    type Consumers = RefCell<Vec<Option<Arc<str>>>>;
+
+    #[derive(Default)]
+    struct ConsumerLog {
+        started: Consumers,
+        ended: Consumers,
+    }
+
+    impl Observer for ConsumerLog {
+        fn on_request_started(&self, data: RequestStartData) {
+            self.started.borrow_mut().push(data.consumer);
+        }
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.ended.borrow_mut().push(data.consumer);
+        }
+    }
+
+    fn consumer_of<R: ConsumerResolver>(resolver: &R, req: TestRequest) -> Option<String> {
+        resolver
+            .resolve(&req.to_srv_request())
+            .map(|consumer| consumer.to_string())
+    }
+
+    #[test]
+    fn test_header_consumer() {
+        let resolver = HeaderConsumer::new("X-Consumer-Id");
+        let req = TestRequest::default().insert_header(("x-consumer-id", " acme "));
+        assert_eq!(consumer_of(&resolver, req), Some("acme".to_string()));
+        let req = TestRequest::default().insert_header(("X-Consumer-Id", ""));
+        assert_eq!(consumer_of(&resolver, req), None);
+        assert_eq!(consumer_of(&resolver, TestRequest::default()), None);
+    }
+
+    #[test]
+    fn test_query_consumer() {
+        let resolver = QueryConsumer::new("client_id");
+        let req = TestRequest::with_uri("/users?page=2&client%5Fid=acme+corp%21&client_id=other");
+        assert_eq!(consumer_of(&resolver, req), Some("acme corp!".to_string()));
+        let req = TestRequest::with_uri("/users?client_id=");
+        assert_eq!(consumer_of(&resolver, req), None);
+        let req = TestRequest::with_uri("/users?client_ids=acme");
+        assert_eq!(consumer_of(&resolver, req), None);
+    }
+
+    #[actix_web::test]
+    async fn test_consumer_in_events() {
+        let log = Rc::new(ConsumerLog::default());
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .consumer_resolver(|req: &ServiceRequest| {
+                            req.headers()
+                                .get("X-Api-Key")
+                                .filter(|key| *key == "k-123")
+                                .map(|_| "acme")
+                        })
+                        .register(log.clone()),
+                )
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        for key in ["k-123", "unknown"] {
+            let req = TestRequest::get().insert_header(("X-Api-Key", key));
+            call_service(&app, req.to_request()).await;
+        }
+
+        let expected: Vec<Option<Arc<str>>> = vec![Some("acme".into()), None];
+        assert_eq!(*log.started.borrow(), expected);
+        assert_eq!(*log.ended.borrow(), expected);
+    }
+}
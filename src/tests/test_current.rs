@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod tests {
+    use crate::current::CurrentRequest;
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::rt::time::sleep;
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    #[derive(Default)]
+    struct IdObserver {
+        ids: RefCell<Vec<Uuid>>,
+    }
+
+    impl Observer for IdObserver {
+        fn on_request_started(&self, data: RequestStartData) {
+            assert!(CurrentRequest::get().is_none());
+            self.ids.borrow_mut().push(data.request_id);
+        }
+
+        fn on_request_ended(&self, _data: RequestEndData) {}
+    }
+
+    /// Deep down in application code, without access to the request.
+    fn describe() -> String {
+        CurrentRequest::with(|current| match current {
+            Some(current) => format!("{} {}", current.request_id(), current.route()),
+            None => "none".to_string(),
+        })
+    }
+
+    #[actix_web::test]
+    async fn test_current_request() {
+        let observer = Rc::new(IdObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .exclude("/excluded")
+                        .register(observer.clone()),
+                )
+                .route(
+                    "/users/{id}",
+                    web::get().to(|path: web::Path<u64>| async move {
+                        // other requests run meanwhile
+                        sleep(Duration::from_millis(*path)).await;
+                        describe()
+                    }),
+                )
+                .route(
+                    "/spawned",
+                    web::get().to(|| async {
+                        let current = CurrentRequest::get().unwrap();
+                        let spawned = actix_web::rt::spawn(current.scope(async { describe() }));
+                        let outside = actix_web::rt::spawn(async { describe() });
+                        HttpResponse::Ok().body(format!(
+                            "{}|{}",
+                            spawned.await.unwrap(),
+                            outside.await.unwrap()
+                        ))
+                    }),
+                )
+                .route("/excluded", web::get().to(|| async { describe() })),
+        )
+        .await;
+
+        let (slow, fast) = futures_util::join!(
+            call_service(&app, TestRequest::with_uri("/users/20").to_request()),
+            call_service(&app, TestRequest::with_uri("/users/1").to_request()),
+        );
+        let (slow, fast) = (read_body(slow).await, read_body(fast).await);
+        let spawned = call_service(&app, TestRequest::with_uri("/spawned").to_request()).await;
+        let spawned = read_body(spawned).await;
+        let excluded = call_service(&app, TestRequest::with_uri("/excluded").to_request()).await;
+        assert_eq!(read_body(excluded).await, "none");
+
+        let ids = observer.ids.borrow();
+        assert_eq!(slow, format!("{} /users/{{id}}", ids[0]));
+        assert_eq!(fast, format!("{} /users/{{id}}", ids[1]));
+        assert_eq!(spawned, format!("{} /spawned|none", ids[2]));
+        assert!(CurrentRequest::get().is_none());
+    }
+}
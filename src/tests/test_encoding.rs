@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "cbor")]
+    use crate::encoding::CborEncoder;
+    use crate::encoding::EventEncoder;
+    #[cfg(feature = "msgpack")]
+    use crate::encoding::MessagePackEncoder;
+    use crate::observer::RequestEndData;
+    use crate::tests::end_data;
+    use actix_web::http::StatusCode;
+
+    fn event() -> RequestEndData {
+        RequestEndData {
+            uri: "/users/1?full=true".into(),
+            status: StatusCode::NOT_FOUND,
+            ..end_data()
+        }
+    }
+
+    fn assert_same(decoded: RequestEndData, data: &RequestEndData) {
+        assert_eq!(decoded.request_id, data.request_id);
+        assert_eq!(decoded.elapsed, data.elapsed);
+        assert_eq!(decoded.uri, data.uri);
+        assert_eq!(decoded.method, data.method);
+        assert_eq!(decoded.status, data.status);
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[actix_web::test]
+    async fn test_message_pack_round_trip() {
+        let data = event();
+        let encoded = MessagePackEncoder.encode(&data).unwrap();
+        assert_eq!(MessagePackEncoder.content_type(), "application/msgpack");
+
+        #[derive(serde::Deserialize)]
+        struct Fields {
+            status: u16,
+            elapsed_us: u64,
+        }
+        let fields: Fields = rmp_serde::from_slice(&encoded).unwrap();
+        assert_eq!(fields.status, 404);
+        assert_eq!(fields.elapsed_us, 1500);
+        assert_same(rmp_serde::from_slice(&encoded).unwrap(), &data);
+    }
+
+    #[cfg(feature = "cbor")]
+    #[actix_web::test]
+    async fn test_cbor_round_trip() {
+        let data = event();
+        let encoded = CborEncoder.encode(&data).unwrap();
+        assert_eq!(CborEncoder.content_type(), "application/cbor");
+
+        let value: ciborium::value::Value = ciborium::de::from_reader(&encoded[..]).unwrap();
+        let fields = value.as_map().unwrap();
+        assert!(fields
+            .iter()
+            .any(|(key, value)| key.as_text() == Some("status")
+                && value.as_integer() == Some(404.into())));
+        assert_same(ciborium::de::from_reader(&encoded[..]).unwrap(), &data);
+    }
+}
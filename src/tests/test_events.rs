@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod tests {
+    use crate::event::RequestEvent;
+    use crate::RequestHook;
+    use actix_web::{test, web, App, HttpResponse};
+    use futures_util::StreamExt;
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn test_event_receiver() {
+        let request_hook = RequestHook::new().exclude("/excluded");
+        let mut receiver = request_hook.event_receiver();
+        let app = test::init_service(
+            App::new()
+                .wrap(request_hook)
+                .default_service(web::to(HttpResponse::Created)),
+        )
+        .await;
+        test::call_service(&app, test::TestRequest::with_uri("/excluded").to_request()).await;
+        let req = test::TestRequest::post()
+            .uri("/users")
+            .set_payload("{}")
+            .to_request();
+        test::call_service(&app, req).await;
+
+        let started = match receiver.try_recv().unwrap() {
+            RequestEvent::Started(started) => started,
+            event => panic!("unexpected {:?}", event),
+        };
+        assert_eq!(&*started.uri, "/users");
+        assert_eq!(started.body, "{}");
+        match receiver.try_recv().unwrap() {
+            RequestEvent::Ended(ended) => {
+                assert_eq!(ended.request_id, started.request_id);
+                assert_eq!(ended.status, 201);
+            }
+            event => panic!("unexpected {:?}", event),
+        }
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_shared_event_sender() {
+        let (sender, mut receiver) = tokio::sync::broadcast::channel(16);
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().event_sender(sender))
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        test::call_service(&app, test::TestRequest::default().to_request()).await;
+
+        assert!(matches!(receiver.try_recv(), Ok(RequestEvent::Started(_))));
+        assert!(matches!(receiver.try_recv(), Ok(RequestEvent::Ended(_))));
+    }
+
+    #[actix_web::test]
+    async fn test_event_stream() {
+        let request_hook = RequestHook::new();
+        let stream = request_hook.event_stream();
+        let app = test::init_service(
+            App::new()
+                .wrap(request_hook)
+                .route("/ok", web::get().to(HttpResponse::Ok))
+                .route("/fail", web::get().to(HttpResponse::BadGateway)),
+        )
+        .await;
+        for uri in &["/ok", "/fail", "/ok"] {
+            test::call_service(&app, test::TestRequest::with_uri(uri).to_request()).await;
+        }
+        drop(app);
+
+        let failed: Vec<Arc<str>> = stream
+            .filter_map(|event| async move {
+                match event {
+                    RequestEvent::Ended(ended) if ended.status.is_server_error() => Some(ended.uri),
+                    _ => None,
+                }
+            })
+            .collect()
+            .await;
+        assert_eq!(failed, vec!["/fail".into()]);
+    }
+
+    #[actix_web::test]
+    async fn test_deadline_exceeded_event() {
+        let request_hook = RequestHook::new().soft_timeout(std::time::Duration::ZERO);
+        let mut receiver = request_hook.event_receiver();
+        let app = test::init_service(
+            App::new()
+                .wrap(request_hook)
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        test::call_service(&app, test::TestRequest::default().to_request()).await;
+
+        let kinds: Vec<_> = std::iter::from_fn(|| receiver.try_recv().ok())
+            .map(|event| event.kind())
+            .collect();
+        assert_eq!(kinds, ["started", "deadline_exceeded", "ended"]);
+    }
+}
@@ -0,0 +1,167 @@
+#[cfg(test)]
+mod tests {
+    use crate::clock::MockClock;
+    use crate::extract::{Correlation, ElapsedSoFar, RequestStart};
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::{test, web, App, HttpMessage, HttpRequest, HttpResponse};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    #[derive(Default)]
+    struct ElapsedObserver {
+        elapsed: RefCell<Vec<Duration>>,
+    }
+
+    impl Observer for ElapsedObserver {
+        fn on_request_started(&self, _data: RequestStartData) {}
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.elapsed.borrow_mut().push(data.elapsed);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_elapsed_so_far() {
+        let clock = MockClock::new();
+        let handler_clock = clock.clone();
+        let observer = Rc::new(ElapsedObserver::default());
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .clock(clock.clone())
+                        .exclude("/excluded")
+                        .register(observer.clone()),
+                )
+                .route(
+                    "/",
+                    web::get().to(move |req: HttpRequest, elapsed: ElapsedSoFar| {
+                        handler_clock.advance(Duration::from_millis(5));
+                        let start = req.extensions().get::<RequestStart>().cloned().unwrap();
+                        let runtime =
+                            format!("{} {}", elapsed.as_millis(), start.elapsed().as_millis());
+                        async move {
+                            HttpResponse::Ok()
+                                .insert_header(("X-Runtime", runtime))
+                                .finish()
+                        }
+                    }),
+                )
+                .route(
+                    "/excluded",
+                    web::get().to(|elapsed: Option<ElapsedSoFar>| async move {
+                        HttpResponse::Ok().body(format!("{:?}", elapsed))
+                    }),
+                ),
+        )
+        .await;
+
+        clock.advance(Duration::from_millis(20));
+        let res = test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+        assert_eq!(res.headers().get("X-Runtime").unwrap(), "0 5");
+        assert_eq!(*observer.elapsed.borrow(), vec![Duration::from_millis(5)]);
+
+        let res =
+            test::call_service(&app, test::TestRequest::get().uri("/excluded").to_request()).await;
+        assert_eq!(test::read_body(res).await, "None");
+    }
+
+    #[derive(Default)]
+    struct IdObserver {
+        ids: RefCell<Vec<Uuid>>,
+    }
+
+    impl Observer for IdObserver {
+        fn on_request_started(&self, data: RequestStartData) {
+            self.ids.borrow_mut().push(data.request_id);
+        }
+
+        fn on_request_ended(&self, _data: RequestEndData) {}
+    }
+
+    #[actix_web::test]
+    async fn test_correlation() {
+        let observer = Rc::new(IdObserver::default());
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(observer.clone()))
+                .default_service(web::to(|correlation: Correlation| async move {
+                    let mut res = HttpResponse::Ok();
+                    for header in correlation.headers() {
+                        res.insert_header(header);
+                    }
+                    res.finish()
+                })),
+        )
+        .await;
+
+        let res = test::call_service(&app, test::TestRequest::default().to_request()).await;
+        let id = observer.ids.borrow()[0];
+        let simple = id.to_simple().to_string();
+        assert_eq!(res.headers().get("x-request-id").unwrap(), &id.to_string());
+        assert_eq!(
+            res.headers().get("traceparent").unwrap(),
+            &format!("00-{}-{}-01", simple, &simple[..16])
+        );
+
+        let req = test::TestRequest::default()
+            .insert_header((
+                "traceparent",
+                "00-0AF7651916CD43DD8448EB211C80319C-b7ad6b7169203331-00",
+            ))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let simple = observer.ids.borrow()[1].to_simple().to_string();
+        assert_eq!(
+            res.headers().get("traceparent").unwrap(),
+            &format!("00-0af7651916cd43dd8448eb211c80319c-{}-00", &simple[..16])
+        );
+    }
+
+    #[cfg(feature = "awc")]
+    #[actix_web::test]
+    async fn test_correlate_client_requests() {
+        use crate::extract::CorrelateRequest;
+
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().exclude("/excluded"))
+                .default_service(web::to(|req: HttpRequest| async move {
+                    let client = awc::Client::default();
+                    let current = client.get("http://localhost/").correlate();
+                    let of = client
+                        .get("http://localhost/")
+                        .correlate_with(&Correlation::of(&req).unwrap());
+                    let headers = |request: &awc::ClientRequest| {
+                        format!(
+                            "{:?} {:?}",
+                            request.headers().get("x-request-id"),
+                            request.headers().get("traceparent")
+                        )
+                    };
+                    assert_eq!(headers(&current), headers(&of));
+                    HttpResponse::Ok().body(headers(&current))
+                })),
+        )
+        .await;
+
+        let req = test::TestRequest::default()
+            .insert_header((
+                "traceparent",
+                "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+            ))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let body = test::read_body(res).await;
+        assert!(std::str::from_utf8(&body)
+            .unwrap()
+            .contains("\"00-0af7651916cd43dd8448eb211c80319c-"));
+
+        // outside of handlers there is nothing to add
+        let request = awc::Client::default().get("http://localhost/").correlate();
+        assert!(request.headers().get("x-request-id").is_none());
+        assert!(Correlation::current().is_none());
+    }
+}
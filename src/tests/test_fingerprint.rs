@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use crate::fingerprint::Fingerprint;
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Default)]
+    struct FingerprintObserver {
+        started: RefCell<Vec<Option<Fingerprint>>>,
+        ended: RefCell<Vec<Option<Fingerprint>>>,
+    }
+
+    impl Observer for FingerprintObserver {
+        fn on_request_started(&self, data: RequestStartData) {
+            self.started.borrow_mut().push(data.fingerprint);
+        }
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.ended.borrow_mut().push(data.fingerprint);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_fingerprint() {
+        let observer = Rc::new(FingerprintObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .fingerprint(["x-signature"])
+                        .register(observer.clone()),
+                )
+                .route("/orders/{id}", web::post().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let requests = [
+            ("/orders/1", "s1", "a", "agent/1"),
+            // same route and body, other user agent which isn't selected
+            ("/orders/2", "s1", "a", "agent/2"),
+            ("/orders/1", "s2", "a", "agent/1"),
+            ("/orders/1", "s1", "b", "agent/1"),
+        ];
+        for (path, signature, body, agent) in requests {
+            let req = TestRequest::post()
+                .uri(path)
+                .insert_header(("x-signature", signature))
+                .insert_header(("user-agent", agent))
+                .set_payload(body)
+                .to_request();
+            call_service(&app, req).await;
+        }
+
+        let started: Vec<_> = observer
+            .started
+            .borrow()
+            .iter()
+            .map(|f| f.unwrap())
+            .collect();
+        assert_eq!(started[0], started[1]);
+        assert_ne!(started[0], started[2]);
+        assert_ne!(started[0], started[3]);
+        assert_ne!(started[2], started[3]);
+        assert_eq!(*observer.ended.borrow(), *observer.started.borrow());
+
+        let hex = started[0].to_string();
+        assert_eq!(hex.len(), 16);
+        assert_eq!(hex.parse::<Fingerprint>().unwrap(), started[0]);
+    }
+
+    #[actix_web::test]
+    async fn test_without_fingerprint() {
+        let observer = Rc::new(FingerprintObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(RequestHook::new().register(observer.clone()))
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        call_service(&app, TestRequest::default().to_request()).await;
+        assert_eq!(*observer.ended.borrow(), vec![None]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[actix_web::test]
+    async fn test_fingerprint_serde() {
+        let fingerprint = Fingerprint::from(0xab);
+        let json = serde_json::to_string(&fingerprint).unwrap();
+        assert_eq!(json, r#""00000000000000ab""#);
+        assert_eq!(
+            serde_json::from_str::<Fingerprint>(&json).unwrap(),
+            fingerprint
+        );
+    }
+}
@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod tests {
+    use crate::observers::fluentd::FluentdObserver;
+    use crate::RequestHook;
+    use actix_web::rt::net::TcpListener;
+    use actix_web::{test, web, App, HttpResponse};
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    type Message = (String, Vec<(u64, Value)>, HashMap<String, Value>);
+
+    #[actix_web::test]
+    async fn test_forwards_and_waits_for_ack() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let server_received = received.clone();
+        actix_web::rt::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut message = Vec::new();
+            let mut buffer = [0; 1024];
+            loop {
+                let read = socket.read(&mut buffer).await.unwrap();
+                message.extend_from_slice(&buffer[..read]);
+                if let Ok(message) = rmp_serde::from_slice::<Message>(&message) {
+                    let chunk = message.2["chunk"].as_str().unwrap().to_string();
+                    let mut ack = HashMap::new();
+                    ack.insert("ack", chunk);
+                    socket
+                        .write_all(&rmp_serde::to_vec_named(&ack).unwrap())
+                        .await
+                        .unwrap();
+                    server_received.lock().unwrap().push(message);
+                    break;
+                }
+            }
+        });
+
+        let errors = Rc::new(Mutex::new(0));
+        let callback_errors = errors.clone();
+        let fluentd = FluentdObserver::tcp(addr.to_string(), "app.requests")
+            .require_ack()
+            .batch(2, Duration::from_secs(60))
+            .on_error(move |_| *callback_errors.lock().unwrap() += 1)
+            .build();
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(fluentd)))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        test::call_service(&app, test::TestRequest::with_uri("/").to_request()).await;
+        test::call_service(&app, test::TestRequest::with_uri("/missing").to_request()).await;
+        actix_web::rt::time::sleep(Duration::from_millis(500)).await;
+
+        assert_eq!(*errors.lock().unwrap(), 0);
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let (tag, entries, options) = &received[0];
+        assert_eq!(tag, "app.requests");
+        assert_eq!(options["size"], 2);
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].0 > 0);
+        assert_eq!(entries[0].1["uri"], "/");
+        assert_eq!(entries[1].1["status"], 404);
+        assert!(entries[1].1["request_id"].is_string());
+    }
+}
@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod tests {
+    use crate::observer::GrpcCall;
+    use crate::util::grpc_call;
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::http::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Default)]
+    struct GrpcObserver {
+        started: RefCell<Vec<Option<GrpcCall>>>,
+        ended: RefCell<Vec<Option<GrpcCall>>>,
+    }
+
+    impl Observer for GrpcObserver {
+        fn on_request_started(&self, data: RequestStartData) {
+            self.started.borrow_mut().push(data.grpc);
+        }
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.ended.borrow_mut().push(data.grpc);
+        }
+    }
+
+    fn call(service: &str, method: &str, status: Option<u32>) -> Option<GrpcCall> {
+        Some(GrpcCall {
+            service: service.into(),
+            method: method.into(),
+            status,
+        })
+    }
+
+    #[actix_web::test]
+    async fn test_grpc() {
+        let observer = Rc::new(GrpcObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(RequestHook::new().register(observer.clone()))
+                .route(
+                    "/helloworld.Greeter/SayHello",
+                    web::post().to(|| async {
+                        // trailers-only response
+                        HttpResponse::Ok()
+                            .content_type("application/grpc")
+                            .insert_header(("grpc-status", "5"))
+                            .finish()
+                    }),
+                )
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+
+        for (uri, content_type) in [
+            ("/helloworld.Greeter/SayHello", "application/grpc"),
+            ("/helloworld.Greeter/SayHi", "application/grpc-web+proto"),
+            ("/helloworld.Greeter/SayHello", "application/json"),
+        ] {
+            let req = TestRequest::post()
+                .uri(uri)
+                .insert_header((CONTENT_TYPE, content_type))
+                .to_request();
+            call_service(&app, req).await;
+        }
+
+        assert_eq!(
+            *observer.started.borrow(),
+            vec![
+                call("helloworld.Greeter", "SayHello", None),
+                call("helloworld.Greeter", "SayHi", None),
+                None
+            ]
+        );
+        assert_eq!(
+            *observer.ended.borrow(),
+            vec![
+                call("helloworld.Greeter", "SayHello", Some(5)),
+                call("helloworld.Greeter", "SayHi", None),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_grpc_call() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(grpc_call("/helloworld.Greeter/SayHello", &headers), None);
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("Application/gRPC-Web-Text; charset=utf-8"),
+        );
+        assert_eq!(
+            grpc_call("/helloworld.Greeter/SayHello", &headers),
+            call("helloworld.Greeter", "SayHello", None)
+        );
+        for path in ["/", "/helloworld.Greeter", "/helloworld.Greeter/", "/a/b/c"] {
+            assert_eq!(grpc_call(path, &headers), None, "{}", path);
+        }
+    }
+}
@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod tests {
+    use crate::observers::har::HarRecorder;
+    use crate::RequestHook;
+    use actix_web::http::Version;
+    use actix_web::{test, web, App, HttpResponse};
+    use serde_json::Value;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[actix_web::test]
+    async fn test_records_har() {
+        let dir = std::env::temp_dir().join(format!("har-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let recorder = HarRecorder::new(&dir)
+            .path_regex("^/orders")
+            .max_body_size(4);
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(recorder)))
+                .default_service(web::to(|| async {
+                    HttpResponse::Ok()
+                        .content_type("text/plain")
+                        .body("Hi there!")
+                })),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/orders?page=2")
+            .insert_header(("authorization", "Bearer secret"))
+            .version(Version::HTTP_2)
+            .set_payload("{}")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(test::read_body(res).await, "Hi there!");
+        let res =
+            test::call_service(&app, test::TestRequest::with_uri("/other").to_request()).await;
+        assert_eq!(test::read_body(res).await, "Hi there!");
+        // written on the blocking thread pool
+        actix_web::rt::time::sleep(Duration::from_millis(200)).await;
+
+        let files: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        let path = files[0].as_ref().unwrap().path();
+        let har: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(har["log"]["version"], "1.2");
+        let entry = &har["log"]["entries"][0];
+        assert_eq!(entry["request"]["method"], "POST");
+        assert_eq!(entry["request"]["httpVersion"], "HTTP/2.0");
+        assert_eq!(entry["response"]["httpVersion"], "HTTP/2.0");
+        assert_eq!(
+            entry["request"]["url"],
+            "http://localhost:8080/orders?page=2"
+        );
+        assert_eq!(
+            entry["request"]["queryString"][0],
+            serde_json::json!({ "name": "page", "value": "2" })
+        );
+        assert!(entry["request"]["headers"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!({ "name": "authorization", "value": "[REDACTED]" })));
+        assert_eq!(entry["request"]["postData"]["text"], "{}");
+        assert_eq!(entry["response"]["status"], 200);
+        assert_eq!(entry["response"]["content"]["size"], 9);
+        assert_eq!(entry["response"]["content"]["text"], "Hi t");
+        assert_eq!(entry["response"]["content"]["mimeType"], "text/plain");
+    }
+}
@@ -0,0 +1,89 @@
+#[cfg(test)]
+mod tests {
+    use crate::observers::honeycomb::HoneycombObserver;
+    use crate::RequestHook;
+    use actix_web::{test, web, App, HttpRequest, HttpResponse, HttpServer};
+    use serde_json::Value;
+    use std::rc::Rc;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[actix_web::test]
+    async fn test_sends_wide_events() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let server_received = received.clone();
+        let server = HttpServer::new(move || {
+            let received = server_received.clone();
+            App::new().route(
+                "/1/batch/{dataset}",
+                web::post().to(
+                    move |req: HttpRequest, dataset: web::Path<String>, body: web::Json<Value>| {
+                        let team = req.headers().get("x-honeycomb-team").cloned();
+                        received.lock().unwrap().push((
+                            dataset.into_inner(),
+                            team,
+                            body.into_inner(),
+                        ));
+                        async { HttpResponse::Ok().finish() }
+                    },
+                ),
+            )
+        })
+        .workers(1)
+        .bind("127.0.0.1:0")
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let honeycomb = HoneycombObserver::builder("secret", "requests")
+            .api_host(format!("http://{}/", addr))
+            .service_name("orders")
+            .batch(2, Duration::from_secs(60))
+            .build()
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(honeycomb)))
+                .route("/users/{id}", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let traced = test::TestRequest::with_uri("/users/1")
+            .insert_header((
+                "traceparent",
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            ))
+            .set_payload("hello")
+            .to_request();
+        test::call_service(&app, traced).await;
+        test::call_service(&app, test::TestRequest::with_uri("/missing").to_request()).await;
+        actix_web::rt::time::sleep(Duration::from_millis(500)).await;
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let (dataset, team, body) = &received[0];
+        assert_eq!(dataset, "requests");
+        assert_eq!(team.as_ref().unwrap(), "secret");
+        let events = body.as_array().unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(events[0]["time"].as_str().unwrap().ends_with('Z'));
+
+        let traced = &events[0]["data"];
+        assert_eq!(traced["name"], "GET /users/{id}");
+        assert_eq!(traced["http.route"], "/users/{id}");
+        assert_eq!(traced["http.status_code"], 200);
+        assert_eq!(traced["http.request_body_size"], 5);
+        assert_eq!(traced["service.name"], "orders");
+        assert_eq!(traced["trace.trace_id"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(traced["trace.parent_id"], "00f067aa0ba902b7");
+        assert_eq!(traced["trace.span_id"].as_str().unwrap().len(), 16);
+
+        let untraced = &events[1]["data"];
+        assert_eq!(untraced["http.status_code"], 404);
+        assert_eq!(untraced["http.uri"], "/missing");
+        assert!(untraced.get("trace.parent_id").is_none());
+        assert_eq!(
+            untraced["trace.trace_id"],
+            untraced["request_id"].as_str().unwrap().replace('-', "")
+        );
+    }
+}
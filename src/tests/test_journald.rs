@@ -0,0 +1,38 @@
+#[cfg(test)]
+mod tests {
+    use crate::observers::journald::JournaldObserver;
+    use crate::tests::end_data;
+    use crate::{Observer, RequestEndData};
+    use actix_web::http::StatusCode;
+    use std::os::unix::net::UnixDatagram;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_writes_end_entry() {
+        let path = std::env::temp_dir().join(format!("journald-{}.sock", Uuid::new_v4()));
+        let journal = UnixDatagram::bind(&path).unwrap();
+        let observer = JournaldObserver::new()
+            .unwrap()
+            .identifier("test-service")
+            .socket_path(&path);
+
+        let request_id = Uuid::new_v4();
+        observer.on_request_ended(RequestEndData {
+            request_id,
+            status: StatusCode::NOT_FOUND,
+            ..end_data()
+        });
+
+        let mut buf = [0; 1024];
+        let size = journal.recv(&mut buf).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            String::from_utf8_lossy(&buf[..size]),
+            format!(
+                "MESSAGE=GET /hey 404 1ms\nPRIORITY=4\nSYSLOG_IDENTIFIER=test-service\nREQUEST_ID={}\nHTTP_METHOD=GET\nHTTP_URI=/hey\nHTTP_STATUS=404\nELAPSED_US=1500\n",
+                request_id
+            )
+        );
+    }
+}
@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod tests {
+    use crate::observers::kafka::{KafkaObserver, KafkaObserverBuilder};
+    use crate::tests::end_data;
+    use crate::{Observer, RequestEndData};
+    use rdkafka::config::ClientConfig;
+    use rdkafka::consumer::{BaseConsumer, Consumer};
+    use rdkafka::mocking::MockCluster;
+    use rdkafka::{Message, Offset, TopicPartitionList};
+    use serde_json::Value;
+    use std::time::Duration;
+
+    /// Publishes `events` with the observer of `builder` to an in-process mock cluster, and returns keys and
+    /// payloads of the messages it received.
+    async fn publish(
+        builder: impl Fn(&str) -> KafkaObserverBuilder,
+        events: Vec<RequestEndData>,
+    ) -> Vec<(String, Vec<u8>)> {
+        let cluster = MockCluster::new(1).unwrap();
+        cluster.create_topic("requests", 1, 1).unwrap();
+        let observer = builder(&cluster.bootstrap_servers()).build().unwrap();
+        let count = events.len();
+        for data in events {
+            observer.on_request_ended(data);
+        }
+        observer.on_shutdown().await;
+
+        let consumer: BaseConsumer = ClientConfig::new()
+            .set("bootstrap.servers", cluster.bootstrap_servers())
+            .set("group.id", "test")
+            .create()
+            .unwrap();
+        let mut partitions = TopicPartitionList::new();
+        partitions
+            .add_partition_offset("requests", 0, Offset::Beginning)
+            .unwrap();
+        consumer.assign(&partitions).unwrap();
+        let mut messages = Vec::new();
+        while messages.len() < count {
+            let message = consumer.poll(Duration::from_secs(10)).unwrap().unwrap();
+            messages.push((
+                String::from_utf8(message.key().unwrap().to_vec()).unwrap(),
+                message.payload().unwrap().to_vec(),
+            ));
+        }
+        messages
+    }
+
+    #[actix_web::test]
+    async fn test_keys_by_request_id() {
+        let data = end_data();
+        let messages = publish(
+            |brokers| KafkaObserver::builder(brokers, "requests"),
+            vec![data.clone()],
+        )
+        .await;
+
+        assert_eq!(messages[0].0, data.request_id.to_string());
+    }
+
+    #[actix_web::test]
+    async fn test_keys_by_tenant() {
+        let messages = publish(
+            |brokers| {
+                KafkaObserver::builder(brokers, "requests")
+                    .key_by(|data| data.consumer.as_deref().unwrap_or("anonymous").to_string())
+            },
+            vec![
+                RequestEndData {
+                    consumer: Some("acme".into()),
+                    ..end_data()
+                },
+                end_data(),
+            ],
+        )
+        .await;
+
+        assert_eq!(messages[0].0, "acme");
+        assert_eq!(messages[1].0, "anonymous");
+    }
+
+    #[actix_web::test]
+    async fn test_serializes_payload() {
+        let data = end_data();
+        let messages = publish(
+            |brokers| KafkaObserver::builder(brokers, "requests"),
+            vec![data.clone()],
+        )
+        .await;
+
+        let payload = &messages[0].1;
+        assert_eq!(*payload, serde_json::to_vec(&data).unwrap());
+        let json: Value = serde_json::from_slice(payload).unwrap();
+        assert_eq!(json["request_id"], data.request_id.to_string());
+        assert_eq!(json["method"], "GET");
+        assert_eq!(json["uri"], "/hey");
+        assert_eq!(json["status"], 200);
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(test)]
+mod tests {
+    use crate::observers::loki::LokiObserver;
+    use crate::RequestHook;
+    use actix_web::{test, web, App, HttpRequest, HttpResponse, HttpServer};
+    use serde_json::{json, Value};
+    use std::rc::Rc;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[actix_web::test]
+    async fn test_pushes_labeled_streams() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let server_received = received.clone();
+        let server = HttpServer::new(move || {
+            let received = server_received.clone();
+            App::new().route(
+                "/loki/api/v1/push",
+                web::post().to(move |req: HttpRequest, body: web::Json<Value>| {
+                    let org_id = req.headers().get("x-scope-orgid").cloned();
+                    received.lock().unwrap().push((org_id, body.into_inner()));
+                    async { HttpResponse::NoContent().finish() }
+                }),
+            )
+        })
+        .workers(1)
+        .bind("127.0.0.1:0")
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let loki = LokiObserver::builder(format!("http://{}/", addr))
+            .label("service", "orders")
+            .route_label()
+            .status_class_label()
+            .org_id("team-a")
+            .batch(3, Duration::from_secs(60))
+            .build()
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(loki)))
+                .route("/users/{id}", web::get().to(HttpResponse::Ok))
+                .route("/fail", web::get().to(HttpResponse::BadGateway)),
+        )
+        .await;
+        for uri in &["/users/1", "/users/2", "/fail"] {
+            test::call_service(&app, test::TestRequest::with_uri(uri).to_request()).await;
+        }
+        actix_web::rt::time::sleep(Duration::from_millis(500)).await;
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let (org_id, body) = &received[0];
+        assert_eq!(org_id.as_ref().unwrap(), "team-a");
+        let streams = body["streams"].as_array().unwrap();
+        assert_eq!(streams.len(), 2);
+        assert_eq!(
+            streams[0]["stream"],
+            json!({ "route": "/fail", "service": "orders", "status_class": "5xx" })
+        );
+        assert_eq!(
+            streams[1]["stream"],
+            json!({ "route": "/users/{id}", "service": "orders", "status_class": "2xx" })
+        );
+        assert_eq!(streams[1]["values"].as_array().unwrap().len(), 2);
+        let line: Value =
+            serde_json::from_str(streams[1]["values"][0][1].as_str().unwrap()).unwrap();
+        assert_eq!(line["uri"], "/users/1");
+    }
+}
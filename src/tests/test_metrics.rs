@@ -0,0 +1,377 @@
+#[cfg(test)]
+mod tests {
+    use crate::clock::MockClock;
+    use crate::consumer::HeaderConsumer;
+    use crate::metrics::{MetricsCollector, RouteKey, OTHER_CONSUMER, OTHER_ROUTE};
+    use crate::RequestHook;
+    use actix_web::dev::Service;
+    use actix_web::http::Method;
+    use actix_web::{test, web, App, HttpRequest, HttpResponse};
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    fn key(method: &str, route: &str) -> RouteKey {
+        RouteKey {
+            method: method.to_string(),
+            route: route.to_string(),
+        }
+    }
+
+    /// Calls `path` of an app with `metrics` registered once per elapsed time in `millis`, advancing a mock clock of
+    /// the hook by it while handling each request.
+    async fn record(metrics: &MetricsCollector, method: Method, path: &str, millis: &[u64]) {
+        let clock = MockClock::new();
+        let handler_clock = clock.clone();
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .clock(clock)
+                        .register(Rc::new(metrics.clone())),
+                )
+                .default_service(web::to(move |req: HttpRequest| {
+                    let ms = req.query_string().parse().unwrap();
+                    handler_clock.advance(Duration::from_millis(ms));
+                    HttpResponse::Ok()
+                })),
+        )
+        .await;
+        for ms in millis {
+            let req = test::TestRequest::default()
+                .method(method.clone())
+                .uri(&format!("{}?{}", path, ms))
+                .to_request();
+            test::call_service(&app, req).await;
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_percentiles() {
+        let metrics = MetricsCollector::new();
+        let millis: Vec<u64> = (1..=100).collect();
+        record(&metrics, Method::GET, "/users", &millis).await;
+        let p50 = metrics.percentile("GET", "/users", 0.5).unwrap();
+        assert!((49..=51).contains(&p50.as_millis()), "{:?}", p50);
+        let summary = metrics.latency("GET", "/users").unwrap();
+        assert_eq!(summary.count, 100);
+        assert!((94..=96).contains(&summary.p95.as_millis()));
+        assert!((99..=101).contains(&summary.max.as_millis()));
+        assert!(metrics.latency("POST", "/users").is_none());
+
+        metrics.reset();
+        assert!(metrics.percentile("GET", "/users", 0.5).is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_clamps_significant_figures() {
+        for significant_figures in [0, 9] {
+            let metrics = MetricsCollector::new().significant_figures(significant_figures);
+            record(&metrics, Method::GET, "/users", &[5]).await;
+            let summary = metrics.latency("GET", "/users").unwrap();
+            assert_eq!(summary.count, 1);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_bounds_routes() {
+        let metrics = MetricsCollector::new().max_routes(2);
+        for route in &["/a", "/b", "/c", "/d", "/a"] {
+            record(&metrics, Method::GET, route, &[1]).await;
+        }
+        let summaries = metrics.summaries();
+        assert_eq!(summaries.len(), 3);
+        assert_eq!(metrics.latency("GET", "/a").unwrap().count, 2);
+        assert_eq!(metrics.latency("GET", OTHER_ROUTE).unwrap().count, 2);
+    }
+
+    #[actix_web::test]
+    async fn test_slowest_routes() {
+        let metrics = MetricsCollector::new();
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(metrics.clone())))
+                .route("/fast/{id}", web::get().to(HttpResponse::Ok))
+                .route(
+                    "/slow",
+                    web::get().to(|| async {
+                        actix_web::rt::time::sleep(Duration::from_millis(20)).await;
+                        HttpResponse::Ok().finish()
+                    }),
+                ),
+        )
+        .await;
+        for uri in &["/fast/1", "/fast/2", "/slow"] {
+            test::call_service(&app, test::TestRequest::with_uri(uri).to_request()).await;
+        }
+
+        let slowest = metrics.slowest(1);
+        assert_eq!(slowest.len(), 1);
+        assert_eq!(slowest[0].0, key("GET", "/slow"));
+        assert!(slowest[0].1.p99 >= Duration::from_millis(20));
+        assert_eq!(metrics.latency("GET", "/fast/{id}").unwrap().count, 2);
+    }
+
+    #[actix_web::test]
+    async fn test_body_sizes() {
+        let metrics = MetricsCollector::new();
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(metrics.clone())))
+                .route(
+                    "/echo",
+                    web::post().to(|body: web::Bytes| async move { [body.clone(), body].concat() }),
+                )
+                .route(
+                    "/stream",
+                    web::get().to(|| async {
+                        HttpResponse::Ok().streaming(futures_util::stream::once(async {
+                            Ok::<_, actix_web::Error>(web::Bytes::from_static(b"chunk"))
+                        }))
+                    }),
+                ),
+        )
+        .await;
+        for size in [10, 100, 1000] {
+            let req = test::TestRequest::post()
+                .uri("/echo")
+                .set_payload(vec![b'a'; size])
+                .to_request();
+            test::call_service(&app, req).await;
+        }
+        test::call_service(&app, test::TestRequest::with_uri("/stream").to_request()).await;
+
+        let request_sizes = metrics.request_sizes("POST", "/echo").unwrap();
+        assert_eq!(request_sizes.count, 3);
+        assert_eq!(request_sizes.p50, 100);
+        assert!((1000..1020).contains(&request_sizes.max));
+        let response_sizes = metrics.response_sizes("POST", "/echo").unwrap();
+        assert_eq!(response_sizes.count, 3);
+        assert_eq!(response_sizes.p50, 200);
+        assert!((2000..2040).contains(&response_sizes.max));
+
+        assert_eq!(metrics.request_sizes("GET", "/stream").unwrap().max, 0);
+        assert!(metrics.response_sizes("GET", "/stream").is_none());
+        assert_eq!(metrics.latency("GET", "/stream").unwrap().count, 1);
+    }
+
+    #[actix_web::test]
+    async fn test_consumers() {
+        let metrics = MetricsCollector::new().max_consumers(2);
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .consumer_resolver(HeaderConsumer::new("X-Consumer"))
+                        .register(Rc::new(metrics.clone())),
+                )
+                .route("/echo", web::post().to(|body: web::Bytes| async { body }))
+                .route("/fail", web::get().to(HttpResponse::InternalServerError)),
+        )
+        .await;
+        for (consumer, uri, size) in [
+            ("acme", "/echo", 10),
+            ("acme", "/echo", 20),
+            ("acme", "/fail", 0),
+            ("globex", "/echo", 5),
+            ("", "/echo", 100),
+            ("initech", "/echo", 1),
+        ] {
+            let mut req = test::TestRequest::post()
+                .uri(uri)
+                .set_payload(vec![b'a'; size]);
+            if uri == "/fail" {
+                req = req.method(actix_web::http::Method::GET);
+            }
+            if !consumer.is_empty() {
+                req = req.insert_header(("X-Consumer", consumer));
+            }
+            test::call_service(&app, req.to_request()).await;
+        }
+
+        let acme = metrics.consumer("acme").unwrap();
+        assert_eq!(acme.latency.count, 3);
+        assert_eq!(acme.server_errors, 1);
+        assert_eq!(acme.request_bytes, 30);
+        assert_eq!(acme.response_bytes, 30);
+        let consumers: Vec<_> = metrics
+            .consumers()
+            .into_iter()
+            .map(|(consumer, summary)| (consumer, summary.latency.count))
+            .collect();
+        assert_eq!(
+            consumers,
+            vec![
+                (OTHER_CONSUMER.to_string(), 1),
+                ("acme".to_string(), 3),
+                ("globex".to_string(), 1),
+            ]
+        );
+
+        let merged = MetricsCollector::new();
+        merged.merge(&metrics.snapshot());
+        merged.merge(&metrics.snapshot());
+        assert_eq!(merged.consumer("acme").unwrap().request_bytes, 60);
+        metrics.reset();
+        assert!(metrics.consumers().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_cancelled_requests() {
+        let metrics = MetricsCollector::new();
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(metrics.clone())))
+                .route(
+                    "/slow",
+                    web::get().to(|| async {
+                        actix_web::rt::time::sleep(Duration::from_secs(10)).await;
+                        HttpResponse::Ok().finish()
+                    }),
+                ),
+        )
+        .await;
+        for _ in 0..3 {
+            let slow = app.call(test::TestRequest::with_uri("/slow").to_request());
+            let timed_out = actix_web::rt::time::timeout(Duration::from_millis(10), slow).await;
+            assert!(timed_out.is_err());
+        }
+
+        assert!(metrics.latency("GET", "/slow").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_apdex() {
+        let metrics = MetricsCollector::new()
+            .apdex_threshold(Duration::from_millis(100))
+            .apdex_route_threshold("/reports", Duration::from_secs(1));
+        record(&metrics, Method::GET, "/users", &[10, 200, 500, 50]).await;
+        record(&metrics, Method::GET, "/reports", &[500]).await;
+
+        assert_eq!(metrics.apdex("GET", "/users"), Some(0.625));
+        assert_eq!(metrics.apdex("GET", "/reports"), Some(1.0));
+        assert_eq!(metrics.apdex("POST", "/users"), None);
+        assert_eq!(metrics.global_apdex(), Some(0.7));
+        metrics.reset();
+        assert_eq!(metrics.global_apdex(), None);
+    }
+
+    #[actix_web::test]
+    async fn test_cache_hits() {
+        let metrics = MetricsCollector::new();
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(metrics.clone())))
+                .route(
+                    "/cached/{hit}",
+                    web::get().to(|hit: web::Path<String>| async move {
+                        HttpResponse::Ok()
+                            .insert_header(("x-cache", hit.into_inner()))
+                            .finish()
+                    }),
+                )
+                .route("/uncached", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        for uri in &[
+            "/cached/HIT",
+            "/cached/HIT",
+            "/cached/HIT",
+            "/cached/MISS",
+            "/uncached",
+        ] {
+            test::call_service(&app, test::TestRequest::with_uri(uri).to_request()).await;
+        }
+
+        let cache = metrics.cache("GET", "/cached/{hit}").unwrap();
+        assert_eq!((cache.hits, cache.misses), (3, 1));
+        assert_eq!(cache.hit_ratio(), Some(0.75));
+        assert_eq!(metrics.cache("GET", "/uncached").unwrap().hit_ratio(), None);
+        assert!(metrics.cache("GET", "/unknown").is_none());
+
+        let merged = MetricsCollector::new();
+        merged.merge(&metrics.snapshot());
+        merged.merge(&metrics.snapshot());
+        assert_eq!(merged.cache("GET", "/cached/{hit}").unwrap().hits, 6);
+    }
+
+    #[actix_web::test]
+    async fn test_duplicates() {
+        let metrics = MetricsCollector::new().count_duplicates(2);
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .fingerprint(["x-signature"])
+                        .register(Rc::new(metrics.clone())),
+                )
+                .route("/webhooks", web::post().to(HttpResponse::Ok)),
+        )
+        .await;
+        for (signature, body) in [
+            ("s1", "a"),
+            ("s1", "a"),
+            ("s2", "a"),
+            ("s1", "b"),
+            ("s1", "a"),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/webhooks")
+                .insert_header(("x-signature", signature))
+                .set_payload(body)
+                .to_request();
+            test::call_service(&app, req).await;
+        }
+        // the first request was forgotten by its last replay, only 2 recent fingerprints are kept
+        assert_eq!(metrics.duplicates("POST", "/webhooks"), Some(1));
+        assert_eq!(metrics.duplicates("GET", "/webhooks"), None);
+    }
+
+    #[actix_web::test]
+    async fn test_snapshot_merge() {
+        let worker1 = MetricsCollector::new();
+        let worker2 = MetricsCollector::new();
+        let millis: Vec<u64> = (1..=50).collect();
+        record(&worker1, Method::GET, "/users", &millis).await;
+        let millis: Vec<u64> = (51..=100).collect();
+        record(&worker2, Method::GET, "/users", &millis).await;
+        record(&worker2, Method::POST, "/users", &[1000]).await;
+
+        let snapshot1 = worker1.snapshot();
+        let snapshot2 = worker2.snapshot();
+        assert_eq!(snapshot2.routes.len(), 2);
+        assert_eq!(snapshot2.routes[0].method, "GET");
+        assert_eq!(snapshot2.routes[0].apdex.total, 50);
+
+        let merged = MetricsCollector::new();
+        merged.merge(&snapshot1);
+        merged.merge(&snapshot2);
+        let summary = merged.latency("GET", "/users").unwrap();
+        assert_eq!(summary.count, 100);
+        assert!(
+            (49..=51).contains(&summary.p50.as_millis()),
+            "{:?}",
+            summary
+        );
+        assert_eq!(merged.latency("POST", "/users").unwrap().count, 1);
+        // 1s is tolerating with the default threshold of 500ms
+        assert_eq!(merged.apdex("POST", "/users"), Some(0.5));
+
+        let mut combined = snapshot1.clone();
+        combined.merge(&snapshot2);
+        assert_eq!(combined, merged.snapshot());
+        assert_eq!(combined.routes[0].apdex.score(), Some(1.0));
+    }
+
+    #[cfg(feature = "serde")]
+    #[actix_web::test]
+    async fn test_snapshot_serde() {
+        use crate::metrics::MetricsSnapshot;
+
+        let metrics = MetricsCollector::new();
+        record(&metrics, Method::GET, "/users", &[3]).await;
+        let snapshot = metrics.snapshot();
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let deserialized: MetricsSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, snapshot);
+    }
+}
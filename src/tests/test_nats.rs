@@ -0,0 +1,21 @@
+#[cfg(test)]
+mod tests {
+    use crate::observers::nats::route_subject;
+
+    #[test]
+    fn test_route_subject() {
+        assert_eq!(route_subject("requests", "/"), "requests");
+        assert_eq!(
+            route_subject("requests", "/users/42?page=1"),
+            "requests.users.42"
+        );
+        assert_eq!(
+            route_subject("requests", "/users/{id}"),
+            "requests.users.{id}"
+        );
+        assert_eq!(
+            route_subject("requests", "/files/a.txt/*/>"),
+            "requests.files.a_txt._._"
+        );
+    }
+}
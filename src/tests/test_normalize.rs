@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod tests {
+    use crate::normalize::PathNormalizer;
+    use crate::RequestHook;
+    use actix_web::{test, web, App, HttpResponse};
+
+    #[actix_web::test]
+    async fn test_normalize() {
+        let normalizer = PathNormalizer::default();
+        assert_eq!(normalizer.normalize("/users/42"), "/users/:id");
+        assert_eq!(
+            normalizer.normalize("/orders/67e55044-10b1-426f-9247-bb680e5fe0c8/items/7/"),
+            "/orders/:uuid/items/:id/"
+        );
+        assert_eq!(
+            normalizer.normalize("/blobs/9f86d081884c7d659a2feaa0c55ad015"),
+            "/blobs/:hash"
+        );
+        // short hex words aren't hashes
+        assert_eq!(normalizer.normalize("/beef/cafe"), "/beef/cafe");
+        assert_eq!(normalizer.normalize("/"), "/");
+
+        let normalizer = PathNormalizer::default()
+            .rule("^v\\d+$", ":version")
+            .rule("^@", ":handle");
+        assert_eq!(
+            normalizer.normalize("/v2/@alice/posts/3"),
+            "/:version/:handle/posts/:id"
+        );
+        let normalizer = PathNormalizer::empty().rule("^[a-z]+$", ":word");
+        assert_eq!(normalizer.normalize("/users/42"), "/:word/42");
+    }
+
+    #[actix_web::test]
+    async fn test_unmatched_paths_are_normalized() {
+        let request_hook = RequestHook::new().normalize_paths(PathNormalizer::default());
+        let stats = request_hook.stats();
+        let app = test::init_service(
+            App::new()
+                .wrap(request_hook)
+                .route("/users/{id}", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        for uri in &["/users/1", "/missing/1", "/missing/2", "/missing/3"] {
+            test::call_service(&app, test::TestRequest::with_uri(uri).to_request()).await;
+        }
+
+        let mut routes: Vec<_> = stats
+            .status_counts()
+            .into_iter()
+            .map(|counts| counts.route)
+            .collect();
+        routes.sort();
+        assert_eq!(routes, vec!["/missing/:id", "/users/{id}"]);
+    }
+}
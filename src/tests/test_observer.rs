@@ -1,6 +1,8 @@
 #[cfg(test)]
 mod tests {
-    use crate::{Observer, RequestEndData, RequestStartData};
+    use crate::observer::CapturedResponse;
+    use crate::tests::end_data;
+    use crate::{Observer, Origin, Outcome, RequestEndData, RequestStartData};
     use actix_http::HttpMessage;
     use actix_web::test;
     use actix_web::web::{Buf, BytesMut};
@@ -54,16 +56,32 @@ mod tests {
         my_observer.on_request_started(RequestStartData {
             req: &service_req,
             request_id,
-            uri: "".to_string(),
-            method: "".to_string(),
+            uri: "".into(),
+            method: "".into(),
             body,
+            origin: Origin::default(),
+            body_sizes: None,
+            body_truncated: false,
+            grpc: None,
+            sampled: true,
+            force_sampled: false,
+            baggage: None,
+            retry_of: None,
+            fingerprint: None,
+            geo: None,
+            security_flags: Default::default(),
+            rendered_body: None,
+            operation_id: None,
+            deprecated: false,
+            consumer: None,
         });
         my_observer.on_request_ended(RequestEndData {
             request_id,
             elapsed: Default::default(),
-            uri: "".to_string(),
-            method: "".to_string(),
+            uri: "".into(),
+            method: "".into(),
             status: Default::default(),
+            ..end_data()
         });
 
         assert_eq!(
@@ -74,4 +92,202 @@ mod tests {
             ]
         )
     }
+
+    #[cfg(feature = "serde")]
+    #[actix_web::test]
+    async fn test_end_data_serde() {
+        let request_id = Uuid::new_v4();
+        let data = RequestEndData {
+            request_id,
+            status: actix_web::http::StatusCode::CREATED,
+            origin: Origin {
+                worker: "actix-rt|system:0|arbiter:0".into(),
+                instance: None,
+            },
+            ..end_data()
+        };
+
+        let json = serde_json::to_string(&data).unwrap();
+        assert_eq!(
+            json,
+            format!(
+                "{{\"request_id\":\"{}\",\"elapsed_us\":1500,\"uri\":\"/hey\",\"method\":\"GET\",\"status\":201,\"origin\":{{\"worker\":\"actix-rt|system:0|arbiter:0\"}}}}",
+                request_id
+            )
+        );
+
+        let deserialized: RequestEndData = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.request_id, request_id);
+        assert_eq!(deserialized.elapsed.as_micros(), 1500);
+        assert_eq!(deserialized.status, actix_web::http::StatusCode::CREATED);
+    }
+
+    #[actix_web::test]
+    async fn test_display_and_redacted_debug() {
+        let request_id = Uuid::nil();
+        let req = test::TestRequest::get()
+            .uri("/users")
+            .insert_header(("Authorization", "Bearer secret"))
+            .insert_header(("Accept", "application/json"))
+            .to_srv_request();
+        let start = RequestStartData {
+            req: &req,
+            request_id,
+            uri: "/users".into(),
+            method: "GET".into(),
+            body: BytesMut::from("password=secret"),
+            origin: Origin::default(),
+            body_sizes: None,
+            body_truncated: false,
+            grpc: None,
+            sampled: true,
+            force_sampled: false,
+            baggage: None,
+            retry_of: None,
+            fingerprint: None,
+            geo: None,
+            security_flags: Default::default(),
+            rendered_body: None,
+            operation_id: None,
+            deprecated: false,
+            consumer: None,
+        };
+        assert_eq!(
+            start.to_string(),
+            "GET /users [00000000-0000-0000-0000-000000000000]"
+        );
+        let debug = format!("{:?}", start);
+        assert!(debug.contains("\"accept\": \"application/json\""));
+        assert!(debug.contains("\"authorization\": \"[REDACTED]\""));
+        assert!(debug.contains("body: <15 bytes>"));
+        assert!(!debug.contains("secret"));
+
+        let mut headers = actix_web::http::header::HeaderMap::new();
+        headers.insert(
+            actix_web::http::header::SET_COOKIE,
+            "session=secret".parse().unwrap(),
+        );
+        let mut end = RequestEndData {
+            request_id,
+            elapsed: std::time::Duration::from_micros(12_400),
+            uri: "/users".into(),
+            request_body: Some("password=secret".into()),
+            response: Some(CapturedResponse {
+                headers,
+                body: "token=secret".into(),
+                rendered: None,
+            }),
+            ..end_data()
+        };
+        assert_eq!(
+            end.to_string(),
+            "GET /users 200 12ms [00000000-0000-0000-0000-000000000000]"
+        );
+        let debug = format!("{:?}", end);
+        assert!(debug.contains("request_body: Some(<15 bytes>)"));
+        assert!(debug.contains("\"set-cookie\": \"[REDACTED]\""));
+        assert!(!debug.contains("secret"));
+
+        end.status = actix_web::http::StatusCode::INTERNAL_SERVER_ERROR;
+        end.outcome = Outcome::Panicked("boom".to_string());
+        end.deadline_exceeded = true;
+        assert_eq!(
+            end.to_string(),
+            "GET /users 500 12ms [00000000-0000-0000-0000-000000000000] panicked deadline exceeded"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_event_conversions() {
+        use crate::event::RequestEvent;
+        use crate::observer::{DeadlineExceededData, RequestCancelledData};
+
+        let request_id = Uuid::new_v4();
+        let cancelled = RequestCancelledData {
+            request_id,
+            elapsed: std::time::Duration::from_millis(3),
+            uri: "/slow".into(),
+            method: "GET".into(),
+            origin: Origin::default(),
+        };
+        let event = RequestEvent::from(cancelled);
+        assert_eq!(event.kind(), "cancelled");
+        assert_eq!(event.request_id(), request_id);
+        assert_eq!(
+            event.to_string(),
+            format!("GET /slow cancelled 3ms [{}]", request_id)
+        );
+
+        let event: RequestEvent = DeadlineExceededData {
+            request_id,
+            deadline: std::time::Duration::from_millis(500),
+            uri: "/slow".into(),
+            method: "GET".into(),
+        }
+        .into();
+        assert_eq!(event.kind(), "deadline_exceeded");
+    }
+
+    #[cfg(feature = "serde")]
+    #[actix_web::test]
+    async fn test_event_serde() {
+        use crate::event::{RequestEvent, RequestStartEvent};
+
+        let request_id = Uuid::new_v4();
+        let event = RequestEvent::from(RequestStartEvent {
+            request_id,
+            uri: "/hey".into(),
+            method: "GET".into(),
+            body: "secret".into(),
+            origin: Origin::default(),
+            body_sizes: None,
+            grpc: None,
+            force_sampled: false,
+            baggage: None,
+            retry_of: None,
+            fingerprint: None,
+            geo: None,
+            security_flags: Default::default(),
+            rendered_body: None,
+            operation_id: None,
+            deprecated: false,
+            consumer: None,
+        });
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(
+            json,
+            format!(
+                "{{\"type\":\"started\",\"request_id\":\"{}\",\"uri\":\"/hey\",\"method\":\"GET\",\"origin\":{{\"worker\":\"\"}}}}",
+                request_id
+            )
+        );
+
+        let ended = format!(
+            "{{\"type\":\"ended\",\"request_id\":\"{}\",\"elapsed_us\":1500,\"uri\":\"/hey\",\"method\":\"GET\",\"status\":201}}",
+            request_id
+        );
+        match serde_json::from_str::<RequestEvent>(&ended).unwrap() {
+            RequestEvent::Ended(data) => {
+                assert_eq!(data.request_id, request_id);
+                assert_eq!(data.status, actix_web::http::StatusCode::CREATED);
+            }
+            event => panic!("unexpected {:?}", event),
+        }
+
+        let upgrade = format!(
+            "{{\"type\":\"upgrade\",\"handshake\":{{\"request_id\":\"{}\",\"elapsed_us\":800,\"uri\":\"/ws\",\"method\":\"GET\",\"status\":101,\"origin\":{{\"worker\":\"\"}}}},\"protocol\":\"websocket\",\"duration_us\":90000000}}",
+            request_id
+        );
+        let event = serde_json::from_str::<RequestEvent>(&upgrade).unwrap();
+        assert_eq!(event.kind(), "upgrade");
+        assert_eq!(event.request_id(), request_id);
+        assert_eq!(
+            event.to_string(),
+            format!(
+                "GET /ws 101 0ms [{}] upgraded to websocket for 90s",
+                request_id
+            )
+        );
+        assert_eq!(serde_json::to_string(&event).unwrap(), upgrade);
+    }
 }
@@ -0,0 +1,178 @@
+#[cfg(test)]
+mod tests {
+    use crate::observer::ApiDeviation;
+    use crate::openapi::OpenApi;
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::http::{Method, StatusCode};
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+    use serde_json::json;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    type Operation = (Option<Arc<str>>, Vec<ApiDeviation>);
+
+    #[derive(Default)]
+    struct ApiObserver {
+        started: RefCell<Vec<Option<Arc<str>>>>,
+        ended: RefCell<Vec<Operation>>,
+    }
+
+    impl Observer for ApiObserver {
+        fn on_request_started(&self, data: RequestStartData) {
+            self.started.borrow_mut().push(data.operation_id);
+        }
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.ended
+                .borrow_mut()
+                .push((data.operation_id, data.api_deviations));
+        }
+    }
+
+    fn api() -> OpenApi {
+        OpenApi::from_value(&json!({
+            "openapi": "3.1.0",
+            "paths": {
+                "/users/{id}": {
+                    "parameters": [{"name": "id", "in": "path", "required": true}],
+                    "get": {
+                        "operationId": "getUser",
+                        "parameters": [
+                            {"$ref": "#/components/parameters/Tenant"},
+                            {"name": "fields", "in": "query"},
+                        ],
+                        "responses": {"200": {}, "4XX": {}},
+                    },
+                    "delete": {"operationId": "deleteUser", "responses": {"default": {}}},
+                },
+                "/users/me": {
+                    "get": {
+                        "operationId": "getMe",
+                        "parameters": [{"name": "expand", "in": "query", "required": true}],
+                        "responses": {"200": {}},
+                    },
+                },
+            },
+            "components": {
+                "parameters": {"Tenant": {"name": "X-Tenant", "in": "header", "required": true}},
+            },
+        }))
+        .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_find() {
+        let api = api();
+        assert_eq!(api.operations().count(), 3);
+        let operation_id = |method: &Method, path: &str| {
+            api.find(method, path)
+                .and_then(|operation| operation.operation_id().cloned())
+        };
+        assert_eq!(
+            operation_id(&Method::GET, "/users/7").as_deref(),
+            Some("getUser")
+        );
+        assert_eq!(
+            operation_id(&Method::GET, "/users/me").as_deref(),
+            Some("getMe")
+        );
+        assert_eq!(
+            operation_id(&Method::DELETE, "/users/me").as_deref(),
+            Some("deleteUser")
+        );
+        assert_eq!(api.find(&Method::GET, "/users/").map(|op| op.path()), None);
+        assert_eq!(
+            api.find(&Method::GET, "/users/7/posts").map(|op| op.path()),
+            None
+        );
+        assert_eq!(
+            api.find(&Method::POST, "/users/7").map(|op| op.path()),
+            None
+        );
+
+        let get_user = api.find(&Method::GET, "/users/7").unwrap();
+        assert!(get_user.expects(StatusCode::NOT_FOUND));
+        assert!(!get_user.expects(StatusCode::INTERNAL_SERVER_ERROR));
+        let non_ascii = OpenApi::from_value(&json!({
+            "openapi": "3.1.0",
+            "paths": {"/a": {"get": {"responses": {"é1": {}}}}},
+        }))
+        .unwrap();
+        let operation = non_ascii.find(&Method::GET, "/a").unwrap();
+        assert!(!operation.expects(StatusCode::OK));
+
+        assert!(OpenApi::from_json(r#"{"paths": {}}"#).is_err());
+        assert!(OpenApi::from_json(r#"{"openapi": "3.0.0"}"#).is_err());
+        let unresolved = OpenApi::from_value(&json!({
+            "openapi": "3.0.0",
+            "paths": {"/a": {"get": {"parameters": [{"$ref": "#/components/parameters/B"}]}}},
+        }));
+        assert_eq!(
+            unresolved.unwrap_err().to_string(),
+            "invalid OpenAPI document: unresolved reference `#/components/parameters/B`"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_openapi() {
+        let observer = Rc::new(ApiObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(RequestHook::new().register(observer.clone()).openapi(api()))
+                .route(
+                    "/users/{id}",
+                    web::get().to(|path: web::Path<String>| async move {
+                        match path.as_str() {
+                            "teapot" => HttpResponse::ImATeapot().finish(),
+                            "error" => HttpResponse::InternalServerError().finish(),
+                            _ => HttpResponse::Ok().finish(),
+                        }
+                    }),
+                )
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+
+        for (uri, tenant) in [
+            ("/users/7", true),
+            ("/users/7?fields=name", false),
+            ("/users/teapot", true),
+            ("/users/error", true),
+            ("/users/me?page=1&exp%61nd", false),
+            ("/orders", true),
+        ] {
+            let mut req = TestRequest::get().uri(uri);
+            if tenant {
+                req = req.insert_header(("x-tenant", "acme"));
+            }
+            call_service(&app, req.to_request()).await;
+        }
+
+        let get_user = Some(Arc::from("getUser"));
+        let ended = observer.ended.borrow();
+        assert_eq!(ended[0], (get_user.clone(), vec![]));
+        assert_eq!(
+            ended[1],
+            (
+                get_user.clone(),
+                vec![ApiDeviation::MissingParameter {
+                    name: "x-tenant".into()
+                }]
+            )
+        );
+        // 4XX is documented
+        assert_eq!(ended[2], (get_user.clone(), vec![]));
+        assert_eq!(
+            ended[3],
+            (
+                get_user.clone(),
+                vec![ApiDeviation::UnexpectedStatus { status: 500 }]
+            )
+        );
+        assert_eq!(ended[4], (Some(Arc::from("getMe")), vec![]));
+        assert_eq!(ended[5], (None, vec![ApiDeviation::UndocumentedEndpoint]));
+        assert_eq!(observer.started.borrow()[0], get_user);
+    }
+}
@@ -0,0 +1,15 @@
+#[cfg(test)]
+mod tests {
+    use crate::observers::postgres::insert_statement;
+
+    #[test]
+    fn test_insert_statement() {
+        assert_eq!(
+            insert_statement("audit.requests"),
+            "INSERT INTO audit.requests (request_id, method, uri, status, elapsed_us) \
+            SELECT request_id::uuid, method, uri, status, elapsed_us \
+            FROM UNNEST($1::text[], $2::text[], $3::text[], $4::int2[], $5::int8[]) \
+            AS t (request_id, method, uri, status, elapsed_us)"
+        );
+    }
+}
@@ -0,0 +1,41 @@
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use actix_web::{test, web, App, HttpResponse};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct IdRecorder {
+        ids: Mutex<Vec<Uuid>>,
+    }
+
+    impl Observer for IdRecorder {
+        fn on_request_started(&self, data: RequestStartData) {
+            self.ids.lock().unwrap().push(data.request_id);
+        }
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.ids.lock().unwrap().push(data.request_id);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_prelude_imports() {
+        let observer = Arc::new(IdRecorder::default());
+        let request_hook: Result<SharedRequestHook, BuildError> = RequestHookBuilder::new()
+            .with_settings(HookSettings::default())
+            .register(observer.clone())
+            .build();
+        let app = test::init_service(
+            App::new()
+                .wrap(request_hook.unwrap())
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        test::call_service(&app, test::TestRequest::get().to_request()).await;
+
+        let ids = observer.ids.lock().unwrap();
+        assert_eq!(ids.len(), 2);
+        assert_eq!(ids[0], ids[1]);
+    }
+}
@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod tests {
+    use crate::encoding::{EventEncoder, ProtobufEncoder};
+    use crate::observer::{Origin, RequestEndData, RequestStartData};
+    use crate::proto::{request_event, RequestEnded, RequestEvent, RequestStarted};
+    use crate::tests::end_data;
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use actix_web::web::BytesMut;
+    use prost::Message;
+    use uuid::Uuid;
+
+    #[actix_web::test]
+    async fn test_converts_start_data() {
+        let req = test::TestRequest::post().uri("/users").to_srv_request();
+        let request_id = Uuid::new_v4();
+        let started = RequestStarted::from(&RequestStartData {
+            req: &req,
+            request_id,
+            uri: "/users".into(),
+            method: "POST".into(),
+            body: BytesMut::from(&b"{}"[..]),
+            origin: Origin::default(),
+            body_sizes: None,
+            body_truncated: false,
+            grpc: None,
+            sampled: true,
+            force_sampled: false,
+            baggage: None,
+            retry_of: None,
+            fingerprint: None,
+            geo: None,
+            security_flags: Default::default(),
+            rendered_body: None,
+            operation_id: None,
+            deprecated: false,
+            consumer: None,
+        });
+        assert_eq!(started.request_id, request_id.to_string());
+        assert_eq!(started.method, "POST");
+        assert_eq!(started.body, b"{}");
+    }
+
+    #[actix_web::test]
+    async fn test_encodes_end_event_envelope() {
+        let data = RequestEndData {
+            uri: "/users/1".into(),
+            status: StatusCode::NOT_FOUND,
+            response_size: Some(9),
+            ..end_data()
+        };
+        let encoded = ProtobufEncoder.encode(&data).unwrap();
+
+        let event = RequestEvent::decode(encoded.as_slice()).unwrap();
+        assert_eq!(
+            event.event,
+            Some(request_event::Event::Ended(RequestEnded {
+                request_id: data.request_id.to_string(),
+                uri: "/users/1".to_string(),
+                method: "GET".to_string(),
+                status: 404,
+                elapsed_us: 1500,
+                response_size: Some(9),
+            }))
+        );
+        // field numbers are part of the schema contract: envelope field 2, status field 4
+        assert_eq!(encoded[0], 2 << 3 | 2);
+        assert!(encoded
+            .windows(3)
+            .any(|field| field == [4 << 3, 0x94, 0x03]));
+    }
+}
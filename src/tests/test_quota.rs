@@ -0,0 +1,187 @@
+#[cfg(test)]
+mod tests {
+    use crate::consumer::HeaderConsumer;
+    use crate::quota::{BillingWindow, QuotaLedger, QuotaUsage, OTHER_CONSUMER};
+    use crate::RequestHook;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    /// Consumer, window start and requests of each usage.
+    fn requests(usage: &[QuotaUsage]) -> Vec<(&str, SystemTime, u64)> {
+        usage
+            .iter()
+            .map(|usage| (usage.consumer.as_str(), usage.window_start, usage.requests))
+            .collect()
+    }
+
+    #[test]
+    fn test_window_bounds() {
+        let hourly = BillingWindow::Every(Duration::from_secs(3600));
+        assert_eq!(hourly.bounds(at(7300)), (at(7200), at(10_800)));
+        // 2024-02-15T12:00:00Z, in a leap year
+        assert_eq!(
+            BillingWindow::Month.bounds(at(1_707_998_400)),
+            (at(1_706_745_600), at(1_709_251_200))
+        );
+        // 2023-12-31T23:59:59Z
+        assert_eq!(
+            BillingWindow::Month.bounds(at(1_704_067_199)),
+            (at(1_701_388_800), at(1_704_067_200))
+        );
+    }
+
+    #[test]
+    fn test_closed_windows() {
+        let day = 86_400;
+        // far in the future, so the current window has ended
+        let start = 100_000 * day;
+        let ledger = QuotaLedger::new(BillingWindow::Every(Duration::from_secs(day)))
+            .max_consumers(2)
+            .max_closed_windows(2);
+        ledger.record_at("acme", at(start), 10, 100);
+        ledger.record_at("acme", at(start + 60), 20, 200);
+        ledger.record_at("globex", at(start + 60), 0, 5);
+        ledger.record_at("initech", at(start + 120), 1, 1);
+        ledger.record_at("acme", at(start + day), 0, 0);
+        ledger.record_at("acme", at(start + 2 * day), 0, 0);
+        ledger.record_at("globex", at(start + 3 * day), 0, 0);
+
+        // the first window was dropped, only 2 ended windows are kept
+        let closed = ledger.take_closed_at(at(start + 3 * day));
+        assert_eq!(
+            requests(&closed),
+            vec![
+                ("acme", at(start + day), 1),
+                ("acme", at(start + 2 * day), 1),
+            ]
+        );
+        assert!(ledger.take_closed_at(at(start + 3 * day)).is_empty());
+        let closed = ledger.take_closed_at(at(start + 4 * day));
+        assert_eq!(requests(&closed), vec![("globex", at(start + 3 * day), 1)]);
+
+        ledger.record_at("acme", at(start + 5 * day), 10, 100);
+        ledger.record_at("acme", at(start + 5 * day), 20, 200);
+        ledger.record_at("globex", at(start + 5 * day), 0, 5);
+        ledger.record_at("initech", at(start + 5 * day), 1, 1);
+        let closed = ledger.take_closed_at(at(start + 6 * day));
+        assert_eq!(
+            closed[0],
+            QuotaUsage {
+                consumer: OTHER_CONSUMER.to_string(),
+                window_start: at(start + 5 * day),
+                window_end: at(start + 6 * day),
+                requests: 1,
+                request_bytes: 1,
+                response_bytes: 1,
+            }
+        );
+        assert_eq!(
+            requests(&closed[1..]),
+            vec![
+                ("acme", at(start + 5 * day), 2),
+                ("globex", at(start + 5 * day), 1),
+            ]
+        );
+        assert_eq!(
+            (closed[1].request_bytes, closed[1].response_bytes),
+            (30, 300)
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_usage_of_requests() {
+        let flushed = Arc::new(Mutex::new(Vec::new()));
+        let sink = flushed.clone();
+        let ledger = QuotaLedger::new(BillingWindow::Every(Duration::from_secs(1)))
+            .on_flush(move |usage| sink.lock().unwrap().extend_from_slice(usage));
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .consumer_resolver(HeaderConsumer::new("X-Consumer"))
+                        .quota_ledger(ledger.clone()),
+                )
+                .route("/echo", web::post().to(|body: web::Bytes| async { body })),
+        )
+        .await;
+        // shortly after a window starts, so the requests end within it
+        let subsec = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .subsec_millis();
+        actix_web::rt::time::sleep(Duration::from_millis(1010 - subsec as u64)).await;
+        for consumer in ["acme", "acme", ""] {
+            let mut req = TestRequest::post().uri("/echo").set_payload("hello");
+            if !consumer.is_empty() {
+                req = req.insert_header(("X-Consumer", consumer));
+            }
+            call_service(&app, req.to_request()).await;
+        }
+
+        let usage = ledger.usage("acme").unwrap();
+        assert_eq!(
+            (usage.requests, usage.request_bytes, usage.response_bytes),
+            (2, 10, 10)
+        );
+        assert_eq!(ledger.snapshot(), vec![usage.clone()]);
+        ledger.flush();
+        assert!(flushed.lock().unwrap().is_empty());
+
+        actix_web::rt::time::sleep(Duration::from_secs(1)).await;
+        ledger.flush();
+        assert_eq!(*flushed.lock().unwrap(), vec![usage]);
+        assert!(ledger.snapshot().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_usage_of_unsampled_requests() {
+        let ledger = QuotaLedger::new(BillingWindow::Every(Duration::from_secs(86_400)));
+        let hook = RequestHook::builder()
+            .sample_rate(0.0)
+            .consumer_resolver(HeaderConsumer::new("X-Consumer"))
+            .quota_ledger(ledger.clone())
+            .build()
+            .unwrap();
+        let app = init_service(
+            App::new()
+                .wrap(hook)
+                .route("/echo", web::post().to(|body: web::Bytes| async { body })),
+        )
+        .await;
+        for _ in 0..3 {
+            let req = TestRequest::post()
+                .uri("/echo")
+                .insert_header(("X-Consumer", "acme"))
+                .set_payload("hello");
+            call_service(&app, req.to_request()).await;
+        }
+
+        let usage = ledger.usage("acme").unwrap();
+        assert_eq!(
+            (usage.requests, usage.request_bytes, usage.response_bytes),
+            (3, 15, 15)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_usage_serde() {
+        let usage = QuotaUsage {
+            consumer: "acme".to_string(),
+            window_start: at(3600),
+            window_end: at(7200),
+            requests: 2,
+            request_bytes: 10,
+            response_bytes: 20,
+        };
+        let json = serde_json::to_value(&usage).unwrap();
+        assert_eq!(json["window_start"], 3600);
+        assert_eq!(serde_json::from_value::<QuotaUsage>(json).unwrap(), usage);
+    }
+}
@@ -0,0 +1,178 @@
+#[cfg(test)]
+mod tests {
+    use crate::observer::{Observer, RequestEndData, RequestStartData};
+    use crate::observers::rate_limit::RateLimitObserver;
+    use crate::RequestHook;
+    use actix_web::http::header::RETRY_AFTER;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, web, App, HttpResponse};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[derive(Default)]
+    struct StatusObserver {
+        started: RefCell<usize>,
+        ended: RefCell<Vec<StatusCode>>,
+    }
+
+    impl Observer for StatusObserver {
+        fn on_request_started(&self, _data: RequestStartData) {
+            *self.started.borrow_mut() += 1;
+        }
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.ended.borrow_mut().push(data.status);
+        }
+    }
+
+    fn api_key(rate_limit: RateLimitObserver) -> RateLimitObserver {
+        rate_limit.key(|req| {
+            req.headers()
+                .get("X-Api-Key")
+                .and_then(|key| key.to_str().ok())
+                .map(str::to_string)
+        })
+    }
+
+    #[actix_web::test]
+    async fn test_rejects_over_limit() {
+        let rate_limit = api_key(RateLimitObserver::new(2, Duration::from_secs(60)));
+        let observer = Rc::new(StatusObserver::default());
+        let handled = Rc::new(RefCell::new(0));
+        let handler_handled = handled.clone();
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(Rc::new(rate_limit.clone()))
+                        .register(observer.clone()),
+                )
+                .default_service(web::to(move || {
+                    *handler_handled.borrow_mut() += 1;
+                    HttpResponse::Ok()
+                })),
+        )
+        .await;
+
+        for key in &["a", "a", "a", "b"] {
+            let req = test::TestRequest::default()
+                .insert_header(("X-Api-Key", *key))
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            if res.status() == StatusCode::TOO_MANY_REQUESTS {
+                // one token per 30 seconds
+                assert_eq!(res.headers().get(RETRY_AFTER).unwrap(), "30");
+            }
+        }
+        test::call_service(&app, test::TestRequest::default().to_request()).await;
+
+        assert_eq!(*handled.borrow(), 4);
+        assert_eq!(rate_limit.limited(), 1);
+        assert_eq!(*observer.started.borrow(), 5);
+        assert_eq!(
+            *observer.ended.borrow(),
+            vec![
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::OK,
+                StatusCode::OK,
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_zero_limit() {
+        let rate_limit = api_key(RateLimitObserver::new(0, Duration::from_secs(60)));
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(rate_limit.clone())))
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        for _ in 0..2 {
+            let req = test::TestRequest::default()
+                .insert_header(("X-Api-Key", "a"))
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+            assert_eq!(res.headers().get(RETRY_AFTER).unwrap(), "60");
+        }
+        assert_eq!(rate_limit.limited(), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_dry_run() {
+        let rate_limit = api_key(RateLimitObserver::new(1, Duration::from_secs(60))).dry_run();
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(rate_limit.clone())))
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        for _ in 0..3 {
+            let req = test::TestRequest::default()
+                .insert_header(("X-Api-Key", "a"))
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+        assert_eq!(rate_limit.limited(), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_per_route() {
+        let rate_limit = RateLimitObserver::new(1, Duration::from_secs(60)).per_route();
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(rate_limit)))
+                .route("/users/{id}", web::get().to(HttpResponse::Ok))
+                .route("/health", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let mut statuses = Vec::new();
+        for uri in &["/users/1", "/users/2", "/health"] {
+            let req = test::TestRequest::with_uri(uri).to_request();
+            statuses.push(test::call_service(&app, req).await.status());
+        }
+        assert_eq!(
+            statuses,
+            vec![
+                StatusCode::OK,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::OK
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_forgets_least_recent_keys() {
+        let rate_limit = api_key(RateLimitObserver::new(1, Duration::from_secs(60)).max_keys(2));
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(rate_limit)))
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        let mut statuses = Vec::new();
+        for key in &["a", "b", "a", "c", "a", "b"] {
+            let req = test::TestRequest::default()
+                .insert_header(("X-Api-Key", *key))
+                .to_request();
+            statuses.push(test::call_service(&app, req).await.status());
+        }
+        // "a" was used more recently than "b" when "c" came
+        assert_eq!(
+            statuses,
+            vec![
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::OK,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::OK,
+            ]
+        );
+    }
+}
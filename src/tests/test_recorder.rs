@@ -0,0 +1,76 @@
+#[cfg(test)]
+mod tests {
+    use crate::observers::recorder::{Recorder, Replayer};
+    use crate::util::sample_point;
+    use crate::RequestHook;
+    use actix_web::{test, web, App, HttpRequest, HttpResponse};
+    use std::rc::Rc;
+    use uuid::Uuid;
+
+    #[actix_web::test]
+    async fn test_records_and_replays() {
+        let path = std::env::temp_dir().join(format!("recording-{}.jsonl", Uuid::new_v4()));
+        let recorder = Recorder::new(&path);
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(recorder)))
+                .default_service(web::to(|req: HttpRequest, body: String| async move {
+                    HttpResponse::Ok().body(format!("{} {} {}", req.method(), req.uri(), body))
+                })),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/orders?page=2")
+            .insert_header(("x-tenant", "eisberg"))
+            .insert_header(("authorization", "Bearer secret"))
+            .set_payload("{\"size\":1}")
+            .to_request();
+        test::call_service(&app, req).await;
+
+        let replayer = Replayer::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let recorded = &replayer.requests()[0];
+        assert_eq!(replayer.requests().len(), 1);
+        assert_eq!(recorded.method, "POST");
+        assert_eq!(recorded.uri, "/orders?page=2");
+        assert!(recorded
+            .headers
+            .contains(&("x-tenant".to_string(), "eisberg".to_string())));
+        assert!(!recorded
+            .headers
+            .iter()
+            .any(|(name, _)| name == "authorization"));
+
+        let replayed = replayer.replay(&app).await;
+        assert_eq!(replayed.len(), 1);
+        let (_, res) = replayed.into_iter().next().unwrap();
+        assert_eq!(
+            test::read_body(res).await,
+            "POST /orders?page=2 {\"size\":1}"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_skips_unsampled_requests() {
+        let path = std::env::temp_dir().join(format!("recording-{}.jsonl", Uuid::new_v4()));
+        let recorder = Recorder::new(&path).sample_rate(0.0);
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(recorder)))
+                .default_service(web::to(|body: String| async move { body })),
+        )
+        .await;
+
+        let req = test::TestRequest::post().set_payload("{}").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(test::read_body(res).await, "{}");
+        assert!(!path.exists());
+    }
+
+    #[actix_web::test]
+    async fn test_sample_point() {
+        assert_eq!(sample_point(&Uuid::nil()), 0.0);
+        assert!(sample_point(&Uuid::from_u128(u128::MAX)) < 1.0);
+    }
+}
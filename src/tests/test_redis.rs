@@ -0,0 +1,63 @@
+#[cfg(test)]
+mod tests {
+    use crate::observers::redis::Target;
+    use crate::tests::end_data;
+    use crate::RequestEndData;
+    use redis::Arg;
+    use uuid::Uuid;
+
+    fn args(target: Target, data: &RequestEndData) -> Vec<String> {
+        target
+            .command(data)
+            .args_iter()
+            .map(|arg| match arg {
+                Arg::Simple(arg) => String::from_utf8_lossy(arg).to_string(),
+                Arg::Cursor => "cursor".to_string(),
+            })
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_stream_command() {
+        let request_id = Uuid::new_v4();
+        let data = RequestEndData {
+            request_id,
+            ..end_data()
+        };
+        let target = Target::Stream {
+            key: "requests".to_string(),
+            max_len: Some(100),
+        };
+
+        assert_eq!(
+            args(target, &data),
+            vec![
+                "XADD".to_string(),
+                "requests".to_string(),
+                "MAXLEN".to_string(),
+                "~".to_string(),
+                "100".to_string(),
+                "*".to_string(),
+                "request_id".to_string(),
+                request_id.to_string(),
+                "method".to_string(),
+                "GET".to_string(),
+                "uri".to_string(),
+                "/hey".to_string(),
+                "status".to_string(),
+                "200".to_string(),
+                "elapsed_us".to_string(),
+                "1500".to_string(),
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_channel_command() {
+        let data = RequestEndData { ..end_data() };
+        let args = args(Target::Channel("requests".to_string()), &data);
+
+        assert_eq!(args[..2], ["PUBLISH".to_string(), "requests".to_string()]);
+        assert_eq!(args[2], serde_json::to_string(&data).unwrap());
+    }
+}
@@ -0,0 +1,142 @@
+#[cfg(test)]
+mod tests {
+    use crate::render::{BodyEncoding, RenderedBody};
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::dev::ServiceRequest;
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::web::Bytes;
+    use actix_web::{web, App, HttpResponse};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Default)]
+    struct RenderObserver {
+        started: RefCell<Vec<Option<RenderedBody>>>,
+        ended: RefCell<Vec<(Option<RenderedBody>, Option<RenderedBody>)>>,
+    }
+
+    impl Observer for RenderObserver {
+        fn on_request_started(&self, data: RequestStartData) {
+            self.started.borrow_mut().push(data.rendered_body);
+        }
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            let response = data.response.and_then(|response| response.rendered);
+            self.ended
+                .borrow_mut()
+                .push((data.rendered_request_body, response));
+        }
+
+        fn capture_response(&self, _req: &ServiceRequest) -> bool {
+            true
+        }
+    }
+
+    fn text_of(rendered: &Option<RenderedBody>) -> Option<&str> {
+        rendered.as_ref().map(|rendered| &*rendered.text)
+    }
+
+    #[actix_web::test]
+    async fn test_render() {
+        let utf8 = RenderedBody::render("héllo".as_bytes(), BodyEncoding::Auto);
+        assert_eq!(utf8.encoding, BodyEncoding::Utf8Lossy);
+        assert_eq!(&*utf8.text, "héllo");
+
+        let binary = b"\xff\x00ab";
+        let lossy = RenderedBody::render(binary, BodyEncoding::Utf8Lossy);
+        assert_eq!(&*lossy.text, "\u{fffd}\0ab");
+        let auto = RenderedBody::render(binary, BodyEncoding::Auto);
+        assert_eq!(auto.encoding, BodyEncoding::Base64);
+        assert_eq!(&*auto.text, "/wBhYg==");
+        for (body, base64) in [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v")] {
+            let rendered = RenderedBody::render(body.as_bytes(), BodyEncoding::Base64);
+            assert_eq!(&*rendered.text, base64);
+        }
+
+        let hex = RenderedBody::render(binary, BodyEncoding::Hex { max_bytes: 3 });
+        assert_eq!(&*hex.text, "ff 00 61");
+        assert!(hex.truncated);
+        let hex = RenderedBody::render(binary, BodyEncoding::Hex { max_bytes: 4 });
+        assert_eq!(&*hex.text, "ff 00 61 62");
+        assert!(!hex.truncated);
+        assert_eq!(
+            format!("{:?}", hex),
+            "RenderedBody { encoding: Hex { max_bytes: 4 }, text: <11 bytes>, truncated: false }"
+        );
+    }
+
+    async fn rendered_of(hook: RequestHook) -> Rc<RenderObserver> {
+        let observer = Rc::new(RenderObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(
+                    hook.register(observer.clone())
+                        .render_bodies(BodyEncoding::Auto),
+                )
+                .default_service(web::to(|body: Bytes| async move {
+                    HttpResponse::Ok().body(if body.is_empty() {
+                        Bytes::new()
+                    } else {
+                        Bytes::from_static(b"\x89PNG")
+                    })
+                })),
+        )
+        .await;
+        let req = TestRequest::post().set_payload("name=Ada").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(read_body(res).await, &b"\x89PNG"[..]);
+        call_service(&app, TestRequest::default().to_request()).await;
+        observer
+    }
+
+    #[actix_web::test]
+    async fn test_render_bodies() {
+        let observer = rendered_of(RequestHook::new()).await;
+        {
+            let started = observer.started.borrow();
+            assert_eq!(text_of(&started[0]), Some("name=Ada"));
+            assert_eq!(started[1], None);
+            let ended = observer.ended.borrow();
+            assert_eq!(ended[0].0, None);
+            assert_eq!(text_of(&ended[0].1), Some("iVBORw=="));
+            assert_eq!(ended[1], (None, None));
+        }
+
+        // teed bodies are captured at the end only
+        let observer = rendered_of(RequestHook::new().tee_body()).await;
+        assert_eq!(observer.started.borrow()[0], None);
+        let ended = observer.ended.borrow();
+        assert_eq!(text_of(&ended[0].0), Some("name=Ada"));
+        assert_eq!(text_of(&ended[0].1), Some("iVBORw=="));
+    }
+
+    #[actix_web::test]
+    async fn test_render_bodies_disabled() {
+        let observer = Rc::new(RenderObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(RequestHook::new().register(observer.clone()))
+                .default_service(web::to(|| async { HttpResponse::Ok().body("ok") })),
+        )
+        .await;
+        let req = TestRequest::post().set_payload("name=Ada").to_request();
+        call_service(&app, req).await;
+        assert_eq!(observer.started.borrow()[0], None);
+        assert_eq!(observer.ended.borrow()[0], (None, None));
+    }
+
+    #[cfg(feature = "serde")]
+    #[actix_web::test]
+    async fn test_rendered_body_serde() {
+        let rendered = RenderedBody::render(b"abc", BodyEncoding::Hex { max_bytes: 2 });
+        let json = serde_json::to_string(&rendered).unwrap();
+        assert_eq!(
+            json,
+            r#"{"encoding":{"hex":{"max_bytes":2}},"text":"61 62","truncated":true}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<RenderedBody>(&json).unwrap(),
+            rendered
+        );
+    }
+}
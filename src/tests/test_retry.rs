@@ -0,0 +1,104 @@
+#[cfg(test)]
+mod tests {
+    use crate::retry::RetryDetector;
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use uuid::Uuid;
+
+    #[derive(Default)]
+    struct RetryObserver {
+        started: RefCell<Vec<(Uuid, Option<Uuid>)>>,
+        ended: RefCell<Vec<Option<Uuid>>>,
+    }
+
+    impl Observer for RetryObserver {
+        fn on_request_started(&self, data: RequestStartData) {
+            self.started
+                .borrow_mut()
+                .push((data.request_id, data.retry_of));
+        }
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.ended.borrow_mut().push(data.retry_of);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_detect_retries() {
+        let retries = RetryDetector::new(2);
+        let observer = Rc::new(RetryObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(observer.clone())
+                        .detect_retries(retries.clone()),
+                )
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let requests = [
+            ("/payments", Some("a")),
+            ("/payments", Some("a")),
+            ("/refunds", Some("a")),
+            ("/payments", None),
+            ("/payments", Some("b")),
+            ("/payments", Some("c")),
+            // forgotten, only the 2 most recent keys are kept
+            ("/payments", Some("a")),
+            ("/payments", Some("c")),
+        ];
+        for (path, key) in requests {
+            let mut req = TestRequest::post().uri(path);
+            if let Some(key) = key {
+                req = req.insert_header(("idempotency-key", key));
+            }
+            call_service(&app, req.to_request()).await;
+        }
+
+        let started = observer.started.borrow();
+        let retry_of: Vec<_> = started.iter().map(|(_, retry_of)| *retry_of).collect();
+        assert_eq!(
+            retry_of,
+            vec![
+                None,
+                Some(started[0].0),
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(started[5].0),
+            ]
+        );
+        assert_eq!(*observer.ended.borrow(), retry_of);
+        assert_eq!(retries.len(), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_retry_header() {
+        let retries = RetryDetector::new(100).header("x-request-key");
+        let observer = Rc::new(RetryObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(observer.clone())
+                        .detect_retries(retries),
+                )
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        for header in ["idempotency-key", "x-request-key", "x-request-key"] {
+            let req = TestRequest::default().insert_header((header, "k1"));
+            call_service(&app, req.to_request()).await;
+        }
+        let ended = observer.ended.borrow();
+        assert_eq!(ended[..2], [None, None]);
+        assert!(ended[2].is_some());
+    }
+}
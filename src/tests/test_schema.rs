@@ -0,0 +1,143 @@
+#[cfg(test)]
+mod tests {
+    use crate::observer::{Observer, RequestEndData, RequestStartData};
+    use crate::observers::schema::{SchemaObserver, SchemaViolation};
+    use crate::RequestHook;
+    use actix_web::http::{Method, StatusCode};
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::web::Bytes;
+    use actix_web::{web, App, HttpResponse};
+    use serde_json::{json, Value};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::{Arc, Mutex};
+    use uuid::Uuid;
+
+    #[derive(Default)]
+    struct IdObserver {
+        ended: RefCell<Vec<(Uuid, StatusCode)>>,
+    }
+
+    impl Observer for IdObserver {
+        fn on_request_started(&self, _data: RequestStartData) {}
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.ended.borrow_mut().push((data.request_id, data.status));
+        }
+    }
+
+    fn users() -> Value {
+        json!({
+            "type": "object",
+            "required": ["name"],
+            "properties": {"name": {"type": "string"}, "age": {"type": "integer"}},
+        })
+    }
+
+    fn post(body: &str) -> TestRequest {
+        TestRequest::post()
+            .uri("/users/7")
+            .insert_header(("content-type", "application/json"))
+            .set_payload(body.to_string())
+    }
+
+    #[actix_web::test]
+    async fn test_report_violations() {
+        let violations = Arc::new(Mutex::new(Vec::<SchemaViolation>::new()));
+        let reported = violations.clone();
+        let schema = SchemaObserver::new()
+            .schema(Method::POST, "/users/{id}", &users())
+            .on_violation(move |violation| reported.lock().unwrap().push(violation.clone()));
+        let observer = Rc::new(IdObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(Rc::new(schema.clone()))
+                        .register(observer.clone()),
+                )
+                .route(
+                    "/users/{id}",
+                    web::post().to(|body: Bytes| async move { HttpResponse::Ok().body(body) }),
+                ),
+        )
+        .await;
+
+        let res = call_service(&app, post(r#"{"name":"Ada","age":36}"#).to_request()).await;
+        assert_eq!(read_body(res).await, r#"{"name":"Ada","age":36}"#);
+        // reported, and the handler still gets the body
+        let res = call_service(&app, post(r#"{"age":"ten"}"#).to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, r#"{"age":"ten"}"#);
+        call_service(&app, post("name=Ada").to_request()).await;
+        // no schema for the method
+        let req = TestRequest::put()
+            .uri("/users/7")
+            .set_payload("{}")
+            .to_request();
+        call_service(&app, req).await;
+
+        assert_eq!(schema.violations(), 2);
+        assert_eq!(schema.violations_of(&Method::POST, "/users/{id}"), 2);
+        assert_eq!(schema.violations_of(&Method::PUT, "/users/{id}"), 0);
+        let violations = violations.lock().unwrap();
+        let ended = observer.ended.borrow();
+        assert_eq!(violations[0].request_id, ended[1].0);
+        assert_eq!(violations[0].method, Method::POST);
+        assert_eq!(violations[0].route, "/users/{id}");
+        let mut errors = violations[0].errors.clone();
+        errors.sort();
+        assert_eq!(
+            errors,
+            vec![
+                r#""name" is a required property"#.to_string(),
+                r#"/age: "ten" is not of type "integer""#.to_string(),
+            ]
+        );
+        assert!(violations[1].errors[0].starts_with("invalid JSON: "));
+    }
+
+    #[actix_web::test]
+    async fn test_reject_violations() {
+        let schema = SchemaObserver::new()
+            .schema(Method::POST, "/users/{id}", &users())
+            .max_errors(1)
+            .reject();
+        let observer = Rc::new(IdObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(Rc::new(schema.clone()))
+                        .register(observer.clone()),
+                )
+                .route(
+                    "/users/{id}",
+                    web::post().to(|| async { HttpResponse::Created().finish() }),
+                ),
+        )
+        .await;
+
+        let res = call_service(&app, post(r#"{"name":"Ada"}"#).to_request()).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let res = call_service(&app, post(r#"{"name":1,"age":"ten"}"#).to_request()).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body: Value = serde_json::from_slice(&read_body(res).await).unwrap();
+        assert_eq!(body["errors"].as_array().unwrap().len(), 1);
+
+        assert_eq!(schema.violations(), 1);
+        let statuses: Vec<_> = observer
+            .ended
+            .borrow()
+            .iter()
+            .map(|(_, status)| *status)
+            .collect();
+        assert_eq!(statuses, vec![StatusCode::CREATED, StatusCode::BAD_REQUEST]);
+    }
+
+    #[actix_web::test]
+    #[should_panic(expected = "invalid JSON Schema")]
+    async fn test_invalid_schema() {
+        SchemaObserver::new().schema(Method::POST, "/users", &json!({"type": 1}));
+    }
+}
@@ -0,0 +1,161 @@
+#[cfg(test)]
+mod tests {
+    use crate::scrub::BodyScrubber;
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::dev::ServiceRequest;
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::web::Bytes;
+    use actix_web::{web, App, HttpResponse};
+    use serde_json::{json, Value};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Default)]
+    struct BodyObserver {
+        started: RefCell<Vec<Bytes>>,
+        ended: RefCell<Vec<(Option<Bytes>, Bytes)>>,
+    }
+
+    impl Observer for BodyObserver {
+        fn on_request_started(&self, data: RequestStartData) {
+            self.started.borrow_mut().push(data.body.freeze());
+        }
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            let response = data
+                .response
+                .map(|response| response.body)
+                .unwrap_or_default();
+            self.ended.borrow_mut().push((data.request_body, response));
+        }
+
+        fn capture_response(&self, _req: &ServiceRequest) -> bool {
+            true
+        }
+    }
+
+    fn scrubber() -> BodyScrubber {
+        BodyScrubber::new()
+            .field("$.password")
+            .field("$.card.number")
+            .field("**.ssn")
+            .field("$.users.*.token")
+    }
+
+    fn json_of(body: &[u8]) -> Value {
+        serde_json::from_slice(body).unwrap()
+    }
+
+    async fn echo(body: Bytes) -> HttpResponse {
+        HttpResponse::Ok()
+            .content_type("application/json")
+            .body(body)
+    }
+
+    #[actix_web::test]
+    async fn test_scrub_bodies() {
+        let observer = Rc::new(BodyObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(observer.clone())
+                        .scrub_bodies(scrubber()),
+                )
+                .default_service(web::to(echo)),
+        )
+        .await;
+
+        let body = json!({
+            "password": "hunter2",
+            "name": "Ada",
+            "card": {"number": "4111111111111111", "expiry": "12/30"},
+            "family": [{"ssn": "078-05-1120", "spouse": {"ssn": "219-09-9999"}}],
+            "users": [{"a": {"token": "t1"}, "b": {"token": "t2", "name": "b"}}],
+            "password_hint": "pet",
+        });
+        let req = TestRequest::post()
+            .insert_header(("content-type", "application/json"))
+            .set_payload(body.to_string())
+            .to_request();
+        let res = call_service(&app, req).await;
+        // the handler and the client see the original body
+        assert_eq!(json_of(&read_body(res).await), body);
+
+        let scrubbed = json!({
+            "password": "[REDACTED]",
+            "name": "Ada",
+            "card": {"number": "[REDACTED]", "expiry": "12/30"},
+            "family": [{"ssn": "[REDACTED]", "spouse": {"ssn": "[REDACTED]"}}],
+            "users": [{"a": {"token": "[REDACTED]"}, "b": {"token": "[REDACTED]", "name": "b"}}],
+            "password_hint": "pet",
+        });
+        assert_eq!(json_of(&observer.started.borrow()[0]), scrubbed);
+        assert_eq!(json_of(&observer.ended.borrow()[0].1), scrubbed);
+    }
+
+    #[actix_web::test]
+    async fn test_unparsable_bodies() {
+        let observer = Rc::new(BodyObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(observer.clone())
+                        .max_body_size(16)
+                        .scrub_bodies(scrubber().replacement(Value::Null)),
+                )
+                .default_service(web::to(|| async { HttpResponse::Ok().body("not json") })),
+        )
+        .await;
+
+        // truncated to 16 bytes, so it can't be scrubbed
+        let req = TestRequest::post()
+            .insert_header(("content-type", "application/json"))
+            .set_payload(r#"{"name": "Ada", "password": "hunter2"}"#)
+            .to_request();
+        call_service(&app, req).await;
+        let req = TestRequest::post()
+            .insert_header(("content-type", "text/plain"))
+            .set_payload("password=1")
+            .to_request();
+        call_service(&app, req).await;
+        let req = TestRequest::post()
+            .set_payload(r#"{"password":1}"#)
+            .to_request();
+        call_service(&app, req).await;
+
+        let started = observer.started.borrow();
+        assert_eq!(started[0], "");
+        assert_eq!(started[1], "password=1");
+        assert_eq!(started[2], r#"{"password":null}"#);
+        assert_eq!(observer.ended.borrow()[0].1, "not json");
+    }
+
+    #[actix_web::test]
+    async fn test_scrub_teed_body() {
+        let observer = Rc::new(BodyObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(observer.clone())
+                        .tee_body()
+                        .scrub_bodies(scrubber()),
+                )
+                .default_service(web::to(echo)),
+        )
+        .await;
+        let req = TestRequest::post()
+            .insert_header(("content-type", "application/json"))
+            .set_payload(r#"{"password":"hunter2"}"#)
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(read_body(res).await, r#"{"password":"hunter2"}"#);
+        let ended = observer.ended.borrow();
+        assert_eq!(
+            ended[0].0.as_deref(),
+            Some(&br#"{"password":"[REDACTED]"}"#[..])
+        );
+    }
+}
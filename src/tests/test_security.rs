@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod tests {
+    use crate::security::{SecurityDetectors, SecurityFlags};
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::http::Method;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Default)]
+    struct FlagObserver {
+        started: RefCell<Vec<SecurityFlags>>,
+        ended: RefCell<Vec<SecurityFlags>>,
+    }
+
+    impl Observer for FlagObserver {
+        fn on_request_started(&self, data: RequestStartData) {
+            self.started.borrow_mut().push(data.security_flags);
+        }
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.ended.borrow_mut().push(data.security_flags);
+        }
+    }
+
+    async fn flags_of(
+        detectors: SecurityDetectors,
+        requests: Vec<TestRequest>,
+    ) -> Vec<SecurityFlags> {
+        let observer = Rc::new(FlagObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(observer.clone())
+                        .security_detectors(detectors),
+                )
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        for req in requests {
+            call_service(&app, req.to_request()).await;
+        }
+        assert_eq!(*observer.started.borrow(), *observer.ended.borrow());
+        let flags = observer.ended.borrow().clone();
+        flags
+    }
+
+    #[actix_web::test]
+    async fn test_security_flags() {
+        let requests = vec![
+            TestRequest::with_uri("/users?page=2&q=o%27brien"),
+            TestRequest::with_uri("/static/..%2f..%2fetc/passwd"),
+            TestRequest::with_uri("/download?file=../../etc/passwd"),
+            TestRequest::with_uri("/users?id=1%27%20OR%20%271%27=%271"),
+            TestRequest::with_uri("/search?q=x+UNION+ALL+SELECT+password+FROM+users"),
+            TestRequest::with_uri("/items?id=5;DROP%20TABLE%20items"),
+            TestRequest::default().insert_header(("x-padding", "a".repeat(9000))),
+            TestRequest::default().method(Method::TRACE),
+            TestRequest::with_uri("/a/%2e%2e/b?id=1+or+1=1")
+                .method(Method::from_bytes(b"PROPFIND").unwrap()),
+        ];
+        let flags = flags_of(SecurityDetectors::new(), requests).await;
+        assert_eq!(
+            flags,
+            vec![
+                SecurityFlags::empty(),
+                SecurityFlags::PATH_TRAVERSAL,
+                SecurityFlags::PATH_TRAVERSAL,
+                SecurityFlags::SQL_INJECTION,
+                SecurityFlags::SQL_INJECTION,
+                SecurityFlags::SQL_INJECTION,
+                SecurityFlags::OVERSIZED_HEADER,
+                SecurityFlags::ABNORMAL_METHOD,
+                SecurityFlags::PATH_TRAVERSAL
+                    | SecurityFlags::SQL_INJECTION
+                    | SecurityFlags::ABNORMAL_METHOD,
+            ]
+        );
+        assert_eq!(
+            flags[8].to_string(),
+            "path_traversal|sql_injection|abnormal_method"
+        );
+        assert_eq!(flags[0].to_string(), "");
+    }
+
+    #[actix_web::test]
+    async fn test_configured_detectors() {
+        let detectors = SecurityDetectors::new()
+            .without(SecurityFlags::SQL_INJECTION)
+            .max_header_size(64)
+            .allowed_methods([Method::GET, Method::TRACE]);
+        let requests = vec![
+            TestRequest::with_uri("/users?id=1%27%20OR%20%271%27=%271"),
+            TestRequest::default().insert_header(("x-padding", "a".repeat(100))),
+            TestRequest::default().method(Method::TRACE),
+            TestRequest::post(),
+        ];
+        let flags = flags_of(detectors, requests).await;
+        assert_eq!(
+            flags,
+            vec![
+                SecurityFlags::empty(),
+                SecurityFlags::OVERSIZED_HEADER,
+                SecurityFlags::empty(),
+                SecurityFlags::ABNORMAL_METHOD,
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_flags() {
+        let mut flags = SecurityFlags::PATH_TRAVERSAL | SecurityFlags::OVERSIZED_HEADER;
+        assert!(flags.contains(SecurityFlags::PATH_TRAVERSAL));
+        assert!(!flags.contains(SecurityFlags::all()));
+        flags.remove(SecurityFlags::PATH_TRAVERSAL);
+        assert_eq!(flags.bits(), 2);
+        assert_eq!(
+            SecurityFlags::from_bits_truncate(0xff),
+            SecurityFlags::all()
+        );
+        assert!(SecurityFlags::default().is_empty());
+    }
+}
@@ -1,11 +1,28 @@
 #[cfg(test)]
 mod tests {
+    use crate::observer::{
+        BodySizes, CapturedResponse, DeadlineExceededData, Origin, Outcome, RequestCancelledData,
+    };
+    use crate::util::ExclusionCache;
     use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_http::error::PayloadError;
+    use actix_http::{BoxedPayloadStream, Payload};
     use actix_web::dev::Service;
-    use actix_web::dev::Transform;
-    use actix_web::test;
+    use actix_web::dev::{ServiceRequest, Transform};
+    use actix_web::http::header::CONTENT_ENCODING;
+    use actix_web::http::Method;
+    use actix_web::middleware::Condition;
+    use actix_web::{test, web, App, Error, HttpRequest, HttpResponse};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use futures_util::future::{Either, LocalBoxFuture};
     use std::cell::RefCell;
+    use std::future::ready;
+    use std::io::Write;
     use std::rc::Rc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
 
     struct MyObserver1 {
         sent_messages: RefCell<Vec<String>>,
@@ -74,8 +91,10 @@ mod tests {
         let result1 = srv.call(service_req1).await;
         assert!(result1.is_ok());
 
-        let result2 = srv.call(service_req2).await;
-        assert!(result2.is_ok());
+        let future2 = srv.call(service_req2);
+        // excluded requests aren't boxed
+        assert!(matches!(future2, Either::Left(_)));
+        assert!(future2.await.is_ok());
 
         let sent_messages = rc.sent_messages.borrow();
         assert_eq!((*sent_messages).len(), 2)
@@ -113,4 +132,928 @@ mod tests {
         assert!(*observer2.started.borrow());
         assert!(*observer2.ended.borrow());
     }
+
+    #[actix_web::test]
+    async fn test_register_and_exclude_all() {
+        let observer1 = Rc::new(MyObserver1::default());
+        let observer2 = Rc::new(MyObserver2::default());
+        let observers: Vec<Rc<dyn Observer>> = vec![observer1.clone(), observer2.clone()];
+        let service = RequestHook::new()
+            .register_all(observers)
+            .exclude_all(["/health", "/metrics"].iter().copied());
+        assert_eq!(service.observer_names().len(), 2);
+
+        let srv = service.new_transform(test::ok_service()).await.unwrap();
+        for uri in ["/health", "/metrics", "/12"] {
+            let result = srv
+                .call(test::TestRequest::with_uri(uri).to_srv_request())
+                .await;
+            assert!(result.is_ok());
+        }
+
+        assert_eq!(observer1.sent_messages.borrow().len(), 2);
+        assert!(*observer2.ended.borrow());
+    }
+
+    #[actix_web::test]
+    async fn test_enabled() {
+        let observer = Rc::new(MyObserver1::default());
+        let enabled = Arc::new(AtomicBool::new(false));
+        let request_hook = RequestHook::new()
+            .enabled(enabled.clone())
+            .register(observer.clone());
+        let stats = request_hook.stats();
+        let srv = request_hook
+            .new_transform(test::ok_service())
+            .await
+            .unwrap();
+
+        srv.call(test::TestRequest::with_uri("/off").to_srv_request())
+            .await
+            .unwrap();
+        enabled.store(true, Ordering::Relaxed);
+        srv.call(test::TestRequest::with_uri("/on").to_srv_request())
+            .await
+            .unwrap();
+
+        assert_eq!(observer.sent_messages.borrow().len(), 2);
+        assert_eq!(stats.snapshot(Duration::from_secs(60)).requests, 1);
+    }
+
+    #[actix_web::test]
+    async fn test_condition() {
+        for enabled in [true, false] {
+            let observer = Rc::new(MyObserver1::default());
+            let app = test::init_service(
+                App::new()
+                    .wrap(Condition::new(
+                        enabled,
+                        RequestHook::new().register(observer.clone()),
+                    ))
+                    .default_service(web::to(HttpResponse::Ok)),
+            )
+            .await;
+            let res = test::call_service(&app, test::TestRequest::default().to_request()).await;
+            assert!(res.status().is_success());
+            assert_eq!(observer.sent_messages.borrow().is_empty(), !enabled);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_captures_response() {
+        struct CapturingObserver {
+            captured: RefCell<Option<CapturedResponse>>,
+        }
+
+        impl Observer for CapturingObserver {
+            fn on_request_started(&self, _data: RequestStartData) {}
+
+            fn on_request_ended(&self, data: RequestEndData) {
+                *self.captured.borrow_mut() = data.response;
+            }
+
+            fn capture_response(&self, req: &ServiceRequest) -> bool {
+                req.path() == "/captured"
+            }
+        }
+
+        let observer = Rc::new(CapturingObserver {
+            captured: RefCell::new(None),
+        });
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .max_body_size(16)
+                        .register(observer.clone()),
+                )
+                .default_service(web::to(|req: HttpRequest| async move {
+                    let mut res = HttpResponse::Ok();
+                    res.insert_header(("x-custom", "yes"));
+                    match req.query_string() {
+                        "stream" => {
+                            res.streaming(futures_util::stream::iter(vec![Ok::<_, Error>(
+                                web::Bytes::from_static(b"Hi there!"),
+                            )]))
+                        }
+                        "large" => res.body("Hi there! Hi there!"),
+                        _ => res.body("Hi there!"),
+                    }
+                })),
+        )
+        .await;
+
+        let res =
+            test::call_service(&app, test::TestRequest::with_uri("/other").to_request()).await;
+        assert_eq!(test::read_body(res).await, "Hi there!");
+        assert!(observer.captured.borrow().is_none());
+
+        let res =
+            test::call_service(&app, test::TestRequest::with_uri("/captured").to_request()).await;
+        assert_eq!(test::read_body(res).await, "Hi there!");
+        let captured = observer.captured.borrow_mut().take().unwrap();
+        assert_eq!(captured.headers.get("x-custom").unwrap(), "yes");
+        assert_eq!(captured.body, "Hi there!");
+
+        // streamed and oversized responses pass through without being buffered
+        for (uri, body) in [
+            ("/captured?stream", "Hi there!"),
+            ("/captured?large", "Hi there! Hi there!"),
+        ] {
+            let res = test::call_service(&app, test::TestRequest::with_uri(uri).to_request()).await;
+            assert_eq!(test::read_body(res).await, body);
+            assert!(observer.captured.borrow().is_none());
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_intercepts_request() {
+        struct MaintenanceObserver;
+
+        impl Observer for MaintenanceObserver {
+            fn on_request_started(&self, _data: RequestStartData) {}
+
+            fn on_request_ended(&self, _data: RequestEndData) {}
+
+            fn intercept(&self, req: &ServiceRequest) -> Option<HttpResponse> {
+                req.path()
+                    .starts_with("/admin")
+                    .then(|| HttpResponse::ServiceUnavailable().body("maintenance"))
+            }
+        }
+
+        let observer = Rc::new(MyObserver1::default());
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(Rc::new(MaintenanceObserver))
+                        .register(observer.clone()),
+                )
+                .default_service(web::to(|| async { "Hi there!" })),
+        )
+        .await;
+
+        let res =
+            test::call_service(&app, test::TestRequest::with_uri("/admin").to_request()).await;
+        assert_eq!(res.status(), 503);
+        assert_eq!(test::read_body(res).await, "maintenance");
+        let res =
+            test::call_service(&app, test::TestRequest::with_uri("/other").to_request()).await;
+        assert_eq!(test::read_body(res).await, "Hi there!");
+        assert_eq!(observer.sent_messages.borrow().len(), 4);
+    }
+
+    #[actix_web::test]
+    async fn test_ticks_observers() {
+        struct TickObserver {
+            ticks: RefCell<Vec<Duration>>,
+        }
+
+        impl Observer for TickObserver {
+            fn on_request_started(&self, _data: RequestStartData) {}
+
+            fn on_request_ended(&self, _data: RequestEndData) {}
+
+            fn on_tick(&self, elapsed: Duration) {
+                self.ticks.borrow_mut().push(elapsed);
+            }
+        }
+
+        let observer = Rc::new(TickObserver {
+            ticks: RefCell::new(vec![]),
+        });
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(observer.clone())
+                        .tick_interval(Duration::from_millis(20)),
+                )
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+
+        actix_web::rt::time::sleep(Duration::from_millis(70)).await;
+        let ticks = observer.ticks.borrow().len();
+        assert!((2..=4).contains(&ticks), "{} ticks", ticks);
+        assert!(observer.ticks.borrow()[0] >= Duration::from_millis(20));
+
+        drop(app);
+        actix_web::rt::time::sleep(Duration::from_millis(50)).await;
+        assert!(observer.ticks.borrow().len() <= ticks + 1);
+    }
+
+    #[actix_web::test]
+    async fn test_body_captured_only_when_wanted() {
+        struct BodyObserver {
+            capture_body: bool,
+            body_sizes: RefCell<Vec<usize>>,
+        }
+
+        impl Observer for BodyObserver {
+            fn on_request_started(&self, data: RequestStartData) {
+                self.body_sizes.borrow_mut().push(data.body.len());
+            }
+
+            fn on_request_ended(&self, _data: RequestEndData) {}
+
+            fn capture_body(&self, _req: &ServiceRequest) -> bool {
+                self.capture_body
+            }
+        }
+
+        let observer = |capture_body| {
+            Rc::new(BodyObserver {
+                capture_body,
+                body_sizes: RefCell::new(vec![]),
+            })
+        };
+        let (ignoring, reading) = (observer(false), observer(true));
+        let echo = || web::to(|body: web::Bytes| async move { body });
+        let app = test::init_service(
+            App::new()
+                .service(
+                    web::scope("/ignoring")
+                        .wrap(RequestHook::new().register(ignoring.clone()))
+                        .default_service(echo()),
+                )
+                .service(
+                    web::scope("/both")
+                        .wrap(
+                            RequestHook::new()
+                                .register(ignoring.clone())
+                                .register(reading.clone()),
+                        )
+                        .default_service(echo()),
+                ),
+        )
+        .await;
+
+        for uri in &["/ignoring", "/both"] {
+            let req = test::TestRequest::post()
+                .uri(uri)
+                .set_payload("Hi there!")
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(test::read_body(res).await, "Hi there!");
+        }
+        // untouched payload reaches the handler, only observers of the second hook see the body
+        assert_eq!(*ignoring.body_sizes.borrow(), vec![0, 9]);
+        assert_eq!(*reading.body_sizes.borrow(), vec![9]);
+    }
+
+    #[actix_web::test]
+    async fn test_generated_by_framework() {
+        #[derive(Default)]
+        struct EndObserver {
+            ended: RefCell<Vec<(u16, bool)>>,
+        }
+
+        impl Observer for EndObserver {
+            fn on_request_started(&self, _data: RequestStartData) {}
+
+            fn on_request_ended(&self, data: RequestEndData) {
+                self.ended
+                    .borrow_mut()
+                    .push((data.status.as_u16(), data.generated_by_framework));
+            }
+        }
+
+        let observer = Rc::new(EndObserver::default());
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(observer.clone()))
+                .service(web::resource("/users").route(web::get().to(HttpResponse::Ok)))
+                .route("/posts", web::get().to(HttpResponse::Ok))
+                .route(
+                    "/missing",
+                    web::get().to(|| async { HttpResponse::NotFound().body("no such user") }),
+                )
+                .service(web::redirect("/users/", "/users"))
+                .service(web::redirect("/old", "/users")),
+        )
+        .await;
+
+        for (method, uri) in [
+            (Method::GET, "/users"),
+            (Method::HEAD, "/users"),
+            (Method::OPTIONS, "/users"),
+            (Method::HEAD, "/posts"),
+            (Method::GET, "/unknown"),
+            (Method::GET, "/missing"),
+            (Method::GET, "/users/"),
+            (Method::GET, "/old"),
+        ] {
+            let req = test::TestRequest::default()
+                .method(method)
+                .uri(uri)
+                .to_request();
+            test::call_service(&app, req).await;
+        }
+
+        assert_eq!(
+            *observer.ended.borrow(),
+            vec![
+                (200, false),
+                (405, true),
+                (405, true),
+                (404, true),
+                (404, true),
+                (404, false),
+                (307, true),
+                (307, false)
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_defer_end() {
+        #[derive(Default)]
+        struct EndObserver {
+            ended: RefCell<Vec<(String, Option<Duration>)>>,
+        }
+
+        impl Observer for EndObserver {
+            fn on_request_started(&self, _data: RequestStartData) {}
+
+            fn on_request_ended(&self, data: RequestEndData) {
+                self.ended
+                    .borrow_mut()
+                    .push((data.uri.to_string(), data.headers_elapsed));
+            }
+        }
+
+        async fn events() -> HttpResponse {
+            let events = vec![Ok::<_, actix_web::Error>(web::Bytes::from_static(
+                b"data: hi\n\n",
+            ))];
+            HttpResponse::Ok()
+                .content_type("text/event-stream; charset=utf-8")
+                .streaming(futures_util::stream::iter(events))
+        }
+
+        let observer = Rc::new(EndObserver::default());
+        let request_hook = RequestHook::new()
+            .register(observer.clone())
+            .defer_end("text/event-stream");
+        let stats = request_hook.stats();
+        let app = test::init_service(
+            App::new()
+                .wrap(request_hook)
+                .route("/events", web::get().to(events))
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let res =
+            test::call_service(&app, test::TestRequest::with_uri("/events").to_request()).await;
+        // the stream is still being sent
+        assert!(observer.ended.borrow().is_empty());
+        assert_eq!(stats.in_flight(), 1);
+        assert_eq!(test::read_body(res).await, "data: hi\n\n");
+        test::call_service(&app, test::TestRequest::with_uri("/plain").to_request()).await;
+
+        let ended = observer.ended.borrow();
+        assert_eq!(ended.len(), 2);
+        assert_eq!(ended[0].0, "/events");
+        assert!(ended[0].1.is_some());
+        assert_eq!(ended[1], ("/plain".to_string(), None));
+        assert_eq!(stats.in_flight(), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_tee_body() {
+        struct TeeObserver {
+            bodies: RefCell<Vec<String>>,
+        }
+
+        impl Observer for TeeObserver {
+            fn on_request_started(&self, data: RequestStartData) {
+                assert!(data.body.is_empty());
+            }
+
+            fn on_request_ended(&self, data: RequestEndData) {
+                let body = data.request_body.unwrap();
+                self.bodies
+                    .borrow_mut()
+                    .push(String::from_utf8_lossy(&body).to_string());
+            }
+        }
+
+        let observer = Rc::new(TeeObserver {
+            bodies: RefCell::new(vec![]),
+        });
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(observer.clone()).tee_body())
+                .route(
+                    "/echo",
+                    web::post().to(|body: web::Bytes| async move { body }),
+                )
+                .route("/ignore", web::post().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/echo")
+            .set_payload("Hi there!")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(test::read_body(res).await, "Hi there!");
+        let req = test::TestRequest::post()
+            .uri("/ignore")
+            .set_payload("unread")
+            .to_request();
+        test::call_service(&app, req).await;
+        assert_eq!(*observer.bodies.borrow(), vec!["Hi there!", ""]);
+    }
+
+    #[actix_web::test]
+    async fn test_exclusion_cache() {
+        let observer = Rc::new(MyObserver1::default());
+        let request_hook = RequestHook::new()
+            .exclude_regex("^/\\d+$")
+            .exclusion_cache_capacity(2)
+            .register(observer.clone());
+        let srv = request_hook
+            .new_transform(test::ok_service())
+            .await
+            .unwrap();
+        for uri in &["/1", "/users", "/1", "/2", "/3", "/users"] {
+            let req = test::TestRequest::with_uri(uri).to_srv_request();
+            assert!(srv.call(req).await.is_ok());
+        }
+        assert_eq!(observer.sent_messages.borrow().len(), 4);
+        assert_eq!(srv.inner.exclusion_cache.borrow().len(), 2);
+
+        let mut cache = ExclusionCache::new(2);
+        assert!(cache.get_or_insert_with("/a", || true));
+        assert!(!cache.get_or_insert_with("/b", || false));
+        // cached decisions aren't decided again
+        assert!(cache.get_or_insert_with("/a", || unreachable!()));
+        // least recently used "/b" is evicted
+        assert!(!cache.get_or_insert_with("/c", || false));
+        assert!(cache.get_or_insert_with("/b", || true));
+        assert!(cache.get_or_insert_with("/a", || true));
+    }
+
+    #[actix_web::test]
+    async fn test_max_body_size() {
+        struct BodyObserver {
+            bodies: RefCell<Vec<String>>,
+        }
+
+        impl Observer for BodyObserver {
+            fn on_request_started(&self, data: RequestStartData) {
+                self.bodies
+                    .borrow_mut()
+                    .push(String::from_utf8_lossy(&data.body).to_string());
+            }
+
+            fn on_request_ended(&self, _data: RequestEndData) {}
+        }
+
+        let observer = Rc::new(BodyObserver {
+            bodies: RefCell::new(vec![]),
+        });
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(observer.clone())
+                        .max_body_size(4),
+                )
+                .default_service(web::to(|body: web::Bytes| async move { body })),
+        )
+        .await;
+
+        for (body, content_length) in &[("Hi!", true), ("Hi there!", true), ("Hi there!", false)] {
+            let mut req = test::TestRequest::post().set_payload(*body).to_request();
+            if !content_length {
+                req.headers_mut()
+                    .remove(actix_web::http::header::CONTENT_LENGTH);
+            }
+            let res = test::call_service(&app, req).await;
+            assert_eq!(test::read_body(res).await, *body);
+        }
+        // oversized content length isn't read, bodies of unknown length are read up to the limit
+        assert_eq!(*observer.bodies.borrow(), vec!["Hi!", "", "Hi t"]);
+    }
+
+    #[actix_web::test]
+    async fn test_decompress_body() {
+        struct BodyObserver {
+            bodies: RefCell<Vec<(String, Option<BodySizes>)>>,
+        }
+
+        impl Observer for BodyObserver {
+            fn on_request_started(&self, data: RequestStartData) {
+                if !data.body.is_empty() {
+                    let body = String::from_utf8_lossy(&data.body).to_string();
+                    self.bodies.borrow_mut().push((body, data.body_sizes));
+                }
+            }
+
+            fn on_request_ended(&self, data: RequestEndData) {
+                if let Some(body) = data.request_body {
+                    let body = String::from_utf8_lossy(&body).to_string();
+                    self.bodies
+                        .borrow_mut()
+                        .push((body, data.request_body_sizes));
+                }
+            }
+        }
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&b"Hi there!".repeat(10)).unwrap();
+        let gzipped = encoder.finish().unwrap();
+        let sizes = BodySizes {
+            raw: gzipped.len(),
+            decoded: 90,
+        };
+
+        for tee in [false, true] {
+            let observer = Rc::new(BodyObserver {
+                bodies: RefCell::new(vec![]),
+            });
+            let hook = RequestHook::new()
+                .register(observer.clone())
+                .decompress_body();
+            let hook = if tee { hook.tee_body() } else { hook };
+            let app = test::init_service(App::new().wrap(hook).default_service(web::to(
+                |body: web::Bytes| async move { body.len().to_string() },
+            )))
+            .await;
+
+            let req = test::TestRequest::post()
+                .insert_header((CONTENT_ENCODING, "gzip"))
+                .set_payload(gzipped.clone())
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            // the handler decodes the body as sent
+            assert_eq!(test::read_body(res).await, "90");
+            let req = test::TestRequest::post().set_payload("plain").to_request();
+            test::call_service(&app, req).await;
+
+            assert_eq!(
+                *observer.bodies.borrow(),
+                vec![
+                    ("Hi there!".repeat(10), Some(sizes)),
+                    ("plain".to_string(), None)
+                ]
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_payload_error() {
+        struct StatusObserver {
+            statuses: RefCell<Vec<u16>>,
+        }
+
+        impl Observer for StatusObserver {
+            fn on_request_started(&self, _data: RequestStartData) {}
+
+            fn on_request_ended(&self, data: RequestEndData) {
+                self.statuses.borrow_mut().push(data.status.as_u16());
+            }
+        }
+
+        let observer = Rc::new(StatusObserver {
+            statuses: RefCell::new(vec![]),
+        });
+        let srv = RequestHook::new()
+            .register(observer.clone())
+            .new_transform(test::ok_service())
+            .await
+            .unwrap();
+
+        let timed_out = std::io::Error::from(std::io::ErrorKind::TimedOut);
+        for err in [PayloadError::Incomplete(None), PayloadError::Io(timed_out)] {
+            let mut req = test::TestRequest::post().to_srv_request();
+            let chunks = vec![Ok(web::Bytes::from_static(b"Hi ")), Err(err)];
+            let payload: BoxedPayloadStream = Box::pin(futures_util::stream::iter(chunks));
+            req.set_payload(Payload::from(payload));
+            assert!(srv.call(req).await.is_err());
+        }
+        assert_eq!(*observer.statuses.borrow(), vec![400, 408]);
+    }
+
+    #[actix_web::test]
+    async fn test_repacked_payload_kind() {
+        async fn payload_kind(payload: web::Payload) -> String {
+            let kind = match payload.into_inner() {
+                Payload::None => "none",
+                Payload::H1 { .. } => "h1",
+                Payload::Stream { .. } => "stream",
+                _ => "h2",
+            };
+            kind.to_string()
+        }
+
+        for tee in &[false, true] {
+            let hook = RequestHook::new().register(Rc::new(MyObserver1::default()));
+            let hook = if *tee { hook.tee_body() } else { hook };
+            let app =
+                test::init_service(App::new().wrap(hook).default_service(web::to(payload_kind)))
+                    .await;
+
+            let (req, _) = test::TestRequest::get()
+                .to_request()
+                .replace_payload(Payload::None);
+            let res = test::call_service(&app, req).await;
+            assert_eq!(test::read_body(res).await, "none");
+
+            let req = test::TestRequest::post().set_payload("Hi!").to_request();
+            let res = test::call_service(&app, req).await;
+            let expected = if *tee { "stream" } else { "h1" };
+            assert_eq!(test::read_body(res).await, expected);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_cancelled_request() {
+        struct CancelObserver {
+            events: RefCell<Vec<String>>,
+        }
+
+        impl Observer for CancelObserver {
+            fn on_request_started(&self, data: RequestStartData) {
+                self.events
+                    .borrow_mut()
+                    .push(format!("started {}", data.uri));
+            }
+
+            fn on_request_ended(&self, data: RequestEndData) {
+                self.events.borrow_mut().push(format!("ended {}", data.uri));
+            }
+
+            fn on_request_cancelled(&self, data: RequestCancelledData) {
+                assert!(data.elapsed >= Duration::from_millis(20));
+                self.events
+                    .borrow_mut()
+                    .push(format!("cancelled {}", data.uri));
+            }
+        }
+
+        let observer = Rc::new(CancelObserver {
+            events: RefCell::new(vec![]),
+        });
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(observer.clone()))
+                .route("/fast", web::get().to(HttpResponse::Ok))
+                .route(
+                    "/slow",
+                    web::get().to(|| async {
+                        actix_web::rt::time::sleep(Duration::from_secs(10)).await;
+                        HttpResponse::Ok().finish()
+                    }),
+                ),
+        )
+        .await;
+
+        test::call_service(&app, test::TestRequest::with_uri("/fast").to_request()).await;
+        let slow = app.call(test::TestRequest::with_uri("/slow").to_request());
+        let timed_out = actix_web::rt::time::timeout(Duration::from_millis(20), slow).await;
+        assert!(timed_out.is_err());
+
+        assert_eq!(
+            *observer.events.borrow(),
+            vec![
+                "started /fast",
+                "ended /fast",
+                "started /slow",
+                "cancelled /slow"
+            ]
+        );
+    }
+
+    async fn panicking() -> HttpResponse {
+        panic!("no response")
+    }
+
+    #[actix_web::test]
+    async fn test_handler_panic() {
+        struct OutcomeObserver {
+            outcomes: RefCell<Vec<(u16, Outcome)>>,
+        }
+
+        impl Observer for OutcomeObserver {
+            fn on_request_started(&self, _data: RequestStartData) {}
+
+            fn on_request_ended(&self, data: RequestEndData) {
+                self.outcomes
+                    .borrow_mut()
+                    .push((data.status.as_u16(), data.outcome));
+            }
+
+            fn on_request_cancelled(&self, _data: RequestCancelledData) {
+                panic!("panicked request must end, not be cancelled");
+            }
+        }
+
+        let observer = Rc::new(OutcomeObserver {
+            outcomes: RefCell::new(vec![]),
+        });
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(observer.clone()))
+                .route("/ok", web::get().to(HttpResponse::Ok))
+                .route("/panic", web::get().to(panicking)),
+        )
+        .await;
+
+        for uri in &["/ok", "/panic"] {
+            let req = test::TestRequest::with_uri(uri).to_request();
+            let res = app.call(req).await;
+            assert_eq!(res.is_err(), *uri == "/panic");
+        }
+        assert_eq!(
+            *observer.outcomes.borrow(),
+            vec![
+                (200, Outcome::Completed),
+                (500, Outcome::Panicked("no response".to_string())),
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_deadline() {
+        struct DeadlineObserver {
+            events: RefCell<Vec<String>>,
+        }
+
+        impl Observer for DeadlineObserver {
+            fn on_request_started(&self, _data: RequestStartData) {}
+
+            fn on_request_ended(&self, data: RequestEndData) {
+                self.events.borrow_mut().push(format!(
+                    "ended {} exceeded {}",
+                    data.uri, data.deadline_exceeded
+                ));
+            }
+
+            fn on_deadline_exceeded(&self, data: DeadlineExceededData) {
+                self.events.borrow_mut().push(format!(
+                    "{} exceeded {}ms",
+                    data.uri,
+                    data.deadline.as_millis()
+                ));
+            }
+        }
+
+        let observer = Rc::new(DeadlineObserver {
+            events: RefCell::new(vec![]),
+        });
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(observer.clone())
+                        .soft_timeout(Duration::from_millis(30))
+                        .deadline_header("x-request-deadline"),
+                )
+                .route("/fast", web::get().to(HttpResponse::Ok))
+                .route(
+                    "/slow",
+                    web::get().to(|| async {
+                        actix_web::rt::time::sleep(Duration::from_millis(60)).await;
+                        HttpResponse::Ok().finish()
+                    }),
+                ),
+        )
+        .await;
+
+        for uri in &["/fast", "/slow"] {
+            test::call_service(&app, test::TestRequest::with_uri(uri).to_request()).await;
+        }
+        // deadline in the past is exceeded right away, even by a fast handler
+        let req = test::TestRequest::with_uri("/fast")
+            .insert_header(("x-request-deadline", "1000"))
+            .to_request();
+        test::call_service(&app, req).await;
+
+        assert_eq!(
+            *observer.events.borrow(),
+            vec![
+                "ended /fast exceeded false",
+                "/slow exceeded 30ms",
+                "ended /slow exceeded true",
+                "/fast exceeded 0ms",
+                "ended /fast exceeded true",
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_shutdown_observers() {
+        struct ShutdownObserver {
+            shutdowns: RefCell<u32>,
+        }
+
+        impl Observer for ShutdownObserver {
+            fn on_request_started(&self, _data: RequestStartData) {}
+
+            fn on_request_ended(&self, _data: RequestEndData) {}
+
+            fn on_shutdown(&self) -> LocalBoxFuture<'static, ()> {
+                *self.shutdowns.borrow_mut() += 1;
+                Box::pin(ready(()))
+            }
+        }
+
+        let observer = Rc::new(ShutdownObserver {
+            shutdowns: RefCell::new(0),
+        });
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(observer.clone()))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        test::call_service(&app, test::TestRequest::get().to_request()).await;
+        assert_eq!(*observer.shutdowns.borrow(), 0);
+
+        drop(app);
+        assert_eq!(*observer.shutdowns.borrow(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_shutdown_awaits_observers() {
+        struct FlushingObserver {
+            sent: Rc<RefCell<u32>>,
+        }
+
+        impl Observer for FlushingObserver {
+            fn on_request_started(&self, _data: RequestStartData) {}
+
+            fn on_request_ended(&self, _data: RequestEndData) {}
+
+            fn on_shutdown(&self) -> LocalBoxFuture<'static, ()> {
+                let sent = self.sent.clone();
+                Box::pin(async move {
+                    actix_web::rt::time::sleep(Duration::from_millis(10)).await;
+                    *sent.borrow_mut() += 1;
+                })
+            }
+        }
+
+        let sent = Rc::new(RefCell::new(0));
+        let hook = RequestHook::new().register(Rc::new(FlushingObserver { sent: sent.clone() }));
+        let app = test::init_service(
+            App::new()
+                .wrap(hook.clone())
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        test::call_service(&app, test::TestRequest::get().to_request()).await;
+
+        hook.shutdown().await;
+        assert_eq!(*sent.borrow(), 1);
+
+        hook.shutdown().await;
+        drop(app);
+        drop(hook);
+        actix_web::rt::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(*sent.borrow(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_origin() {
+        #[derive(Default)]
+        struct OriginObserver {
+            origins: RefCell<Vec<Origin>>,
+        }
+
+        impl Observer for OriginObserver {
+            fn on_request_started(&self, data: RequestStartData) {
+                self.origins.borrow_mut().push(data.origin);
+            }
+
+            fn on_request_ended(&self, data: RequestEndData) {
+                self.origins.borrow_mut().push(data.origin);
+            }
+        }
+
+        let observer = Rc::new(OriginObserver::default());
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .instance_id("pod-1")
+                        .register(observer.clone()),
+                )
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        test::call_service(&app, test::TestRequest::get().to_request()).await;
+
+        let origins = observer.origins.borrow();
+        assert_eq!(origins.len(), 2);
+        assert_eq!(origins[0], origins[1]);
+        assert_eq!(origins[0].instance.as_deref(), Some("pod-1"));
+        assert!(!origins[0].worker.is_empty());
+    }
 }
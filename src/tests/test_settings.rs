@@ -0,0 +1,325 @@
+#[cfg(test)]
+mod tests {
+    use crate::observer::{Observer, RequestEndData, RequestStartData};
+    use crate::settings::{HookSettings, UpstreamSampling};
+    use crate::RequestHook;
+    use actix_web::dev::ServiceRequest;
+    use actix_web::{test, web, App, HttpRequest, HttpResponse};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Default)]
+    struct BodyObserver {
+        started: RefCell<Vec<Vec<u8>>>,
+        ended: RefCell<usize>,
+    }
+
+    impl Observer for BodyObserver {
+        fn on_request_started(&self, data: RequestStartData) {
+            self.started.borrow_mut().push(data.body.to_vec());
+        }
+
+        fn on_request_ended(&self, _data: RequestEndData) {
+            *self.ended.borrow_mut() += 1;
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_sample_rate() {
+        let settings = HookSettings::default();
+        assert_eq!(settings.sample_rate(), 1.0);
+        let observer = Rc::new(BodyObserver::default());
+        let request_hook = RequestHook::new()
+            .with_settings(settings.clone())
+            .register(observer.clone());
+        let stats = request_hook.stats();
+        let app = test::init_service(
+            App::new()
+                .wrap(request_hook)
+                .default_service(web::to(|| async { "ok" })),
+        )
+        .await;
+
+        settings.set_sample_rate(0.0);
+        for _ in 0..10 {
+            test::call_service(&app, test::TestRequest::default().to_request()).await;
+        }
+        assert_eq!(*observer.ended.borrow(), 0);
+        assert!(observer.started.borrow().is_empty());
+
+        settings.set_sample_rate(0.5);
+        for _ in 0..200 {
+            test::call_service(&app, test::TestRequest::default().to_request()).await;
+        }
+        let ended = *observer.ended.borrow();
+        assert_eq!(observer.started.borrow().len(), ended);
+        assert!((50..150).contains(&ended), "{}", ended);
+        assert_eq!(
+            stats.snapshot(std::time::Duration::from_secs(60)).requests,
+            210
+        );
+
+        settings.set_sample_rate(7.0);
+        assert_eq!(settings.sample_rate(), 1.0);
+    }
+
+    #[actix_web::test]
+    async fn test_force_sample() {
+        #[derive(Default)]
+        struct SamplingObserver {
+            started: RefCell<Vec<(String, bool, bool, usize)>>,
+        }
+
+        impl Observer for SamplingObserver {
+            fn on_request_started(&self, data: RequestStartData) {
+                self.started.borrow_mut().push((
+                    data.uri.to_string(),
+                    data.sampled,
+                    data.force_sampled,
+                    data.body.len(),
+                ));
+            }
+
+            fn on_request_ended(&self, _data: RequestEndData) {}
+
+            fn force_sample(&self, req: &ServiceRequest) -> bool {
+                req.path() == "/investigated"
+            }
+        }
+
+        let settings = HookSettings::default();
+        settings.set_sample_rate(0.0);
+        settings.set_capture_body(false);
+        let observer = Rc::new(SamplingObserver::default());
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .with_settings(settings.clone())
+                        .force_sample_header("x-debug-trace", "s3cret")
+                        .register(observer.clone()),
+                )
+                .default_service(web::to(|| async { "ok" })),
+        )
+        .await;
+
+        for (uri, secret) in [
+            ("/debug", Some("s3cret")),
+            ("/guessed", Some("s3cre")),
+            ("/plain", None),
+            ("/investigated", None),
+        ] {
+            let mut req = test::TestRequest::post().uri(uri).set_payload("hello");
+            if let Some(secret) = secret {
+                req = req.insert_header(("x-debug-trace", secret));
+            }
+            test::call_service(&app, req.to_request()).await;
+        }
+        settings.set_sample_rate(1.0);
+        settings.set_capture_body(true);
+        test::call_service(&app, test::TestRequest::with_uri("/sampled").to_request()).await;
+
+        assert_eq!(
+            *observer.started.borrow(),
+            vec![
+                ("/debug".to_string(), false, true, 5),
+                ("/investigated".to_string(), false, true, 5),
+                ("/sampled".to_string(), true, false, 0),
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_verbose_header() {
+        const TOKEN: &str = "0123456789abcdef";
+
+        #[derive(Default)]
+        struct QuietObserver {
+            started: RefCell<Vec<(bool, usize)>>,
+            responses: RefCell<Vec<Option<String>>>,
+        }
+
+        impl Observer for QuietObserver {
+            fn on_request_started(&self, data: RequestStartData) {
+                assert!(!data.req.headers().contains_key("x-verbose"));
+                self.started
+                    .borrow_mut()
+                    .push((data.force_sampled, data.body.len()));
+            }
+
+            fn on_request_ended(&self, data: RequestEndData) {
+                self.responses.borrow_mut().push(
+                    data.response
+                        .map(|res| String::from_utf8_lossy(&res.body).into_owned()),
+                );
+            }
+
+            fn capture_body(&self, _req: &ServiceRequest) -> bool {
+                false
+            }
+        }
+
+        let settings = HookSettings::default();
+        settings.set_capture_body(false);
+        let observer = Rc::new(QuietObserver::default());
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .with_settings(settings.clone())
+                        .verbose_header("x-verbose", TOKEN)
+                        .register(observer.clone()),
+                )
+                .default_service(web::to(|req: HttpRequest, body: String| async move {
+                    // the token never reaches the handler
+                    assert!(!req.headers().contains_key("x-verbose"));
+                    body
+                })),
+        )
+        .await;
+
+        for token in [Some(TOKEN), Some("0123456789abcdeF"), None] {
+            let mut req = test::TestRequest::post().set_payload("hello");
+            if let Some(token) = token {
+                req = req.insert_header(("x-verbose", token));
+            }
+            let res = test::call_service(&app, req.to_request()).await;
+            assert_eq!(test::read_body(res).await, "hello");
+        }
+        settings.set_sample_rate(0.0);
+        let req = test::TestRequest::post()
+            .insert_header(("x-verbose", TOKEN))
+            .set_payload("hello");
+        test::call_service(&app, req.to_request()).await;
+
+        assert_eq!(
+            *observer.started.borrow(),
+            vec![(true, 5), (false, 0), (false, 0), (true, 5)]
+        );
+        assert_eq!(
+            *observer.responses.borrow(),
+            vec![
+                Some("hello".to_string()),
+                None,
+                None,
+                Some("hello".to_string())
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    #[should_panic(expected = "at least 16 bytes")]
+    async fn test_short_verbose_token() {
+        let _ = RequestHook::new().verbose_header("x-verbose", "secret");
+    }
+
+    #[actix_web::test]
+    async fn test_upstream_sampling() {
+        const SAMPLED: &str = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+        const SKIPPED: &str = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-00";
+
+        #[derive(Default)]
+        struct SamplingObserver {
+            events: RefCell<Vec<String>>,
+        }
+
+        impl Observer for SamplingObserver {
+            fn on_request_started(&self, data: RequestStartData) {
+                self.events
+                    .borrow_mut()
+                    .push(format!("started {} {}", data.uri, data.sampled));
+            }
+
+            fn on_request_ended(&self, data: RequestEndData) {
+                self.events.borrow_mut().push(format!(
+                    "ended {} {}",
+                    data.uri,
+                    data.status.as_u16()
+                ));
+            }
+        }
+
+        for (mode, rate, expected) in [
+            (UpstreamSampling::Ignore, 1.0, 8),
+            (UpstreamSampling::Ignore, 0.0, 0),
+            (UpstreamSampling::Respect, 1.0, 4),
+            (UpstreamSampling::Respect, 0.0, 4),
+            (UpstreamSampling::AlwaysOnError, 0.0, 6),
+        ] {
+            let settings = HookSettings::default();
+            settings.set_sample_rate(rate);
+            let observer = Rc::new(SamplingObserver::default());
+            let app = test::init_service(
+                App::new()
+                    .wrap(
+                        RequestHook::new()
+                            .with_settings(settings)
+                            .upstream_sampling(mode)
+                            .register(observer.clone()),
+                    )
+                    .route("/fail", web::get().to(HttpResponse::InternalServerError))
+                    .default_service(web::to(HttpResponse::Ok)),
+            )
+            .await;
+
+            for (uri, traceparent, b3) in [
+                ("/sampled", Some(SAMPLED), None),
+                ("/skipped", Some(SKIPPED), None),
+                ("/b3", None, Some("1")),
+                ("/fail", Some(SKIPPED), Some("d")),
+            ] {
+                let mut req = test::TestRequest::with_uri(uri);
+                if let Some(traceparent) = traceparent {
+                    req = req.insert_header(("traceparent", traceparent));
+                }
+                if let Some(b3) = b3 {
+                    req = req.insert_header(("b3", b3));
+                }
+                test::call_service(&app, req.to_request()).await;
+            }
+
+            let events = observer.events.borrow();
+            assert_eq!(events.len(), expected, "{:?} {:?}", mode, events);
+            if mode == UpstreamSampling::AlwaysOnError {
+                assert_eq!(
+                    *events,
+                    vec![
+                        "started /sampled true",
+                        "ended /sampled 200",
+                        "started /b3 true",
+                        "ended /b3 200",
+                        "started /fail false",
+                        "ended /fail 500",
+                    ]
+                );
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_capture_body() {
+        let settings = HookSettings::default();
+        let observer = Rc::new(BodyObserver::default());
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .with_settings(settings.clone())
+                        .register(observer.clone()),
+                )
+                .default_service(web::to(|body: String| async move { body })),
+        )
+        .await;
+
+        for capture_body in [true, false] {
+            settings.set_capture_body(capture_body);
+            let req = test::TestRequest::post().set_payload("hello").to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(test::read_body(res).await, "hello");
+        }
+        assert_eq!(
+            *observer.started.borrow(),
+            vec![b"hello".to_vec(), Vec::new()]
+        );
+    }
+}
@@ -0,0 +1,160 @@
+#[cfg(test)]
+mod tests {
+    use crate::extract::RequestStart;
+    use crate::observers::shadow::{ShadowObserver, X_SHADOW_REQUEST};
+    use crate::RequestHook;
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::{web, App, HttpMessage, HttpRequest, HttpResponse, HttpServer};
+    use std::rc::Rc;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    type Mirrored = (String, String, Option<String>, Option<String>, web::Bytes);
+
+    #[actix_web::test]
+    async fn test_mirrors_requests() {
+        let mirrored = Arc::new(Mutex::new(Vec::<Mirrored>::new()));
+        let server_mirrored = mirrored.clone();
+        let server = HttpServer::new(move || {
+            let mirrored = server_mirrored.clone();
+            App::new().default_service(web::to(move |req: HttpRequest, body: web::Bytes| {
+                let header = |name: &str| {
+                    req.headers()
+                        .get(name)
+                        .map(|value| value.to_str().unwrap().to_string())
+                };
+                mirrored.lock().unwrap().push((
+                    req.method().to_string(),
+                    req.uri().to_string(),
+                    header("x-tenant"),
+                    header("authorization"),
+                    body,
+                ));
+                async { HttpResponse::InternalServerError().finish() }
+            }))
+        })
+        .workers(1)
+        .bind("127.0.0.1:0")
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let shadow = Rc::new(
+            ShadowObserver::builder(format!("http://{}/", addr))
+                .skip_header("Authorization")
+                .build(),
+        );
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(shadow.clone())
+                        .max_body_size(16),
+                )
+                .default_service(web::to(|| async { HttpResponse::Ok().body("app") })),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .uri("/users?active=true")
+            .insert_header(("x-tenant", "acme"))
+            .insert_header(("authorization", "Bearer secret"))
+            .set_payload("name=Ada")
+            .to_request();
+        let res = call_service(&app, req).await;
+        // the client only sees the app's response
+        assert_eq!(read_body(res).await, "app");
+        // a body too large to be captured
+        let req = TestRequest::put()
+            .uri("/users/7")
+            .set_payload("x".repeat(32))
+            .to_request();
+        call_service(&app, req).await;
+        // a chunked body cut at the limit
+        let mut req = TestRequest::put()
+            .uri("/users/8")
+            .insert_header(("transfer-encoding", "chunked"))
+            .set_payload("x".repeat(32))
+            .to_request();
+        req.headers_mut().remove("content-length");
+        call_service(&app, req).await;
+        actix_web::rt::time::sleep(Duration::from_millis(500)).await;
+
+        let mirrored = mirrored.lock().unwrap();
+        assert_eq!(
+            *mirrored,
+            vec![(
+                "POST".to_string(),
+                "/users?active=true".to_string(),
+                Some("acme".to_string()),
+                None,
+                web::Bytes::from_static(b"name=Ada"),
+            )]
+        );
+        assert_eq!(shadow.sent(), 1);
+        assert_eq!(shadow.dropped(), 2);
+        assert_eq!(shadow.failed(), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_reports_errors() {
+        let errors = Rc::new(std::cell::Cell::new(0));
+        let reported = errors.clone();
+        let shadow = Rc::new(
+            // nothing listens on port 9 of localhost
+            ShadowObserver::builder("http://127.0.0.1:9")
+                .on_error(move |err| {
+                    assert!(err.to_string().starts_with("shadow request failed"));
+                    reported.set(reported.get() + 1);
+                })
+                .build(),
+        );
+        let app = init_service(
+            App::new()
+                .wrap(RequestHook::new().register(shadow.clone()))
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        call_service(&app, TestRequest::default().to_request()).await;
+        actix_web::rt::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(shadow.failed(), 1);
+        assert_eq!(errors.get(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_shadow_request_header() {
+        let id = Arc::new(Mutex::new(None));
+        let server_id = id.clone();
+        let server = HttpServer::new(move || {
+            let id = server_id.clone();
+            App::new().default_service(web::to(move |req: HttpRequest| {
+                *id.lock().unwrap() = req.headers().get(X_SHADOW_REQUEST).cloned();
+                async { HttpResponse::Ok().finish() }
+            }))
+        })
+        .workers(1)
+        .bind("127.0.0.1:0")
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let shadow = ShadowObserver::builder(format!("http://{}", addr)).build();
+        let app = init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(shadow)))
+                .default_service(web::to(|req: HttpRequest| async move {
+                    let extensions = req.extensions();
+                    let start = extensions.get::<RequestStart>().unwrap();
+                    HttpResponse::Ok().body(start.request_id().to_string())
+                })),
+        )
+        .await;
+        let res = call_service(&app, TestRequest::default().to_request()).await;
+        let request_id = read_body(res).await;
+        actix_web::rt::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(
+            id.lock().unwrap().as_ref().unwrap().as_bytes(),
+            &request_id[..]
+        );
+    }
+}
@@ -0,0 +1,177 @@
+#[cfg(test)]
+mod tests {
+    use crate::shared::SharedRequestHook;
+    use crate::{Observer, RequestEndData, RequestStartData};
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+    use futures_util::future::LocalBoxFuture;
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+    use std::future::ready;
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// Records uris of ended requests, and the threads which observed them.
+    #[derive(Default)]
+    struct SharedRecorder {
+        uris: Mutex<Vec<String>>,
+        threads: Mutex<HashSet<std::thread::ThreadId>>,
+    }
+
+    impl Observer for SharedRecorder {
+        fn on_request_started(&self, _data: RequestStartData) {}
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.uris.lock().unwrap().push(data.uri.to_string());
+            self.threads
+                .lock()
+                .unwrap()
+                .insert(std::thread::current().id());
+        }
+    }
+
+    #[derive(Default)]
+    struct WorkerRecorder {
+        ended: RefCell<usize>,
+    }
+
+    impl Observer for WorkerRecorder {
+        fn on_request_started(&self, _data: RequestStartData) {}
+
+        fn on_request_ended(&self, _data: RequestEndData) {
+            *self.ended.borrow_mut() += 1;
+        }
+    }
+
+    #[test]
+    fn test_shared_across_workers() {
+        let recorder = Arc::new(SharedRecorder::default());
+        let request_hook = SharedRequestHook::new()
+            .configure(|hook| {
+                hook.exclude("/health")
+                    .register(Rc::new(WorkerRecorder::default()))
+            })
+            .register(recorder.clone());
+        let stats = request_hook.stats();
+
+        // every worker runs the app factory on its own thread
+        let workers: Vec<_> = (0..2)
+            .map(|worker| {
+                let request_hook = request_hook.clone();
+                std::thread::spawn(move || {
+                    actix_web::rt::System::new().block_on(async move {
+                        let app = init_service(
+                            App::new()
+                                .wrap(request_hook)
+                                .default_service(web::to(HttpResponse::Ok)),
+                        )
+                        .await;
+                        for uri in [format!("/{}", worker), "/health".to_string()] {
+                            call_service(&app, TestRequest::get().uri(&uri).to_request()).await;
+                        }
+                    })
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        let mut uris = recorder.uris.lock().unwrap().clone();
+        uris.sort();
+        assert_eq!(uris, vec!["/0", "/1"]);
+        assert_eq!(recorder.threads.lock().unwrap().len(), 2);
+        assert_eq!(stats.snapshot(Duration::from_secs(60)).requests, 2);
+    }
+
+    /// Records threads which ticked it and how many times it was shut down.
+    #[derive(Default)]
+    struct LifecycleRecorder {
+        ticks: Mutex<Vec<std::thread::ThreadId>>,
+        shutdowns: AtomicUsize,
+    }
+
+    impl Observer for LifecycleRecorder {
+        fn on_request_started(&self, _data: RequestStartData) {}
+
+        fn on_request_ended(&self, _data: RequestEndData) {}
+
+        fn on_tick(&self, _elapsed: Duration) {
+            self.ticks.lock().unwrap().push(std::thread::current().id());
+        }
+
+        fn on_shutdown(&self) -> LocalBoxFuture<'static, ()> {
+            self.shutdowns.fetch_add(1, Ordering::SeqCst);
+            Box::pin(ready(()))
+        }
+    }
+
+    /// Runs an app wrapped with `request_hook` on each of two worker threads for `duration`.
+    fn run_workers(request_hook: &SharedRequestHook, duration: Duration) {
+        let workers: Vec<_> = (0..2)
+            .map(|_| {
+                let request_hook = request_hook.clone();
+                std::thread::spawn(move || {
+                    actix_web::rt::System::new().block_on(async move {
+                        let app = init_service(
+                            App::new()
+                                .wrap(request_hook)
+                                .default_service(web::to(HttpResponse::Ok)),
+                        )
+                        .await;
+                        call_service(&app, TestRequest::get().to_request()).await;
+                        actix_web::rt::time::sleep(duration).await;
+                    })
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_ticks_once_for_all_workers() {
+        let recorder = Arc::new(LifecycleRecorder::default());
+        let request_hook = SharedRequestHook::new()
+            .configure(|hook| hook.tick_interval(Duration::from_millis(10)))
+            .tick_interval(Duration::from_millis(10))
+            .register(recorder.clone());
+        run_workers(&request_hook, Duration::from_millis(100));
+
+        let ticks = recorder.ticks.lock().unwrap();
+        assert!(!ticks.is_empty());
+        assert!(ticks.iter().all(|thread| *thread == ticks[0]));
+    }
+
+    #[test]
+    fn test_shuts_down_once_for_all_workers() {
+        let recorder = Arc::new(LifecycleRecorder::default());
+        let request_hook = SharedRequestHook::new().register(recorder.clone());
+        run_workers(&request_hook, Duration::ZERO);
+        assert_eq!(recorder.shutdowns.load(Ordering::SeqCst), 1);
+
+        actix_web::rt::System::new().block_on(request_hook.shutdown());
+        assert_eq!(recorder.shutdowns.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_shutdown() {
+        let recorder = Arc::new(LifecycleRecorder::default());
+        let request_hook = SharedRequestHook::new().register(recorder.clone());
+        let app = init_service(
+            App::new()
+                .wrap(request_hook.clone())
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        call_service(&app, TestRequest::get().to_request()).await;
+
+        request_hook.shutdown().await;
+        assert_eq!(recorder.shutdowns.load(Ordering::SeqCst), 1);
+        drop(app);
+        assert_eq!(recorder.shutdowns.load(Ordering::SeqCst), 1);
+    }
+}
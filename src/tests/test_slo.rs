@@ -0,0 +1,106 @@
+#[cfg(test)]
+mod tests {
+    use crate::clock::MockClock;
+    use crate::slo::{BurnAlert, Objective, Severity, SloMonitor};
+    use crate::util::path_matches;
+    use crate::RequestHook;
+    use actix_web::{test, web, App, HttpResponse};
+    use std::rc::Rc;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[actix_web::test]
+    async fn test_path_matches() {
+        assert!(path_matches("/api/**", "/api"));
+        assert!(path_matches("/api/**", "/api/users/1"));
+        assert!(path_matches("/users/*", "/users/1/"));
+        assert!(!path_matches("/users/*", "/users/1/orders"));
+        assert!(path_matches("/**/orders", "/users/1/orders"));
+        assert!(!path_matches("/api/**", "/health"));
+    }
+
+    #[actix_web::test]
+    async fn test_burn_alerts() {
+        let alerts: Arc<Mutex<Vec<BurnAlert>>> = Arc::new(Mutex::new(Vec::new()));
+        let callback_alerts = alerts.clone();
+        let slo = SloMonitor::new()
+            .objective(Objective::availability("/api/**", 0.99).named("api"))
+            .objective(Objective::latency("/api/**", Duration::from_secs(10), 0.99))
+            .on_burn(move |alert| callback_alerts.lock().unwrap().push(alert.clone()));
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(slo.clone())))
+                .route("/api/ok", web::get().to(HttpResponse::Ok))
+                .route("/api/fail", web::get().to(HttpResponse::ServiceUnavailable))
+                .route("/fail", web::get().to(HttpResponse::ServiceUnavailable)),
+        )
+        .await;
+        for _ in 0..9 {
+            test::call_service(&app, test::TestRequest::with_uri("/api/ok").to_request()).await;
+            test::call_service(&app, test::TestRequest::with_uri("/fail").to_request()).await;
+        }
+        assert!(alerts.lock().unwrap().is_empty());
+        assert_eq!(slo.burn_rate("api", Duration::from_secs(3600)), Some(0.0));
+
+        // 1 of 10 requests failed, burning 1% budget 10 times faster than sustainable
+        test::call_service(&app, test::TestRequest::with_uri("/api/fail").to_request()).await;
+        let burn_rate = slo.burn_rate("api", Duration::from_secs(3600)).unwrap();
+        assert!((burn_rate - 10.0).abs() < 1e-9);
+        assert_eq!(alerts.lock().unwrap().len(), 1);
+
+        // still burning slowly, alerted once
+        test::call_service(&app, test::TestRequest::with_uri("/api/ok").to_request()).await;
+        assert_eq!(alerts.lock().unwrap().len(), 1);
+
+        test::call_service(&app, test::TestRequest::with_uri("/api/fail").to_request()).await;
+        test::call_service(&app, test::TestRequest::with_uri("/api/fail").to_request()).await;
+        let alerts = alerts.lock().unwrap();
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(alerts[0].objective, "api");
+        assert_eq!(alerts[0].severity, Severity::Slow);
+        assert_eq!(alerts[1].severity, Severity::Fast);
+        assert!(alerts[1].long_window_burn_rate > 14.4);
+        assert_eq!(
+            alerts[1].short_window_burn_rate,
+            alerts[1].long_window_burn_rate
+        );
+        assert_eq!(
+            slo.burn_rate("/api/**", Duration::from_secs(3600)),
+            Some(0.0)
+        );
+        assert_eq!(slo.burn_rate("unknown", Duration::from_secs(3600)), None);
+    }
+
+    #[actix_web::test]
+    async fn test_windows_of_routes() {
+        let clock = MockClock::new();
+        let slo = SloMonitor::new()
+            .clock(clock.clone())
+            .objective(Objective::availability("/users/{id}", 0.9).named("users"));
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(slo.clone())))
+                .route(
+                    "/users/{id}",
+                    web::get().to(|id: web::Path<String>| async move {
+                        match id.as_str() {
+                            "ok" => HttpResponse::Ok().finish(),
+                            _ => HttpResponse::ServiceUnavailable().finish(),
+                        }
+                    }),
+                ),
+        )
+        .await;
+
+        // matched by route, not by path
+        test::call_service(&app, test::TestRequest::with_uri("/users/7").to_request()).await;
+        let burn_rate = slo.burn_rate("users", Duration::from_secs(3600)).unwrap();
+        assert!((burn_rate - 10.0).abs() < 1e-9);
+
+        clock.advance(Duration::from_secs(600));
+        test::call_service(&app, test::TestRequest::with_uri("/users/ok").to_request()).await;
+        assert_eq!(slo.burn_rate("users", Duration::from_secs(300)), Some(0.0));
+        let burn_rate = slo.burn_rate("users", Duration::from_secs(3600)).unwrap();
+        assert!((burn_rate - 5.0).abs() < 1e-9);
+    }
+}
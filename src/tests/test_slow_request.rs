@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod tests {
+    use crate::observer::SlowRequestData;
+    use crate::RequestHook;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, web, App, HttpResponse};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[actix_web::test]
+    async fn test_on_slow_request() {
+        let slow: Rc<RefCell<Vec<SlowRequestData>>> = Rc::new(RefCell::new(Vec::new()));
+        let callback_slow = slow.clone();
+        let request_hook = RequestHook::new()
+            .on_slow_request(Duration::from_millis(20), move |data| {
+                callback_slow.borrow_mut().push(data.clone())
+            });
+        // sampling doesn't drop slow requests
+        request_hook.settings().set_sample_rate(0.0);
+        let app = test::init_service(
+            App::new()
+                .wrap(request_hook)
+                .route("/fast", web::post().to(HttpResponse::Ok))
+                .route(
+                    "/slow/{id}",
+                    web::post().to(|body: String| async move {
+                        actix_web::rt::time::sleep(Duration::from_millis(50)).await;
+                        HttpResponse::Accepted().body(body)
+                    }),
+                ),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/fast")
+            .set_payload("fast")
+            .to_request();
+        test::call_service(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/slow/1?verbose=true")
+            .insert_header(("X-Client", "tests"))
+            .set_payload(vec![b'a'; 5000])
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(test::read_body(res).await.len(), 5000);
+
+        let slow = slow.borrow();
+        assert_eq!(slow.len(), 1);
+        assert_eq!(&*slow[0].uri, "/slow/1?verbose=true");
+        assert_eq!(slow[0].route, "/slow/{id}");
+        assert_eq!(slow[0].status, StatusCode::ACCEPTED);
+        assert!(slow[0].elapsed >= Duration::from_millis(50));
+        assert_eq!(slow[0].headers.get("X-Client").unwrap(), "tests");
+        assert_eq!(slow[0].body_prefix.len(), 4096);
+    }
+}
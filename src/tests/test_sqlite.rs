@@ -0,0 +1,117 @@
+#[cfg(test)]
+mod tests {
+    use crate::observers::sqlite::SqliteAuditObserver;
+    use crate::tests::end_data;
+    use crate::{Observer, Origin, RequestEndData, RequestStartData};
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use actix_web::web::BytesMut;
+    use rusqlite::Connection;
+    use uuid::Uuid;
+
+    #[actix_web::test]
+    async fn test_writes_audit_row() {
+        let observer = SqliteAuditObserver::new(Connection::open_in_memory().unwrap())
+            .unwrap()
+            .user_by(|req| {
+                req.headers()
+                    .get("x-user")
+                    .and_then(|user| user.to_str().ok())
+                    .map(String::from)
+            });
+        let req = test::TestRequest::post()
+            .uri("/orders?page=1")
+            .insert_header(("x-user", "ana"))
+            .to_srv_request();
+        let request_id = Uuid::new_v4();
+
+        observer.on_request_started(RequestStartData {
+            req: &req,
+            request_id,
+            uri: req.uri().to_string().into(),
+            method: "POST".into(),
+            body: BytesMut::from("abc"),
+            origin: Origin::default(),
+            body_sizes: None,
+            body_truncated: false,
+            grpc: None,
+            sampled: true,
+            force_sampled: false,
+            baggage: None,
+            retry_of: None,
+            fingerprint: None,
+            geo: None,
+            security_flags: Default::default(),
+            rendered_body: None,
+            operation_id: None,
+            deprecated: false,
+            consumer: None,
+        });
+        observer.on_request_ended(RequestEndData {
+            request_id,
+            uri: req.uri().to_string().into(),
+            method: "POST".into(),
+            status: StatusCode::CREATED,
+            ..end_data()
+        });
+        observer.on_shutdown().await;
+
+        let row: (
+            String,
+            String,
+            String,
+            Option<String>,
+            u16,
+            i64,
+            Option<String>,
+        ) = observer
+            .connection()
+            .query_row(
+                "SELECT id, method, path, user, status, elapsed_us, body_sha256 FROM request_audit",
+                [],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                        row.get(5)?,
+                        row.get(6)?,
+                    ))
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            row,
+            (
+                request_id.to_string(),
+                "POST".to_string(),
+                "/orders".to_string(),
+                Some("ana".to_string()),
+                201,
+                1500,
+                Some(
+                    "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".to_string()
+                ),
+            )
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_migrates_once() {
+        let path = std::env::temp_dir().join(format!("audit-{}.db", Uuid::new_v4()));
+        SqliteAuditObserver::open(&path).unwrap();
+        let observer = SqliteAuditObserver::open(&path).unwrap();
+        let version: usize = observer
+            .connection()
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        drop(observer);
+        std::fs::remove_file(&path).unwrap();
+        let _ = std::fs::remove_file(path.with_extension("db-wal"));
+        let _ = std::fs::remove_file(path.with_extension("db-shm"));
+
+        assert_eq!(version, 1);
+    }
+}
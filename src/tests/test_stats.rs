@@ -0,0 +1,391 @@
+#[cfg(test)]
+mod tests {
+    use crate::stats::{HookStats, RouteStatusCounts, OTHER_ROUTE, OTHER_TENANT};
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::dev::Service;
+    use actix_web::{test, web, App, HttpResponse};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    async fn in_flight(stats: web::Data<HookStats>) -> HttpResponse {
+        HttpResponse::Ok().body(stats.in_flight().to_string())
+    }
+
+    #[actix_web::test]
+    async fn test_in_flight() {
+        let stats = HookStats::default();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(stats.clone()))
+                .wrap(
+                    RequestHook::new()
+                        .exclude("/excluded")
+                        .with_stats(stats.clone()),
+                )
+                .default_service(web::to(in_flight)),
+        )
+        .await;
+
+        let res = test::call_service(&app, test::TestRequest::default().to_request()).await;
+        assert_eq!(test::read_body(res).await, "1");
+        let req = test::TestRequest::with_uri("/excluded").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(test::read_body(res).await, "0");
+        assert_eq!(stats.in_flight(), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_expose_stats() {
+        async fn status(stats: web::Data<HookStats>, version: web::Data<&str>) -> HttpResponse {
+            let requests = stats.snapshot(Duration::from_secs(60)).requests;
+            HttpResponse::Ok().body(format!("{} {}", version.get_ref(), requests))
+        }
+
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .exclude("/status")
+                        .expose_stats()
+                        .app_data(web::Data::new("v1")),
+                )
+                .route("/status", web::get().to(status))
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+
+        test::call_service(&app, test::TestRequest::with_uri("/hey").to_request()).await;
+        let req = test::TestRequest::with_uri("/status").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(test::read_body(res).await, "v1 1");
+    }
+
+    #[actix_web::test]
+    async fn test_cancelled_request_is_not_in_flight() {
+        let request_hook = RequestHook::new();
+        let stats = request_hook.stats();
+        let app = test::init_service(
+            App::new()
+                .wrap(request_hook)
+                .default_service(web::to(futures_util::future::pending::<HttpResponse>)),
+        )
+        .await;
+
+        let mut pending = Box::pin(app.call(test::TestRequest::default().to_request()));
+        assert!(futures_util::poll!(&mut pending).is_pending());
+        assert_eq!(stats.in_flight(), 1);
+        drop(pending);
+        assert_eq!(stats.in_flight(), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_orphaned_requests() {
+        let orphans = Rc::new(RefCell::new(Vec::new()));
+        let reported = orphans.clone();
+        let request_hook = RequestHook::new()
+            .on_orphaned_request(Duration::from_millis(50), move |orphan| {
+                reported.borrow_mut().push(orphan.clone())
+            });
+        let stats = request_hook.stats();
+        let app = test::init_service(
+            App::new()
+                .wrap(request_hook)
+                .route("/ok", web::get().to(HttpResponse::Ok))
+                .default_service(web::to(futures_util::future::pending::<HttpResponse>)),
+        )
+        .await;
+
+        let mut pending = Box::pin(app.call(test::TestRequest::with_uri("/stuck").to_request()));
+        assert!(futures_util::poll!(&mut pending).is_pending());
+        test::call_service(&app, test::TestRequest::with_uri("/ok").to_request()).await;
+        actix_web::rt::time::sleep(Duration::from_millis(200)).await;
+
+        let orphans = orphans.borrow().clone();
+        assert_eq!(orphans.len(), 1);
+        assert_eq!((&*orphans[0].method, &*orphans[0].uri), ("GET", "/stuck"));
+        assert!(orphans[0].age >= Duration::from_millis(50));
+        assert_eq!(stats.orphaned(), 1);
+        assert_eq!(stats.snapshot(Duration::from_secs(60)).orphaned, 1);
+        drop(pending);
+        assert_eq!(stats.orphaned(), 0);
+        assert_eq!(stats.in_flight(), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_snapshot() {
+        let request_hook = RequestHook::new().with_stats(HookStats::new(Duration::from_secs(120)));
+        let stats = request_hook.stats();
+        let app = test::init_service(
+            App::new()
+                .wrap(request_hook)
+                .route("/ok", web::get().to(HttpResponse::Ok))
+                .route("/fail", web::get().to(HttpResponse::InternalServerError))
+                .route("/missing", web::get().to(HttpResponse::NotFound)),
+        )
+        .await;
+        for uri in &["/ok", "/ok", "/fail", "/missing"] {
+            test::call_service(&app, test::TestRequest::with_uri(uri).to_request()).await;
+        }
+
+        let snapshot = stats.snapshot(Duration::from_secs(60));
+        assert_eq!(snapshot.window, Duration::from_secs(60));
+        assert_eq!(snapshot.requests, 4);
+        assert_eq!(snapshot.requests_per_second, 4.0 / 60.0);
+        assert_eq!(snapshot.error_rate, 0.25);
+        assert_eq!(snapshot.in_flight, 0);
+        assert!(snapshot.average_latency < Duration::from_secs(1));
+        // server error is frustrated, other requests satisfied
+        assert_eq!(snapshot.apdex, Some(0.75));
+
+        let snapshot = stats.snapshot(Duration::from_secs(600));
+        assert_eq!(snapshot.window, Duration::from_secs(120));
+        assert_eq!(snapshot.requests, 4);
+    }
+
+    #[actix_web::test]
+    async fn test_unique_clients() {
+        let request_hook = RequestHook::new();
+        let stats = request_hook.stats();
+        let app = test::init_service(
+            App::new()
+                .wrap(request_hook)
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        for ip in &["10.0.0.1:1000", "10.0.0.2:1000", "10.0.0.1:2000"] {
+            let req = test::TestRequest::default().peer_addr(ip.parse().unwrap());
+            test::call_service(&app, req.to_request()).await;
+        }
+        test::call_service(&app, test::TestRequest::default().to_request()).await;
+        assert_eq!(stats.snapshot(Duration::from_secs(60)).unique_clients, 2);
+
+        let request_hook =
+            RequestHook::new().with_stats(HookStats::default().client_resolver(|req| {
+                Some(req.headers().get("x-user")?.to_str().ok()?.to_string())
+            }));
+        let stats = request_hook.stats();
+        let app = test::init_service(
+            App::new()
+                .wrap(request_hook)
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        for i in 0..6000 {
+            let req =
+                test::TestRequest::default().insert_header(("x-user", (i % 3000).to_string()));
+            test::call_service(&app, req.to_request()).await;
+        }
+        let unique_clients = stats.snapshot(Duration::from_secs(60)).unique_clients;
+        assert!(
+            (2850..=3150).contains(&unique_clients),
+            "{}",
+            unique_clients
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_slowest() {
+        let request_hook = RequestHook::new().with_stats(HookStats::default().slowest_capacity(2));
+        let stats = request_hook.stats();
+        let app = test::init_service(App::new().wrap(request_hook).route(
+            "/sleep/{ms}",
+            web::get().to(|ms: web::Path<u64>| async move {
+                actix_web::rt::time::sleep(Duration::from_millis(*ms)).await;
+                HttpResponse::Ok().finish()
+            }),
+        ))
+        .await;
+        for uri in &["/sleep/30", "/sleep/0", "/sleep/60", "/sleep/10"] {
+            test::call_service(&app, test::TestRequest::with_uri(uri).to_request()).await;
+        }
+
+        let slowest = stats.slowest();
+        assert_eq!(slowest.len(), 2);
+        assert_eq!(slowest[0].route, "/sleep/{ms}");
+        assert_eq!(slowest[0].method, "GET");
+        assert_eq!(slowest[0].status, 200);
+        assert!(slowest[0].elapsed >= Duration::from_millis(60));
+        assert!(slowest[1].elapsed >= Duration::from_millis(30));
+        assert!(slowest[1].elapsed < Duration::from_millis(60));
+    }
+
+    #[actix_web::test]
+    async fn test_status_counts() {
+        let request_hook = RequestHook::new().with_stats(HookStats::default().max_routes(2));
+        let stats = request_hook.stats();
+        let app = test::init_service(
+            App::new()
+                .wrap(request_hook)
+                .route(
+                    "/users/{id}",
+                    web::get().to(|id: web::Path<u16>| async move {
+                        HttpResponse::build(actix_web::http::StatusCode::from_u16(*id).unwrap())
+                            .finish()
+                    }),
+                )
+                .route("/users/{id}", web::post().to(HttpResponse::Created)),
+        )
+        .await;
+        for uri in &[
+            "/users/200",
+            "/users/204",
+            "/users/302",
+            "/users/404",
+            "/users/503",
+        ] {
+            test::call_service(&app, test::TestRequest::with_uri(uri).to_request()).await;
+        }
+        test::call_service(&app, test::TestRequest::post().uri("/users/1").to_request()).await;
+        test::call_service(&app, test::TestRequest::with_uri("/unknown").to_request()).await;
+
+        assert_eq!(
+            stats.status_counts(),
+            vec![
+                RouteStatusCounts {
+                    method: "GET".to_string(),
+                    route: "/users/{id}".to_string(),
+                    success: 2,
+                    redirection: 1,
+                    client_error: 1,
+                    server_error: 1,
+                    ..Default::default()
+                },
+                RouteStatusCounts {
+                    method: "POST".to_string(),
+                    route: "/users/{id}".to_string(),
+                    success: 1,
+                    ..Default::default()
+                },
+                RouteStatusCounts {
+                    method: "GET".to_string(),
+                    route: OTHER_ROUTE.to_string(),
+                    client_error: 1,
+                    ..Default::default()
+                },
+            ]
+        );
+
+        stats.reset_status_counts();
+        assert!(stats.status_counts().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_tenants() {
+        let stats = HookStats::default().max_tenants(2).tenant_resolver(|req| {
+            req.headers()
+                .get("X-Tenant")
+                .and_then(|tenant| tenant.to_str().ok())
+                .map(str::to_string)
+        });
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().with_stats(stats.clone()))
+                .route("/ok", web::get().to(HttpResponse::Ok))
+                .route("/fail", web::get().to(HttpResponse::InternalServerError))
+                .route(
+                    "/slow",
+                    web::get().to(|| async {
+                        actix_web::rt::time::sleep(Duration::from_millis(50)).await;
+                        HttpResponse::Ok().finish()
+                    }),
+                ),
+        )
+        .await;
+        for (tenant, uri) in &[
+            ("a", "/ok"),
+            ("a", "/fail"),
+            ("b", "/ok"),
+            ("a", "/slow"),
+            ("", "/ok"),
+            ("c", "/ok"),
+        ] {
+            let mut req = test::TestRequest::with_uri(uri);
+            if !tenant.is_empty() {
+                req = req.insert_header(("X-Tenant", *tenant));
+            }
+            test::call_service(&app, req.to_request()).await;
+        }
+
+        // b was least recently active when c arrived
+        let tenants = stats.tenants();
+        assert_eq!(tenants.len(), 2);
+        assert_eq!(tenants[0].tenant, "a");
+        assert_eq!(tenants[0].requests, 3);
+        assert!((tenants[0].error_rate - 1.0 / 3.0).abs() < 1e-9);
+        assert!(tenants[0].p95 >= Duration::from_millis(50));
+        assert!(tenants[0].p95 < Duration::from_millis(100));
+        assert_eq!(tenants[1].tenant, "c");
+        assert_eq!(tenants[1].error_rate, 0.0);
+        assert_eq!(stats.tenant_stats("c"), Some(tenants[1].clone()));
+        assert_eq!(stats.tenant_stats("b"), None);
+    }
+
+    #[derive(Default)]
+    struct Deprecations(RefCell<Vec<bool>>);
+
+    impl Observer for Deprecations {
+        fn on_request_started(&self, _data: RequestStartData) {}
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.0.borrow_mut().push(data.deprecated);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_deprecated_usage() {
+        let stats = HookStats::default().max_tenants(1).tenant_resolver(|req| {
+            req.headers()
+                .get("X-Api-Key")
+                .and_then(|key| key.to_str().ok())
+                .map(str::to_string)
+        });
+        let deprecated = Rc::new(Deprecations::default());
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .deprecate_route("/v1/users/{id}")
+                        .with_stats(stats.clone())
+                        .register(deprecated.clone()),
+                )
+                .route("/v1/users/{id}", web::get().to(HttpResponse::Ok))
+                .route("/v2/users/{id}", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        for (key, uri) in &[
+            ("a", "/v1/users/1"),
+            ("a", "/v1/users/2"),
+            ("a", "/v2/users/1"),
+            ("", "/v1/users/3"),
+            ("b", "/v1/users/4"),
+        ] {
+            let mut req = test::TestRequest::with_uri(uri);
+            if !key.is_empty() {
+                req = req.insert_header(("X-Api-Key", *key));
+            }
+            test::call_service(&app, req.to_request()).await;
+        }
+
+        assert_eq!(*deprecated.0.borrow(), vec![true, true, false, true, true]);
+        let usage: Vec<_> = stats
+            .deprecated_usage()
+            .into_iter()
+            .map(|usage| (usage.method, usage.route, usage.tenant, usage.requests))
+            .collect();
+        let route = "/v1/users/{id}".to_string();
+        assert_eq!(
+            usage,
+            vec![
+                ("GET".to_string(), route.clone(), None, 1),
+                (
+                    "GET".to_string(),
+                    route.clone(),
+                    Some(OTHER_TENANT.to_string()),
+                    1
+                ),
+                ("GET".to_string(), route, Some("a".to_string()), 2),
+            ]
+        );
+    }
+}
@@ -0,0 +1,202 @@
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{MockBehavior, MockObserver, RecordingObserver};
+    use crate::tests::end_data;
+    use crate::{Observer, Origin};
+    use crate::{RequestCancelledData, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::dev::Service;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, web, App, HttpResponse};
+    use std::rc::Rc;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    #[actix_web::test]
+    async fn test_records_events() {
+        let recorder = RecordingObserver::new();
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(recorder.clone())))
+                .route("/", web::get().to(HttpResponse::Ok))
+                .route("/missing", web::get().to(HttpResponse::NotFound)),
+        )
+        .await;
+        for uri in ["/", "/missing"] {
+            test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+        }
+
+        crate::assert_events!(
+            recorder,
+            [
+                "started GET /",
+                "ended GET / 200",
+                "started GET /missing",
+                "ended GET /missing 404",
+            ]
+        );
+        crate::assert_paired!(recorder);
+        assert_eq!(recorder.started().len(), 2);
+        assert_eq!(recorder.ended()[1].status, StatusCode::NOT_FOUND);
+        assert_eq!(
+            recorder.started()[0].request_id,
+            recorder.ended()[0].request_id
+        );
+        recorder.clear();
+        assert!(recorder.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_wait_for_events() {
+        let recorder = RecordingObserver::new();
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(recorder.clone())))
+                .route(
+                    "/",
+                    web::get().to(|| async {
+                        actix_web::rt::time::sleep(Duration::from_millis(50)).await;
+                        HttpResponse::Ok().finish()
+                    }),
+                ),
+        )
+        .await;
+        let response = app.call(test::TestRequest::get().to_request());
+        let waiting = async {
+            recorder.wait_for(1, Duration::from_secs(1)).await;
+            assert_eq!(recorder.summary(), vec!["started GET /"]);
+            recorder.wait_for_ended(1, Duration::from_secs(1)).await;
+        };
+        let (response, _) = futures_util::join!(response, waiting);
+        assert!(response.unwrap().status().is_success());
+        crate::assert_events!(recorder, ["started GET /", "ended GET / 200"]);
+    }
+
+    #[actix_web::test]
+    #[should_panic(expected = "expected 1 ended requests within 10ms")]
+    async fn test_wait_for_times_out() {
+        RecordingObserver::new()
+            .wait_for_ended(1, Duration::from_millis(10))
+            .await;
+    }
+
+    #[actix_web::test]
+    async fn test_check_pairing() {
+        let recorder = RecordingObserver::new();
+        let request_id = Uuid::new_v4();
+        let req = test::TestRequest::get().to_srv_request();
+        recorder.on_request_started(RequestStartData {
+            request_id,
+            req: &req,
+            uri: "/".into(),
+            method: "GET".into(),
+            body: Default::default(),
+            origin: Origin::default(),
+            body_sizes: None,
+            body_truncated: false,
+            grpc: None,
+            sampled: true,
+            force_sampled: false,
+            baggage: None,
+            retry_of: None,
+            fingerprint: None,
+            geo: None,
+            security_flags: Default::default(),
+            rendered_body: None,
+            operation_id: None,
+            deprecated: false,
+            consumer: None,
+        });
+        assert_eq!(
+            recorder.check_pairing(),
+            Err(format!("request {} / never ended", request_id))
+        );
+
+        recorder.on_request_cancelled(RequestCancelledData {
+            request_id,
+            elapsed: Duration::ZERO,
+            uri: "/".into(),
+            method: "GET".into(),
+            origin: Origin::default(),
+        });
+        assert_eq!(recorder.check_pairing(), Ok(()));
+        crate::assert_events!(recorder, ["started GET /", "cancelled GET /"]);
+
+        recorder.on_request_ended(RequestEndData {
+            request_id,
+            elapsed: Duration::ZERO,
+            uri: "/".into(),
+            ..end_data()
+        });
+        assert_eq!(
+            recorder.check_pairing(),
+            Err(format!("request {} / ended twice", request_id))
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_mock_records_scripted_paths() {
+        let mock = Rc::new(
+            MockObserver::new()
+                .on_started("/audited", MockBehavior::Record)
+                .on_ended("/audited", MockBehavior::Record)
+                .otherwise(MockBehavior::Ignore),
+        );
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(mock.clone()))
+                .route("/{path}", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        for uri in ["/audited?page=2", "/other"] {
+            test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+        }
+        crate::assert_events!(
+            mock.recorder(),
+            [
+                "started GET /audited?page=2",
+                "ended GET /audited?page=2 200"
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_mock_sleeps() {
+        let mock = Rc::new(
+            MockObserver::new().on_started("/slow", MockBehavior::Sleep(Duration::from_millis(50))),
+        );
+        let recorder = RecordingObserver::new();
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(mock.clone())
+                        .register(Rc::new(recorder.clone())),
+                )
+                .route("/{path}", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        for uri in ["/slow", "/fast"] {
+            test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+        }
+        // observers run on the worker, so a slow observer slows down requests
+        let ended = recorder.ended();
+        assert!(ended[0].elapsed >= Duration::from_millis(50));
+        assert!(ended[1].elapsed < Duration::from_millis(50));
+        assert_eq!(mock.recorder().len(), 4);
+    }
+
+    #[actix_web::test]
+    #[should_panic(expected = "sink failed")]
+    async fn test_mock_panics() {
+        let mock =
+            MockObserver::new().on_ended("/boom", MockBehavior::Panic("sink failed".to_string()));
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(mock)))
+                .route("/{path}", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        test::call_service(&app, test::TestRequest::get().uri("/fine").to_request()).await;
+        test::call_service(&app, test::TestRequest::get().uri("/boom").to_request()).await;
+    }
+}
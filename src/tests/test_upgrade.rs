@@ -0,0 +1,129 @@
+#[cfg(test)]
+mod tests {
+    use crate::observer::UpgradeData;
+    use crate::{Observer, RequestEndData, RequestHook, RequestStartData};
+    use actix_web::http::header::{CONNECTION, UPGRADE};
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::web::Bytes;
+    use actix_web::{web, App, Error, HttpResponse};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Default)]
+    struct UpgradeObserver {
+        ended: RefCell<Vec<u16>>,
+        upgrades: RefCell<Vec<UpgradeData>>,
+    }
+
+    impl Observer for UpgradeObserver {
+        fn on_request_started(&self, _data: RequestStartData) {}
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.ended.borrow_mut().push(data.status.as_u16());
+        }
+
+        fn on_upgrade(&self, data: UpgradeData) {
+            self.upgrades.borrow_mut().push(data);
+        }
+    }
+
+    /// Records every request as ended, the default for upgrades.
+    #[derive(Default)]
+    struct EndObserver {
+        ended: RefCell<Vec<u16>>,
+    }
+
+    impl Observer for EndObserver {
+        fn on_request_started(&self, _data: RequestStartData) {}
+
+        fn on_request_ended(&self, data: RequestEndData) {
+            self.ended.borrow_mut().push(data.status.as_u16());
+        }
+    }
+
+    /// Accepts the upgrade, the body stands in for frames of the socket.
+    async fn socket() -> HttpResponse {
+        let frames = vec![Ok::<_, Error>(Bytes::from_static(b"frame"))];
+        HttpResponse::SwitchingProtocols()
+            .insert_header((UPGRADE, "websocket"))
+            .streaming(futures_util::stream::iter(frames))
+    }
+
+    fn handshake(uri: &str) -> TestRequest {
+        TestRequest::get()
+            .uri(uri)
+            .insert_header((CONNECTION, "keep-alive, Upgrade"))
+            .insert_header((UPGRADE, "WebSocket"))
+    }
+
+    #[actix_web::test]
+    async fn test_upgrade() {
+        let observer = Rc::new(UpgradeObserver::default());
+        let ends = Rc::new(EndObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(observer.clone())
+                        .register(ends.clone()),
+                )
+                .route("/ws", web::get().to(socket))
+                .default_service(web::to(HttpResponse::BadRequest)),
+        )
+        .await;
+
+        let res = call_service(&app, handshake("/ws").to_request()).await;
+        // observed once the handshake is answered
+        assert_eq!(observer.upgrades.borrow().len(), 1);
+        assert_eq!(read_body(res).await, "frame");
+        call_service(&app, handshake("/rejected").to_request()).await;
+        call_service(&app, TestRequest::get().uri("/plain").to_request()).await;
+
+        let upgrades = observer.upgrades.borrow();
+        let handshakes: Vec<_> = upgrades
+            .iter()
+            .map(|data| {
+                (
+                    data.handshake.status.as_u16(),
+                    &*data.protocol,
+                    data.duration,
+                )
+            })
+            .collect();
+        assert_eq!(
+            handshakes,
+            vec![(101, "websocket", None), (400, "websocket", None)]
+        );
+        assert_eq!(*observer.ended.borrow(), vec![400]);
+        assert_eq!(*ends.ended.borrow(), vec![101, 400, 400]);
+    }
+
+    #[actix_web::test]
+    async fn test_track_upgrades() {
+        let observer = Rc::new(UpgradeObserver::default());
+        let app = init_service(
+            App::new()
+                .wrap(
+                    RequestHook::new()
+                        .register(observer.clone())
+                        .track_upgrades(),
+                )
+                .route("/ws", web::get().to(socket))
+                .default_service(web::to(HttpResponse::BadRequest)),
+        )
+        .await;
+
+        let res = call_service(&app, handshake("/ws").to_request()).await;
+        // the connection is still open
+        assert!(observer.upgrades.borrow().is_empty());
+        assert_eq!(read_body(res).await, "frame");
+        call_service(&app, handshake("/rejected").to_request()).await;
+
+        let upgrades = observer.upgrades.borrow();
+        assert_eq!(upgrades.len(), 2);
+        assert_eq!(upgrades[0].handshake.status.as_u16(), 101);
+        assert!(upgrades[0].duration.is_some());
+        assert_eq!(upgrades[1].handshake.status.as_u16(), 400);
+        assert_eq!(upgrades[1].duration, None);
+    }
+}
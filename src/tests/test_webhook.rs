@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod tests {
+    use crate::observers::webhook::WebhookObserver;
+    use crate::tests::end_data;
+    use crate::{Observer, RequestEndData};
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    #[actix_web::test]
+    async fn test_posts_batch() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let server_received = received.clone();
+        let server = HttpServer::new(move || {
+            let received = server_received.clone();
+            App::new().route(
+                "/events",
+                web::post().to(move |req: HttpRequest, body: web::Bytes| {
+                    let auth = req.headers().get("authorization").cloned();
+                    received.lock().unwrap().push((auth, body));
+                    async { HttpResponse::Ok().finish() }
+                }),
+            )
+        })
+        .workers(1)
+        .bind("127.0.0.1:0")
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let observer = WebhookObserver::builder(format!("http://{}/events", addr))
+            .bearer_token("secret")
+            .batch(2, Duration::from_secs(60))
+            .build()
+            .unwrap();
+        observer.on_request_ended(end_data());
+        observer.on_request_ended(end_data());
+        actix_web::rt::time::sleep(Duration::from_millis(500)).await;
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].0.as_ref().unwrap(), "Bearer secret");
+        let batch: Vec<RequestEndData> = serde_json::from_slice(&received[0].1).unwrap();
+        assert_eq!(batch.len(), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_overflows_to_file() {
+        let path = std::env::temp_dir().join(format!("webhook-{}.jsonl", Uuid::new_v4()));
+        let observer = WebhookObserver::builder("http://127.0.0.1:1/events")
+            .batch(1, Duration::from_secs(60))
+            .max_retries(0)
+            .overflow_file(&path)
+            .build()
+            .unwrap();
+        observer.on_request_ended(end_data());
+        actix_web::rt::time::sleep(Duration::from_millis(500)).await;
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(content.lines().count(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_spools_pending_events_on_shutdown() {
+        let path = std::env::temp_dir().join(format!("webhook-{}.jsonl", Uuid::new_v4()));
+        let observer = WebhookObserver::builder("http://127.0.0.1:1/events")
+            .batch(10, Duration::from_secs(60))
+            .overflow_file(&path)
+            .build()
+            .unwrap();
+        observer.on_request_ended(end_data());
+        observer.on_request_ended(end_data());
+        observer.on_shutdown().await;
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(content.lines().count(), 2);
+    }
+}
@@ -0,0 +1,109 @@
+#[cfg(test)]
+mod tests {
+    use crate::observers::zipkin::{B3Context, ZipkinObserver};
+    use crate::RequestHook;
+    use actix_web::{test, web, App, HttpResponse, HttpServer};
+    use serde_json::Value;
+    use std::rc::Rc;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[actix_web::test]
+    async fn test_parses_b3_headers() {
+        let req = test::TestRequest::default()
+            .insert_header((
+                "b3",
+                "80F198EE56343BA864FE8B2A57D3EFF7-e457b5a2e4d86bd1-1-05e3ac9a4f6e3b90",
+            ))
+            .to_srv_request();
+        assert_eq!(
+            B3Context::from_request(&req).unwrap(),
+            B3Context {
+                trace_id: "80f198ee56343ba864fe8b2a57d3eff7".to_string(),
+                span_id: "e457b5a2e4d86bd1".to_string(),
+                parent_id: Some("05e3ac9a4f6e3b90".to_string()),
+                sampled: Some(true),
+            }
+        );
+
+        let req = test::TestRequest::default()
+            .insert_header(("x-b3-traceid", "463ac35c9f6413ad"))
+            .insert_header(("x-b3-spanid", "a2fb4a1d1a96d312"))
+            .insert_header(("x-b3-sampled", "0"))
+            .to_srv_request();
+        let context = B3Context::from_request(&req).unwrap();
+        assert_eq!(context.trace_id, "463ac35c9f6413ad");
+        assert_eq!(context.parent_id, None);
+        assert_eq!(context.sampled, Some(false));
+
+        let req = test::TestRequest::default()
+            .insert_header(("b3", "0"))
+            .to_srv_request();
+        assert!(B3Context::from_request(&req).is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_reports_server_spans() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let server_received = received.clone();
+        let server = HttpServer::new(move || {
+            let received = server_received.clone();
+            App::new().route(
+                "/api/v2/spans",
+                web::post().to(move |body: web::Json<Value>| {
+                    received.lock().unwrap().push(body.into_inner());
+                    async { HttpResponse::Accepted().finish() }
+                }),
+            )
+        })
+        .workers(1)
+        .bind("127.0.0.1:0")
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let zipkin = ZipkinObserver::builder(format!("http://{}", addr), "orders")
+            .batch(2, Duration::from_secs(60))
+            .build()
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestHook::new().register(Rc::new(zipkin)))
+                .route("/users/{id}", web::get().to(HttpResponse::Ok))
+                .route("/fail", web::get().to(HttpResponse::InternalServerError)),
+        )
+        .await;
+        let joined = test::TestRequest::with_uri("/users/1?full=true")
+            .insert_header(("b3", "80f198ee56343ba864fe8b2a57d3eff7-e457b5a2e4d86bd1-1"))
+            .to_request();
+        test::call_service(&app, joined).await;
+        let unsampled = test::TestRequest::with_uri("/users/2")
+            .insert_header(("b3", "80f198ee56343ba864fe8b2a57d3eff7-e457b5a2e4d86bd1-0"))
+            .to_request();
+        test::call_service(&app, unsampled).await;
+        test::call_service(&app, test::TestRequest::with_uri("/fail").to_request()).await;
+        actix_web::rt::time::sleep(Duration::from_millis(500)).await;
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let spans = received[0].as_array().unwrap();
+        assert_eq!(spans.len(), 2);
+
+        let joined = &spans[0];
+        assert_eq!(joined["name"], "get /users/{id}");
+        assert_eq!(joined["kind"], "SERVER");
+        assert_eq!(joined["traceId"], "80f198ee56343ba864fe8b2a57d3eff7");
+        assert_eq!(joined["id"], "e457b5a2e4d86bd1");
+        assert_eq!(joined["shared"], true);
+        assert_eq!(joined["localEndpoint"]["serviceName"], "orders");
+        assert_eq!(joined["tags"]["http.path"], "/users/1");
+        assert_eq!(joined["tags"]["http.status_code"], "200");
+        assert!(joined["tags"].get("error").is_none());
+
+        let root = &spans[1];
+        assert_eq!(root["traceId"].as_str().unwrap().len(), 32);
+        assert_eq!(root["id"].as_str().unwrap().len(), 16);
+        assert!(root.get("shared").is_none());
+        assert_eq!(root["tags"]["error"], "500");
+    }
+}
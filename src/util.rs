@@ -1,9 +1,609 @@
-use actix_http::Payload;
-use actix_web::web::Bytes;
-
-/// Converts bytes to payload stream
-pub fn get_payload(bytes: Bytes) -> Payload {
-    let mut repack_payload = actix_http::h1::Payload::create(true);
-    repack_payload.1.unread_data(bytes);
-    repack_payload.1.into()
+use std::any::Any;
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use actix_http::encoding::Decoder;
+use actix_http::error::PayloadError;
+use actix_http::{BoxedPayloadStream, Payload};
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::dev::ServiceResponse;
+use actix_web::error::ErrorRequestTimeout;
+use actix_web::http::header::{
+    ContentEncoding, HeaderMap, HeaderValue, CONNECTION, CONTENT_TYPE, LOCATION, UPGRADE,
+};
+use actix_web::http::{Method, StatusCode};
+use actix_web::web::{Bytes, BytesMut};
+use actix_web::Error;
+use futures_util::future::ready;
+use futures_util::{stream, Stream, StreamExt};
+
+use crate::observer::{BodySizes, GrpcCall};
+
+/// Payload of already `read` bytes followed by the unread rest of `payload`, of the same kind as `payload` where
+/// possible, so extractors see the payload of the request's protocol: none for requests without a body, h1 for
+/// HTTP/1.x including chunked bodies, and a stream for HTTP/2 and other payloads, which can't be rebuilt.
+pub(crate) fn repack_payload(read: Bytes, payload: Payload) -> Payload {
+    match payload {
+        Payload::None if read.is_empty() => Payload::None,
+        Payload::H1 { mut payload } => {
+            if !read.is_empty() {
+                payload.unread_data(read);
+            }
+            Payload::H1 { payload }
+        }
+        payload => chain_payload(read, payload),
+    }
+}
+
+/// Message a panic was started with, e.g. by `panic!` or a failed `unwrap`.
+pub(crate) fn panic_message(panic: &(dyn Any + Send)) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+/// Error response for a request body which couldn't be read, 408 Request Timeout if reading timed out, otherwise the
+/// status of the payload error, e.g. 400 Bad Request for an incomplete body of a disconnected client.
+pub(crate) fn payload_error(err: PayloadError) -> Error {
+    let timed_out = match &err {
+        PayloadError::Io(io) | PayloadError::Incomplete(Some(io)) => {
+            io.kind() == std::io::ErrorKind::TimedOut
+        }
+        _ => false,
+    };
+    if timed_out {
+        ErrorRequestTimeout(err)
+    } else {
+        err.into()
+    }
+}
+
+/// Payload passing chunks of `payload` through as they are read, while copying them into the returned buffer.
+/// Copies at most `limit` bytes. Requests without a body keep their empty payload.
+pub(crate) fn tee_payload(payload: Payload, limit: usize) -> (Payload, Rc<RefCell<BytesMut>>) {
+    let captured = Rc::new(RefCell::new(BytesMut::new()));
+    if let Payload::None = payload {
+        return (payload, captured);
+    }
+    let tee: BoxedPayloadStream = Box::pin(TeePayload {
+        payload,
+        captured: captured.clone(),
+        limit,
+    });
+    (Payload::from(tee), captured)
+}
+
+/// Stream of already `read` bytes, followed by the unread rest of `payload`.
+fn chain_payload(read: Bytes, payload: Payload) -> Payload {
+    let chained: BoxedPayloadStream = Box::pin(stream::once(ready(Ok(read))).chain(payload));
+    Payload::from(chained)
+}
+
+struct TeePayload {
+    payload: Payload,
+    captured: Rc<RefCell<BytesMut>>,
+    limit: usize,
+}
+
+impl Stream for TeePayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = Pin::new(&mut self.payload).poll_next(cx);
+        if let Poll::Ready(Some(Ok(chunk))) = &poll {
+            let mut captured = self.captured.borrow_mut();
+            let remaining = self.limit.saturating_sub(captured.len());
+            captured.extend_from_slice(&chunk[..chunk.len().min(remaining)]);
+        }
+        poll
+    }
+}
+
+/// Map of up to `capacity` entries, least recently used are evicted first, in logarithmic time.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct LruMap<K, V> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<K, (V, u64)>,
+    /// Keys by the tick they were last used at.
+    recency: BTreeMap<u64, K>,
+}
+
+impl<K: Clone + Eq + Hash, V> LruMap<K, V> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+        }
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Value of `key`, marked as used.
+    pub(crate) fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let (value, used) = self.entries.get_mut(key)?;
+        self.tick += 1;
+        let key = self.recency.remove(used)?;
+        self.recency.insert(self.tick, key);
+        *used = self.tick;
+        Some(value)
+    }
+
+    /// Value of `key`, marked as used, or `insert`s it, evicting the least recently used entry if full.
+    pub(crate) fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, insert: F) -> &mut V {
+        if self.get_mut(&key).is_none() {
+            if self.entries.len() >= self.capacity {
+                if let Some((_, least_recent)) = self.recency.pop_first() {
+                    self.entries.remove(&least_recent);
+                }
+            }
+            self.tick += 1;
+            self.recency.insert(self.tick, key.clone());
+            self.entries.insert(key.clone(), (insert(), self.tick));
+        }
+        &mut self.entries.get_mut(&key).unwrap().0
+    }
+}
+
+/// Exclusion decisions of recently requested paths, least recently used are evicted first.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ExclusionCache(LruMap<String, bool>);
+
+impl ExclusionCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self(LruMap::new(capacity))
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+
+    /// Cached decision of `path`, or `decide`s and caches it.
+    pub(crate) fn get_or_insert_with<F: FnOnce() -> bool>(
+        &mut self,
+        path: &str,
+        decide: F,
+    ) -> bool {
+        match self.0.get_mut(path) {
+            Some(excluded) => *excluded,
+            None => *self.0.get_or_insert_with(path.to_string(), decide),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.0.entries.len()
+    }
+}
+
+/// Headers whose values are redacted by default, from debug output of event data and from recordings.
+pub(crate) const SENSITIVE_HEADERS: [&str; 4] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+];
+
+/// Debug output of headers, with values of [SENSITIVE_HEADERS] redacted.
+pub(crate) struct RedactedHeaders<'a>(pub &'a HeaderMap);
+
+impl fmt::Debug for RedactedHeaders<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.0.iter().map(|(name, value)| {
+                let value = if SENSITIVE_HEADERS.contains(&name.as_str()) {
+                    "[REDACTED]"
+                } else {
+                    value.to_str().unwrap_or("[BINARY]")
+                };
+                (name.as_str(), value)
+            }))
+            .finish()
+    }
+}
+
+/// Debug output of a body as its length, bodies may hold credentials or personal data.
+pub(crate) struct BodyLen(pub usize);
+
+impl fmt::Debug for BodyLen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{} bytes>", self.0)
+    }
+}
+
+/// Decodes a captured request body by its `Content-Encoding` header, keeping at most `limit` decoded bytes, so a small
+/// compressed body can't expand without bound. None for bodies without an encoding. Decoding stops at the first
+/// error, e.g. of a body cut at the capture limit, with the bytes decoded until then.
+pub(crate) async fn decode_body(
+    raw: Bytes,
+    content_encoding: Option<&HeaderValue>,
+    limit: usize,
+) -> Option<(BytesMut, BodySizes)> {
+    let encoding = content_encoding?
+        .to_str()
+        .ok()?
+        .parse::<ContentEncoding>()
+        .ok()?;
+    if encoding == ContentEncoding::Identity {
+        return None;
+    }
+    let raw_len = raw.len();
+    let mut decoder = Decoder::new(stream::once(ready(Ok(raw))), encoding);
+    let mut decoded = BytesMut::new();
+    while let Some(Ok(chunk)) = decoder.next().await {
+        decoded.extend_from_slice(&chunk);
+        if decoded.len() >= limit {
+            decoded.truncate(limit);
+            break;
+        }
+    }
+    let sizes = BodySizes {
+        raw: raw_len,
+        decoded: decoded.len(),
+    };
+    Some((decoded, sizes))
+}
+
+/// Protocol a request asks to upgrade its connection to with `Connection: Upgrade`, lowercase, e.g. `websocket`.
+pub(crate) fn upgrade_protocol(headers: &HeaderMap) -> Option<Arc<str>> {
+    let connection = headers.get(CONNECTION)?.to_str().ok()?;
+    if !connection
+        .split(',')
+        .any(|token| token.trim().eq_ignore_ascii_case("upgrade"))
+    {
+        return None;
+    }
+    let protocol = headers.get(UPGRADE)?.to_str().ok()?;
+    Some(protocol.trim().to_ascii_lowercase().into())
+}
+
+/// Service and method of a request with a gRPC content type, e.g. `application/grpc+proto` or
+/// `application/grpc-web-text`, from its `/{service}/{method}` path.
+pub(crate) fn grpc_call(path: &str, headers: &HeaderMap) -> Option<GrpcCall> {
+    let content_type = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+    let media_type = content_type.split(';').next()?.trim();
+    let grpc = media_type
+        .get(..16)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("application/grpc"));
+    if !grpc {
+        return None;
+    }
+    let (service, method) = path.strip_prefix('/')?.split_once('/')?;
+    if service.is_empty() || method.is_empty() || method.contains('/') {
+        return None;
+    }
+    Some(GrpcCall {
+        service: service.into(),
+        method: method.into(),
+        status: None,
+    })
+}
+
+/// `grpc-status` code of a response, if it's in its headers.
+pub(crate) fn grpc_status(headers: &HeaderMap) -> Option<u32> {
+    headers
+        .get("grpc-status")?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Whether actix generated a response instead of a handler: an empty `404 Not Found` of a request matching no
+/// resource or of a `HEAD` or `OPTIONS` request, an empty `405 Method Not Allowed` of a resource without a route
+/// for the method, or a redirect to the same path with or without a trailing slash.
+pub(crate) fn generated_by_framework<B: MessageBody>(res: &ServiceResponse<B>) -> bool {
+    let empty = matches!(
+        res.response().body().size(),
+        BodySize::None | BodySize::Sized(0)
+    );
+    let status = res.status();
+    if status == StatusCode::NOT_FOUND {
+        // routes added with `App::route` answer other methods with 404 too
+        let method = res.request().method();
+        let unrouted = res.request().match_pattern().is_none()
+            || method == Method::HEAD
+            || method == Method::OPTIONS;
+        return empty && unrouted;
+    }
+    if status == StatusCode::METHOD_NOT_ALLOWED {
+        return empty;
+    }
+    if !status.is_redirection() {
+        return false;
+    }
+    let Some(location) = res
+        .headers()
+        .get(LOCATION)
+        .and_then(|location| location.to_str().ok())
+    else {
+        return false;
+    };
+    let path = res.request().path();
+    let location = location.split(['?', '#']).next().unwrap_or_default();
+    location != path && location.trim_end_matches('/') == path.trim_end_matches('/')
+}
+
+/// Compares `a` and `b` in time depending on their length only, so secrets can't be guessed byte by byte.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Size of a body in bytes, unless it is streamed.
+pub(crate) fn body_size(size: BodySize) -> Option<u64> {
+    match size {
+        BodySize::Sized(size) => Some(size),
+        BodySize::None => Some(0),
+        BodySize::Stream => None,
+    }
+}
+
+/// (De)serializes [Duration](std::time::Duration) as whole microseconds.
+#[cfg(feature = "serde")]
+pub(crate) mod serde_micros {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_micros() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_micros)
+    }
+}
+
+/// (De)serializes an optional [Duration](std::time::Duration) as whole microseconds.
+#[cfg(feature = "serde")]
+pub(crate) mod serde_micros_opt {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => serializer.serialize_some(&(duration.as_micros() as u64)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Option::<u64>::deserialize(deserializer).map(|micros| micros.map(Duration::from_micros))
+    }
+}
+
+/// (De)serializes [Duration](std::time::Duration) as whole seconds.
+#[cfg(feature = "serde")]
+pub(crate) mod serde_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_secs())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_secs)
+    }
+}
+
+/// (De)serializes [StatusCode](actix_web::http::StatusCode) as a number.
+#[cfg(feature = "serde")]
+pub(crate) mod serde_status {
+    use actix_web::http::StatusCode;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(status: &StatusCode, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(status.as_u16())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<StatusCode, D::Error> {
+        let code = u16::deserialize(deserializer)?;
+        StatusCode::from_u16(code).map_err(D::Error::custom)
+    }
+}
+
+/// Returns status class of `status`, e.g. `"2xx"`.
+#[cfg(any(feature = "amqp", feature = "loki"))]
+pub(crate) fn status_class(status: actix_web::http::StatusCode) -> &'static str {
+    match status.as_u16() {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
+
+/// Maps request id to `[0, 1)`. Version 4 ids are random, which makes this a free sampling source.
+#[cfg(any(feature = "recorder", feature = "shadow"))]
+pub(crate) fn sample_point(request_id: &uuid::Uuid) -> f64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&request_id.as_bytes()[..8]);
+    (u64::from_be_bytes(bytes) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Returns lowercase hex encoded SHA-256 digest of `bytes`.
+#[cfg(feature = "sqlite")]
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    use std::fmt::Write;
+
+    Sha256::digest(bytes)
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+}
+
+/// Formats `time` as RFC 3339 UTC timestamp with milliseconds, e.g. `2022-08-01T10:20:30.123Z`.
+#[cfg(any(feature = "har", feature = "honeycomb"))]
+pub(crate) fn rfc3339(time: std::time::SystemTime) -> String {
+    let since_epoch = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, secs_of_day) = ((secs / 86_400) as i64, secs % 86_400);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Year, month and day of `days` since the UNIX epoch, http://howardhinnant.github.io/date_algorithms.html
+#[cfg(any(feature = "har", feature = "honeycomb", feature = "quota"))]
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Days since the UNIX epoch of `year`, `month` and `day`, inverse of [civil_from_days].
+#[cfg(feature = "quota")]
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Sampling decision of the caller, from the flags of a W3C `traceparent` header, else a single `b3` header or
+/// `X-B3-Flags` and `X-B3-Sampled` headers. B3 debug counts as sampled.
+pub(crate) fn upstream_sampled(headers: &HeaderMap) -> Option<bool> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    if let Some((_, _, sampled)) = header("traceparent").and_then(parse_traceparent) {
+        return Some(sampled);
+    }
+    let b3_sampled = |sampled: &str| match sampled.trim() {
+        "1" | "d" | "true" => Some(true),
+        "0" | "false" => Some(false),
+        _ => None,
+    };
+    if let Some(b3) = header("b3") {
+        // either only the decision, or `{trace_id}-{span_id}-{sampled}-{parent_id}`
+        let mut parts = b3.split('-');
+        return match (parts.next(), parts.next(), parts.next()) {
+            (Some(sampled), None, _) | (_, Some(_), Some(sampled)) => b3_sampled(sampled),
+            _ => None,
+        };
+    }
+    if header("x-b3-flags").map(str::trim) == Some("1") {
+        return Some(true);
+    }
+    header("x-b3-sampled").and_then(b3_sampled)
+}
+
+/// Parses a W3C `traceparent` header into trace id, parent span id and sampled flag.
+pub(crate) fn parse_traceparent(traceparent: &str) -> Option<(String, String, bool)> {
+    let mut parts = traceparent.trim().split('-');
+    let version = parts.next()?;
+    let trace_id = parts.next()?;
+    let parent_id = parts.next()?;
+    let flags = parts.next()?;
+    let is_hex = |value: &str, len: usize| {
+        value.len() == len && value.bytes().all(|byte| byte.is_ascii_hexdigit())
+    };
+    if !is_hex(version, 2)
+        || version == "ff"
+        || !is_hex(trace_id, 32)
+        || trace_id.bytes().all(|byte| byte == b'0')
+        || !is_hex(parent_id, 16)
+        || parent_id.bytes().all(|byte| byte == b'0')
+        || !is_hex(flags, 2)
+    {
+        return None;
+    }
+    let sampled = u8::from_str_radix(flags, 16).ok()? & 1 == 1;
+    Some((trace_id.to_lowercase(), parent_id.to_lowercase(), sampled))
+}
+
+/// Matches `path` against a glob of `/` separated segments, where `*` matches one segment and `**` any number
+/// of segments, e.g. `/api/**` matches `/api` and `/api/users/1`.
+pub(crate) fn path_matches(pattern: &str, path: &str) -> bool {
+    fn matches(pattern: &[&str], path: &[&str]) -> bool {
+        match pattern.split_first() {
+            None => path.is_empty(),
+            Some((&"**", rest)) => (0..=path.len()).any(|skip| matches(rest, &path[skip..])),
+            Some((segment, rest)) => match path.split_first() {
+                Some((first, path_rest)) => {
+                    (*segment == "*" || segment == first) && matches(rest, path_rest)
+                }
+                None => false,
+            },
+        }
+    }
+    fn segments(value: &str) -> Vec<&str> {
+        value
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect()
+    }
+    matches(&segments(pattern), &segments(path))
+}
+
+/// Decodes `%XX` escapes of `value`, leaving malformed ones as they are.
+pub(crate) fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
 }